@@ -2,15 +2,30 @@
 // WebAssembly binaryにも対応できる想定
 // https://webassembly.github.io/spec/core/syntax/index.html
 
+pub mod instr;
+
+// 仕様のモジュールの構造と同じ順序でフィールドを持つ
+// 関数、テーブル、メモリ、グローバル、タグのインデックスは、インポートしたものから順に数える
 // https://webassembly.github.io/spec/core/syntax/modules.html
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub struct Module {
     pub types: Vec<Type>,
     pub funcs: Vec<Func>,
+    pub tables: Vec<Table>,
+    pub mems: Vec<Mem>,
+    pub globals: Vec<Global>,
+    pub tags: Vec<Tag>,
+    pub elems: Vec<Elem>,
+    pub datas: Vec<Data>,
+    pub start: Option<u32>, // funcidx
+    pub imports: Vec<Import>,
     pub exports: Vec<Export>,
+    pub names: Names,
+    pub customs: Vec<Custom>,
 }
 
-// 現在はNumber Typesのみサポート
+// Number Types、Vector Types、Reference Typesをサポート
+// ExnRefはException Handling提案で追加された参照型
 // https://webassembly.github.io/spec/core/syntax/types.html#value-types
 #[derive(Debug, PartialEq, Clone, Copy, Eq)]
 pub enum ValueType {
@@ -18,6 +33,17 @@ pub enum ValueType {
     I64,
     F32,
     F64,
+    V128,
+    FuncRef,
+    ExternRef,
+    ExnRef,
+}
+
+impl ValueType {
+    // 参照型かどうか
+    pub fn is_ref(&self) -> bool {
+        matches!(self, Self::FuncRef | Self::ExternRef | Self::ExnRef)
+    }
 }
 pub type StackType = Vec<ValueType>;
 
@@ -29,8 +55,96 @@ pub type FuncType = (StackType, StackType);
 // https://webassembly.github.io/spec/core/syntax/modules.html#types
 pub type Type = FuncType;
 
-// https://webassembly.github.io/spec/core/syntax/modules.html#functions
+// 最大値がない場合はNoneになる
+// https://webassembly.github.io/spec/core/syntax/types.html#limits
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub struct Limits {
+    pub min: u32,
+    pub max: Option<u32>,
+}
+
+// sharedはThreads提案で追加された、複数のスレッドから共有されるメモリを表す
+// https://webassembly.github.io/spec/core/syntax/types.html#memory-types
+// https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub struct MemType {
+    pub limits: Limits,
+    pub shared: bool,
+}
+
+// MemTypeのエイリアス
+// https://webassembly.github.io/spec/core/syntax/modules.html#memories
+pub type Mem = MemType;
+
+// elemは参照型のみ
+// https://webassembly.github.io/spec/core/syntax/types.html#table-types
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub struct TableType {
+    pub limits: Limits,
+    pub elem: ValueType,
+}
+
+// TableTypeのエイリアス
+// https://webassembly.github.io/spec/core/syntax/modules.html#tables
+pub type Table = TableType;
+
+// https://webassembly.github.io/spec/core/syntax/types.html#global-types
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub struct GlobalType {
+    pub value_type: ValueType,
+    pub mutable: bool,
+}
+
+// 定数式
+// 末尾のend命令は含まない
+// https://webassembly.github.io/spec/core/syntax/instructions.html#expressions
+pub type Expr = Vec<Instruction>;
+
+// https://webassembly.github.io/spec/core/syntax/modules.html#globals
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct Global {
+    pub g_type: GlobalType,
+    pub init: Expr,
+}
+
+// https://webassembly.github.io/spec/core/syntax/modules.html#element-segments
 #[derive(Debug, PartialEq, Clone, Eq)]
+pub struct Elem {
+    pub e_type: ValueType,
+    pub init: Vec<Expr>,
+    pub mode: ElemMode,
+}
+
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub enum ElemMode {
+    Passive,
+    Active { table: u32, offset: Expr },
+    Declarative,
+}
+
+// https://webassembly.github.io/spec/core/syntax/modules.html#data-segments
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct Data {
+    pub init: Vec<u8>,
+    pub mode: DataMode,
+}
+
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub enum DataMode {
+    Passive,
+    Active { memory: u32, offset: Expr },
+}
+
+// 例外のタグ
+// f_typeは例外が運ぶ値をパラメータとし、戻り値を持たない関数型のインデックス
+// https://webassembly.github.io/exception-handling/core/syntax/modules.html#tags
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub struct Tag {
+    pub f_type: u32,
+}
+
+// https://webassembly.github.io/spec/core/syntax/modules.html#functions
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
 pub struct Func {
     pub f_type: u32,
     pub locals: Vec<ValueType>,
@@ -41,8 +155,601 @@ pub struct Func {
 // https://webassembly.github.io/spec/core/text/instructions.html
 #[derive(Debug, PartialEq, Clone, Eq)]
 pub enum Instruction {
+    // Control Instructions
+    // https://webassembly.github.io/spec/core/syntax/instructions.html#control-instructions
+    // ブロックはバイナリ形式と同様に平坦に保持し、Endでブロックの終わりを表す
+    // call_indirectのインデックスはバイナリ形式と同じ順序で保持する
+    Unreachable,
+    Nop,
+    Block(BlockType),
+    Loop(BlockType),
+    If(BlockType),
+    Else,
+    End,
+    Br(u32),                // labelidx
+    BrIf(u32),              // labelidx
+    BrTable(Vec<u32>, u32), // labelidxのリスト, デフォルトのlabelidx
+    Return,
+    Call(u32),                    // funcidx
+    CallIndirect(u32, u32),       // typeidx, tableidx
+    ReturnCall(u32),              // funcidx
+    ReturnCallIndirect(u32, u32), // typeidx, tableidx
+
+    // Exception Handling
+    // https://webassembly.github.io/exception-handling/core/syntax/instructions.html#control-instructions
+    Throw(u32), // tagidx
+    ThrowRef,
+    TryTable(BlockType, Vec<Catch>),
+
+    // Reference Instructions
+    // https://webassembly.github.io/spec/core/syntax/instructions.html#reference-instructions
+    RefNull(ValueType), // 参照型
+    RefIsNull,
+    RefFunc(u32), // funcidx
+
+    // Parametric Instructions
+    // https://webassembly.github.io/spec/core/syntax/instructions.html#parametric-instructions
+    // 型を指定しないselectはNoneになる
+    Drop,
+    Select(Option<ValueType>),
+
+    // Variable Instructions
+    // https://webassembly.github.io/spec/core/syntax/instructions.html#variable-instructions
     LocalGet(usize),
+    LocalSet(usize),
+    LocalTee(usize),
+    GlobalGet(u32),
+    GlobalSet(u32),
+
+    // Table Instructions
+    // https://webassembly.github.io/spec/core/syntax/instructions.html#table-instructions
+    TableGet(u32),  // tableidx
+    TableSet(u32),  // tableidx
+    TableSize(u32), // tableidx
+    TableGrow(u32), // tableidx
+
+    // Memory Instructions
+    // https://webassembly.github.io/spec/core/syntax/instructions.html#memory-instructions
+    I32Load(MemArg),
+    I64Load(MemArg),
+    F32Load(MemArg),
+    F64Load(MemArg),
+    I32Load8S(MemArg),
+    I32Load8U(MemArg),
+    I32Load16S(MemArg),
+    I32Load16U(MemArg),
+    I64Load8S(MemArg),
+    I64Load8U(MemArg),
+    I64Load16S(MemArg),
+    I64Load16U(MemArg),
+    I64Load32S(MemArg),
+    I64Load32U(MemArg),
+    I32Store(MemArg),
+    I64Store(MemArg),
+    F32Store(MemArg),
+    F64Store(MemArg),
+    I32Store8(MemArg),
+    I32Store16(MemArg),
+    I64Store8(MemArg),
+    I64Store16(MemArg),
+    I64Store32(MemArg),
+    MemorySize(u32), // memidx
+    MemoryGrow(u32), // memidx
+
+    // Bulk Memory Instructions
+    // https://webassembly.github.io/spec/core/syntax/instructions.html#memory-instructions
+    // https://webassembly.github.io/spec/core/syntax/instructions.html#table-instructions
+    // インデックスはバイナリ形式と同じ順序で保持する
+    MemoryInit(u32, u32), // dataidx, memidx
+    DataDrop(u32),        // dataidx
+    MemoryCopy(u32, u32), // コピー先のmemidx, コピー元のmemidx
+    MemoryFill(u32),      // memidx
+    TableInit(u32, u32),  // elemidx, tableidx
+    ElemDrop(u32),        // elemidx
+    TableCopy(u32, u32),  // コピー先のtableidx, コピー元のtableidx
+    TableFill(u32),       // tableidx
+
+    // Numeric Instructions
+    // https://webassembly.github.io/spec/core/syntax/instructions.html#numeric-instructions
+    // 浮動小数点数の定数はNaNのペイロードを保持するため、ビット列で保持する
+    I32Const(i32),
+    I64Const(i64),
+    F32Const(u32),
+    F64Const(u64),
+    I32Eqz,
+    I32Eq,
+    I32Ne,
+    I32LtS,
+    I32LtU,
+    I32GtS,
+    I32GtU,
+    I32LeS,
+    I32LeU,
+    I32GeS,
+    I32GeU,
+    I64Eqz,
+    I64Eq,
+    I64Ne,
+    I64LtS,
+    I64LtU,
+    I64GtS,
+    I64GtU,
+    I64LeS,
+    I64LeU,
+    I64GeS,
+    I64GeU,
+    F32Eq,
+    F32Ne,
+    F32Lt,
+    F32Gt,
+    F32Le,
+    F32Ge,
+    F64Eq,
+    F64Ne,
+    F64Lt,
+    F64Gt,
+    F64Le,
+    F64Ge,
+    I32Clz,
+    I32Ctz,
+    I32Popcnt,
     I32Add,
+    I32Sub,
+    I32Mul,
+    I32DivS,
+    I32DivU,
+    I32RemS,
+    I32RemU,
+    I32And,
+    I32Or,
+    I32Xor,
+    I32Shl,
+    I32ShrS,
+    I32ShrU,
+    I32Rotl,
+    I32Rotr,
+    I64Clz,
+    I64Ctz,
+    I64Popcnt,
+    I64Add,
+    I64Sub,
+    I64Mul,
+    I64DivS,
+    I64DivU,
+    I64RemS,
+    I64RemU,
+    I64And,
+    I64Or,
+    I64Xor,
+    I64Shl,
+    I64ShrS,
+    I64ShrU,
+    I64Rotl,
+    I64Rotr,
+    F32Abs,
+    F32Neg,
+    F32Ceil,
+    F32Floor,
+    F32Trunc,
+    F32Nearest,
+    F32Sqrt,
+    F32Add,
+    F32Sub,
+    F32Mul,
+    F32Div,
+    F32Min,
+    F32Max,
+    F32Copysign,
+    F64Abs,
+    F64Neg,
+    F64Ceil,
+    F64Floor,
+    F64Trunc,
+    F64Nearest,
+    F64Sqrt,
+    F64Add,
+    F64Sub,
+    F64Mul,
+    F64Div,
+    F64Min,
+    F64Max,
+    F64Copysign,
+    I32WrapI64,
+    I32TruncF32S,
+    I32TruncF32U,
+    I32TruncF64S,
+    I32TruncF64U,
+    I64ExtendI32S,
+    I64ExtendI32U,
+    I64TruncF32S,
+    I64TruncF32U,
+    I64TruncF64S,
+    I64TruncF64U,
+    F32ConvertI32S,
+    F32ConvertI32U,
+    F32ConvertI64S,
+    F32ConvertI64U,
+    F32DemoteF64,
+    F64ConvertI32S,
+    F64ConvertI32U,
+    F64ConvertI64S,
+    F64ConvertI64U,
+    F64PromoteF32,
+    I32ReinterpretF32,
+    I64ReinterpretF64,
+    F32ReinterpretI32,
+    F64ReinterpretI64,
+    I32Extend8S,
+    I32Extend16S,
+    I64Extend8S,
+    I64Extend16S,
+    I64Extend32S,
+    I32TruncSatF32S,
+    I32TruncSatF32U,
+    I32TruncSatF64S,
+    I32TruncSatF64U,
+    I64TruncSatF32S,
+    I64TruncSatF32U,
+    I64TruncSatF64S,
+    I64TruncSatF64U,
+
+    // Atomic Instructions
+    // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md
+    MemoryAtomicNotify(MemArg),
+    MemoryAtomicWait32(MemArg),
+    MemoryAtomicWait64(MemArg),
+    AtomicFence,
+    I32AtomicLoad(MemArg),
+    I64AtomicLoad(MemArg),
+    I32AtomicLoad8U(MemArg),
+    I32AtomicLoad16U(MemArg),
+    I64AtomicLoad8U(MemArg),
+    I64AtomicLoad16U(MemArg),
+    I64AtomicLoad32U(MemArg),
+    I32AtomicStore(MemArg),
+    I64AtomicStore(MemArg),
+    I32AtomicStore8(MemArg),
+    I32AtomicStore16(MemArg),
+    I64AtomicStore8(MemArg),
+    I64AtomicStore16(MemArg),
+    I64AtomicStore32(MemArg),
+    I32AtomicRmwAdd(MemArg),
+    I64AtomicRmwAdd(MemArg),
+    I32AtomicRmw8AddU(MemArg),
+    I32AtomicRmw16AddU(MemArg),
+    I64AtomicRmw8AddU(MemArg),
+    I64AtomicRmw16AddU(MemArg),
+    I64AtomicRmw32AddU(MemArg),
+    I32AtomicRmwSub(MemArg),
+    I64AtomicRmwSub(MemArg),
+    I32AtomicRmw8SubU(MemArg),
+    I32AtomicRmw16SubU(MemArg),
+    I64AtomicRmw8SubU(MemArg),
+    I64AtomicRmw16SubU(MemArg),
+    I64AtomicRmw32SubU(MemArg),
+    I32AtomicRmwAnd(MemArg),
+    I64AtomicRmwAnd(MemArg),
+    I32AtomicRmw8AndU(MemArg),
+    I32AtomicRmw16AndU(MemArg),
+    I64AtomicRmw8AndU(MemArg),
+    I64AtomicRmw16AndU(MemArg),
+    I64AtomicRmw32AndU(MemArg),
+    I32AtomicRmwOr(MemArg),
+    I64AtomicRmwOr(MemArg),
+    I32AtomicRmw8OrU(MemArg),
+    I32AtomicRmw16OrU(MemArg),
+    I64AtomicRmw8OrU(MemArg),
+    I64AtomicRmw16OrU(MemArg),
+    I64AtomicRmw32OrU(MemArg),
+    I32AtomicRmwXor(MemArg),
+    I64AtomicRmwXor(MemArg),
+    I32AtomicRmw8XorU(MemArg),
+    I32AtomicRmw16XorU(MemArg),
+    I64AtomicRmw8XorU(MemArg),
+    I64AtomicRmw16XorU(MemArg),
+    I64AtomicRmw32XorU(MemArg),
+    I32AtomicRmwXchg(MemArg),
+    I64AtomicRmwXchg(MemArg),
+    I32AtomicRmw8XchgU(MemArg),
+    I32AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw8XchgU(MemArg),
+    I64AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw32XchgU(MemArg),
+    I32AtomicRmwCmpxchg(MemArg),
+    I64AtomicRmwCmpxchg(MemArg),
+    I32AtomicRmw8CmpxchgU(MemArg),
+    I32AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw8CmpxchgU(MemArg),
+    I64AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw32CmpxchgU(MemArg),
+
+    // Vector Instructions
+    // https://webassembly.github.io/spec/core/syntax/instructions.html#vector-instructions
+    // v128.constの値とi8x16.shuffleのレーンはリトルエンディアンで保持する
+    V128Load(MemArg),
+    V128Load8x8S(MemArg),
+    V128Load8x8U(MemArg),
+    V128Load16x4S(MemArg),
+    V128Load16x4U(MemArg),
+    V128Load32x2S(MemArg),
+    V128Load32x2U(MemArg),
+    V128Load8Splat(MemArg),
+    V128Load16Splat(MemArg),
+    V128Load32Splat(MemArg),
+    V128Load64Splat(MemArg),
+    V128Store(MemArg),
+    V128Const(u128),
+    I8x16Shuffle([u8; 16]),
+    I8x16Swizzle,
+    I8x16Splat,
+    I16x8Splat,
+    I32x4Splat,
+    I64x2Splat,
+    F32x4Splat,
+    F64x2Splat,
+    I8x16ExtractLaneS(u8),
+    I8x16ExtractLaneU(u8),
+    I8x16ReplaceLane(u8),
+    I16x8ExtractLaneS(u8),
+    I16x8ExtractLaneU(u8),
+    I16x8ReplaceLane(u8),
+    I32x4ExtractLane(u8),
+    I32x4ReplaceLane(u8),
+    I64x2ExtractLane(u8),
+    I64x2ReplaceLane(u8),
+    F32x4ExtractLane(u8),
+    F32x4ReplaceLane(u8),
+    F64x2ExtractLane(u8),
+    F64x2ReplaceLane(u8),
+    I8x16Eq,
+    I8x16Ne,
+    I8x16LtS,
+    I8x16LtU,
+    I8x16GtS,
+    I8x16GtU,
+    I8x16LeS,
+    I8x16LeU,
+    I8x16GeS,
+    I8x16GeU,
+    I16x8Eq,
+    I16x8Ne,
+    I16x8LtS,
+    I16x8LtU,
+    I16x8GtS,
+    I16x8GtU,
+    I16x8LeS,
+    I16x8LeU,
+    I16x8GeS,
+    I16x8GeU,
+    I32x4Eq,
+    I32x4Ne,
+    I32x4LtS,
+    I32x4LtU,
+    I32x4GtS,
+    I32x4GtU,
+    I32x4LeS,
+    I32x4LeU,
+    I32x4GeS,
+    I32x4GeU,
+    F32x4Eq,
+    F32x4Ne,
+    F32x4Lt,
+    F32x4Gt,
+    F32x4Le,
+    F32x4Ge,
+    F64x2Eq,
+    F64x2Ne,
+    F64x2Lt,
+    F64x2Gt,
+    F64x2Le,
+    F64x2Ge,
+    V128Not,
+    V128And,
+    V128Andnot,
+    V128Or,
+    V128Xor,
+    V128Bitselect,
+    V128AnyTrue,
+    V128Load8Lane(MemArg, u8),
+    V128Load16Lane(MemArg, u8),
+    V128Load32Lane(MemArg, u8),
+    V128Load64Lane(MemArg, u8),
+    V128Store8Lane(MemArg, u8),
+    V128Store16Lane(MemArg, u8),
+    V128Store32Lane(MemArg, u8),
+    V128Store64Lane(MemArg, u8),
+    V128Load32Zero(MemArg),
+    V128Load64Zero(MemArg),
+    F32x4DemoteF64x2Zero,
+    F64x2PromoteLowF32x4,
+    I8x16Abs,
+    I8x16Neg,
+    I8x16Popcnt,
+    I8x16AllTrue,
+    I8x16Bitmask,
+    I8x16NarrowI16x8S,
+    I8x16NarrowI16x8U,
+    F32x4Ceil,
+    F32x4Floor,
+    F32x4Trunc,
+    F32x4Nearest,
+    I8x16Shl,
+    I8x16ShrS,
+    I8x16ShrU,
+    I8x16Add,
+    I8x16AddSatS,
+    I8x16AddSatU,
+    I8x16Sub,
+    I8x16SubSatS,
+    I8x16SubSatU,
+    F64x2Ceil,
+    F64x2Floor,
+    I8x16MinS,
+    I8x16MinU,
+    I8x16MaxS,
+    I8x16MaxU,
+    F64x2Trunc,
+    I8x16AvgrU,
+    I16x8ExtaddPairwiseI8x16S,
+    I16x8ExtaddPairwiseI8x16U,
+    I32x4ExtaddPairwiseI16x8S,
+    I32x4ExtaddPairwiseI16x8U,
+    I16x8Abs,
+    I16x8Neg,
+    I16x8Q15mulrSatS,
+    I16x8AllTrue,
+    I16x8Bitmask,
+    I16x8NarrowI32x4S,
+    I16x8NarrowI32x4U,
+    I16x8ExtendLowI8x16S,
+    I16x8ExtendHighI8x16S,
+    I16x8ExtendLowI8x16U,
+    I16x8ExtendHighI8x16U,
+    I16x8Shl,
+    I16x8ShrS,
+    I16x8ShrU,
+    I16x8Add,
+    I16x8AddSatS,
+    I16x8AddSatU,
+    I16x8Sub,
+    I16x8SubSatS,
+    I16x8SubSatU,
+    F64x2Nearest,
+    I16x8Mul,
+    I16x8MinS,
+    I16x8MinU,
+    I16x8MaxS,
+    I16x8MaxU,
+    I16x8AvgrU,
+    I16x8ExtmulLowI8x16S,
+    I16x8ExtmulHighI8x16S,
+    I16x8ExtmulLowI8x16U,
+    I16x8ExtmulHighI8x16U,
+    I32x4Abs,
+    I32x4Neg,
+    I32x4AllTrue,
+    I32x4Bitmask,
+    I32x4ExtendLowI16x8S,
+    I32x4ExtendHighI16x8S,
+    I32x4ExtendLowI16x8U,
+    I32x4ExtendHighI16x8U,
+    I32x4Shl,
+    I32x4ShrS,
+    I32x4ShrU,
+    I32x4Add,
+    I32x4Sub,
+    I32x4Mul,
+    I32x4MinS,
+    I32x4MinU,
+    I32x4MaxS,
+    I32x4MaxU,
+    I32x4DotI16x8S,
+    I32x4ExtmulLowI16x8S,
+    I32x4ExtmulHighI16x8S,
+    I32x4ExtmulLowI16x8U,
+    I32x4ExtmulHighI16x8U,
+    I64x2Abs,
+    I64x2Neg,
+    I64x2AllTrue,
+    I64x2Bitmask,
+    I64x2ExtendLowI32x4S,
+    I64x2ExtendHighI32x4S,
+    I64x2ExtendLowI32x4U,
+    I64x2ExtendHighI32x4U,
+    I64x2Shl,
+    I64x2ShrS,
+    I64x2ShrU,
+    I64x2Add,
+    I64x2Sub,
+    I64x2Mul,
+    I64x2Eq,
+    I64x2Ne,
+    I64x2LtS,
+    I64x2GtS,
+    I64x2LeS,
+    I64x2GeS,
+    I64x2ExtmulLowI32x4S,
+    I64x2ExtmulHighI32x4S,
+    I64x2ExtmulLowI32x4U,
+    I64x2ExtmulHighI32x4U,
+    F32x4Abs,
+    F32x4Neg,
+    F32x4Sqrt,
+    F32x4Add,
+    F32x4Sub,
+    F32x4Mul,
+    F32x4Div,
+    F32x4Min,
+    F32x4Max,
+    F32x4Pmin,
+    F32x4Pmax,
+    F64x2Abs,
+    F64x2Neg,
+    F64x2Sqrt,
+    F64x2Add,
+    F64x2Sub,
+    F64x2Mul,
+    F64x2Div,
+    F64x2Min,
+    F64x2Max,
+    F64x2Pmin,
+    F64x2Pmax,
+    I32x4TruncSatF32x4S,
+    I32x4TruncSatF32x4U,
+    F32x4ConvertI32x4S,
+    F32x4ConvertI32x4U,
+    I32x4TruncSatF64x2SZero,
+    I32x4TruncSatF64x2UZero,
+    F64x2ConvertLowI32x4S,
+    F64x2ConvertLowI32x4U,
+}
+
+// ブロックの型
+// 戻り値が1つ以下の場合は、型のリストを使わずに表現する
+// https://webassembly.github.io/spec/core/syntax/instructions.html#control-instructions
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub enum BlockType {
+    Empty,
+    Value(ValueType),
+    Type(u32),
+}
+
+// try_tableのcatch節
+// 例外が捕捉された場合に分岐するlabelidxを持つ
+// https://webassembly.github.io/exception-handling/core/syntax/instructions.html#control-instructions
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub enum Catch {
+    Catch(u32, u32),    // tagidx, labelidx
+    CatchRef(u32, u32), // tagidx, labelidx
+    CatchAll(u32),      // labelidx
+    CatchAllRef(u32),   // labelidx
+}
+
+// ロード・ストア命令のオフセットとアラインメント
+// alignはバイト数ではなく2の指数で保持する (例: 4バイト境界なら2)
+// https://webassembly.github.io/spec/core/syntax/instructions.html#memory-instructions
+#[derive(Debug, PartialEq, Clone, Copy, Eq, Default)]
+pub struct MemArg {
+    pub offset: u32,
+    pub align: u32,
+}
+
+// https://webassembly.github.io/spec/core/syntax/modules.html#imports
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct Import {
+    pub module: String,
+    pub name: String,
+    pub desc: ImportDesc,
+}
+
+// https://webassembly.github.io/spec/core/syntax/modules.html#syntax-importdesc
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub enum ImportDesc {
+    Func(u32), // typeidx
+    Table(TableType),
+    Memory(MemType),
+    Global(GlobalType),
+    Tag(Tag),
 }
 
 // https://webassembly.github.io/spec/core/syntax/modules.html#exports
@@ -59,4 +766,216 @@ pub enum ExportDesc {
     Table(u32),
     Memory(u32),
     Global(u32),
+    Tag(u32),
+}
+
+// カスタムセクション
+// 名前と任意のバイト列を持ち、モジュールの意味には影響しない
+// placeには、標準のセクションに対してどこに置くかを持つ
+// https://webassembly.github.io/spec/core/binary/modules.html#custom-section
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+pub struct Custom {
+    pub name: String,
+    pub place: CustomPlace,
+    pub data: Vec<u8>,
+}
+
+// カスタムセクションを置く位置
+// テキスト形式の"(before first)"、"(before func)"、"(after data)"、"(after last)"に対応する
+// 指定したセクションが空で出力されない場合も、セクションの順番に従って同じ位置に置く
+// https://github.com/WebAssembly/annotations/blob/main/proposals/annotations/Overview.md
+#[derive(Debug, PartialEq, Clone, Copy, Eq, Default)]
+pub enum CustomPlace {
+    BeforeFirst,
+    Before(Section),
+    After(Section),
+    #[default]
+    AfterLast,
+}
+
+// カスタムセクション以外のセクションを、バイナリ形式で並ぶ順番に持つ
+// タグセクションはメモリとグローバルの間、データ数セクションはエレメントとコードの間に置かれる
+// https://webassembly.github.io/spec/core/binary/modules.html#binary-module
+#[derive(Debug, PartialEq, Clone, Copy, Eq, PartialOrd, Ord)]
+pub enum Section {
+    Type,
+    Import,
+    Func,
+    Table,
+    Memory,
+    Tag,
+    Global,
+    Export,
+    Start,
+    Elem,
+    DataCount,
+    Code,
+    Data,
+}
+
+impl Section {
+    pub const ALL: [Section; 13] = [
+        Self::Type,
+        Self::Import,
+        Self::Func,
+        Self::Table,
+        Self::Memory,
+        Self::Tag,
+        Self::Global,
+        Self::Export,
+        Self::Start,
+        Self::Elem,
+        Self::DataCount,
+        Self::Code,
+        Self::Data,
+    ];
+
+    // テキスト形式で位置を指定するときの名前
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Type => "type",
+            Self::Import => "import",
+            Self::Func => "func",
+            Self::Table => "table",
+            Self::Memory => "memory",
+            Self::Tag => "tag",
+            Self::Global => "global",
+            Self::Export => "export",
+            Self::Start => "start",
+            Self::Elem => "elem",
+            Self::DataCount => "datacount",
+            Self::Code => "code",
+            Self::Data => "data",
+        }
+    }
+}
+
+impl Module {
+    // 指定した名前のカスタムセクションを、出現順に返す
+    // 名前セクションはnamesとして解釈されるため含まない
+    pub fn customs_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Custom> {
+        self.customs.iter().filter(move |c| c.name == name)
+    }
+
+    // 指定した名前の最初のカスタムセクションの内容を返す
+    pub fn custom(&self, name: &str) -> Option<&[u8]> {
+        let custom = self.customs.iter().find(|c| c.name == name)?;
+        Some(&custom.data)
+    }
+
+    // カスタムセクションを追加する
+    // 同じ位置にあるカスタムセクションの後ろに置かれる
+    pub fn add_custom(&mut self, custom: Custom) {
+        self.customs.push(custom);
+    }
+
+    // 指定した名前の最初のカスタムセクションの内容を、位置を保ったまま置き換える
+    // 同じ名前の2つ目以降のカスタムセクションは削除し、存在しない場合は末尾に追加する
+    pub fn replace_custom(&mut self, name: &str, data: Vec<u8>) {
+        match self.customs.iter().position(|c| c.name == name) {
+            Some(i) => {
+                self.customs[i].data = data;
+                let rest = self.customs.split_off(i + 1);
+                self.customs
+                    .extend(rest.into_iter().filter(|c| c.name != name));
+            }
+            None => self.add_custom(Custom {
+                name: name.to_string(),
+                data,
+                ..Custom::default()
+            }),
+        }
+    }
+
+    // 指定した名前のカスタムセクションを全て削除し、削除した数を返す
+    // "name"を指定した場合は、namesも削除する
+    pub fn strip_custom(&mut self, name: &str) -> usize {
+        let len = self.customs.len();
+        self.customs.retain(|c| c.name != name);
+        if name == "name" {
+            self.names = Names::default();
+        }
+        len - self.customs.len()
+    }
+
+    // 名前セクションを含む全てのカスタムセクションを削除する
+    pub fn strip_customs(&mut self) {
+        self.customs.clear();
+        self.names = Names::default();
+    }
+}
+
+// インデックスと名前の組をインデックスの昇順に並べたもの
+// https://webassembly.github.io/spec/core/appendix/custom.html#name-maps
+pub type NameMap = Vec<(u32, String)>;
+
+// 関数のインデックスと、その関数の中のローカル変数やラベルのNameMapの組
+pub type IndirectNameMap = Vec<(u32, NameMap)>;
+
+// 名前セクションに書かれる、モジュールとその要素の名前
+// テキスト形式の識別子から先頭の"$"を除いたものを保持する
+// ラベルは関数の中でblock、loop、if、try_tableが現れる順に0から数える
+// https://webassembly.github.io/spec/core/appendix/custom.html#name-section
+// https://github.com/WebAssembly/extended-name-section/blob/main/proposals/extended-name-section/Overview.md
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+pub struct Names {
+    pub module: Option<String>,
+    pub funcs: NameMap,
+    pub locals: IndirectNameMap,
+    pub labels: IndirectNameMap,
+    pub types: NameMap,
+    pub globals: NameMap,
+}
+
+impl Names {
+    // 名前が1つもないかどうか
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(name: &str, place: CustomPlace, data: &[u8]) -> Custom {
+        Custom {
+            name: name.to_string(),
+            place,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn custom_api() {
+        let mut m = Module::default();
+        m.add_custom(custom("a", CustomPlace::BeforeFirst, b"1"));
+        m.add_custom(custom("b", CustomPlace::After(Section::Code), b"2"));
+        m.add_custom(custom("a", CustomPlace::AfterLast, b"3"));
+        assert_eq!(m.custom("a"), Some(&b"1"[..]));
+        assert_eq!(m.custom("c"), None);
+        assert_eq!(m.customs_named("a").count(), 2);
+
+        // 最初のものを位置を保ったまま置き換え、残りは削除する
+        m.replace_custom("a", b"4".to_vec());
+        assert_eq!(
+            m.customs,
+            vec![
+                custom("a", CustomPlace::BeforeFirst, b"4"),
+                custom("b", CustomPlace::After(Section::Code), b"2"),
+            ]
+        );
+        m.replace_custom("c", b"5".to_vec());
+        assert_eq!(m.customs[2], custom("c", CustomPlace::AfterLast, b"5"));
+
+        assert_eq!(m.strip_custom("b"), 1);
+        assert_eq!(m.strip_custom("b"), 0);
+        m.names.module = Some("m".to_string());
+        assert_eq!(m.strip_custom("name"), 0);
+        assert!(m.names.is_empty());
+
+        m.names.module = Some("m".to_string());
+        m.strip_customs();
+        assert_eq!(m, Module::default());
+    }
 }
@@ -0,0 +1,2024 @@
+use std::mem::discriminant;
+
+use super::{
+    Instruction as I, Instruction, MemArg,
+    ValueType::{self, F32, F64, I32, I64, V128},
+};
+
+// 命令表
+// 命令名、オペコード、即値の形、型をまとめて保持し、
+// テキスト形式とバイナリ形式の変換、検証で共通して参照する
+// https://webassembly.github.io/spec/core/binary/instructions.html
+
+// 命令のオペコード
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub enum Opcode {
+    // 1バイトのオペコード
+    Single(u8),
+    // 0xFDのようなプレフィックスと、LEB128でエンコードされるサブオペコード
+    Prefixed(u8, u32),
+}
+
+// 命令が持つ即値の形
+#[derive(Debug)]
+pub enum Shape {
+    // 即値を持たない命令
+    Plain(Instruction),
+    // memargを持つ命令と、その自然なアラインメント
+    Memory(fn(MemArg) -> Instruction, u32),
+    // レーン番号を持つ命令と、そのレーン数
+    Lane(fn(u8) -> Instruction, u8),
+    // memargとレーン番号を持つ命令と、その自然なアラインメントとレーン数
+    MemoryLane(fn(MemArg, u8) -> Instruction, u32, u8),
+}
+
+// 命令がスタックから取り出す値の型と、積む値の型
+// https://webassembly.github.io/spec/core/valid/instructions.html
+pub type Signature = (&'static [ValueType], &'static [ValueType]);
+
+// 命令表のエントリ
+// tyは型が固定の命令の型で、unreachableやdropのように前後の命令で型が決まる命令はNoneになる
+#[derive(Debug)]
+pub struct Entry {
+    pub name: &'static str,
+    pub opcode: Opcode,
+    pub shape: Shape,
+    pub ty: Option<Signature>,
+}
+
+impl Entry {
+    // 命令の種類を比較するための代表値を作成する
+    fn prototype(&self) -> Instruction {
+        match self.shape {
+            Shape::Plain(ref instr) => instr.clone(),
+            Shape::Memory(ctor, _) => ctor(MemArg::default()),
+            Shape::Lane(ctor, _) => ctor(0),
+            Shape::MemoryLane(ctor, _, _) => ctor(MemArg::default(), 0),
+        }
+    }
+}
+
+// 命令名から命令表のエントリを取得する
+pub fn find_by_name(name: &str) -> Option<&'static Entry> {
+    INSTRUCTIONS.iter().find(|e| e.name == name)
+}
+
+// オペコードから命令表のエントリを取得する
+pub fn find_by_opcode(opcode: Opcode) -> Option<&'static Entry> {
+    INSTRUCTIONS.iter().find(|e| e.opcode == opcode)
+}
+
+// 命令から命令表のエントリを取得する
+// 即値の値は比較せず、命令の種類が一致するエントリを返す
+pub fn find(instr: &Instruction) -> Option<&'static Entry> {
+    let d = discriminant(instr);
+    INSTRUCTIONS
+        .iter()
+        .find(|e| discriminant(&e.prototype()) == d)
+}
+
+impl Instruction {
+    // 命令が持つmemargを取得する
+    pub fn memarg(&self) -> Option<MemArg> {
+        match self {
+            I::I32Load(m)
+            | I::I64Load(m)
+            | I::F32Load(m)
+            | I::F64Load(m)
+            | I::I32Load8S(m)
+            | I::I32Load8U(m)
+            | I::I32Load16S(m)
+            | I::I32Load16U(m)
+            | I::I64Load8S(m)
+            | I::I64Load8U(m)
+            | I::I64Load16S(m)
+            | I::I64Load16U(m)
+            | I::I64Load32S(m)
+            | I::I64Load32U(m)
+            | I::I32Store(m)
+            | I::I64Store(m)
+            | I::F32Store(m)
+            | I::F64Store(m)
+            | I::I32Store8(m)
+            | I::I32Store16(m)
+            | I::I64Store8(m)
+            | I::I64Store16(m)
+            | I::I64Store32(m)
+            | I::MemoryAtomicNotify(m)
+            | I::MemoryAtomicWait32(m)
+            | I::MemoryAtomicWait64(m)
+            | I::I32AtomicLoad(m)
+            | I::I64AtomicLoad(m)
+            | I::I32AtomicLoad8U(m)
+            | I::I32AtomicLoad16U(m)
+            | I::I64AtomicLoad8U(m)
+            | I::I64AtomicLoad16U(m)
+            | I::I64AtomicLoad32U(m)
+            | I::I32AtomicStore(m)
+            | I::I64AtomicStore(m)
+            | I::I32AtomicStore8(m)
+            | I::I32AtomicStore16(m)
+            | I::I64AtomicStore8(m)
+            | I::I64AtomicStore16(m)
+            | I::I64AtomicStore32(m)
+            | I::I32AtomicRmwAdd(m)
+            | I::I64AtomicRmwAdd(m)
+            | I::I32AtomicRmw8AddU(m)
+            | I::I32AtomicRmw16AddU(m)
+            | I::I64AtomicRmw8AddU(m)
+            | I::I64AtomicRmw16AddU(m)
+            | I::I64AtomicRmw32AddU(m)
+            | I::I32AtomicRmwSub(m)
+            | I::I64AtomicRmwSub(m)
+            | I::I32AtomicRmw8SubU(m)
+            | I::I32AtomicRmw16SubU(m)
+            | I::I64AtomicRmw8SubU(m)
+            | I::I64AtomicRmw16SubU(m)
+            | I::I64AtomicRmw32SubU(m)
+            | I::I32AtomicRmwAnd(m)
+            | I::I64AtomicRmwAnd(m)
+            | I::I32AtomicRmw8AndU(m)
+            | I::I32AtomicRmw16AndU(m)
+            | I::I64AtomicRmw8AndU(m)
+            | I::I64AtomicRmw16AndU(m)
+            | I::I64AtomicRmw32AndU(m)
+            | I::I32AtomicRmwOr(m)
+            | I::I64AtomicRmwOr(m)
+            | I::I32AtomicRmw8OrU(m)
+            | I::I32AtomicRmw16OrU(m)
+            | I::I64AtomicRmw8OrU(m)
+            | I::I64AtomicRmw16OrU(m)
+            | I::I64AtomicRmw32OrU(m)
+            | I::I32AtomicRmwXor(m)
+            | I::I64AtomicRmwXor(m)
+            | I::I32AtomicRmw8XorU(m)
+            | I::I32AtomicRmw16XorU(m)
+            | I::I64AtomicRmw8XorU(m)
+            | I::I64AtomicRmw16XorU(m)
+            | I::I64AtomicRmw32XorU(m)
+            | I::I32AtomicRmwXchg(m)
+            | I::I64AtomicRmwXchg(m)
+            | I::I32AtomicRmw8XchgU(m)
+            | I::I32AtomicRmw16XchgU(m)
+            | I::I64AtomicRmw8XchgU(m)
+            | I::I64AtomicRmw16XchgU(m)
+            | I::I64AtomicRmw32XchgU(m)
+            | I::I32AtomicRmwCmpxchg(m)
+            | I::I64AtomicRmwCmpxchg(m)
+            | I::I32AtomicRmw8CmpxchgU(m)
+            | I::I32AtomicRmw16CmpxchgU(m)
+            | I::I64AtomicRmw8CmpxchgU(m)
+            | I::I64AtomicRmw16CmpxchgU(m)
+            | I::I64AtomicRmw32CmpxchgU(m)
+            | I::V128Load(m)
+            | I::V128Load8x8S(m)
+            | I::V128Load8x8U(m)
+            | I::V128Load16x4S(m)
+            | I::V128Load16x4U(m)
+            | I::V128Load32x2S(m)
+            | I::V128Load32x2U(m)
+            | I::V128Load8Splat(m)
+            | I::V128Load16Splat(m)
+            | I::V128Load32Splat(m)
+            | I::V128Load64Splat(m)
+            | I::V128Store(m)
+            | I::V128Load8Lane(m, _)
+            | I::V128Load16Lane(m, _)
+            | I::V128Load32Lane(m, _)
+            | I::V128Load64Lane(m, _)
+            | I::V128Store8Lane(m, _)
+            | I::V128Store16Lane(m, _)
+            | I::V128Store32Lane(m, _)
+            | I::V128Store64Lane(m, _)
+            | I::V128Load32Zero(m)
+            | I::V128Load64Zero(m) => Some(*m),
+            _ => None,
+        }
+    }
+
+    // 命令が持つレーン番号を取得する
+    pub fn lane(&self) -> Option<u8> {
+        match self {
+            I::I8x16ExtractLaneS(l)
+            | I::I8x16ExtractLaneU(l)
+            | I::I8x16ReplaceLane(l)
+            | I::I16x8ExtractLaneS(l)
+            | I::I16x8ExtractLaneU(l)
+            | I::I16x8ReplaceLane(l)
+            | I::I32x4ExtractLane(l)
+            | I::I32x4ReplaceLane(l)
+            | I::I64x2ExtractLane(l)
+            | I::I64x2ReplaceLane(l)
+            | I::F32x4ExtractLane(l)
+            | I::F32x4ReplaceLane(l)
+            | I::F64x2ExtractLane(l)
+            | I::F64x2ReplaceLane(l)
+            | I::V128Load8Lane(_, l)
+            | I::V128Load16Lane(_, l)
+            | I::V128Load32Lane(_, l)
+            | I::V128Load64Lane(_, l)
+            | I::V128Store8Lane(_, l)
+            | I::V128Store16Lane(_, l)
+            | I::V128Store32Lane(_, l)
+            | I::V128Store64Lane(_, l) => Some(*l),
+            _ => None,
+        }
+    }
+}
+
+const fn misc(op: u32) -> Opcode {
+    Opcode::Prefixed(0xfc, op)
+}
+
+const fn atomic(op: u32) -> Opcode {
+    Opcode::Prefixed(0xfe, op)
+}
+
+const fn simd(op: u32) -> Opcode {
+    Opcode::Prefixed(0xfd, op)
+}
+
+const fn ty(params: &'static [ValueType], results: &'static [ValueType]) -> Signature {
+    (params, results)
+}
+
+const fn polymorphic(instr: Instruction, name: &'static str, opcode: Opcode) -> Entry {
+    Entry {
+        name,
+        opcode,
+        shape: Shape::Plain(instr),
+        ty: None,
+    }
+}
+
+const fn plain(instr: Instruction, name: &'static str, opcode: Opcode, ty: Signature) -> Entry {
+    Entry {
+        name,
+        opcode,
+        shape: Shape::Plain(instr),
+        ty: Some(ty),
+    }
+}
+
+const fn memory(
+    ctor: fn(MemArg) -> Instruction,
+    name: &'static str,
+    opcode: Opcode,
+    align: u32,
+    ty: Signature,
+) -> Entry {
+    Entry {
+        name,
+        opcode,
+        shape: Shape::Memory(ctor, align),
+        ty: Some(ty),
+    }
+}
+
+const fn lane(
+    ctor: fn(u8) -> Instruction,
+    name: &'static str,
+    opcode: Opcode,
+    lanes: u8,
+    ty: Signature,
+) -> Entry {
+    Entry {
+        name,
+        opcode,
+        shape: Shape::Lane(ctor, lanes),
+        ty: Some(ty),
+    }
+}
+
+const fn memory_lane(
+    ctor: fn(MemArg, u8) -> Instruction,
+    name: &'static str,
+    opcode: Opcode,
+    align: u32,
+    lanes: u8,
+    ty: Signature,
+) -> Entry {
+    Entry {
+        name,
+        opcode,
+        shape: Shape::MemoryLane(ctor, align, lanes),
+        ty: Some(ty),
+    }
+}
+
+// 命令表で使う型
+// 名前は仕様のunop、binop、testop、relopなどに合わせる
+const I32_UNOP: Signature = (&[I32], &[I32]);
+const I32_BINOP: Signature = (&[I32, I32], &[I32]);
+const I32_TESTOP: Signature = (&[I32], &[I32]);
+const I32_RELOP: Signature = (&[I32, I32], &[I32]);
+const I32_TO_F32: Signature = (&[I32], &[F32]);
+const I32_TO_F64: Signature = (&[I32], &[F64]);
+const I32_TO_I64: Signature = (&[I32], &[I64]);
+const I32_TO_V128: Signature = (&[I32], &[V128]);
+const I32_LOAD: Signature = (&[I32], &[I32]);
+const I32_STORE: Signature = (&[I32, I32], &[]);
+const I32_RMW: Signature = (&[I32, I32], &[I32]);
+const I32_CMPXCHG: Signature = (&[I32, I32, I32], &[I32]);
+const I64_UNOP: Signature = (&[I64], &[I64]);
+const I64_BINOP: Signature = (&[I64, I64], &[I64]);
+const I64_TESTOP: Signature = (&[I64], &[I32]);
+const I64_RELOP: Signature = (&[I64, I64], &[I32]);
+const I64_TO_F32: Signature = (&[I64], &[F32]);
+const I64_TO_F64: Signature = (&[I64], &[F64]);
+const I64_TO_I32: Signature = (&[I64], &[I32]);
+const I64_TO_V128: Signature = (&[I64], &[V128]);
+const I64_LOAD: Signature = (&[I32], &[I64]);
+const I64_STORE: Signature = (&[I32, I64], &[]);
+const I64_RMW: Signature = (&[I32, I64], &[I64]);
+const I64_CMPXCHG: Signature = (&[I32, I64, I64], &[I64]);
+const F32_UNOP: Signature = (&[F32], &[F32]);
+const F32_BINOP: Signature = (&[F32, F32], &[F32]);
+const F32_RELOP: Signature = (&[F32, F32], &[I32]);
+const F32_TO_F64: Signature = (&[F32], &[F64]);
+const F32_TO_I32: Signature = (&[F32], &[I32]);
+const F32_TO_I64: Signature = (&[F32], &[I64]);
+const F32_TO_V128: Signature = (&[F32], &[V128]);
+const F32_LOAD: Signature = (&[I32], &[F32]);
+const F32_STORE: Signature = (&[I32, F32], &[]);
+const F64_UNOP: Signature = (&[F64], &[F64]);
+const F64_BINOP: Signature = (&[F64, F64], &[F64]);
+const F64_RELOP: Signature = (&[F64, F64], &[I32]);
+const F64_TO_F32: Signature = (&[F64], &[F32]);
+const F64_TO_I32: Signature = (&[F64], &[I32]);
+const F64_TO_I64: Signature = (&[F64], &[I64]);
+const F64_TO_V128: Signature = (&[F64], &[V128]);
+const F64_LOAD: Signature = (&[I32], &[F64]);
+const F64_STORE: Signature = (&[I32, F64], &[]);
+const V128_UNOP: Signature = (&[V128], &[V128]);
+const V128_BINOP: Signature = (&[V128, V128], &[V128]);
+const V128_TERNOP: Signature = (&[V128, V128, V128], &[V128]);
+const V128_TESTOP: Signature = (&[V128], &[I32]);
+const V128_SHIFTOP: Signature = (&[V128, I32], &[V128]);
+const V128_TO_F32: Signature = (&[V128], &[F32]);
+const V128_TO_F64: Signature = (&[V128], &[F64]);
+const V128_TO_I32: Signature = (&[V128], &[I32]);
+const V128_TO_I64: Signature = (&[V128], &[I64]);
+const V128_REPLACE_F32: Signature = (&[V128, F32], &[V128]);
+const V128_REPLACE_F64: Signature = (&[V128, F64], &[V128]);
+const V128_REPLACE_I32: Signature = (&[V128, I32], &[V128]);
+const V128_REPLACE_I64: Signature = (&[V128, I64], &[V128]);
+const V128_LOAD: Signature = (&[I32], &[V128]);
+const V128_LOAD_LANE: Signature = (&[I32, V128], &[V128]);
+const V128_STORE: Signature = (&[I32, V128], &[]);
+
+// v128.constとi8x16.shuffleは即値の形が特殊なため、命令表には含めない
+pub static INSTRUCTIONS: &[Entry] = &[
+    polymorphic(I::Unreachable, "unreachable", Opcode::Single(0x00)),
+    plain(I::Nop, "nop", Opcode::Single(0x01), ty(&[], &[])),
+    polymorphic(I::Else, "else", Opcode::Single(0x05)),
+    polymorphic(I::ThrowRef, "throw_ref", Opcode::Single(0x0a)),
+    polymorphic(I::End, "end", Opcode::Single(0x0b)),
+    polymorphic(I::Return, "return", Opcode::Single(0x0f)),
+    polymorphic(I::RefIsNull, "ref.is_null", Opcode::Single(0xd1)),
+    polymorphic(I::Drop, "drop", Opcode::Single(0x1a)),
+    // Memory Instructions
+    memory(I::I32Load, "i32.load", Opcode::Single(0x28), 2, I32_LOAD),
+    memory(I::I64Load, "i64.load", Opcode::Single(0x29), 3, I64_LOAD),
+    memory(I::F32Load, "f32.load", Opcode::Single(0x2a), 2, F32_LOAD),
+    memory(I::F64Load, "f64.load", Opcode::Single(0x2b), 3, F64_LOAD),
+    memory(
+        I::I32Load8S,
+        "i32.load8_s",
+        Opcode::Single(0x2c),
+        0,
+        I32_LOAD,
+    ),
+    memory(
+        I::I32Load8U,
+        "i32.load8_u",
+        Opcode::Single(0x2d),
+        0,
+        I32_LOAD,
+    ),
+    memory(
+        I::I32Load16S,
+        "i32.load16_s",
+        Opcode::Single(0x2e),
+        1,
+        I32_LOAD,
+    ),
+    memory(
+        I::I32Load16U,
+        "i32.load16_u",
+        Opcode::Single(0x2f),
+        1,
+        I32_LOAD,
+    ),
+    memory(
+        I::I64Load8S,
+        "i64.load8_s",
+        Opcode::Single(0x30),
+        0,
+        I64_LOAD,
+    ),
+    memory(
+        I::I64Load8U,
+        "i64.load8_u",
+        Opcode::Single(0x31),
+        0,
+        I64_LOAD,
+    ),
+    memory(
+        I::I64Load16S,
+        "i64.load16_s",
+        Opcode::Single(0x32),
+        1,
+        I64_LOAD,
+    ),
+    memory(
+        I::I64Load16U,
+        "i64.load16_u",
+        Opcode::Single(0x33),
+        1,
+        I64_LOAD,
+    ),
+    memory(
+        I::I64Load32S,
+        "i64.load32_s",
+        Opcode::Single(0x34),
+        2,
+        I64_LOAD,
+    ),
+    memory(
+        I::I64Load32U,
+        "i64.load32_u",
+        Opcode::Single(0x35),
+        2,
+        I64_LOAD,
+    ),
+    memory(I::I32Store, "i32.store", Opcode::Single(0x36), 2, I32_STORE),
+    memory(I::I64Store, "i64.store", Opcode::Single(0x37), 3, I64_STORE),
+    memory(I::F32Store, "f32.store", Opcode::Single(0x38), 2, F32_STORE),
+    memory(I::F64Store, "f64.store", Opcode::Single(0x39), 3, F64_STORE),
+    memory(
+        I::I32Store8,
+        "i32.store8",
+        Opcode::Single(0x3a),
+        0,
+        I32_STORE,
+    ),
+    memory(
+        I::I32Store16,
+        "i32.store16",
+        Opcode::Single(0x3b),
+        1,
+        I32_STORE,
+    ),
+    memory(
+        I::I64Store8,
+        "i64.store8",
+        Opcode::Single(0x3c),
+        0,
+        I64_STORE,
+    ),
+    memory(
+        I::I64Store16,
+        "i64.store16",
+        Opcode::Single(0x3d),
+        1,
+        I64_STORE,
+    ),
+    memory(
+        I::I64Store32,
+        "i64.store32",
+        Opcode::Single(0x3e),
+        2,
+        I64_STORE,
+    ),
+    // Numeric Instructions
+    plain(I::I32Eqz, "i32.eqz", Opcode::Single(0x45), I32_TESTOP),
+    plain(I::I32Eq, "i32.eq", Opcode::Single(0x46), I32_RELOP),
+    plain(I::I32Ne, "i32.ne", Opcode::Single(0x47), I32_RELOP),
+    plain(I::I32LtS, "i32.lt_s", Opcode::Single(0x48), I32_RELOP),
+    plain(I::I32LtU, "i32.lt_u", Opcode::Single(0x49), I32_RELOP),
+    plain(I::I32GtS, "i32.gt_s", Opcode::Single(0x4a), I32_RELOP),
+    plain(I::I32GtU, "i32.gt_u", Opcode::Single(0x4b), I32_RELOP),
+    plain(I::I32LeS, "i32.le_s", Opcode::Single(0x4c), I32_RELOP),
+    plain(I::I32LeU, "i32.le_u", Opcode::Single(0x4d), I32_RELOP),
+    plain(I::I32GeS, "i32.ge_s", Opcode::Single(0x4e), I32_RELOP),
+    plain(I::I32GeU, "i32.ge_u", Opcode::Single(0x4f), I32_RELOP),
+    plain(I::I64Eqz, "i64.eqz", Opcode::Single(0x50), I64_TESTOP),
+    plain(I::I64Eq, "i64.eq", Opcode::Single(0x51), I64_RELOP),
+    plain(I::I64Ne, "i64.ne", Opcode::Single(0x52), I64_RELOP),
+    plain(I::I64LtS, "i64.lt_s", Opcode::Single(0x53), I64_RELOP),
+    plain(I::I64LtU, "i64.lt_u", Opcode::Single(0x54), I64_RELOP),
+    plain(I::I64GtS, "i64.gt_s", Opcode::Single(0x55), I64_RELOP),
+    plain(I::I64GtU, "i64.gt_u", Opcode::Single(0x56), I64_RELOP),
+    plain(I::I64LeS, "i64.le_s", Opcode::Single(0x57), I64_RELOP),
+    plain(I::I64LeU, "i64.le_u", Opcode::Single(0x58), I64_RELOP),
+    plain(I::I64GeS, "i64.ge_s", Opcode::Single(0x59), I64_RELOP),
+    plain(I::I64GeU, "i64.ge_u", Opcode::Single(0x5a), I64_RELOP),
+    plain(I::F32Eq, "f32.eq", Opcode::Single(0x5b), F32_RELOP),
+    plain(I::F32Ne, "f32.ne", Opcode::Single(0x5c), F32_RELOP),
+    plain(I::F32Lt, "f32.lt", Opcode::Single(0x5d), F32_RELOP),
+    plain(I::F32Gt, "f32.gt", Opcode::Single(0x5e), F32_RELOP),
+    plain(I::F32Le, "f32.le", Opcode::Single(0x5f), F32_RELOP),
+    plain(I::F32Ge, "f32.ge", Opcode::Single(0x60), F32_RELOP),
+    plain(I::F64Eq, "f64.eq", Opcode::Single(0x61), F64_RELOP),
+    plain(I::F64Ne, "f64.ne", Opcode::Single(0x62), F64_RELOP),
+    plain(I::F64Lt, "f64.lt", Opcode::Single(0x63), F64_RELOP),
+    plain(I::F64Gt, "f64.gt", Opcode::Single(0x64), F64_RELOP),
+    plain(I::F64Le, "f64.le", Opcode::Single(0x65), F64_RELOP),
+    plain(I::F64Ge, "f64.ge", Opcode::Single(0x66), F64_RELOP),
+    plain(I::I32Clz, "i32.clz", Opcode::Single(0x67), I32_UNOP),
+    plain(I::I32Ctz, "i32.ctz", Opcode::Single(0x68), I32_UNOP),
+    plain(I::I32Popcnt, "i32.popcnt", Opcode::Single(0x69), I32_UNOP),
+    plain(I::I32Add, "i32.add", Opcode::Single(0x6a), I32_BINOP),
+    plain(I::I32Sub, "i32.sub", Opcode::Single(0x6b), I32_BINOP),
+    plain(I::I32Mul, "i32.mul", Opcode::Single(0x6c), I32_BINOP),
+    plain(I::I32DivS, "i32.div_s", Opcode::Single(0x6d), I32_BINOP),
+    plain(I::I32DivU, "i32.div_u", Opcode::Single(0x6e), I32_BINOP),
+    plain(I::I32RemS, "i32.rem_s", Opcode::Single(0x6f), I32_BINOP),
+    plain(I::I32RemU, "i32.rem_u", Opcode::Single(0x70), I32_BINOP),
+    plain(I::I32And, "i32.and", Opcode::Single(0x71), I32_BINOP),
+    plain(I::I32Or, "i32.or", Opcode::Single(0x72), I32_BINOP),
+    plain(I::I32Xor, "i32.xor", Opcode::Single(0x73), I32_BINOP),
+    plain(I::I32Shl, "i32.shl", Opcode::Single(0x74), I32_BINOP),
+    plain(I::I32ShrS, "i32.shr_s", Opcode::Single(0x75), I32_BINOP),
+    plain(I::I32ShrU, "i32.shr_u", Opcode::Single(0x76), I32_BINOP),
+    plain(I::I32Rotl, "i32.rotl", Opcode::Single(0x77), I32_BINOP),
+    plain(I::I32Rotr, "i32.rotr", Opcode::Single(0x78), I32_BINOP),
+    plain(I::I64Clz, "i64.clz", Opcode::Single(0x79), I64_UNOP),
+    plain(I::I64Ctz, "i64.ctz", Opcode::Single(0x7a), I64_UNOP),
+    plain(I::I64Popcnt, "i64.popcnt", Opcode::Single(0x7b), I64_UNOP),
+    plain(I::I64Add, "i64.add", Opcode::Single(0x7c), I64_BINOP),
+    plain(I::I64Sub, "i64.sub", Opcode::Single(0x7d), I64_BINOP),
+    plain(I::I64Mul, "i64.mul", Opcode::Single(0x7e), I64_BINOP),
+    plain(I::I64DivS, "i64.div_s", Opcode::Single(0x7f), I64_BINOP),
+    plain(I::I64DivU, "i64.div_u", Opcode::Single(0x80), I64_BINOP),
+    plain(I::I64RemS, "i64.rem_s", Opcode::Single(0x81), I64_BINOP),
+    plain(I::I64RemU, "i64.rem_u", Opcode::Single(0x82), I64_BINOP),
+    plain(I::I64And, "i64.and", Opcode::Single(0x83), I64_BINOP),
+    plain(I::I64Or, "i64.or", Opcode::Single(0x84), I64_BINOP),
+    plain(I::I64Xor, "i64.xor", Opcode::Single(0x85), I64_BINOP),
+    plain(I::I64Shl, "i64.shl", Opcode::Single(0x86), I64_BINOP),
+    plain(I::I64ShrS, "i64.shr_s", Opcode::Single(0x87), I64_BINOP),
+    plain(I::I64ShrU, "i64.shr_u", Opcode::Single(0x88), I64_BINOP),
+    plain(I::I64Rotl, "i64.rotl", Opcode::Single(0x89), I64_BINOP),
+    plain(I::I64Rotr, "i64.rotr", Opcode::Single(0x8a), I64_BINOP),
+    plain(I::F32Abs, "f32.abs", Opcode::Single(0x8b), F32_UNOP),
+    plain(I::F32Neg, "f32.neg", Opcode::Single(0x8c), F32_UNOP),
+    plain(I::F32Ceil, "f32.ceil", Opcode::Single(0x8d), F32_UNOP),
+    plain(I::F32Floor, "f32.floor", Opcode::Single(0x8e), F32_UNOP),
+    plain(I::F32Trunc, "f32.trunc", Opcode::Single(0x8f), F32_UNOP),
+    plain(I::F32Nearest, "f32.nearest", Opcode::Single(0x90), F32_UNOP),
+    plain(I::F32Sqrt, "f32.sqrt", Opcode::Single(0x91), F32_UNOP),
+    plain(I::F32Add, "f32.add", Opcode::Single(0x92), F32_BINOP),
+    plain(I::F32Sub, "f32.sub", Opcode::Single(0x93), F32_BINOP),
+    plain(I::F32Mul, "f32.mul", Opcode::Single(0x94), F32_BINOP),
+    plain(I::F32Div, "f32.div", Opcode::Single(0x95), F32_BINOP),
+    plain(I::F32Min, "f32.min", Opcode::Single(0x96), F32_BINOP),
+    plain(I::F32Max, "f32.max", Opcode::Single(0x97), F32_BINOP),
+    plain(
+        I::F32Copysign,
+        "f32.copysign",
+        Opcode::Single(0x98),
+        F32_BINOP,
+    ),
+    plain(I::F64Abs, "f64.abs", Opcode::Single(0x99), F64_UNOP),
+    plain(I::F64Neg, "f64.neg", Opcode::Single(0x9a), F64_UNOP),
+    plain(I::F64Ceil, "f64.ceil", Opcode::Single(0x9b), F64_UNOP),
+    plain(I::F64Floor, "f64.floor", Opcode::Single(0x9c), F64_UNOP),
+    plain(I::F64Trunc, "f64.trunc", Opcode::Single(0x9d), F64_UNOP),
+    plain(I::F64Nearest, "f64.nearest", Opcode::Single(0x9e), F64_UNOP),
+    plain(I::F64Sqrt, "f64.sqrt", Opcode::Single(0x9f), F64_UNOP),
+    plain(I::F64Add, "f64.add", Opcode::Single(0xa0), F64_BINOP),
+    plain(I::F64Sub, "f64.sub", Opcode::Single(0xa1), F64_BINOP),
+    plain(I::F64Mul, "f64.mul", Opcode::Single(0xa2), F64_BINOP),
+    plain(I::F64Div, "f64.div", Opcode::Single(0xa3), F64_BINOP),
+    plain(I::F64Min, "f64.min", Opcode::Single(0xa4), F64_BINOP),
+    plain(I::F64Max, "f64.max", Opcode::Single(0xa5), F64_BINOP),
+    plain(
+        I::F64Copysign,
+        "f64.copysign",
+        Opcode::Single(0xa6),
+        F64_BINOP,
+    ),
+    plain(
+        I::I32WrapI64,
+        "i32.wrap_i64",
+        Opcode::Single(0xa7),
+        I64_TO_I32,
+    ),
+    plain(
+        I::I32TruncF32S,
+        "i32.trunc_f32_s",
+        Opcode::Single(0xa8),
+        F32_TO_I32,
+    ),
+    plain(
+        I::I32TruncF32U,
+        "i32.trunc_f32_u",
+        Opcode::Single(0xa9),
+        F32_TO_I32,
+    ),
+    plain(
+        I::I32TruncF64S,
+        "i32.trunc_f64_s",
+        Opcode::Single(0xaa),
+        F64_TO_I32,
+    ),
+    plain(
+        I::I32TruncF64U,
+        "i32.trunc_f64_u",
+        Opcode::Single(0xab),
+        F64_TO_I32,
+    ),
+    plain(
+        I::I64ExtendI32S,
+        "i64.extend_i32_s",
+        Opcode::Single(0xac),
+        I32_TO_I64,
+    ),
+    plain(
+        I::I64ExtendI32U,
+        "i64.extend_i32_u",
+        Opcode::Single(0xad),
+        I32_TO_I64,
+    ),
+    plain(
+        I::I64TruncF32S,
+        "i64.trunc_f32_s",
+        Opcode::Single(0xae),
+        F32_TO_I64,
+    ),
+    plain(
+        I::I64TruncF32U,
+        "i64.trunc_f32_u",
+        Opcode::Single(0xaf),
+        F32_TO_I64,
+    ),
+    plain(
+        I::I64TruncF64S,
+        "i64.trunc_f64_s",
+        Opcode::Single(0xb0),
+        F64_TO_I64,
+    ),
+    plain(
+        I::I64TruncF64U,
+        "i64.trunc_f64_u",
+        Opcode::Single(0xb1),
+        F64_TO_I64,
+    ),
+    plain(
+        I::F32ConvertI32S,
+        "f32.convert_i32_s",
+        Opcode::Single(0xb2),
+        I32_TO_F32,
+    ),
+    plain(
+        I::F32ConvertI32U,
+        "f32.convert_i32_u",
+        Opcode::Single(0xb3),
+        I32_TO_F32,
+    ),
+    plain(
+        I::F32ConvertI64S,
+        "f32.convert_i64_s",
+        Opcode::Single(0xb4),
+        I64_TO_F32,
+    ),
+    plain(
+        I::F32ConvertI64U,
+        "f32.convert_i64_u",
+        Opcode::Single(0xb5),
+        I64_TO_F32,
+    ),
+    plain(
+        I::F32DemoteF64,
+        "f32.demote_f64",
+        Opcode::Single(0xb6),
+        F64_TO_F32,
+    ),
+    plain(
+        I::F64ConvertI32S,
+        "f64.convert_i32_s",
+        Opcode::Single(0xb7),
+        I32_TO_F64,
+    ),
+    plain(
+        I::F64ConvertI32U,
+        "f64.convert_i32_u",
+        Opcode::Single(0xb8),
+        I32_TO_F64,
+    ),
+    plain(
+        I::F64ConvertI64S,
+        "f64.convert_i64_s",
+        Opcode::Single(0xb9),
+        I64_TO_F64,
+    ),
+    plain(
+        I::F64ConvertI64U,
+        "f64.convert_i64_u",
+        Opcode::Single(0xba),
+        I64_TO_F64,
+    ),
+    plain(
+        I::F64PromoteF32,
+        "f64.promote_f32",
+        Opcode::Single(0xbb),
+        F32_TO_F64,
+    ),
+    plain(
+        I::I32ReinterpretF32,
+        "i32.reinterpret_f32",
+        Opcode::Single(0xbc),
+        F32_TO_I32,
+    ),
+    plain(
+        I::I64ReinterpretF64,
+        "i64.reinterpret_f64",
+        Opcode::Single(0xbd),
+        F64_TO_I64,
+    ),
+    plain(
+        I::F32ReinterpretI32,
+        "f32.reinterpret_i32",
+        Opcode::Single(0xbe),
+        I32_TO_F32,
+    ),
+    plain(
+        I::F64ReinterpretI64,
+        "f64.reinterpret_i64",
+        Opcode::Single(0xbf),
+        I64_TO_F64,
+    ),
+    plain(
+        I::I32Extend8S,
+        "i32.extend8_s",
+        Opcode::Single(0xc0),
+        I32_UNOP,
+    ),
+    plain(
+        I::I32Extend16S,
+        "i32.extend16_s",
+        Opcode::Single(0xc1),
+        I32_UNOP,
+    ),
+    plain(
+        I::I64Extend8S,
+        "i64.extend8_s",
+        Opcode::Single(0xc2),
+        I64_UNOP,
+    ),
+    plain(
+        I::I64Extend16S,
+        "i64.extend16_s",
+        Opcode::Single(0xc3),
+        I64_UNOP,
+    ),
+    plain(
+        I::I64Extend32S,
+        "i64.extend32_s",
+        Opcode::Single(0xc4),
+        I64_UNOP,
+    ),
+    plain(
+        I::I32TruncSatF32S,
+        "i32.trunc_sat_f32_s",
+        misc(0x00),
+        F32_TO_I32,
+    ),
+    plain(
+        I::I32TruncSatF32U,
+        "i32.trunc_sat_f32_u",
+        misc(0x01),
+        F32_TO_I32,
+    ),
+    plain(
+        I::I32TruncSatF64S,
+        "i32.trunc_sat_f64_s",
+        misc(0x02),
+        F64_TO_I32,
+    ),
+    plain(
+        I::I32TruncSatF64U,
+        "i32.trunc_sat_f64_u",
+        misc(0x03),
+        F64_TO_I32,
+    ),
+    plain(
+        I::I64TruncSatF32S,
+        "i64.trunc_sat_f32_s",
+        misc(0x04),
+        F32_TO_I64,
+    ),
+    plain(
+        I::I64TruncSatF32U,
+        "i64.trunc_sat_f32_u",
+        misc(0x05),
+        F32_TO_I64,
+    ),
+    plain(
+        I::I64TruncSatF64S,
+        "i64.trunc_sat_f64_s",
+        misc(0x06),
+        F64_TO_I64,
+    ),
+    plain(
+        I::I64TruncSatF64U,
+        "i64.trunc_sat_f64_u",
+        misc(0x07),
+        F64_TO_I64,
+    ),
+    // Atomic Instructions
+    // atomic.fenceはバイナリ形式で0x00の予約バイトを持つが、テキスト形式では即値を持たない
+    memory(
+        I::MemoryAtomicNotify,
+        "memory.atomic.notify",
+        atomic(0x00),
+        2,
+        ty(&[I32, I32], &[I32]),
+    ),
+    memory(
+        I::MemoryAtomicWait32,
+        "memory.atomic.wait32",
+        atomic(0x01),
+        2,
+        ty(&[I32, I32, I64], &[I32]),
+    ),
+    memory(
+        I::MemoryAtomicWait64,
+        "memory.atomic.wait64",
+        atomic(0x02),
+        3,
+        ty(&[I32, I64, I64], &[I32]),
+    ),
+    plain(I::AtomicFence, "atomic.fence", atomic(0x03), ty(&[], &[])),
+    memory(
+        I::I32AtomicLoad,
+        "i32.atomic.load",
+        atomic(0x10),
+        2,
+        I32_LOAD,
+    ),
+    memory(
+        I::I64AtomicLoad,
+        "i64.atomic.load",
+        atomic(0x11),
+        3,
+        I64_LOAD,
+    ),
+    memory(
+        I::I32AtomicLoad8U,
+        "i32.atomic.load8_u",
+        atomic(0x12),
+        0,
+        I32_LOAD,
+    ),
+    memory(
+        I::I32AtomicLoad16U,
+        "i32.atomic.load16_u",
+        atomic(0x13),
+        1,
+        I32_LOAD,
+    ),
+    memory(
+        I::I64AtomicLoad8U,
+        "i64.atomic.load8_u",
+        atomic(0x14),
+        0,
+        I64_LOAD,
+    ),
+    memory(
+        I::I64AtomicLoad16U,
+        "i64.atomic.load16_u",
+        atomic(0x15),
+        1,
+        I64_LOAD,
+    ),
+    memory(
+        I::I64AtomicLoad32U,
+        "i64.atomic.load32_u",
+        atomic(0x16),
+        2,
+        I64_LOAD,
+    ),
+    memory(
+        I::I32AtomicStore,
+        "i32.atomic.store",
+        atomic(0x17),
+        2,
+        I32_STORE,
+    ),
+    memory(
+        I::I64AtomicStore,
+        "i64.atomic.store",
+        atomic(0x18),
+        3,
+        I64_STORE,
+    ),
+    memory(
+        I::I32AtomicStore8,
+        "i32.atomic.store8",
+        atomic(0x19),
+        0,
+        I32_STORE,
+    ),
+    memory(
+        I::I32AtomicStore16,
+        "i32.atomic.store16",
+        atomic(0x1a),
+        1,
+        I32_STORE,
+    ),
+    memory(
+        I::I64AtomicStore8,
+        "i64.atomic.store8",
+        atomic(0x1b),
+        0,
+        I64_STORE,
+    ),
+    memory(
+        I::I64AtomicStore16,
+        "i64.atomic.store16",
+        atomic(0x1c),
+        1,
+        I64_STORE,
+    ),
+    memory(
+        I::I64AtomicStore32,
+        "i64.atomic.store32",
+        atomic(0x1d),
+        2,
+        I64_STORE,
+    ),
+    memory(
+        I::I32AtomicRmwAdd,
+        "i32.atomic.rmw.add",
+        atomic(0x1e),
+        2,
+        I32_RMW,
+    ),
+    memory(
+        I::I64AtomicRmwAdd,
+        "i64.atomic.rmw.add",
+        atomic(0x1f),
+        3,
+        I64_RMW,
+    ),
+    memory(
+        I::I32AtomicRmw8AddU,
+        "i32.atomic.rmw8.add_u",
+        atomic(0x20),
+        0,
+        I32_RMW,
+    ),
+    memory(
+        I::I32AtomicRmw16AddU,
+        "i32.atomic.rmw16.add_u",
+        atomic(0x21),
+        1,
+        I32_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw8AddU,
+        "i64.atomic.rmw8.add_u",
+        atomic(0x22),
+        0,
+        I64_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw16AddU,
+        "i64.atomic.rmw16.add_u",
+        atomic(0x23),
+        1,
+        I64_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw32AddU,
+        "i64.atomic.rmw32.add_u",
+        atomic(0x24),
+        2,
+        I64_RMW,
+    ),
+    memory(
+        I::I32AtomicRmwSub,
+        "i32.atomic.rmw.sub",
+        atomic(0x25),
+        2,
+        I32_RMW,
+    ),
+    memory(
+        I::I64AtomicRmwSub,
+        "i64.atomic.rmw.sub",
+        atomic(0x26),
+        3,
+        I64_RMW,
+    ),
+    memory(
+        I::I32AtomicRmw8SubU,
+        "i32.atomic.rmw8.sub_u",
+        atomic(0x27),
+        0,
+        I32_RMW,
+    ),
+    memory(
+        I::I32AtomicRmw16SubU,
+        "i32.atomic.rmw16.sub_u",
+        atomic(0x28),
+        1,
+        I32_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw8SubU,
+        "i64.atomic.rmw8.sub_u",
+        atomic(0x29),
+        0,
+        I64_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw16SubU,
+        "i64.atomic.rmw16.sub_u",
+        atomic(0x2a),
+        1,
+        I64_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw32SubU,
+        "i64.atomic.rmw32.sub_u",
+        atomic(0x2b),
+        2,
+        I64_RMW,
+    ),
+    memory(
+        I::I32AtomicRmwAnd,
+        "i32.atomic.rmw.and",
+        atomic(0x2c),
+        2,
+        I32_RMW,
+    ),
+    memory(
+        I::I64AtomicRmwAnd,
+        "i64.atomic.rmw.and",
+        atomic(0x2d),
+        3,
+        I64_RMW,
+    ),
+    memory(
+        I::I32AtomicRmw8AndU,
+        "i32.atomic.rmw8.and_u",
+        atomic(0x2e),
+        0,
+        I32_RMW,
+    ),
+    memory(
+        I::I32AtomicRmw16AndU,
+        "i32.atomic.rmw16.and_u",
+        atomic(0x2f),
+        1,
+        I32_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw8AndU,
+        "i64.atomic.rmw8.and_u",
+        atomic(0x30),
+        0,
+        I64_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw16AndU,
+        "i64.atomic.rmw16.and_u",
+        atomic(0x31),
+        1,
+        I64_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw32AndU,
+        "i64.atomic.rmw32.and_u",
+        atomic(0x32),
+        2,
+        I64_RMW,
+    ),
+    memory(
+        I::I32AtomicRmwOr,
+        "i32.atomic.rmw.or",
+        atomic(0x33),
+        2,
+        I32_RMW,
+    ),
+    memory(
+        I::I64AtomicRmwOr,
+        "i64.atomic.rmw.or",
+        atomic(0x34),
+        3,
+        I64_RMW,
+    ),
+    memory(
+        I::I32AtomicRmw8OrU,
+        "i32.atomic.rmw8.or_u",
+        atomic(0x35),
+        0,
+        I32_RMW,
+    ),
+    memory(
+        I::I32AtomicRmw16OrU,
+        "i32.atomic.rmw16.or_u",
+        atomic(0x36),
+        1,
+        I32_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw8OrU,
+        "i64.atomic.rmw8.or_u",
+        atomic(0x37),
+        0,
+        I64_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw16OrU,
+        "i64.atomic.rmw16.or_u",
+        atomic(0x38),
+        1,
+        I64_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw32OrU,
+        "i64.atomic.rmw32.or_u",
+        atomic(0x39),
+        2,
+        I64_RMW,
+    ),
+    memory(
+        I::I32AtomicRmwXor,
+        "i32.atomic.rmw.xor",
+        atomic(0x3a),
+        2,
+        I32_RMW,
+    ),
+    memory(
+        I::I64AtomicRmwXor,
+        "i64.atomic.rmw.xor",
+        atomic(0x3b),
+        3,
+        I64_RMW,
+    ),
+    memory(
+        I::I32AtomicRmw8XorU,
+        "i32.atomic.rmw8.xor_u",
+        atomic(0x3c),
+        0,
+        I32_RMW,
+    ),
+    memory(
+        I::I32AtomicRmw16XorU,
+        "i32.atomic.rmw16.xor_u",
+        atomic(0x3d),
+        1,
+        I32_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw8XorU,
+        "i64.atomic.rmw8.xor_u",
+        atomic(0x3e),
+        0,
+        I64_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw16XorU,
+        "i64.atomic.rmw16.xor_u",
+        atomic(0x3f),
+        1,
+        I64_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw32XorU,
+        "i64.atomic.rmw32.xor_u",
+        atomic(0x40),
+        2,
+        I64_RMW,
+    ),
+    memory(
+        I::I32AtomicRmwXchg,
+        "i32.atomic.rmw.xchg",
+        atomic(0x41),
+        2,
+        I32_RMW,
+    ),
+    memory(
+        I::I64AtomicRmwXchg,
+        "i64.atomic.rmw.xchg",
+        atomic(0x42),
+        3,
+        I64_RMW,
+    ),
+    memory(
+        I::I32AtomicRmw8XchgU,
+        "i32.atomic.rmw8.xchg_u",
+        atomic(0x43),
+        0,
+        I32_RMW,
+    ),
+    memory(
+        I::I32AtomicRmw16XchgU,
+        "i32.atomic.rmw16.xchg_u",
+        atomic(0x44),
+        1,
+        I32_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw8XchgU,
+        "i64.atomic.rmw8.xchg_u",
+        atomic(0x45),
+        0,
+        I64_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw16XchgU,
+        "i64.atomic.rmw16.xchg_u",
+        atomic(0x46),
+        1,
+        I64_RMW,
+    ),
+    memory(
+        I::I64AtomicRmw32XchgU,
+        "i64.atomic.rmw32.xchg_u",
+        atomic(0x47),
+        2,
+        I64_RMW,
+    ),
+    memory(
+        I::I32AtomicRmwCmpxchg,
+        "i32.atomic.rmw.cmpxchg",
+        atomic(0x48),
+        2,
+        I32_CMPXCHG,
+    ),
+    memory(
+        I::I64AtomicRmwCmpxchg,
+        "i64.atomic.rmw.cmpxchg",
+        atomic(0x49),
+        3,
+        I64_CMPXCHG,
+    ),
+    memory(
+        I::I32AtomicRmw8CmpxchgU,
+        "i32.atomic.rmw8.cmpxchg_u",
+        atomic(0x4a),
+        0,
+        I32_CMPXCHG,
+    ),
+    memory(
+        I::I32AtomicRmw16CmpxchgU,
+        "i32.atomic.rmw16.cmpxchg_u",
+        atomic(0x4b),
+        1,
+        I32_CMPXCHG,
+    ),
+    memory(
+        I::I64AtomicRmw8CmpxchgU,
+        "i64.atomic.rmw8.cmpxchg_u",
+        atomic(0x4c),
+        0,
+        I64_CMPXCHG,
+    ),
+    memory(
+        I::I64AtomicRmw16CmpxchgU,
+        "i64.atomic.rmw16.cmpxchg_u",
+        atomic(0x4d),
+        1,
+        I64_CMPXCHG,
+    ),
+    memory(
+        I::I64AtomicRmw32CmpxchgU,
+        "i64.atomic.rmw32.cmpxchg_u",
+        atomic(0x4e),
+        2,
+        I64_CMPXCHG,
+    ),
+    // Vector Instructions
+    memory(I::V128Load, "v128.load", simd(0x00), 4, V128_LOAD),
+    memory(I::V128Load8x8S, "v128.load8x8_s", simd(0x01), 3, V128_LOAD),
+    memory(I::V128Load8x8U, "v128.load8x8_u", simd(0x02), 3, V128_LOAD),
+    memory(
+        I::V128Load16x4S,
+        "v128.load16x4_s",
+        simd(0x03),
+        3,
+        V128_LOAD,
+    ),
+    memory(
+        I::V128Load16x4U,
+        "v128.load16x4_u",
+        simd(0x04),
+        3,
+        V128_LOAD,
+    ),
+    memory(
+        I::V128Load32x2S,
+        "v128.load32x2_s",
+        simd(0x05),
+        3,
+        V128_LOAD,
+    ),
+    memory(
+        I::V128Load32x2U,
+        "v128.load32x2_u",
+        simd(0x06),
+        3,
+        V128_LOAD,
+    ),
+    memory(
+        I::V128Load8Splat,
+        "v128.load8_splat",
+        simd(0x07),
+        0,
+        V128_LOAD,
+    ),
+    memory(
+        I::V128Load16Splat,
+        "v128.load16_splat",
+        simd(0x08),
+        1,
+        V128_LOAD,
+    ),
+    memory(
+        I::V128Load32Splat,
+        "v128.load32_splat",
+        simd(0x09),
+        2,
+        V128_LOAD,
+    ),
+    memory(
+        I::V128Load64Splat,
+        "v128.load64_splat",
+        simd(0x0a),
+        3,
+        V128_LOAD,
+    ),
+    memory(I::V128Store, "v128.store", simd(0x0b), 4, V128_STORE),
+    plain(I::I8x16Swizzle, "i8x16.swizzle", simd(0x0e), V128_BINOP),
+    plain(I::I8x16Splat, "i8x16.splat", simd(0x0f), I32_TO_V128),
+    plain(I::I16x8Splat, "i16x8.splat", simd(0x10), I32_TO_V128),
+    plain(I::I32x4Splat, "i32x4.splat", simd(0x11), I32_TO_V128),
+    plain(I::I64x2Splat, "i64x2.splat", simd(0x12), I64_TO_V128),
+    plain(I::F32x4Splat, "f32x4.splat", simd(0x13), F32_TO_V128),
+    plain(I::F64x2Splat, "f64x2.splat", simd(0x14), F64_TO_V128),
+    lane(
+        I::I8x16ExtractLaneS,
+        "i8x16.extract_lane_s",
+        simd(0x15),
+        16,
+        V128_TO_I32,
+    ),
+    lane(
+        I::I8x16ExtractLaneU,
+        "i8x16.extract_lane_u",
+        simd(0x16),
+        16,
+        V128_TO_I32,
+    ),
+    lane(
+        I::I8x16ReplaceLane,
+        "i8x16.replace_lane",
+        simd(0x17),
+        16,
+        V128_REPLACE_I32,
+    ),
+    lane(
+        I::I16x8ExtractLaneS,
+        "i16x8.extract_lane_s",
+        simd(0x18),
+        8,
+        V128_TO_I32,
+    ),
+    lane(
+        I::I16x8ExtractLaneU,
+        "i16x8.extract_lane_u",
+        simd(0x19),
+        8,
+        V128_TO_I32,
+    ),
+    lane(
+        I::I16x8ReplaceLane,
+        "i16x8.replace_lane",
+        simd(0x1a),
+        8,
+        V128_REPLACE_I32,
+    ),
+    lane(
+        I::I32x4ExtractLane,
+        "i32x4.extract_lane",
+        simd(0x1b),
+        4,
+        V128_TO_I32,
+    ),
+    lane(
+        I::I32x4ReplaceLane,
+        "i32x4.replace_lane",
+        simd(0x1c),
+        4,
+        V128_REPLACE_I32,
+    ),
+    lane(
+        I::I64x2ExtractLane,
+        "i64x2.extract_lane",
+        simd(0x1d),
+        2,
+        V128_TO_I64,
+    ),
+    lane(
+        I::I64x2ReplaceLane,
+        "i64x2.replace_lane",
+        simd(0x1e),
+        2,
+        V128_REPLACE_I64,
+    ),
+    lane(
+        I::F32x4ExtractLane,
+        "f32x4.extract_lane",
+        simd(0x1f),
+        4,
+        V128_TO_F32,
+    ),
+    lane(
+        I::F32x4ReplaceLane,
+        "f32x4.replace_lane",
+        simd(0x20),
+        4,
+        V128_REPLACE_F32,
+    ),
+    lane(
+        I::F64x2ExtractLane,
+        "f64x2.extract_lane",
+        simd(0x21),
+        2,
+        V128_TO_F64,
+    ),
+    lane(
+        I::F64x2ReplaceLane,
+        "f64x2.replace_lane",
+        simd(0x22),
+        2,
+        V128_REPLACE_F64,
+    ),
+    plain(I::I8x16Eq, "i8x16.eq", simd(0x23), V128_BINOP),
+    plain(I::I8x16Ne, "i8x16.ne", simd(0x24), V128_BINOP),
+    plain(I::I8x16LtS, "i8x16.lt_s", simd(0x25), V128_BINOP),
+    plain(I::I8x16LtU, "i8x16.lt_u", simd(0x26), V128_BINOP),
+    plain(I::I8x16GtS, "i8x16.gt_s", simd(0x27), V128_BINOP),
+    plain(I::I8x16GtU, "i8x16.gt_u", simd(0x28), V128_BINOP),
+    plain(I::I8x16LeS, "i8x16.le_s", simd(0x29), V128_BINOP),
+    plain(I::I8x16LeU, "i8x16.le_u", simd(0x2a), V128_BINOP),
+    plain(I::I8x16GeS, "i8x16.ge_s", simd(0x2b), V128_BINOP),
+    plain(I::I8x16GeU, "i8x16.ge_u", simd(0x2c), V128_BINOP),
+    plain(I::I16x8Eq, "i16x8.eq", simd(0x2d), V128_BINOP),
+    plain(I::I16x8Ne, "i16x8.ne", simd(0x2e), V128_BINOP),
+    plain(I::I16x8LtS, "i16x8.lt_s", simd(0x2f), V128_BINOP),
+    plain(I::I16x8LtU, "i16x8.lt_u", simd(0x30), V128_BINOP),
+    plain(I::I16x8GtS, "i16x8.gt_s", simd(0x31), V128_BINOP),
+    plain(I::I16x8GtU, "i16x8.gt_u", simd(0x32), V128_BINOP),
+    plain(I::I16x8LeS, "i16x8.le_s", simd(0x33), V128_BINOP),
+    plain(I::I16x8LeU, "i16x8.le_u", simd(0x34), V128_BINOP),
+    plain(I::I16x8GeS, "i16x8.ge_s", simd(0x35), V128_BINOP),
+    plain(I::I16x8GeU, "i16x8.ge_u", simd(0x36), V128_BINOP),
+    plain(I::I32x4Eq, "i32x4.eq", simd(0x37), V128_BINOP),
+    plain(I::I32x4Ne, "i32x4.ne", simd(0x38), V128_BINOP),
+    plain(I::I32x4LtS, "i32x4.lt_s", simd(0x39), V128_BINOP),
+    plain(I::I32x4LtU, "i32x4.lt_u", simd(0x3a), V128_BINOP),
+    plain(I::I32x4GtS, "i32x4.gt_s", simd(0x3b), V128_BINOP),
+    plain(I::I32x4GtU, "i32x4.gt_u", simd(0x3c), V128_BINOP),
+    plain(I::I32x4LeS, "i32x4.le_s", simd(0x3d), V128_BINOP),
+    plain(I::I32x4LeU, "i32x4.le_u", simd(0x3e), V128_BINOP),
+    plain(I::I32x4GeS, "i32x4.ge_s", simd(0x3f), V128_BINOP),
+    plain(I::I32x4GeU, "i32x4.ge_u", simd(0x40), V128_BINOP),
+    plain(I::F32x4Eq, "f32x4.eq", simd(0x41), V128_BINOP),
+    plain(I::F32x4Ne, "f32x4.ne", simd(0x42), V128_BINOP),
+    plain(I::F32x4Lt, "f32x4.lt", simd(0x43), V128_BINOP),
+    plain(I::F32x4Gt, "f32x4.gt", simd(0x44), V128_BINOP),
+    plain(I::F32x4Le, "f32x4.le", simd(0x45), V128_BINOP),
+    plain(I::F32x4Ge, "f32x4.ge", simd(0x46), V128_BINOP),
+    plain(I::F64x2Eq, "f64x2.eq", simd(0x47), V128_BINOP),
+    plain(I::F64x2Ne, "f64x2.ne", simd(0x48), V128_BINOP),
+    plain(I::F64x2Lt, "f64x2.lt", simd(0x49), V128_BINOP),
+    plain(I::F64x2Gt, "f64x2.gt", simd(0x4a), V128_BINOP),
+    plain(I::F64x2Le, "f64x2.le", simd(0x4b), V128_BINOP),
+    plain(I::F64x2Ge, "f64x2.ge", simd(0x4c), V128_BINOP),
+    plain(I::V128Not, "v128.not", simd(0x4d), V128_UNOP),
+    plain(I::V128And, "v128.and", simd(0x4e), V128_BINOP),
+    plain(I::V128Andnot, "v128.andnot", simd(0x4f), V128_BINOP),
+    plain(I::V128Or, "v128.or", simd(0x50), V128_BINOP),
+    plain(I::V128Xor, "v128.xor", simd(0x51), V128_BINOP),
+    plain(I::V128Bitselect, "v128.bitselect", simd(0x52), V128_TERNOP),
+    plain(I::V128AnyTrue, "v128.any_true", simd(0x53), V128_TESTOP),
+    memory_lane(
+        I::V128Load8Lane,
+        "v128.load8_lane",
+        simd(0x54),
+        0,
+        16,
+        V128_LOAD_LANE,
+    ),
+    memory_lane(
+        I::V128Load16Lane,
+        "v128.load16_lane",
+        simd(0x55),
+        1,
+        8,
+        V128_LOAD_LANE,
+    ),
+    memory_lane(
+        I::V128Load32Lane,
+        "v128.load32_lane",
+        simd(0x56),
+        2,
+        4,
+        V128_LOAD_LANE,
+    ),
+    memory_lane(
+        I::V128Load64Lane,
+        "v128.load64_lane",
+        simd(0x57),
+        3,
+        2,
+        V128_LOAD_LANE,
+    ),
+    memory_lane(
+        I::V128Store8Lane,
+        "v128.store8_lane",
+        simd(0x58),
+        0,
+        16,
+        V128_STORE,
+    ),
+    memory_lane(
+        I::V128Store16Lane,
+        "v128.store16_lane",
+        simd(0x59),
+        1,
+        8,
+        V128_STORE,
+    ),
+    memory_lane(
+        I::V128Store32Lane,
+        "v128.store32_lane",
+        simd(0x5a),
+        2,
+        4,
+        V128_STORE,
+    ),
+    memory_lane(
+        I::V128Store64Lane,
+        "v128.store64_lane",
+        simd(0x5b),
+        3,
+        2,
+        V128_STORE,
+    ),
+    memory(
+        I::V128Load32Zero,
+        "v128.load32_zero",
+        simd(0x5c),
+        2,
+        V128_LOAD,
+    ),
+    memory(
+        I::V128Load64Zero,
+        "v128.load64_zero",
+        simd(0x5d),
+        3,
+        V128_LOAD,
+    ),
+    plain(
+        I::F32x4DemoteF64x2Zero,
+        "f32x4.demote_f64x2_zero",
+        simd(0x5e),
+        V128_UNOP,
+    ),
+    plain(
+        I::F64x2PromoteLowF32x4,
+        "f64x2.promote_low_f32x4",
+        simd(0x5f),
+        V128_UNOP,
+    ),
+    plain(I::I8x16Abs, "i8x16.abs", simd(0x60), V128_UNOP),
+    plain(I::I8x16Neg, "i8x16.neg", simd(0x61), V128_UNOP),
+    plain(I::I8x16Popcnt, "i8x16.popcnt", simd(0x62), V128_UNOP),
+    plain(I::I8x16AllTrue, "i8x16.all_true", simd(0x63), V128_TESTOP),
+    plain(I::I8x16Bitmask, "i8x16.bitmask", simd(0x64), V128_TESTOP),
+    plain(
+        I::I8x16NarrowI16x8S,
+        "i8x16.narrow_i16x8_s",
+        simd(0x65),
+        V128_BINOP,
+    ),
+    plain(
+        I::I8x16NarrowI16x8U,
+        "i8x16.narrow_i16x8_u",
+        simd(0x66),
+        V128_BINOP,
+    ),
+    plain(I::F32x4Ceil, "f32x4.ceil", simd(0x67), V128_UNOP),
+    plain(I::F32x4Floor, "f32x4.floor", simd(0x68), V128_UNOP),
+    plain(I::F32x4Trunc, "f32x4.trunc", simd(0x69), V128_UNOP),
+    plain(I::F32x4Nearest, "f32x4.nearest", simd(0x6a), V128_UNOP),
+    plain(I::I8x16Shl, "i8x16.shl", simd(0x6b), V128_SHIFTOP),
+    plain(I::I8x16ShrS, "i8x16.shr_s", simd(0x6c), V128_SHIFTOP),
+    plain(I::I8x16ShrU, "i8x16.shr_u", simd(0x6d), V128_SHIFTOP),
+    plain(I::I8x16Add, "i8x16.add", simd(0x6e), V128_BINOP),
+    plain(I::I8x16AddSatS, "i8x16.add_sat_s", simd(0x6f), V128_BINOP),
+    plain(I::I8x16AddSatU, "i8x16.add_sat_u", simd(0x70), V128_BINOP),
+    plain(I::I8x16Sub, "i8x16.sub", simd(0x71), V128_BINOP),
+    plain(I::I8x16SubSatS, "i8x16.sub_sat_s", simd(0x72), V128_BINOP),
+    plain(I::I8x16SubSatU, "i8x16.sub_sat_u", simd(0x73), V128_BINOP),
+    plain(I::F64x2Ceil, "f64x2.ceil", simd(0x74), V128_UNOP),
+    plain(I::F64x2Floor, "f64x2.floor", simd(0x75), V128_UNOP),
+    plain(I::I8x16MinS, "i8x16.min_s", simd(0x76), V128_BINOP),
+    plain(I::I8x16MinU, "i8x16.min_u", simd(0x77), V128_BINOP),
+    plain(I::I8x16MaxS, "i8x16.max_s", simd(0x78), V128_BINOP),
+    plain(I::I8x16MaxU, "i8x16.max_u", simd(0x79), V128_BINOP),
+    plain(I::F64x2Trunc, "f64x2.trunc", simd(0x7a), V128_UNOP),
+    plain(I::I8x16AvgrU, "i8x16.avgr_u", simd(0x7b), V128_BINOP),
+    plain(
+        I::I16x8ExtaddPairwiseI8x16S,
+        "i16x8.extadd_pairwise_i8x16_s",
+        simd(0x7c),
+        V128_UNOP,
+    ),
+    plain(
+        I::I16x8ExtaddPairwiseI8x16U,
+        "i16x8.extadd_pairwise_i8x16_u",
+        simd(0x7d),
+        V128_UNOP,
+    ),
+    plain(
+        I::I32x4ExtaddPairwiseI16x8S,
+        "i32x4.extadd_pairwise_i16x8_s",
+        simd(0x7e),
+        V128_UNOP,
+    ),
+    plain(
+        I::I32x4ExtaddPairwiseI16x8U,
+        "i32x4.extadd_pairwise_i16x8_u",
+        simd(0x7f),
+        V128_UNOP,
+    ),
+    plain(I::I16x8Abs, "i16x8.abs", simd(0x80), V128_UNOP),
+    plain(I::I16x8Neg, "i16x8.neg", simd(0x81), V128_UNOP),
+    plain(
+        I::I16x8Q15mulrSatS,
+        "i16x8.q15mulr_sat_s",
+        simd(0x82),
+        V128_BINOP,
+    ),
+    plain(I::I16x8AllTrue, "i16x8.all_true", simd(0x83), V128_TESTOP),
+    plain(I::I16x8Bitmask, "i16x8.bitmask", simd(0x84), V128_TESTOP),
+    plain(
+        I::I16x8NarrowI32x4S,
+        "i16x8.narrow_i32x4_s",
+        simd(0x85),
+        V128_BINOP,
+    ),
+    plain(
+        I::I16x8NarrowI32x4U,
+        "i16x8.narrow_i32x4_u",
+        simd(0x86),
+        V128_BINOP,
+    ),
+    plain(
+        I::I16x8ExtendLowI8x16S,
+        "i16x8.extend_low_i8x16_s",
+        simd(0x87),
+        V128_UNOP,
+    ),
+    plain(
+        I::I16x8ExtendHighI8x16S,
+        "i16x8.extend_high_i8x16_s",
+        simd(0x88),
+        V128_UNOP,
+    ),
+    plain(
+        I::I16x8ExtendLowI8x16U,
+        "i16x8.extend_low_i8x16_u",
+        simd(0x89),
+        V128_UNOP,
+    ),
+    plain(
+        I::I16x8ExtendHighI8x16U,
+        "i16x8.extend_high_i8x16_u",
+        simd(0x8a),
+        V128_UNOP,
+    ),
+    plain(I::I16x8Shl, "i16x8.shl", simd(0x8b), V128_SHIFTOP),
+    plain(I::I16x8ShrS, "i16x8.shr_s", simd(0x8c), V128_SHIFTOP),
+    plain(I::I16x8ShrU, "i16x8.shr_u", simd(0x8d), V128_SHIFTOP),
+    plain(I::I16x8Add, "i16x8.add", simd(0x8e), V128_BINOP),
+    plain(I::I16x8AddSatS, "i16x8.add_sat_s", simd(0x8f), V128_BINOP),
+    plain(I::I16x8AddSatU, "i16x8.add_sat_u", simd(0x90), V128_BINOP),
+    plain(I::I16x8Sub, "i16x8.sub", simd(0x91), V128_BINOP),
+    plain(I::I16x8SubSatS, "i16x8.sub_sat_s", simd(0x92), V128_BINOP),
+    plain(I::I16x8SubSatU, "i16x8.sub_sat_u", simd(0x93), V128_BINOP),
+    plain(I::F64x2Nearest, "f64x2.nearest", simd(0x94), V128_UNOP),
+    plain(I::I16x8Mul, "i16x8.mul", simd(0x95), V128_BINOP),
+    plain(I::I16x8MinS, "i16x8.min_s", simd(0x96), V128_BINOP),
+    plain(I::I16x8MinU, "i16x8.min_u", simd(0x97), V128_BINOP),
+    plain(I::I16x8MaxS, "i16x8.max_s", simd(0x98), V128_BINOP),
+    plain(I::I16x8MaxU, "i16x8.max_u", simd(0x99), V128_BINOP),
+    plain(I::I16x8AvgrU, "i16x8.avgr_u", simd(0x9b), V128_BINOP),
+    plain(
+        I::I16x8ExtmulLowI8x16S,
+        "i16x8.extmul_low_i8x16_s",
+        simd(0x9c),
+        V128_BINOP,
+    ),
+    plain(
+        I::I16x8ExtmulHighI8x16S,
+        "i16x8.extmul_high_i8x16_s",
+        simd(0x9d),
+        V128_BINOP,
+    ),
+    plain(
+        I::I16x8ExtmulLowI8x16U,
+        "i16x8.extmul_low_i8x16_u",
+        simd(0x9e),
+        V128_BINOP,
+    ),
+    plain(
+        I::I16x8ExtmulHighI8x16U,
+        "i16x8.extmul_high_i8x16_u",
+        simd(0x9f),
+        V128_BINOP,
+    ),
+    plain(I::I32x4Abs, "i32x4.abs", simd(0xa0), V128_UNOP),
+    plain(I::I32x4Neg, "i32x4.neg", simd(0xa1), V128_UNOP),
+    plain(I::I32x4AllTrue, "i32x4.all_true", simd(0xa3), V128_TESTOP),
+    plain(I::I32x4Bitmask, "i32x4.bitmask", simd(0xa4), V128_TESTOP),
+    plain(
+        I::I32x4ExtendLowI16x8S,
+        "i32x4.extend_low_i16x8_s",
+        simd(0xa7),
+        V128_UNOP,
+    ),
+    plain(
+        I::I32x4ExtendHighI16x8S,
+        "i32x4.extend_high_i16x8_s",
+        simd(0xa8),
+        V128_UNOP,
+    ),
+    plain(
+        I::I32x4ExtendLowI16x8U,
+        "i32x4.extend_low_i16x8_u",
+        simd(0xa9),
+        V128_UNOP,
+    ),
+    plain(
+        I::I32x4ExtendHighI16x8U,
+        "i32x4.extend_high_i16x8_u",
+        simd(0xaa),
+        V128_UNOP,
+    ),
+    plain(I::I32x4Shl, "i32x4.shl", simd(0xab), V128_SHIFTOP),
+    plain(I::I32x4ShrS, "i32x4.shr_s", simd(0xac), V128_SHIFTOP),
+    plain(I::I32x4ShrU, "i32x4.shr_u", simd(0xad), V128_SHIFTOP),
+    plain(I::I32x4Add, "i32x4.add", simd(0xae), V128_BINOP),
+    plain(I::I32x4Sub, "i32x4.sub", simd(0xb1), V128_BINOP),
+    plain(I::I32x4Mul, "i32x4.mul", simd(0xb5), V128_BINOP),
+    plain(I::I32x4MinS, "i32x4.min_s", simd(0xb6), V128_BINOP),
+    plain(I::I32x4MinU, "i32x4.min_u", simd(0xb7), V128_BINOP),
+    plain(I::I32x4MaxS, "i32x4.max_s", simd(0xb8), V128_BINOP),
+    plain(I::I32x4MaxU, "i32x4.max_u", simd(0xb9), V128_BINOP),
+    plain(
+        I::I32x4DotI16x8S,
+        "i32x4.dot_i16x8_s",
+        simd(0xba),
+        V128_BINOP,
+    ),
+    plain(
+        I::I32x4ExtmulLowI16x8S,
+        "i32x4.extmul_low_i16x8_s",
+        simd(0xbc),
+        V128_BINOP,
+    ),
+    plain(
+        I::I32x4ExtmulHighI16x8S,
+        "i32x4.extmul_high_i16x8_s",
+        simd(0xbd),
+        V128_BINOP,
+    ),
+    plain(
+        I::I32x4ExtmulLowI16x8U,
+        "i32x4.extmul_low_i16x8_u",
+        simd(0xbe),
+        V128_BINOP,
+    ),
+    plain(
+        I::I32x4ExtmulHighI16x8U,
+        "i32x4.extmul_high_i16x8_u",
+        simd(0xbf),
+        V128_BINOP,
+    ),
+    plain(I::I64x2Abs, "i64x2.abs", simd(0xc0), V128_UNOP),
+    plain(I::I64x2Neg, "i64x2.neg", simd(0xc1), V128_UNOP),
+    plain(I::I64x2AllTrue, "i64x2.all_true", simd(0xc3), V128_TESTOP),
+    plain(I::I64x2Bitmask, "i64x2.bitmask", simd(0xc4), V128_TESTOP),
+    plain(
+        I::I64x2ExtendLowI32x4S,
+        "i64x2.extend_low_i32x4_s",
+        simd(0xc7),
+        V128_UNOP,
+    ),
+    plain(
+        I::I64x2ExtendHighI32x4S,
+        "i64x2.extend_high_i32x4_s",
+        simd(0xc8),
+        V128_UNOP,
+    ),
+    plain(
+        I::I64x2ExtendLowI32x4U,
+        "i64x2.extend_low_i32x4_u",
+        simd(0xc9),
+        V128_UNOP,
+    ),
+    plain(
+        I::I64x2ExtendHighI32x4U,
+        "i64x2.extend_high_i32x4_u",
+        simd(0xca),
+        V128_UNOP,
+    ),
+    plain(I::I64x2Shl, "i64x2.shl", simd(0xcb), V128_SHIFTOP),
+    plain(I::I64x2ShrS, "i64x2.shr_s", simd(0xcc), V128_SHIFTOP),
+    plain(I::I64x2ShrU, "i64x2.shr_u", simd(0xcd), V128_SHIFTOP),
+    plain(I::I64x2Add, "i64x2.add", simd(0xce), V128_BINOP),
+    plain(I::I64x2Sub, "i64x2.sub", simd(0xd1), V128_BINOP),
+    plain(I::I64x2Mul, "i64x2.mul", simd(0xd5), V128_BINOP),
+    plain(I::I64x2Eq, "i64x2.eq", simd(0xd6), V128_BINOP),
+    plain(I::I64x2Ne, "i64x2.ne", simd(0xd7), V128_BINOP),
+    plain(I::I64x2LtS, "i64x2.lt_s", simd(0xd8), V128_BINOP),
+    plain(I::I64x2GtS, "i64x2.gt_s", simd(0xd9), V128_BINOP),
+    plain(I::I64x2LeS, "i64x2.le_s", simd(0xda), V128_BINOP),
+    plain(I::I64x2GeS, "i64x2.ge_s", simd(0xdb), V128_BINOP),
+    plain(
+        I::I64x2ExtmulLowI32x4S,
+        "i64x2.extmul_low_i32x4_s",
+        simd(0xdc),
+        V128_BINOP,
+    ),
+    plain(
+        I::I64x2ExtmulHighI32x4S,
+        "i64x2.extmul_high_i32x4_s",
+        simd(0xdd),
+        V128_BINOP,
+    ),
+    plain(
+        I::I64x2ExtmulLowI32x4U,
+        "i64x2.extmul_low_i32x4_u",
+        simd(0xde),
+        V128_BINOP,
+    ),
+    plain(
+        I::I64x2ExtmulHighI32x4U,
+        "i64x2.extmul_high_i32x4_u",
+        simd(0xdf),
+        V128_BINOP,
+    ),
+    plain(I::F32x4Abs, "f32x4.abs", simd(0xe0), V128_UNOP),
+    plain(I::F32x4Neg, "f32x4.neg", simd(0xe1), V128_UNOP),
+    plain(I::F32x4Sqrt, "f32x4.sqrt", simd(0xe3), V128_UNOP),
+    plain(I::F32x4Add, "f32x4.add", simd(0xe4), V128_BINOP),
+    plain(I::F32x4Sub, "f32x4.sub", simd(0xe5), V128_BINOP),
+    plain(I::F32x4Mul, "f32x4.mul", simd(0xe6), V128_BINOP),
+    plain(I::F32x4Div, "f32x4.div", simd(0xe7), V128_BINOP),
+    plain(I::F32x4Min, "f32x4.min", simd(0xe8), V128_BINOP),
+    plain(I::F32x4Max, "f32x4.max", simd(0xe9), V128_BINOP),
+    plain(I::F32x4Pmin, "f32x4.pmin", simd(0xea), V128_BINOP),
+    plain(I::F32x4Pmax, "f32x4.pmax", simd(0xeb), V128_BINOP),
+    plain(I::F64x2Abs, "f64x2.abs", simd(0xec), V128_UNOP),
+    plain(I::F64x2Neg, "f64x2.neg", simd(0xed), V128_UNOP),
+    plain(I::F64x2Sqrt, "f64x2.sqrt", simd(0xef), V128_UNOP),
+    plain(I::F64x2Add, "f64x2.add", simd(0xf0), V128_BINOP),
+    plain(I::F64x2Sub, "f64x2.sub", simd(0xf1), V128_BINOP),
+    plain(I::F64x2Mul, "f64x2.mul", simd(0xf2), V128_BINOP),
+    plain(I::F64x2Div, "f64x2.div", simd(0xf3), V128_BINOP),
+    plain(I::F64x2Min, "f64x2.min", simd(0xf4), V128_BINOP),
+    plain(I::F64x2Max, "f64x2.max", simd(0xf5), V128_BINOP),
+    plain(I::F64x2Pmin, "f64x2.pmin", simd(0xf6), V128_BINOP),
+    plain(I::F64x2Pmax, "f64x2.pmax", simd(0xf7), V128_BINOP),
+    plain(
+        I::I32x4TruncSatF32x4S,
+        "i32x4.trunc_sat_f32x4_s",
+        simd(0xf8),
+        V128_UNOP,
+    ),
+    plain(
+        I::I32x4TruncSatF32x4U,
+        "i32x4.trunc_sat_f32x4_u",
+        simd(0xf9),
+        V128_UNOP,
+    ),
+    plain(
+        I::F32x4ConvertI32x4S,
+        "f32x4.convert_i32x4_s",
+        simd(0xfa),
+        V128_UNOP,
+    ),
+    plain(
+        I::F32x4ConvertI32x4U,
+        "f32x4.convert_i32x4_u",
+        simd(0xfb),
+        V128_UNOP,
+    ),
+    plain(
+        I::I32x4TruncSatF64x2SZero,
+        "i32x4.trunc_sat_f64x2_s_zero",
+        simd(0xfc),
+        V128_UNOP,
+    ),
+    plain(
+        I::I32x4TruncSatF64x2UZero,
+        "i32x4.trunc_sat_f64x2_u_zero",
+        simd(0xfd),
+        V128_UNOP,
+    ),
+    plain(
+        I::F64x2ConvertLowI32x4S,
+        "f64x2.convert_low_i32x4_s",
+        simd(0xfe),
+        V128_UNOP,
+    ),
+    plain(
+        I::F64x2ConvertLowI32x4U,
+        "f64x2.convert_low_i32x4_u",
+        simd(0xff),
+        V128_UNOP,
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_by_name_and_opcode() {
+        let add = find_by_name("i8x16.add").unwrap();
+        assert_eq!(add.opcode, simd(0x6e));
+        assert!(matches!(add.shape, Shape::Plain(I::I8x16Add)));
+        assert_eq!(find_by_opcode(simd(0x6e)).unwrap().name, "i8x16.add");
+        assert!(find_by_name("i8x16.foo").is_none());
+    }
+
+    #[test]
+    fn find_by_instruction() {
+        let load = I::V128Load32Lane(
+            MemArg {
+                offset: 8,
+                align: 2,
+            },
+            3,
+        );
+        assert_eq!(find(&load).unwrap().name, "v128.load32_lane");
+        assert_eq!(
+            load.memarg(),
+            Some(MemArg {
+                offset: 8,
+                align: 2
+            })
+        );
+        assert_eq!(load.lane(), Some(3));
+        assert!(find(&I::V128Const(0)).is_none());
+
+        let rmw = I::I64AtomicRmw32CmpxchgU(MemArg {
+            offset: 0,
+            align: 2,
+        });
+        assert_eq!(find(&rmw).unwrap().name, "i64.atomic.rmw32.cmpxchg_u");
+        assert_eq!(find(&rmw).unwrap().opcode, atomic(0x4e));
+        assert_eq!(
+            rmw.memarg(),
+            Some(MemArg {
+                offset: 0,
+                align: 2
+            })
+        );
+    }
+
+    #[test]
+    fn names_and_opcodes_are_unique() {
+        for (i, a) in INSTRUCTIONS.iter().enumerate() {
+            for b in &INSTRUCTIONS[i + 1..] {
+                assert_ne!(a.name, b.name);
+                assert_ne!(a.opcode, b.opcode);
+            }
+        }
+    }
+
+    #[test]
+    fn signatures() {
+        let ty = |name| find_by_name(name).unwrap().ty;
+        assert_eq!(ty("i32.add"), Some((&[I32, I32][..], &[I32][..])));
+        assert_eq!(ty("f64.lt"), Some((&[F64, F64][..], &[I32][..])));
+        assert_eq!(ty("i32.wrap_i64"), Some((&[I64][..], &[I32][..])));
+        assert_eq!(ty("i64.store16"), Some((&[I32, I64][..], &[][..])));
+        assert_eq!(ty("i64.atomic.rmw.cmpxchg"), Some(I64_CMPXCHG));
+        assert_eq!(ty("f32x4.extract_lane"), Some((&[V128][..], &[F32][..])));
+        assert_eq!(ty("i16x8.shr_u"), Some(V128_SHIFTOP));
+        assert_eq!(ty("v128.store64_lane"), Some(V128_STORE));
+        assert_eq!(ty("drop"), None);
+    }
+}
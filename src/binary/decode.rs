@@ -0,0 +1,1044 @@
+use std::fmt;
+
+use crate::{
+    ast::{
+        instr::{self, Opcode, Shape},
+        BlockType, Catch, Custom, CustomPlace, Data, DataMode, Elem, ElemMode, Export, ExportDesc,
+        Expr, Func, FuncType, Global, GlobalType, Import, ImportDesc, IndirectNameMap, Instruction,
+        Limits, MemArg, MemType, Module, NameMap, Names, Section, TableType, Tag, ValueType,
+    },
+    features::Features,
+};
+
+use super::{leb128, SectionId, MAGIC, VERSION};
+
+// 仕様
+// https://webassembly.github.io/spec/core/binary/modules.html
+
+// デコードのエラー
+// offsetは不正な入力が見つかった位置のバイトオフセット
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct DecodeError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at offset {:#x})", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+type Result<T> = std::result::Result<T, DecodeError>;
+
+// バイト列を先頭から読み進める
+// endはセクションや関数本体の終わりを表し、それを超えて読むことはできない
+// featuresで無効な提案の型や命令を読んだ場合はエラーにする
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    end: usize,
+    features: Features,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            end: bytes.len(),
+            features: Features::default(),
+        }
+    }
+
+    // 現在の位置のエラーを作成する
+    fn error<T>(&self, message: &str) -> Result<T> {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at<T>(&self, offset: usize, message: &str) -> Result<T> {
+        Err(DecodeError {
+            offset,
+            message: message.to_string(),
+        })
+    }
+
+    fn eof(&self) -> bool {
+        self.pos >= self.end
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        if self.eof() {
+            return self.error("unexpected end");
+        }
+        let b = self.bytes[self.pos];
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.end - self.pos < n {
+            return self.error("unexpected end");
+        }
+        let b = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(b)
+    }
+
+    // 現在の位置からlenバイトを範囲とするReaderを作成する
+    fn sub(&self, len: usize) -> Result<Reader<'a>> {
+        if self.end - self.pos < len {
+            return self.error("length out of bounds");
+        }
+        Ok(Reader {
+            bytes: self.bytes,
+            pos: self.pos,
+            end: self.pos + len,
+            features: self.features,
+        })
+    }
+
+    // 現在の位置から残りのバイト列をデコードし、読み込んだバイト数だけ進める
+    // エラーのオフセットはバイト列全体の先頭からの位置にする
+    fn read<T>(&mut self, f: impl Fn(&[u8]) -> leb128::Result<T>) -> Result<T> {
+        match f(&self.bytes[self.pos..self.end]) {
+            Ok((value, len)) => {
+                self.pos += len;
+                Ok(value)
+            }
+            Err(e) => self.error_at(self.pos + e.offset, &e.message),
+        }
+    }
+
+    // 無効な提案の機能であれば、その位置のエラーにする
+    fn feature(&self, offset: usize, check: std::result::Result<(), String>) -> Result<()> {
+        check.or_else(|message| self.error_at(offset, &message))
+    }
+
+    // https://webassembly.github.io/spec/core/binary/values.html#integers
+    fn u32(&mut self) -> Result<u32> {
+        self.read(leb128::decode_u32)
+    }
+
+    fn s32(&mut self) -> Result<i32> {
+        self.read(leb128::decode_i32)
+    }
+
+    fn s64(&mut self) -> Result<i64> {
+        self.read(leb128::decode_i64)
+    }
+
+    // https://webassembly.github.io/spec/core/binary/values.html#names
+    fn name(&mut self) -> Result<String> {
+        self.read(leb128::decode_name)
+    }
+
+    // 要素数を前置したベクタをデコードする
+    // https://webassembly.github.io/spec/core/binary/conventions.html#vectors
+    fn vec<T>(&mut self, f: impl Fn(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        let n = self.u32()?;
+        // 要素は少なくとも1バイトなので、残りのバイト数より多くは確保しない
+        let mut items = Vec::with_capacity((n as usize).min(self.end - self.pos));
+        for _ in 0..n {
+            items.push(f(self)?);
+        }
+        Ok(items)
+    }
+
+    // 名前セクションの内容をデコードする
+    // サブセクションはIDの昇順に、それぞれ1つまで置くことができる
+    // https://webassembly.github.io/spec/core/appendix/custom.html#name-section
+    fn names(&mut self) -> Result<Names> {
+        let mut names = Names::default();
+        let mut last = None;
+        while !self.eof() {
+            let pos = self.pos;
+            let id = self.byte()?;
+            if last.is_some_and(|last| id <= last) {
+                return self.error_at(pos, "out of order name subsection");
+            }
+            last = Some(id);
+            let size = self.u32()? as usize;
+            let mut s = self.sub(size)?;
+            match id {
+                0 => names.module = Some(s.name()?),
+                1 => names.funcs = s.name_map()?,
+                2 => names.locals = s.indirect_name_map()?,
+                3 => names.labels = s.indirect_name_map()?,
+                4 => names.types = s.name_map()?,
+                7 => names.globals = s.name_map()?,
+                _ => return self.error_at(pos, "unknown name subsection"),
+            }
+            if !s.eof() {
+                return s.error("name subsection size mismatch");
+            }
+            self.pos = s.end;
+        }
+        Ok(names)
+    }
+
+    fn name_map(&mut self) -> Result<NameMap> {
+        self.vec(|r| Ok((r.u32()?, r.name()?)))
+    }
+
+    fn indirect_name_map(&mut self) -> Result<IndirectNameMap> {
+        self.vec(|r| Ok((r.u32()?, r.name_map()?)))
+    }
+
+    // https://webassembly.github.io/spec/core/binary/types.html#value-types
+    fn value_type(&mut self) -> Result<ValueType> {
+        let pos = self.pos;
+        match value_type(self.byte()?) {
+            Some(t) => {
+                self.feature(pos, self.features.value_type(t))?;
+                Ok(t)
+            }
+            None => self.error_at(pos, "malformed value type"),
+        }
+    }
+
+    // https://webassembly.github.io/spec/core/binary/types.html#reference-types
+    fn ref_type(&mut self) -> Result<ValueType> {
+        let pos = self.pos;
+        match value_type(self.byte()?) {
+            Some(t) if t.is_ref() => {
+                self.feature(pos, self.features.value_type(t))?;
+                Ok(t)
+            }
+            _ => self.error_at(pos, "malformed reference type"),
+        }
+    }
+
+    // https://webassembly.github.io/spec/core/binary/types.html#function-types
+    fn func_type(&mut self) -> Result<FuncType> {
+        if self.byte()? != 0x60 {
+            return self.error_at(self.pos - 1, "malformed function type");
+        }
+        let params = self.vec(Self::value_type)?;
+        let results = self.vec(Self::value_type)?;
+        Ok((params, results))
+    }
+
+    // 最大値の有無と共有メモリかどうかをフラグで表す
+    // https://webassembly.github.io/spec/core/binary/types.html#limits
+    fn limits(&mut self, max_flag: u8) -> Result<(Limits, bool)> {
+        let pos = self.pos;
+        let flag = self.byte()?;
+        if flag > max_flag {
+            return self.error_at(pos, "malformed limits flags");
+        }
+        let min = self.u32()?;
+        let max = if flag & 0x01 != 0 {
+            Some(self.u32()?)
+        } else {
+            None
+        };
+        Ok((Limits { min, max }, flag & 0x02 != 0))
+    }
+
+    // https://webassembly.github.io/spec/core/binary/types.html#memory-types
+    fn mem_type(&mut self) -> Result<MemType> {
+        let pos = self.pos;
+        let (limits, shared) = self.limits(0x03)?;
+        let m = MemType { limits, shared };
+        self.feature(pos, self.features.mem_type(&m))?;
+        Ok(m)
+    }
+
+    // https://webassembly.github.io/spec/core/binary/types.html#table-types
+    fn table_type(&mut self) -> Result<TableType> {
+        let elem = self.ref_type()?;
+        let (limits, _) = self.limits(0x01)?;
+        Ok(TableType { limits, elem })
+    }
+
+    // https://webassembly.github.io/spec/core/binary/types.html#global-types
+    fn global_type(&mut self) -> Result<GlobalType> {
+        let value_type = self.value_type()?;
+        let mutable = match self.byte()? {
+            0x00 => false,
+            0x01 => true,
+            _ => return self.error_at(self.pos - 1, "malformed mutability"),
+        };
+        Ok(GlobalType {
+            value_type,
+            mutable,
+        })
+    }
+
+    // https://webassembly.github.io/exception-handling/core/binary/modules.html#tag-section
+    fn tag(&mut self) -> Result<Tag> {
+        self.feature(self.pos, self.features.tag())?;
+        if self.byte()? != 0x00 {
+            return self.error_at(self.pos - 1, "malformed tag attribute");
+        }
+        let f_type = self.u32()?;
+        Ok(Tag { f_type })
+    }
+
+    // https://webassembly.github.io/spec/core/binary/modules.html#import-section
+    fn import(&mut self) -> Result<Import> {
+        let module = self.name()?;
+        let name = self.name()?;
+        let desc = match self.byte()? {
+            0x00 => ImportDesc::Func(self.u32()?),
+            0x01 => ImportDesc::Table(self.table_type()?),
+            0x02 => ImportDesc::Memory(self.mem_type()?),
+            0x03 => ImportDesc::Global(self.global_type()?),
+            0x04 => ImportDesc::Tag(self.tag()?),
+            _ => return self.error_at(self.pos - 1, "malformed import kind"),
+        };
+        Ok(Import { module, name, desc })
+    }
+
+    // https://webassembly.github.io/spec/core/binary/modules.html#global-section
+    fn global(&mut self) -> Result<Global> {
+        let g_type = self.global_type()?;
+        let init = self.expr()?;
+        Ok(Global { g_type, init })
+    }
+
+    // https://webassembly.github.io/spec/core/binary/modules.html#export-section
+    fn export(&mut self) -> Result<Export> {
+        let name = self.name()?;
+        let kind = self.byte()?;
+        let idx = self.u32()?;
+        let desc = match kind {
+            0x00 => ExportDesc::Func(idx),
+            0x01 => ExportDesc::Table(idx),
+            0x02 => ExportDesc::Memory(idx),
+            0x03 => ExportDesc::Global(idx),
+            0x04 => ExportDesc::Tag(idx),
+            _ => return self.error("malformed export kind"),
+        };
+        Ok(Export { name, desc })
+    }
+
+    // 関数のインデックスのベクタを、ref.funcの式のベクタに変換する
+    fn func_indices(&mut self) -> Result<Vec<Expr>> {
+        self.vec(|r| Ok(vec![Instruction::RefFunc(r.u32()?)]))
+    }
+
+    // 要素の種類は0x00のfuncrefのみが定義されている
+    fn elem_kind(&mut self) -> Result<ValueType> {
+        match self.byte()? {
+            0x00 => Ok(ValueType::FuncRef),
+            _ => self.error_at(self.pos - 1, "malformed element kind"),
+        }
+    }
+
+    // 0x04のビットは初期値が式であること、0x01と0x02のビットはモードを表す
+    // https://webassembly.github.io/spec/core/binary/modules.html#element-section
+    fn elem(&mut self) -> Result<Elem> {
+        let pos = self.pos;
+        let flag = self.u32()?;
+        if flag > 0x07 {
+            return self.error_at(pos, "malformed elements segment kind");
+        }
+        let mode = match flag & 0x03 {
+            0x00 => ElemMode::Active {
+                table: 0,
+                offset: self.expr()?,
+            },
+            0x02 => {
+                let table = self.u32()?;
+                let offset = self.expr()?;
+                ElemMode::Active { table, offset }
+            }
+            0x01 => ElemMode::Passive,
+            _ => ElemMode::Declarative,
+        };
+        // フラグが0と4のセグメントは、要素の種類を省略する
+        let (e_type, init) = match (flag & 0x04 != 0, flag & 0x03 == 0) {
+            (false, true) => (ValueType::FuncRef, self.func_indices()?),
+            (false, false) => (self.elem_kind()?, self.func_indices()?),
+            (true, true) => (ValueType::FuncRef, self.vec(Self::expr)?),
+            (true, false) => (self.ref_type()?, self.vec(Self::expr)?),
+        };
+        Ok(Elem { e_type, init, mode })
+    }
+
+    // https://webassembly.github.io/spec/core/binary/modules.html#data-section
+    fn data(&mut self) -> Result<Data> {
+        let pos = self.pos;
+        let mode = match self.u32()? {
+            0x00 => DataMode::Active {
+                memory: 0,
+                offset: self.expr()?,
+            },
+            0x01 => DataMode::Passive,
+            0x02 => {
+                let memory = self.u32()?;
+                let offset = self.expr()?;
+                DataMode::Active { memory, offset }
+            }
+            _ => return self.error_at(pos, "malformed data segment kind"),
+        };
+        let len = self.u32()? as usize;
+        let init = self.bytes(len)?.to_vec();
+        Ok(Data { init, mode })
+    }
+
+    // 関数のバイト数を前置して、ローカル変数と本体が続く
+    // https://webassembly.github.io/spec/core/binary/modules.html#code-section
+    fn code(&mut self) -> Result<(Vec<ValueType>, Expr)> {
+        let size = self.u32()? as usize;
+        let mut body = self.sub(size)?;
+
+        let runs = body.vec(|r| Ok((r.u32()?, r.value_type()?)))?;
+        let total = runs.iter().map(|(n, _)| *n as u64).sum::<u64>();
+        if total > u32::MAX as u64 {
+            return body.error("too many locals");
+        }
+        let locals = runs
+            .into_iter()
+            .flat_map(|(n, t)| std::iter::repeat_n(t, n as usize))
+            .collect();
+        let expr = body.expr()?;
+        if !body.eof() {
+            return body.error("section size mismatch");
+        }
+
+        self.pos = body.pos;
+        Ok((locals, expr))
+    }
+
+    // 末尾のendまでの命令列をデコードする
+    // 返す命令列に末尾のendは含まない
+    // https://webassembly.github.io/spec/core/binary/instructions.html#expressions
+    fn expr(&mut self) -> Result<Expr> {
+        let mut depth = 0;
+        let mut instrs = Vec::new();
+        loop {
+            let instr = self.instruction()?;
+            match instr {
+                Instruction::Block(_)
+                | Instruction::Loop(_)
+                | Instruction::If(_)
+                | Instruction::TryTable(..) => depth += 1,
+                Instruction::End if depth == 0 => return Ok(instrs),
+                Instruction::End => depth -= 1,
+                _ => (),
+            }
+            instrs.push(instr);
+        }
+    }
+
+    // https://webassembly.github.io/spec/core/binary/instructions.html#memory-instructions
+    fn memarg(&mut self) -> Result<MemArg> {
+        let align = self.u32()?;
+        let offset = self.u32()?;
+        Ok(MemArg { offset, align })
+    }
+
+    // 値の型はそのバイト、型のインデックスは33ビットの符号付き整数で表す
+    // https://webassembly.github.io/spec/core/binary/instructions.html#control-instructions
+    fn block_type(&mut self) -> Result<BlockType> {
+        let pos = self.pos;
+        match self.byte()? {
+            0x40 => return Ok(BlockType::Empty),
+            b => {
+                if let Some(t) = value_type(b) {
+                    self.feature(pos, self.features.value_type(t))?;
+                    return Ok(BlockType::Value(t));
+                }
+            }
+        }
+        self.pos = pos;
+        match self.read(leb128::decode_i33)? {
+            x @ 0..=0xffff_ffff => Ok(BlockType::Type(x as u32)),
+            _ => self.error_at(pos, "malformed block type"),
+        }
+    }
+
+    // https://webassembly.github.io/exception-handling/core/binary/instructions.html#control-instructions
+    fn catch(&mut self) -> Result<Catch> {
+        let catch = match self.byte()? {
+            0x00 => Catch::Catch(self.u32()?, self.u32()?),
+            0x01 => Catch::CatchRef(self.u32()?, self.u32()?),
+            0x02 => Catch::CatchAll(self.u32()?),
+            0x03 => Catch::CatchAllRef(self.u32()?),
+            _ => return self.error_at(self.pos - 1, "malformed catch clause"),
+        };
+        Ok(catch)
+    }
+
+    // 命令をデコードする
+    // 命令表にない即値を持つ命令はここで個別に扱い、それ以外は命令表の形に従う
+    // https://webassembly.github.io/spec/core/binary/instructions.html
+    fn instruction(&mut self) -> Result<Instruction> {
+        use Instruction as I;
+
+        let pos = self.pos;
+        let op = self.byte()?;
+        let instr = match op {
+            0x02 => I::Block(self.block_type()?),
+            0x03 => I::Loop(self.block_type()?),
+            0x04 => I::If(self.block_type()?),
+            0x08 => I::Throw(self.u32()?),
+            0x0c => I::Br(self.u32()?),
+            0x0d => I::BrIf(self.u32()?),
+            0x0e => I::BrTable(self.vec(Self::u32)?, self.u32()?),
+            0x10 => I::Call(self.u32()?),
+            0x11 => I::CallIndirect(self.u32()?, self.u32()?),
+            0x12 => I::ReturnCall(self.u32()?),
+            0x13 => I::ReturnCallIndirect(self.u32()?, self.u32()?),
+            0x1f => I::TryTable(self.block_type()?, self.vec(Self::catch)?),
+            0x1b => I::Select(None),
+            0x1c => match self.vec(Self::value_type)?.as_slice() {
+                [t] => I::Select(Some(*t)),
+                _ => return self.error_at(pos, "invalid result arity"),
+            },
+            0x20 => I::LocalGet(self.u32()? as usize),
+            0x21 => I::LocalSet(self.u32()? as usize),
+            0x22 => I::LocalTee(self.u32()? as usize),
+            0x23 => I::GlobalGet(self.u32()?),
+            0x24 => I::GlobalSet(self.u32()?),
+            0x25 => I::TableGet(self.u32()?),
+            0x26 => I::TableSet(self.u32()?),
+            0x3f => I::MemorySize(self.u32()?),
+            0x40 => I::MemoryGrow(self.u32()?),
+            0x41 => I::I32Const(self.s32()?),
+            0x42 => I::I64Const(self.s64()?),
+            0x43 => {
+                let b = self.bytes(4)?;
+                I::F32Const(u32::from_le_bytes(b.try_into().expect("4 bytes")))
+            }
+            0x44 => {
+                let b = self.bytes(8)?;
+                I::F64Const(u64::from_le_bytes(b.try_into().expect("8 bytes")))
+            }
+            0xd0 => I::RefNull(self.ref_type()?),
+            0xd2 => I::RefFunc(self.u32()?),
+            0xfc => match self.u32()? {
+                8 => I::MemoryInit(self.u32()?, self.u32()?),
+                9 => I::DataDrop(self.u32()?),
+                10 => I::MemoryCopy(self.u32()?, self.u32()?),
+                11 => I::MemoryFill(self.u32()?),
+                12 => I::TableInit(self.u32()?, self.u32()?),
+                13 => I::ElemDrop(self.u32()?),
+                14 => I::TableCopy(self.u32()?, self.u32()?),
+                15 => I::TableGrow(self.u32()?),
+                16 => I::TableSize(self.u32()?),
+                17 => I::TableFill(self.u32()?),
+                sub => self.table_instruction(Opcode::Prefixed(op, sub), pos)?,
+            },
+            0xfd => match self.u32()? {
+                12 => {
+                    let b = self.bytes(16)?;
+                    I::V128Const(u128::from_le_bytes(b.try_into().expect("16 bytes")))
+                }
+                13 => {
+                    let b = self.bytes(16)?;
+                    I::I8x16Shuffle(b.try_into().expect("16 bytes"))
+                }
+                sub => self.table_instruction(Opcode::Prefixed(op, sub), pos)?,
+            },
+            // atomic.fenceは0x00の予約バイトを持つ
+            0xfe => match self.u32()? {
+                3 => match self.byte()? {
+                    0x00 => I::AtomicFence,
+                    _ => return self.error_at(self.pos - 1, "zero byte expected"),
+                },
+                sub => self.table_instruction(Opcode::Prefixed(op, sub), pos)?,
+            },
+            _ => self.table_instruction(Opcode::Single(op), pos)?,
+        };
+        self.feature(pos, self.features.instruction(&instr))?;
+        Ok(instr)
+    }
+
+    // 命令表に登録された命令を、即値の形に従ってデコードする
+    fn table_instruction(&mut self, opcode: Opcode, pos: usize) -> Result<Instruction> {
+        let Some(entry) = instr::find_by_opcode(opcode) else {
+            return self.error_at(pos, "illegal opcode");
+        };
+        let instr = match entry.shape {
+            Shape::Plain(ref i) => i.clone(),
+            Shape::Memory(ctor, _) => ctor(self.memarg()?),
+            Shape::Lane(ctor, _) => ctor(self.byte()?),
+            Shape::MemoryLane(ctor, _, _) => {
+                let m = self.memarg()?;
+                ctor(m, self.byte()?)
+            }
+        };
+        Ok(instr)
+    }
+}
+
+// https://webassembly.github.io/spec/core/binary/types.html#value-types
+fn value_type(byte: u8) -> Option<ValueType> {
+    let t = match byte {
+        0x7f => ValueType::I32,
+        0x7e => ValueType::I64,
+        0x7d => ValueType::F32,
+        0x7c => ValueType::F64,
+        0x7b => ValueType::V128,
+        0x70 => ValueType::FuncRef,
+        0x6f => ValueType::ExternRef,
+        0x69 => ValueType::ExnRef,
+        _ => return None,
+    };
+    Some(t)
+}
+
+// セクションIDを変換する
+fn section_id(byte: u8) -> Option<SectionId> {
+    let id = match byte {
+        0 => SectionId::Custom,
+        1 => SectionId::Type,
+        2 => SectionId::Import,
+        3 => SectionId::Function,
+        4 => SectionId::Table,
+        5 => SectionId::Memory,
+        6 => SectionId::Global,
+        7 => SectionId::Export,
+        8 => SectionId::Start,
+        9 => SectionId::Element,
+        10 => SectionId::Code,
+        11 => SectionId::Data,
+        12 => SectionId::DataCount,
+        13 => SectionId::Tag,
+        _ => return None,
+    };
+    Some(id)
+}
+
+// カスタムセクション以外のセクションIDを、並ぶ順番を持つSectionに変換する
+fn section(id: SectionId) -> Option<Section> {
+    let s = match id {
+        SectionId::Custom => return None,
+        SectionId::Type => Section::Type,
+        SectionId::Import => Section::Import,
+        SectionId::Function => Section::Func,
+        SectionId::Table => Section::Table,
+        SectionId::Memory => Section::Memory,
+        SectionId::Global => Section::Global,
+        SectionId::Export => Section::Export,
+        SectionId::Start => Section::Start,
+        SectionId::Element => Section::Elem,
+        SectionId::Code => Section::Code,
+        SectionId::Data => Section::Data,
+        SectionId::DataCount => Section::DataCount,
+        SectionId::Tag => Section::Tag,
+    };
+    Some(s)
+}
+
+// バイナリ形式のモジュールを、既定の提案を有効にしてデコードする
+// validateと同じ既定値を使い、デコードできたモジュールが提案の違いで検証に失敗しないようにする
+pub fn decode(bytes: &[u8]) -> Result<Module> {
+    decode_with_features(bytes, &Features::default())
+}
+
+// バイナリ形式のモジュールをデコードする
+// 無効な提案の型や命令は、それが置かれた位置のエラーになる
+pub fn decode_with_features(bytes: &[u8], features: &Features) -> Result<Module> {
+    let mut r = Reader {
+        features: *features,
+        ..Reader::new(bytes)
+    };
+    if r.bytes(4).ok() != Some(&MAGIC[..]) {
+        return r.error_at(0, "magic header not detected");
+    }
+    if r.bytes(4).ok() != Some(&VERSION[..]) {
+        return r.error_at(4, "unknown binary version");
+    }
+
+    let mut module = Module::default();
+    let mut func_types = Vec::new();
+    let mut codes = Vec::new();
+    let mut data_count = None;
+    // カスタムセクションは、直前のセクションの後ろに置かれたものとして保持する
+    let mut place = CustomPlace::BeforeFirst;
+
+    while !r.eof() {
+        let id_pos = r.pos;
+        let Some(id) = section_id(r.byte()?) else {
+            return r.error_at(id_pos, "malformed section id");
+        };
+
+        // カスタムセクション以外は、決められた順番に1つずつしか置けない
+        if let Some(s) = section(id) {
+            if matches!(place, CustomPlace::After(last) if s <= last) {
+                return r.error_at(id_pos, "unexpected content after last section");
+            }
+            place = CustomPlace::After(s);
+        }
+
+        let size = r.u32()? as usize;
+        let mut s = r.sub(size)?;
+        match id {
+            SectionId::Custom => {
+                let name = s.name()?;
+                // 名前セクションの誤りはモジュールを不正にしないため、
+                // 解釈できない場合はそのままカスタムセクションとして残す
+                let names = match name.as_str() {
+                    "name" if module.names.is_empty() => s.sub(s.end - s.pos)?.names().ok(),
+                    _ => None,
+                };
+                match names {
+                    Some(names) => {
+                        module.names = names;
+                        s.pos = s.end;
+                    }
+                    None => {
+                        let data = s.bytes(s.end - s.pos)?.to_vec();
+                        module.customs.push(Custom { name, place, data });
+                    }
+                }
+            }
+            SectionId::Type => module.types = s.vec(Reader::func_type)?,
+            SectionId::Import => module.imports = s.vec(Reader::import)?,
+            SectionId::Function => func_types = s.vec(Reader::u32)?,
+            SectionId::Table => module.tables = s.vec(Reader::table_type)?,
+            SectionId::Memory => module.mems = s.vec(Reader::mem_type)?,
+            SectionId::Tag => module.tags = s.vec(Reader::tag)?,
+            SectionId::Global => module.globals = s.vec(Reader::global)?,
+            SectionId::Export => module.exports = s.vec(Reader::export)?,
+            SectionId::Start => module.start = Some(s.u32()?),
+            SectionId::Element => module.elems = s.vec(Reader::elem)?,
+            SectionId::DataCount => data_count = Some(s.u32()?),
+            SectionId::Code => codes = s.vec(Reader::code)?,
+            SectionId::Data => module.datas = s.vec(Reader::data)?,
+        }
+        if !s.eof() {
+            return s.error("section size mismatch");
+        }
+        if matches!(id, SectionId::Import | SectionId::Memory) {
+            let imported = module
+                .imports
+                .iter()
+                .filter(|i| matches!(i.desc, ImportDesc::Memory(_)))
+                .count();
+            r.feature(id_pos, features.memories(imported + module.mems.len()))?;
+        }
+        r.pos = s.end;
+    }
+
+    if func_types.len() != codes.len() {
+        return r.error("function and code section have inconsistent lengths");
+    }
+    module.funcs = func_types
+        .into_iter()
+        .zip(codes)
+        .map(|(f_type, (locals, body))| Func {
+            f_type,
+            locals,
+            body,
+        })
+        .collect();
+
+    // memory.initとdata.dropはデータ数セクションを必要とする
+    match data_count {
+        Some(n) if n as usize != module.datas.len() => {
+            return r.error("data count and data section have inconsistent lengths");
+        }
+        None if module
+            .funcs
+            .iter()
+            .flat_map(|f| &f.body)
+            .any(|i| matches!(i, Instruction::MemoryInit(..) | Instruction::DataDrop(_))) =>
+        {
+            return r.error("data count section required");
+        }
+        _ => (),
+    }
+
+    Ok(module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{binary::encode, wat::module::module};
+
+    // ヘッダーにセクションを続けたバイト列を作成する
+    fn with_header(sections: &[u8]) -> Vec<u8> {
+        [&MAGIC[..], &VERSION[..], sections].concat()
+    }
+
+    #[test]
+    fn header_decode() {
+        assert_eq!(decode(&with_header(&[])), Ok(Module::default()));
+        assert_eq!(
+            decode(b"\0asn\x01\0\0\0"),
+            Err(DecodeError {
+                offset: 0,
+                message: "magic header not detected".to_string()
+            })
+        );
+        assert_eq!(decode(b"\0asm\x02\0\0\0").map_err(|e| e.offset), Err(4));
+        assert_eq!(
+            decode(b"\0asm\x01\0").map_err(|e| e.message),
+            Err("unknown binary version".to_string())
+        );
+    }
+
+    #[test]
+    fn leb128_decode() {
+        let unsigned = |b: &[u8]| Reader::new(b).u32();
+        assert_eq!(unsigned(&[0x80, 0x01]), Ok(128));
+        assert_eq!(unsigned(&[0xff, 0xff, 0xff, 0xff, 0x0f]), Ok(u32::MAX));
+        // 冗長な表現も受け付ける
+        assert_eq!(unsigned(&[0x80, 0x80, 0x00]), Ok(0));
+        assert_eq!(
+            unsigned(&[0xff, 0xff, 0xff, 0xff, 0x1f]).map_err(|e| e.message),
+            Err("integer too large".to_string())
+        );
+        assert_eq!(
+            unsigned(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00]).map_err(|e| e.offset),
+            Err(4)
+        );
+
+        let signed = |b: &[u8]| Reader::new(b).s32();
+        assert_eq!(signed(&[0x7f]), Ok(-1));
+        assert_eq!(signed(&[0xc0, 0xbb, 0x78]), Ok(-123456));
+        assert_eq!(signed(&[0xff, 0xff, 0xff, 0xff, 0x07]), Ok(i32::MAX));
+        assert_eq!(signed(&[0x80, 0x80, 0x80, 0x80, 0x78]), Ok(i32::MIN));
+        assert!(signed(&[0xff, 0xff, 0xff, 0xff, 0x0f]).is_err());
+        assert!(signed(&[0x80, 0x80, 0x80, 0x80, 0x70]).is_err());
+
+        let mut r = Reader::new(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7f]);
+        assert_eq!(r.s64(), Ok(i64::MIN));
+        assert!(Reader::new(&[0x80]).u32().is_err());
+    }
+
+    #[test]
+    fn section_errors() {
+        let err = |sections: &[u8]| decode(&with_header(sections)).unwrap_err();
+        assert_eq!(
+            err(&[0x0e, 0x00]),
+            DecodeError {
+                offset: 8,
+                message: "malformed section id".to_string()
+            }
+        );
+        assert_eq!(err(&[0x01, 0x05, 0x00]).message, "length out of bounds");
+        assert_eq!(
+            err(&[0x01, 0x02, 0x00, 0x00]),
+            DecodeError {
+                offset: 11,
+                message: "section size mismatch".to_string()
+            }
+        );
+        assert_eq!(
+            err(&[0x03, 0x01, 0x00, 0x01, 0x01, 0x00]).message,
+            "unexpected content after last section"
+        );
+        assert_eq!(
+            err(&[0x03, 0x02, 0x01, 0x00]).message,
+            "function and code section have inconsistent lengths"
+        );
+        assert_eq!(
+            err(&[0x00, 0x03, 0x02, 0xc3, 0x28]),
+            DecodeError {
+                offset: 11,
+                message: "malformed UTF-8 encoding".to_string()
+            }
+        );
+        assert_eq!(
+            err(&[0x01, 0x04, 0x01, 0x60, 0x02, 0x7f]).message,
+            "unexpected end"
+        );
+    }
+
+    #[test]
+    fn instruction_errors() {
+        // (func) の本体に不正な命令を置く
+        let body = |instrs: &[u8]| {
+            let code = [&[0x00], instrs, &[0x0b]].concat();
+            let len = code.len() as u8;
+            let sections = [
+                &[0x01, 0x04, 0x01, 0x60, 0x00, 0x00, 0x03, 0x02, 0x01, 0x00][..],
+                &[0x0a, len + 2, 0x01, len],
+                &code,
+            ]
+            .concat();
+            decode(&with_header(&sections))
+        };
+        assert_eq!(
+            body(&[0x01]).map(|m| m.funcs[0].body.clone()),
+            Ok(vec![Instruction::Nop])
+        );
+        assert_eq!(
+            body(&[0x06]),
+            Err(DecodeError {
+                offset: 0x17,
+                message: "illegal opcode".to_string()
+            })
+        );
+        assert_eq!(
+            body(&[0xfe, 0x03, 0x01]).map_err(|e| e.message),
+            Err("zero byte expected".to_string())
+        );
+        assert_eq!(
+            body(&[0xfc, 0x09, 0x00]).map_err(|e| e.message),
+            Err("data count section required".to_string())
+        );
+        assert_eq!(
+            body(&[0x02, 0x40, 0x0b]).map(|m| m.funcs[0].body.clone()),
+            Ok(vec![Instruction::Block(BlockType::Empty), Instruction::End])
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let wat = "(module
+                (type $t (func (param i32) (result i32)))
+                (import \"env\" \"log\" (func $log (param i32)))
+                (import \"env\" \"mem\" (memory 1 2 shared))
+                (table $tab 2 externref)
+                (tag $e (param i32))
+                (global $g (mut i64) (i64.const -1))
+                (func $f (type $t) (local f32 f32 v128)
+                  (block $b (result i32)
+                    (try_table (catch $e $b)
+                      (call $log (local.get 0))
+                      (throw $e (i32.const 7))))
+                  (if (local.get 0) (then (nop)) (else (unreachable)))
+                  (drop (select (f64.const 1.5) (f64.const nan:0x1) (i32.const 0)))
+                  (memory.init 1 (i32.const 0) (i32.const 0) (i32.const 1))
+                  (data.drop 0)
+                  (drop (i32x4.extract_lane 3 (v128.const i32x4 1 2 3 4)))
+                  (drop (i32.atomic.rmw.add offset=8 (i32.const 0) (i32.const 1)))
+                  atomic.fence)
+                (start $f)
+                (elem (table $tab) (i32.const 0) externref (ref.null extern))
+                (elem declare func $f)
+                (data \"\\00\\01\")
+                (data (i32.const 16) \"abc\")
+                (export \"f\" (func $f)))";
+        let (_, m) = module(wat).unwrap();
+        let bytes = encode(&m);
+        assert_eq!(decode(&bytes), Ok(m));
+    }
+
+    #[test]
+    fn names_decode() {
+        let name = |content: &[u8]| {
+            let mut section = vec![0x00, content.len() as u8 + 5, 0x04];
+            section.extend(b"name");
+            section.extend(content);
+            decode(&with_header(&section)).unwrap()
+        };
+        let m = name(&[0x00, 0x02, 0x01, b'm', 0x07, 0x04, 0x01, 0x00, 0x01, b'g']);
+        assert_eq!(m.names.module, Some("m".to_string()));
+        assert_eq!(m.names.globals, vec![(0, "g".to_string())]);
+        assert!(m.customs.is_empty());
+
+        // 解釈できない名前セクションは、カスタムセクションとして残す
+        for content in [
+            &[0x05, 0x01, 0x00][..],
+            &[0x01, 0x01, 0x00, 0x00, 0x02, 0x01, b'm'],
+            &[0x00, 0x02, 0x01, 0xff],
+        ] {
+            let m = name(content);
+            assert!(m.names.is_empty());
+            assert_eq!(
+                m.customs,
+                vec![Custom {
+                    name: "name".to_string(),
+                    place: CustomPlace::BeforeFirst,
+                    data: content.to_vec()
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn customs_decode() {
+        let custom = |name: &str, data: u8| {
+            let mut section = vec![0x00, name.len() as u8 + 2, name.len() as u8];
+            section.extend(name.as_bytes());
+            section.push(data);
+            section
+        };
+        let sections = [
+            custom("a", 1),
+            vec![0x01, 0x04, 0x01, 0x60, 0x00, 0x00],
+            custom("b", 2),
+            custom("a", 3),
+            vec![0x03, 0x02, 0x01, 0x00],
+            vec![0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b],
+            custom("c", 4),
+        ]
+        .concat();
+        let bytes = with_header(&sections);
+        let m = decode(&bytes).unwrap();
+        let places: Vec<_> = m
+            .customs
+            .iter()
+            .map(|c| (c.name.as_str(), c.place))
+            .collect();
+        assert_eq!(
+            places,
+            vec![
+                ("a", CustomPlace::BeforeFirst),
+                ("b", CustomPlace::After(Section::Type)),
+                ("a", CustomPlace::After(Section::Type)),
+                ("c", CustomPlace::After(Section::Code)),
+            ]
+        );
+        assert_eq!(m.custom("a"), Some(&[1][..]));
+
+        // 位置を保ったまま、元のバイト列に戻る
+        assert_eq!(encode(&m), bytes);
+    }
+
+    #[test]
+    fn test_wasm_decode() {
+        let bytes = include_bytes!("../../test.wasm");
+        let (_, mut m) = module(include_str!("../../test.wat")).unwrap();
+        m.names = Names::default();
+        assert_eq!(decode(bytes), Ok(m));
+        assert_eq!(encode(&decode(bytes).unwrap()), bytes.to_vec());
+
+        // 名前セクションを含むバイト列も、デコードしてエンコードすると元に戻る
+        let (_, m) = module(include_str!("../../test.wat")).unwrap();
+        let named = encode(&m);
+        assert_eq!(decode(&named), Ok(m));
+        assert_eq!(encode(&decode(&named).unwrap()), named);
+    }
+
+    #[test]
+    fn decode_features() {
+        let decode_wat = |wat: &str, features: Features| {
+            let (_, m) = module(wat).unwrap();
+            decode_with_features(&encode(&m), &features).map_err(|e| e.message)
+        };
+        let none = Features::none();
+        assert_eq!(
+            decode_wat("(module (func $f return_call $f))", none),
+            Err("tail-call instruction used but tail_call feature disabled".to_string())
+        );
+        assert_eq!(
+            decode_wat("(module (func (param v128)))", none),
+            Err("v128 value type used but simd feature disabled".to_string())
+        );
+        assert_eq!(
+            decode_wat("(module (memory 1 1 shared))", none),
+            Err("shared memory used but threads feature disabled".to_string())
+        );
+        assert_eq!(
+            decode_wat("(module (tag))", none),
+            Err("tag used but exceptions feature disabled".to_string())
+        );
+        assert_eq!(
+            decode_wat(
+                "(module (import \"env\" \"m\" (memory 1)) (memory 1))",
+                Features::default()
+            ),
+            Err("multiple memories used but multi_memory feature disabled".to_string())
+        );
+        assert!(decode_wat("(module (func (param v128)))", Features::default()).is_ok());
+
+        // 命令のエラーは、その命令のオペコードの位置を指す
+        let (_, m) = module("(module (func i32.const 0 i32.atomic.load drop) (memory 1))").unwrap();
+        let bytes = encode(&m);
+        let err = decode_with_features(&bytes, &none).unwrap_err();
+        assert_eq!(bytes[err.offset], 0xfe);
+    }
+}
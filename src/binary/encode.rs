@@ -0,0 +1,784 @@
+use crate::ast::{
+    instr::{self, Opcode, Shape},
+    BlockType, Catch, CustomPlace, Data, DataMode, Elem, ElemMode, Export, ExportDesc, Expr, Func,
+    FuncType, Global, GlobalType, Import, ImportDesc, IndirectNameMap, Instruction, Limits, MemArg,
+    MemType, Module, NameMap, Names, Section, TableType, Tag, ValueType,
+};
+
+use super::{
+    leb128::{encode_i32, encode_i33, encode_i64, encode_name, encode_u32, encode_vec},
+    SectionId, MAGIC, VERSION,
+};
+
+// 仕様
+// https://webassembly.github.io/spec/core/binary/modules.html
+
+// エンコードの設定
+// namesがfalseの場合は、モジュールが名前を持っていても名前セクションを出力しない
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub struct EncodeOptions {
+    pub names: bool,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self { names: true }
+    }
+}
+
+// モジュールを、名前セクションも含めてバイナリ形式にエンコードする
+pub fn encode(module: &Module) -> Vec<u8> {
+    encode_with(module, &EncodeOptions::default())
+}
+
+// モジュールをバイナリ形式にエンコードする
+// 空のセクションは出力しない
+pub fn encode_with(module: &Module, options: &EncodeOptions) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend(MAGIC);
+    buf.extend(VERSION);
+
+    customs(&mut buf, module, CustomPlace::BeforeFirst);
+    for s in Section::ALL {
+        customs(&mut buf, module, CustomPlace::Before(s));
+        match s {
+            Section::Type => section(&mut buf, SectionId::Type, &module.types, func_type),
+            Section::Import => section(&mut buf, SectionId::Import, &module.imports, import),
+            Section::Func => section(&mut buf, SectionId::Function, &module.funcs, |b, f| {
+                encode_u32(b, f.f_type)
+            }),
+            Section::Table => section(&mut buf, SectionId::Table, &module.tables, table_type),
+            Section::Memory => section(&mut buf, SectionId::Memory, &module.mems, mem_type),
+            Section::Tag => section(&mut buf, SectionId::Tag, &module.tags, tag),
+            Section::Global => section(&mut buf, SectionId::Global, &module.globals, global),
+            Section::Export => section(&mut buf, SectionId::Export, &module.exports, export),
+            Section::Start => {
+                if let Some(start) = module.start {
+                    raw_section(&mut buf, SectionId::Start, |b| encode_u32(b, start));
+                }
+            }
+            Section::Elem => section(&mut buf, SectionId::Element, &module.elems, elem),
+            // memory.initとdata.dropはデータ数セクションを必要とする
+            Section::DataCount => {
+                if uses_data_count(module) {
+                    let count = module.datas.len() as u32;
+                    raw_section(&mut buf, SectionId::DataCount, |b| encode_u32(b, count));
+                }
+            }
+            Section::Code => section(&mut buf, SectionId::Code, &module.funcs, code),
+            Section::Data => {
+                section(&mut buf, SectionId::Data, &module.datas, data);
+                // 名前セクションはデータセクションの直後に置く
+                if options.names && !module.names.is_empty() {
+                    raw_section(&mut buf, SectionId::Custom, |b| names(b, &module.names));
+                }
+            }
+        }
+        customs(&mut buf, module, CustomPlace::After(s));
+    }
+    customs(&mut buf, module, CustomPlace::AfterLast);
+
+    buf
+}
+
+// 指定した位置のカスタムセクションを、モジュールでの順番に出力する
+fn customs(buf: &mut Vec<u8>, module: &Module, place: CustomPlace) {
+    for custom in module.customs.iter().filter(|c| c.place == place) {
+        raw_section(buf, SectionId::Custom, |b| {
+            encode_name(b, &custom.name);
+            b.extend(&custom.data);
+        });
+    }
+}
+
+// 要素のベクタをセクションとして出力する
+// 要素が空の場合は出力しない
+fn section<T>(buf: &mut Vec<u8>, id: SectionId, items: &[T], f: impl Fn(&mut Vec<u8>, &T)) {
+    if !items.is_empty() {
+        raw_section(buf, id, |b| encode_vec(b, items, &f));
+    }
+}
+
+// セクションIDと、内容のバイト数を前置したセクションを出力する
+fn raw_section(buf: &mut Vec<u8>, id: SectionId, f: impl FnOnce(&mut Vec<u8>)) {
+    let mut content = Vec::new();
+    f(&mut content);
+    buf.push(id as u8);
+    encode_u32(buf, content.len() as u32);
+    buf.extend(content);
+}
+
+// 名前セクションを出力する
+// 空のサブセクションは出力しない
+// https://webassembly.github.io/spec/core/appendix/custom.html#name-section
+fn names(buf: &mut Vec<u8>, names: &Names) {
+    fn subsection(buf: &mut Vec<u8>, id: u8, empty: bool, f: impl FnOnce(&mut Vec<u8>)) {
+        if empty {
+            return;
+        }
+        let mut content = Vec::new();
+        f(&mut content);
+        buf.push(id);
+        encode_u32(buf, content.len() as u32);
+        buf.extend(content);
+    }
+
+    encode_name(buf, "name");
+    if let Some(name) = &names.module {
+        subsection(buf, 0, false, |b| encode_name(b, name));
+    }
+    subsection(buf, 1, names.funcs.is_empty(), |b| {
+        name_map(b, &names.funcs)
+    });
+    subsection(buf, 2, names.locals.is_empty(), |b| {
+        indirect_name_map(b, &names.locals)
+    });
+    subsection(buf, 3, names.labels.is_empty(), |b| {
+        indirect_name_map(b, &names.labels)
+    });
+    subsection(buf, 4, names.types.is_empty(), |b| {
+        name_map(b, &names.types)
+    });
+    subsection(buf, 7, names.globals.is_empty(), |b| {
+        name_map(b, &names.globals)
+    });
+}
+
+fn name_map(buf: &mut Vec<u8>, map: &NameMap) {
+    encode_vec(buf, map, |b, (idx, name)| {
+        encode_u32(b, *idx);
+        encode_name(b, name);
+    });
+}
+
+fn indirect_name_map(buf: &mut Vec<u8>, map: &IndirectNameMap) {
+    encode_vec(buf, map, |b, (idx, names)| {
+        encode_u32(b, *idx);
+        name_map(b, names);
+    });
+}
+
+// https://webassembly.github.io/spec/core/binary/types.html#value-types
+fn value_type(buf: &mut Vec<u8>, t: &ValueType) {
+    let byte = match t {
+        ValueType::I32 => 0x7f,
+        ValueType::I64 => 0x7e,
+        ValueType::F32 => 0x7d,
+        ValueType::F64 => 0x7c,
+        ValueType::V128 => 0x7b,
+        ValueType::FuncRef => 0x70,
+        ValueType::ExternRef => 0x6f,
+        ValueType::ExnRef => 0x69,
+    };
+    buf.push(byte);
+}
+
+// https://webassembly.github.io/spec/core/binary/types.html#function-types
+fn func_type(buf: &mut Vec<u8>, (params, results): &FuncType) {
+    buf.push(0x60);
+    encode_vec(buf, params, value_type);
+    encode_vec(buf, results, value_type);
+}
+
+// 最大値の有無と共有メモリかどうかをフラグで表す
+// https://webassembly.github.io/spec/core/binary/types.html#limits
+// https://webassembly.github.io/threads/core/binary/types.html#limits
+fn limits(buf: &mut Vec<u8>, limits: &Limits, shared: bool) {
+    let flag = limits.max.is_some() as u8 | (shared as u8) << 1;
+    buf.push(flag);
+    encode_u32(buf, limits.min);
+    if let Some(max) = limits.max {
+        encode_u32(buf, max);
+    }
+}
+
+// https://webassembly.github.io/spec/core/binary/types.html#memory-types
+fn mem_type(buf: &mut Vec<u8>, mt: &MemType) {
+    limits(buf, &mt.limits, mt.shared);
+}
+
+// https://webassembly.github.io/spec/core/binary/types.html#table-types
+fn table_type(buf: &mut Vec<u8>, tt: &TableType) {
+    value_type(buf, &tt.elem);
+    limits(buf, &tt.limits, false);
+}
+
+// https://webassembly.github.io/spec/core/binary/types.html#global-types
+fn global_type(buf: &mut Vec<u8>, gt: &GlobalType) {
+    value_type(buf, &gt.value_type);
+    buf.push(gt.mutable as u8);
+}
+
+// 例外の属性は0のみが定義されている
+// https://webassembly.github.io/exception-handling/core/binary/modules.html#tag-section
+fn tag(buf: &mut Vec<u8>, tag: &Tag) {
+    buf.push(0x00);
+    encode_u32(buf, tag.f_type);
+}
+
+// https://webassembly.github.io/spec/core/binary/modules.html#import-section
+fn import(buf: &mut Vec<u8>, import: &Import) {
+    encode_name(buf, &import.module);
+    encode_name(buf, &import.name);
+    match &import.desc {
+        ImportDesc::Func(t) => {
+            buf.push(0x00);
+            encode_u32(buf, *t);
+        }
+        ImportDesc::Table(tt) => {
+            buf.push(0x01);
+            table_type(buf, tt);
+        }
+        ImportDesc::Memory(mt) => {
+            buf.push(0x02);
+            mem_type(buf, mt);
+        }
+        ImportDesc::Global(gt) => {
+            buf.push(0x03);
+            global_type(buf, gt);
+        }
+        ImportDesc::Tag(t) => {
+            buf.push(0x04);
+            tag(buf, t);
+        }
+    }
+}
+
+// https://webassembly.github.io/spec/core/binary/modules.html#global-section
+fn global(buf: &mut Vec<u8>, global: &Global) {
+    global_type(buf, &global.g_type);
+    expr(buf, &global.init);
+}
+
+// https://webassembly.github.io/spec/core/binary/modules.html#export-section
+fn export(buf: &mut Vec<u8>, export: &Export) {
+    encode_name(buf, &export.name);
+    let (kind, idx) = match export.desc {
+        ExportDesc::Func(x) => (0x00, x),
+        ExportDesc::Table(x) => (0x01, x),
+        ExportDesc::Memory(x) => (0x02, x),
+        ExportDesc::Global(x) => (0x03, x),
+        ExportDesc::Tag(x) => (0x04, x),
+    };
+    buf.push(kind);
+    encode_u32(buf, idx);
+}
+
+// エレメントセグメントを出力する
+// 初期値がすべてref.funcの場合は、関数のインデックスのベクタで表す
+// https://webassembly.github.io/spec/core/binary/modules.html#element-section
+fn elem(buf: &mut Vec<u8>, elem: &Elem) {
+    let funcs = elem
+        .init
+        .iter()
+        .map(|e| match e.as_slice() {
+            [Instruction::RefFunc(f)] => Some(*f),
+            _ => None,
+        })
+        .collect::<Option<Vec<u32>>>()
+        .filter(|_| elem.e_type == ValueType::FuncRef);
+
+    // 0x04のビットは初期値が式であること、0x01と0x02のビットはモードを表す
+    let mode = match &elem.mode {
+        ElemMode::Active { table: 0, .. } if elem.e_type == ValueType::FuncRef => 0x00,
+        ElemMode::Passive => 0x01,
+        ElemMode::Active { .. } => 0x02,
+        ElemMode::Declarative => 0x03,
+    };
+    let flag = mode | if funcs.is_some() { 0x00 } else { 0x04 };
+    buf.push(flag);
+    if let ElemMode::Active { table, offset } = &elem.mode {
+        if mode == 0x02 {
+            encode_u32(buf, *table);
+        }
+        expr(buf, offset);
+    }
+
+    // フラグが0と4のセグメントは、要素の種類を省略する
+    match funcs {
+        Some(funcs) => {
+            if mode != 0x00 {
+                buf.push(0x00); // elemkind funcref
+            }
+            encode_vec(buf, &funcs, |b, f| encode_u32(b, *f));
+        }
+        None => {
+            if mode != 0x00 {
+                value_type(buf, &elem.e_type);
+            }
+            encode_vec(buf, &elem.init, expr);
+        }
+    }
+}
+
+// https://webassembly.github.io/spec/core/binary/modules.html#data-section
+fn data(buf: &mut Vec<u8>, data: &Data) {
+    match &data.mode {
+        DataMode::Active { memory: 0, offset } => {
+            buf.push(0x00);
+            expr(buf, offset);
+        }
+        DataMode::Passive => buf.push(0x01),
+        DataMode::Active { memory, offset } => {
+            buf.push(0x02);
+            encode_u32(buf, *memory);
+            expr(buf, offset);
+        }
+    }
+    encode_vec(buf, &data.init, |b, byte| b.push(*byte));
+}
+
+// データ数セクションが必要かどうか
+// https://webassembly.github.io/spec/core/binary/modules.html#data-count-section
+fn uses_data_count(module: &Module) -> bool {
+    module
+        .funcs
+        .iter()
+        .flat_map(|f| &f.body)
+        .any(|i| matches!(i, Instruction::MemoryInit(..) | Instruction::DataDrop(_)))
+}
+
+// 関数のバイト数を前置して、ローカル変数と本体を出力する
+// 同じ型が連続するローカル変数は、個数と型の組にまとめる
+// https://webassembly.github.io/spec/core/binary/modules.html#code-section
+fn code(buf: &mut Vec<u8>, func: &Func) {
+    let mut runs: Vec<(u32, ValueType)> = Vec::new();
+    for t in &func.locals {
+        match runs.last_mut() {
+            Some((n, last)) if last == t => *n += 1,
+            _ => runs.push((1, *t)),
+        }
+    }
+
+    let mut body = Vec::new();
+    encode_vec(&mut body, &runs, |b, (n, t)| {
+        encode_u32(b, *n);
+        value_type(b, t);
+    });
+    expr(&mut body, &func.body);
+
+    encode_u32(buf, body.len() as u32);
+    buf.extend(body);
+}
+
+// 命令列と末尾のendを出力する
+// https://webassembly.github.io/spec/core/binary/instructions.html#expressions
+fn expr(buf: &mut Vec<u8>, expr: &Expr) {
+    for i in expr {
+        instruction(buf, i);
+    }
+    buf.push(0x0b);
+}
+
+fn opcode(buf: &mut Vec<u8>, op: Opcode) {
+    match op {
+        Opcode::Single(b) => buf.push(b),
+        Opcode::Prefixed(prefix, sub) => {
+            buf.push(prefix);
+            encode_u32(buf, sub);
+        }
+    }
+}
+
+// 0xFCをプレフィックスとする命令のオペコードを出力する
+fn misc(buf: &mut Vec<u8>, sub: u32) {
+    opcode(buf, Opcode::Prefixed(0xfc, sub));
+}
+
+// https://webassembly.github.io/spec/core/binary/instructions.html#memory-instructions
+fn memarg(buf: &mut Vec<u8>, m: &MemArg) {
+    encode_u32(buf, m.align);
+    encode_u32(buf, m.offset);
+}
+
+// 値の型はそのバイト、型のインデックスは33ビットの符号付き整数で表す
+// https://webassembly.github.io/spec/core/binary/instructions.html#control-instructions
+fn block_type(buf: &mut Vec<u8>, bt: &BlockType) {
+    match bt {
+        BlockType::Empty => buf.push(0x40),
+        BlockType::Value(t) => value_type(buf, t),
+        BlockType::Type(x) => encode_i33(buf, *x as i64),
+    }
+}
+
+// https://webassembly.github.io/exception-handling/core/binary/instructions.html#control-instructions
+fn catch(buf: &mut Vec<u8>, c: &Catch) {
+    match c {
+        Catch::Catch(x, l) | Catch::CatchRef(x, l) => {
+            buf.push(if matches!(c, Catch::Catch(..)) {
+                0x00
+            } else {
+                0x01
+            });
+            encode_u32(buf, *x);
+            encode_u32(buf, *l);
+        }
+        Catch::CatchAll(l) => {
+            buf.push(0x02);
+            encode_u32(buf, *l);
+        }
+        Catch::CatchAllRef(l) => {
+            buf.push(0x03);
+            encode_u32(buf, *l);
+        }
+    }
+}
+
+// 命令をエンコードする
+// 命令表にない即値を持つ命令はここで個別に扱い、それ以外は命令表の形に従う
+// https://webassembly.github.io/spec/core/binary/instructions.html
+fn instruction(buf: &mut Vec<u8>, instr: &Instruction) {
+    use Instruction as I;
+
+    // オペコードと1つのインデックスを持つ命令
+    let mut indexed = |op: u8, x: u32| {
+        buf.push(op);
+        encode_u32(buf, x);
+    };
+    match instr {
+        I::Br(l) => return indexed(0x0c, *l),
+        I::BrIf(l) => return indexed(0x0d, *l),
+        I::Call(f) => return indexed(0x10, *f),
+        I::ReturnCall(f) => return indexed(0x12, *f),
+        I::Throw(x) => return indexed(0x08, *x),
+        I::RefFunc(f) => return indexed(0xd2, *f),
+        I::LocalGet(x) => return indexed(0x20, *x as u32),
+        I::LocalSet(x) => return indexed(0x21, *x as u32),
+        I::LocalTee(x) => return indexed(0x22, *x as u32),
+        I::GlobalGet(x) => return indexed(0x23, *x),
+        I::GlobalSet(x) => return indexed(0x24, *x),
+        I::TableGet(x) => return indexed(0x25, *x),
+        I::TableSet(x) => return indexed(0x26, *x),
+        I::MemorySize(x) => return indexed(0x3f, *x),
+        I::MemoryGrow(x) => return indexed(0x40, *x),
+        _ => (),
+    }
+
+    match instr {
+        I::Block(bt) | I::Loop(bt) | I::If(bt) => {
+            buf.push(match instr {
+                I::Block(_) => 0x02,
+                I::Loop(_) => 0x03,
+                _ => 0x04,
+            });
+            block_type(buf, bt);
+        }
+        I::TryTable(bt, catches) => {
+            buf.push(0x1f);
+            block_type(buf, bt);
+            encode_vec(buf, catches, catch);
+        }
+        I::BrTable(labels, default) => {
+            buf.push(0x0e);
+            encode_vec(buf, labels, |b, l| encode_u32(b, *l));
+            encode_u32(buf, *default);
+        }
+        I::CallIndirect(t, table) | I::ReturnCallIndirect(t, table) => {
+            buf.push(match instr {
+                I::CallIndirect(..) => 0x11,
+                _ => 0x13,
+            });
+            encode_u32(buf, *t);
+            encode_u32(buf, *table);
+        }
+        I::RefNull(t) => {
+            buf.push(0xd0);
+            value_type(buf, t);
+        }
+        I::Select(None) => buf.push(0x1b),
+        I::Select(Some(t)) => {
+            buf.push(0x1c);
+            encode_vec(buf, &[*t], value_type);
+        }
+        I::I32Const(n) => {
+            buf.push(0x41);
+            encode_i32(buf, *n);
+        }
+        I::I64Const(n) => {
+            buf.push(0x42);
+            encode_i64(buf, *n);
+        }
+        I::F32Const(bits) => {
+            buf.push(0x43);
+            buf.extend(bits.to_le_bytes());
+        }
+        I::F64Const(bits) => {
+            buf.push(0x44);
+            buf.extend(bits.to_le_bytes());
+        }
+        I::MemoryInit(d, m) => {
+            misc(buf, 8);
+            encode_u32(buf, *d);
+            encode_u32(buf, *m);
+        }
+        I::DataDrop(d) => {
+            misc(buf, 9);
+            encode_u32(buf, *d);
+        }
+        I::MemoryCopy(dst, src) => {
+            misc(buf, 10);
+            encode_u32(buf, *dst);
+            encode_u32(buf, *src);
+        }
+        I::MemoryFill(m) => {
+            misc(buf, 11);
+            encode_u32(buf, *m);
+        }
+        I::TableInit(e, t) => {
+            misc(buf, 12);
+            encode_u32(buf, *e);
+            encode_u32(buf, *t);
+        }
+        I::ElemDrop(e) => {
+            misc(buf, 13);
+            encode_u32(buf, *e);
+        }
+        I::TableCopy(dst, src) => {
+            misc(buf, 14);
+            encode_u32(buf, *dst);
+            encode_u32(buf, *src);
+        }
+        I::TableGrow(t) => {
+            misc(buf, 15);
+            encode_u32(buf, *t);
+        }
+        I::TableSize(t) => {
+            misc(buf, 16);
+            encode_u32(buf, *t);
+        }
+        I::TableFill(t) => {
+            misc(buf, 17);
+            encode_u32(buf, *t);
+        }
+        I::AtomicFence => {
+            opcode(buf, Opcode::Prefixed(0xfe, 0x03));
+            buf.push(0x00);
+        }
+        I::V128Const(v) => {
+            opcode(buf, Opcode::Prefixed(0xfd, 12));
+            buf.extend(v.to_le_bytes());
+        }
+        I::I8x16Shuffle(lanes) => {
+            opcode(buf, Opcode::Prefixed(0xfd, 13));
+            buf.extend(lanes);
+        }
+        _ => {
+            let entry = instr::find(instr).expect("Instruction has to be in the table");
+            opcode(buf, entry.opcode);
+            match entry.shape {
+                Shape::Plain(_) => (),
+                Shape::Memory(..) => memarg(buf, &instr.memarg().expect("memarg")),
+                Shape::Lane(..) => buf.push(instr.lane().expect("lane")),
+                Shape::MemoryLane(..) => {
+                    memarg(buf, &instr.memarg().expect("memarg"));
+                    buf.push(instr.lane().expect("lane"));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{Custom, Tag},
+        wat::module::module,
+    };
+
+    fn encode_instr(i: Instruction) -> Vec<u8> {
+        let mut buf = Vec::new();
+        instruction(&mut buf, &i);
+        buf
+    }
+
+    #[test]
+    fn instruction_encode() {
+        assert_eq!(encode_instr(Instruction::I32Add), vec![0x6a]);
+        assert_eq!(encode_instr(Instruction::I32Const(-1)), vec![0x41, 0x7f]);
+        assert_eq!(
+            encode_instr(Instruction::F32Const(1.0f32.to_bits())),
+            vec![0x43, 0x00, 0x00, 0x80, 0x3f]
+        );
+        assert_eq!(
+            encode_instr(Instruction::I64Load(MemArg {
+                offset: 128,
+                align: 3
+            })),
+            vec![0x29, 0x03, 0x80, 0x01]
+        );
+        assert_eq!(encode_instr(Instruction::I32TruncSatF32S), vec![0xfc, 0x00]);
+        assert_eq!(
+            encode_instr(Instruction::I8x16ExtractLaneS(3)),
+            vec![0xfd, 0x15, 0x03]
+        );
+        assert_eq!(encode_instr(Instruction::I32x4Add), vec![0xfd, 0xae, 0x01]);
+        assert_eq!(
+            encode_instr(Instruction::AtomicFence),
+            vec![0xfe, 0x03, 0x00]
+        );
+        assert_eq!(
+            encode_instr(Instruction::BrTable(vec![0, 1], 2)),
+            vec![0x0e, 0x02, 0x00, 0x01, 0x02]
+        );
+        assert_eq!(
+            encode_instr(Instruction::Block(BlockType::Empty)),
+            vec![0x02, 0x40]
+        );
+        assert_eq!(
+            encode_instr(Instruction::TryTable(
+                BlockType::Value(ValueType::I32),
+                vec![Catch::Catch(0, 1), Catch::CatchAllRef(0)]
+            )),
+            vec![0x1f, 0x7f, 0x02, 0x00, 0x00, 0x01, 0x03, 0x00]
+        );
+        assert_eq!(
+            encode_instr(Instruction::MemoryCopy(0, 0)),
+            vec![0xfc, 0x0a, 0x00, 0x00]
+        );
+        assert_eq!(
+            encode_instr(Instruction::Select(Some(ValueType::F64))),
+            vec![0x1c, 0x01, 0x7c]
+        );
+    }
+
+    #[test]
+    fn sections_encode() {
+        let (_, m) = module(
+            "(module
+                (import \"env\" \"log\" (func $log (param i32)))
+                (table 1 funcref)
+                (memory 1 2)
+                (tag (param i32))
+                (global i32 (i32.const 42))
+                (func $f (local i32 i32 i64))
+                (start $f)
+                (elem (i32.const 0) $f)
+                (data (i32.const 0) \"a\"))",
+        )
+        .unwrap();
+        let bytes = encode(&Module {
+            customs: vec![
+                Custom {
+                    name: "c".to_string(),
+                    place: CustomPlace::AfterLast,
+                    data: vec![0xff],
+                },
+                Custom {
+                    name: "p".to_string(),
+                    place: CustomPlace::Before(Section::Import),
+                    data: vec![],
+                },
+                Custom {
+                    name: "d".to_string(),
+                    place: CustomPlace::After(Section::DataCount),
+                    data: vec![0x01],
+                },
+            ],
+            ..m
+        });
+        let expected = [
+            b"\0asm".to_vec(),
+            vec![0x01, 0x00, 0x00, 0x00],
+            // 型
+            vec![0x01, 0x08, 0x02, 0x60, 0x01, 0x7f, 0x00, 0x60, 0x00, 0x00],
+            // インポートの前のカスタム
+            vec![0x00, 0x02, 0x01, b'p'],
+            // インポート
+            vec![
+                0x02, 0x0b, 0x01, 0x03, b'e', b'n', b'v', 0x03, b'l', b'o', b'g', 0x00, 0x00,
+            ],
+            // 関数
+            vec![0x03, 0x02, 0x01, 0x01],
+            // テーブル
+            vec![0x04, 0x04, 0x01, 0x70, 0x00, 0x01],
+            // メモリ
+            vec![0x05, 0x04, 0x01, 0x01, 0x01, 0x02],
+            // タグ
+            vec![0x0d, 0x03, 0x01, 0x00, 0x00],
+            // グローバル
+            vec![0x06, 0x06, 0x01, 0x7f, 0x00, 0x41, 0x2a, 0x0b],
+            // 開始関数
+            vec![0x08, 0x01, 0x01],
+            // エレメント
+            vec![0x09, 0x07, 0x01, 0x00, 0x41, 0x00, 0x0b, 0x01, 0x01],
+            // 出力されないデータ数セクションの後ろのカスタム
+            vec![0x00, 0x03, 0x01, b'd', 0x01],
+            // コード
+            vec![0x0a, 0x08, 0x01, 0x06, 0x02, 0x02, 0x7f, 0x01, 0x7e, 0x0b],
+            // データ
+            vec![0x0b, 0x07, 0x01, 0x00, 0x41, 0x00, 0x0b, 0x01, b'a'],
+            // 名前
+            vec![
+                0x00, 0x10, 0x04, b'n', b'a', b'm', b'e', 0x01, 0x09, 0x02, 0x00, 0x03, b'l', b'o',
+                b'g', 0x01, 0x01, b'f',
+            ],
+            // カスタム
+            vec![0x00, 0x03, 0x01, b'c', 0xff],
+        ]
+        .concat();
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn elem_encode() {
+        let mut buf = Vec::new();
+        elem(
+            &mut buf,
+            &Elem {
+                e_type: ValueType::FuncRef,
+                init: vec![vec![Instruction::RefNull(ValueType::FuncRef)]],
+                mode: ElemMode::Passive,
+            },
+        );
+        assert_eq!(buf, vec![0x05, 0x70, 0x01, 0xd0, 0x70, 0x0b]);
+
+        let mut buf = Vec::new();
+        elem(
+            &mut buf,
+            &Elem {
+                e_type: ValueType::FuncRef,
+                init: vec![vec![Instruction::RefFunc(2)]],
+                mode: ElemMode::Declarative,
+            },
+        );
+        assert_eq!(buf, vec![0x03, 0x00, 0x01, 0x02]);
+
+        let mut buf = Vec::new();
+        tag(&mut buf, &Tag { f_type: 3 });
+        assert_eq!(buf, vec![0x00, 0x03]);
+    }
+
+    #[test]
+    fn test_wat_encode() {
+        // test.wasmは名前セクションを持たない
+        let (_, m) = module(include_str!("../../test.wat")).unwrap();
+        let options = EncodeOptions { names: false };
+        assert_eq!(
+            encode_with(&m, &options),
+            include_bytes!("../../test.wasm").to_vec()
+        );
+    }
+
+    // 名前セクションは、モジュールの最後のカスタムセクションとして書き出す
+    // https://webassembly.github.io/spec/core/appendix/custom.html#name-section
+    #[test]
+    fn names_encode() {
+        let (_, m) = module(include_str!("../../test.wat")).unwrap();
+        let bytes = encode(&m);
+        let section = [
+            &[0x00, 0x1c][..],
+            &[0x04, b'n', b'a', b'm', b'e'],
+            // 関数の名前
+            &[0x01, 0x06, 0x01, 0x00, 0x03, b'a', b'd', b'd'],
+            // ローカル変数の名前
+            &[0x02, 0x0d, 0x01, 0x00, 0x02],
+            &[0x00, 0x03, b'l', b'h', b's'],
+            &[0x01, 0x03, b'r', b'h', b's'],
+        ]
+        .concat();
+        let (body, custom) = bytes.split_at(bytes.len() - section.len());
+        assert_eq!(custom, section);
+        assert_eq!(body, include_bytes!("../../test.wasm"));
+    }
+}
@@ -0,0 +1,385 @@
+use super::DecodeError;
+
+// LEB128と、名前やベクタのような基本的な値のエンコードとデコード
+// デコードは読み込んだ値とバイト数を返し、エラーのオフセットは入力の先頭からの位置になる
+// https://webassembly.github.io/spec/core/binary/values.html
+// https://webassembly.github.io/spec/core/binary/conventions.html#vectors
+
+// デコードした値と、読み込んだバイト数
+pub type Result<T> = std::result::Result<(T, usize), DecodeError>;
+
+fn error<T>(offset: usize, message: &str) -> Result<T> {
+    Err(DecodeError {
+        offset,
+        message: message.to_string(),
+    })
+}
+
+// 符号無し整数をLEB128でエンコードする
+pub fn encode_u64(buf: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+pub fn encode_u32(buf: &mut Vec<u8>, n: u32) {
+    encode_u64(buf, n as u64);
+}
+
+// 符号付き整数をLEB128でエンコードする
+// 残りのビットが符号ビットと一致したところで終える
+pub fn encode_i64(buf: &mut Vec<u8>, mut n: i64) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        let done = (n == 0 && byte & 0x40 == 0) || (n == -1 && byte & 0x40 != 0);
+        if done {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+pub fn encode_i32(buf: &mut Vec<u8>, n: i32) {
+    encode_i64(buf, n as i64);
+}
+
+// ブロック型の型インデックスに使われる33ビットの符号付き整数
+pub fn encode_i33(buf: &mut Vec<u8>, n: i64) {
+    debug_assert!((-(1 << 32)..1 << 32).contains(&n));
+    encode_i64(buf, n);
+}
+
+// bitsビットの符号無し整数をLEB128でデコードする
+// 最大のバイト数を超える表現と、最後のバイトの使われないビットが0でない表現はエラーにする
+fn decode_unsigned(bytes: &[u8], bits: u32) -> Result<u64> {
+    let mut result = 0u64;
+    let mut shift = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        let value = (byte & 0x7f) as u64;
+        if shift + 7 >= bits {
+            if byte & 0x80 != 0 {
+                return error(i, "integer representation too long");
+            }
+            if value >> (bits - shift) != 0 {
+                return error(i, "integer too large");
+            }
+        }
+        result |= value << shift;
+        if byte & 0x80 == 0 {
+            return Ok((result, i + 1));
+        }
+        shift += 7;
+    }
+    error(bytes.len(), "unexpected end")
+}
+
+// bitsビットの符号付き整数をLEB128でデコードする
+// 最後のバイトの使われないビットは、符号ビットと一致しなければならない
+fn decode_signed(bytes: &[u8], bits: u32) -> Result<i64> {
+    let mut result = 0i64;
+    let mut shift = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        let value = byte & 0x7f;
+        if shift + 7 >= bits {
+            if byte & 0x80 != 0 {
+                return error(i, "integer representation too long");
+            }
+            let used = bits - shift;
+            let extended = ((value << (8 - used)) as i8) >> (8 - used);
+            if extended != ((value << 1) as i8) >> 1 {
+                return error(i, "integer too large");
+            }
+        }
+        result |= (value as i64) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            if shift < 64 && byte & 0x40 != 0 {
+                result |= -1 << shift;
+            }
+            return Ok((result, i + 1));
+        }
+    }
+    error(bytes.len(), "unexpected end")
+}
+
+pub fn decode_u32(bytes: &[u8]) -> Result<u32> {
+    decode_unsigned(bytes, 32).map(|(n, len)| (n as u32, len))
+}
+
+pub fn decode_u64(bytes: &[u8]) -> Result<u64> {
+    decode_unsigned(bytes, 64)
+}
+
+pub fn decode_i32(bytes: &[u8]) -> Result<i32> {
+    decode_signed(bytes, 32).map(|(n, len)| (n as i32, len))
+}
+
+pub fn decode_i33(bytes: &[u8]) -> Result<i64> {
+    decode_signed(bytes, 33)
+}
+
+pub fn decode_i64(bytes: &[u8]) -> Result<i64> {
+    decode_signed(bytes, 64)
+}
+
+// UTF-8のバイト数を前置した名前をエンコードする
+// https://webassembly.github.io/spec/core/binary/values.html#names
+pub fn encode_name(buf: &mut Vec<u8>, s: &str) {
+    encode_u32(buf, s.len() as u32);
+    buf.extend(s.as_bytes());
+}
+
+// 名前をデコードする
+// UTF-8として正しくないバイト列はエラーにする
+pub fn decode_name(bytes: &[u8]) -> Result<String> {
+    let (len, n) = decode_u32(bytes)?;
+    let len = len as usize;
+    let Some(body) = bytes[n..].get(..len) else {
+        return error(bytes.len(), "unexpected end");
+    };
+    match std::str::from_utf8(body) {
+        Ok(s) => Ok((s.to_string(), n + len)),
+        Err(_) => error(n, "malformed UTF-8 encoding"),
+    }
+}
+
+// 要素数を前置したベクタをエンコードする
+pub fn encode_vec<T>(buf: &mut Vec<u8>, items: &[T], f: impl Fn(&mut Vec<u8>, &T)) {
+    encode_u32(buf, items.len() as u32);
+    for item in items {
+        f(buf, item);
+    }
+}
+
+// 要素数を前置したベクタをデコードする
+// fは残りのバイト列から1つの要素をデコードし、要素と読み込んだバイト数を返す
+pub fn decode_vec<T>(bytes: &[u8], f: impl Fn(&[u8]) -> Result<T>) -> Result<Vec<T>> {
+    let (count, mut pos) = decode_u32(bytes)?;
+    // 要素は少なくとも1バイトなので、残りのバイト数より多くは確保しない
+    let mut items = Vec::with_capacity((count as usize).min(bytes.len() - pos));
+    for _ in 0..count {
+        let (item, len) = f(&bytes[pos..]).map_err(|e| DecodeError {
+            offset: e.offset + pos,
+            ..e
+        })?;
+        items.push(item);
+        pos += len;
+    }
+    Ok((items, pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded<T>(f: fn(&mut Vec<u8>, T), n: T) -> Vec<u8> {
+        let mut buf = Vec::new();
+        f(&mut buf, n);
+        buf
+    }
+
+    fn message<T: std::fmt::Debug>(r: Result<T>) -> (usize, String) {
+        let e = r.unwrap_err();
+        (e.offset, e.message)
+    }
+
+    #[test]
+    fn u32_encode() {
+        assert_eq!(encoded(encode_u32, 0), vec![0x00]);
+        assert_eq!(encoded(encode_u32, 127), vec![0x7f]);
+        assert_eq!(encoded(encode_u32, 128), vec![0x80, 0x01]);
+        assert_eq!(encoded(encode_u32, 16383), vec![0xff, 0x7f]);
+        assert_eq!(encoded(encode_u32, 16384), vec![0x80, 0x80, 0x01]);
+        assert_eq!(encoded(encode_u32, 624485), vec![0xe5, 0x8e, 0x26]);
+        assert_eq!(
+            encoded(encode_u32, u32::MAX),
+            vec![0xff, 0xff, 0xff, 0xff, 0x0f]
+        );
+        assert_eq!(encoded(encode_u64, u64::MAX).len(), 10);
+    }
+
+    #[test]
+    fn i64_encode() {
+        assert_eq!(encoded(encode_i64, 0), vec![0x00]);
+        assert_eq!(encoded(encode_i64, -1), vec![0x7f]);
+        assert_eq!(encoded(encode_i64, 63), vec![0x3f]);
+        assert_eq!(encoded(encode_i64, 64), vec![0xc0, 0x00]);
+        assert_eq!(encoded(encode_i64, -64), vec![0x40]);
+        assert_eq!(encoded(encode_i64, -65), vec![0xbf, 0x7f]);
+        assert_eq!(encoded(encode_i64, -123456), vec![0xc0, 0xbb, 0x78]);
+        assert_eq!(
+            encoded(encode_i32, i32::MIN),
+            vec![0x80, 0x80, 0x80, 0x80, 0x78]
+        );
+        assert_eq!(
+            encoded(encode_i32, i32::MAX),
+            vec![0xff, 0xff, 0xff, 0xff, 0x07]
+        );
+        assert_eq!(
+            encoded(encode_i64, i64::MIN),
+            vec![0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7f]
+        );
+        assert_eq!(
+            encoded(encode_i33, u32::MAX as i64),
+            vec![0xff, 0xff, 0xff, 0xff, 0x0f]
+        );
+    }
+
+    // 2の累乗とその前後の値で、エンコードとデコードが一致することを確かめる
+    #[test]
+    fn round_trip_boundaries() {
+        for bit in 0..64 {
+            for n in [(1u64 << bit) - 1, 1 << bit, (1 << bit) + 1] {
+                let buf = encoded(encode_u64, n);
+                assert_eq!(decode_u64(&buf), Ok((n, buf.len())));
+                if let Ok(n) = u32::try_from(n) {
+                    assert_eq!(decode_u32(&buf), Ok((n, buf.len())));
+                }
+
+                for n in [n as i64, (n as i64).wrapping_neg()] {
+                    let buf = encoded(encode_i64, n);
+                    assert_eq!(decode_i64(&buf), Ok((n, buf.len())));
+                    if let Ok(n) = i32::try_from(n) {
+                        assert_eq!(decode_i32(&buf), Ok((n, buf.len())));
+                    }
+                    if (-(1 << 32)..1 << 32).contains(&n) {
+                        assert_eq!(decode_i33(&buf), Ok((n, buf.len())));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unsigned_decode() {
+        assert_eq!(decode_u32(&[0x80, 0x01, 0xff]), Ok((128, 2)));
+        // 最大のバイト数までは冗長な表現も受け付ける
+        assert_eq!(decode_u32(&[0x80, 0x80, 0x80, 0x80, 0x00]), Ok((0, 5)));
+        assert_eq!(decode_u32(&[0xff, 0x80, 0x80, 0x80, 0x00]), Ok((127, 5)));
+        assert_eq!(
+            message(decode_u32(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00])),
+            (4, "integer representation too long".to_string())
+        );
+        // 最後のバイトの使われないビット
+        assert_eq!(
+            message(decode_u32(&[0xff, 0xff, 0xff, 0xff, 0x1f])),
+            (4, "integer too large".to_string())
+        );
+        assert_eq!(
+            message(decode_u32(&[0x80, 0x80, 0x80, 0x80, 0x70])),
+            (4, "integer too large".to_string())
+        );
+        assert_eq!(
+            decode_u64(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]),
+            Ok((u64::MAX, 10))
+        );
+        assert_eq!(
+            message(decode_u64(&[
+                0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x02
+            ])),
+            (9, "integer too large".to_string())
+        );
+        assert_eq!(
+            message(decode_u32(&[0x80, 0x80])),
+            (2, "unexpected end".to_string())
+        );
+        assert_eq!(message(decode_u32(&[])), (0, "unexpected end".to_string()));
+    }
+
+    #[test]
+    fn signed_decode() {
+        assert_eq!(decode_i32(&[0x7f]), Ok((-1, 1)));
+        assert_eq!(decode_i32(&[0xc0, 0xbb, 0x78]), Ok((-123456, 3)));
+        // 冗長な表現
+        assert_eq!(decode_i32(&[0xff, 0x7f]), Ok((-1, 2)));
+        assert_eq!(decode_i32(&[0x80, 0x00]), Ok((0, 2)));
+        assert_eq!(decode_i32(&[0xff, 0xff, 0xff, 0xff, 0x7f]), Ok((-1, 5)));
+        // 最後のバイトの使われないビットが符号ビットと一致しない
+        assert_eq!(
+            message(decode_i32(&[0xff, 0xff, 0xff, 0xff, 0x0f])),
+            (4, "integer too large".to_string())
+        );
+        assert_eq!(
+            message(decode_i32(&[0x80, 0x80, 0x80, 0x80, 0x70])),
+            (4, "integer too large".to_string())
+        );
+        assert_eq!(
+            message(decode_i32(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00])),
+            (4, "integer representation too long".to_string())
+        );
+        assert_eq!(
+            decode_i33(&[0xff, 0xff, 0xff, 0xff, 0x0f]),
+            Ok((u32::MAX as i64, 5))
+        );
+        assert_eq!(
+            decode_i33(&[0x80, 0x80, 0x80, 0x80, 0x70]),
+            Ok((-(1 << 32), 5))
+        );
+        assert!(decode_i33(&[0x80, 0x80, 0x80, 0x80, 0x60]).is_err());
+        assert_eq!(
+            decode_i64(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]),
+            Ok((i64::MAX, 10))
+        );
+        assert_eq!(
+            message(decode_i64(&[
+                0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01
+            ])),
+            (9, "integer too large".to_string())
+        );
+        assert_eq!(
+            message(decode_i64(&[
+                0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7e
+            ])),
+            (9, "integer too large".to_string())
+        );
+        assert_eq!(
+            message(decode_i64(&[0xff])),
+            (1, "unexpected end".to_string())
+        );
+    }
+
+    #[test]
+    fn name_codec() {
+        assert_eq!(encoded(encode_name, "add"), vec![0x03, b'a', b'd', b'd']);
+        assert_eq!(encoded(encode_name, ""), vec![0x00]);
+        let buf = encoded(encode_name, "日本");
+        assert_eq!(buf[0], 6);
+        assert_eq!(decode_name(&buf), Ok(("日本".to_string(), 7)));
+        assert_eq!(
+            message(decode_name(&[0x02, 0xc3, 0x28])),
+            (1, "malformed UTF-8 encoding".to_string())
+        );
+        assert_eq!(
+            message(decode_name(&[0x01, 0xed, 0xa0, 0x80])),
+            (1, "malformed UTF-8 encoding".to_string())
+        );
+        assert_eq!(
+            message(decode_name(&[0x04, b'a'])),
+            (2, "unexpected end".to_string())
+        );
+    }
+
+    #[test]
+    fn vec_codec() {
+        let mut buf = Vec::new();
+        encode_vec(&mut buf, &[1u32, 300], |b, n| encode_u32(b, *n));
+        assert_eq!(buf, vec![0x02, 0x01, 0xac, 0x02]);
+        assert_eq!(decode_vec(&buf, decode_u32), Ok((vec![1, 300], 4)));
+        assert_eq!(decode_vec(&[0x00], decode_u32), Ok((vec![], 1)));
+        // 要素のエラーのオフセットは、ベクタの先頭からの位置になる
+        assert_eq!(
+            message(decode_vec(&[0x02, 0x01, 0x80], decode_u32)),
+            (3, "unexpected end".to_string())
+        );
+        // 要素数が残りのバイト数より多い
+        assert!(decode_vec(&[0xff, 0xff, 0xff, 0xff, 0x0f], decode_u32).is_err());
+    }
+}
@@ -0,0 +1,34 @@
+pub mod decode;
+pub mod encode;
+pub mod leb128;
+
+pub use decode::{decode, decode_with_features, DecodeError};
+pub use encode::{encode, encode_with, EncodeOptions};
+
+// 仕様
+// https://webassembly.github.io/spec/core/binary/index.html
+
+// バイナリ形式の先頭に置かれるマジックナンバーとバージョン
+// https://webassembly.github.io/spec/core/binary/modules.html#binary-module
+pub const MAGIC: [u8; 4] = *b"\0asm";
+pub const VERSION: [u8; 4] = [0x01, 0x00, 0x00, 0x00];
+
+// セクションID
+// https://webassembly.github.io/spec/core/binary/modules.html#sections
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub enum SectionId {
+    Custom = 0,
+    Type = 1,
+    Import = 2,
+    Function = 3,
+    Table = 4,
+    Memory = 5,
+    Global = 6,
+    Export = 7,
+    Start = 8,
+    Element = 9,
+    Code = 10,
+    Data = 11,
+    DataCount = 12,
+    Tag = 13,
+}
@@ -0,0 +1,181 @@
+use std::{
+    collections::HashMap,
+    mem::{discriminant, Discriminant},
+};
+
+use crate::ast::Instruction;
+
+use super::{interpreter::Interpreter, store::Store, trap::RuntimeError, value::Value, Trap};
+
+// 命令ごとに消費する燃料の表
+// 表にない命令は、既定の量を消費する
+#[derive(Debug, PartialEq, Clone)]
+pub struct FuelCosts {
+    default: u64,
+    costs: HashMap<Discriminant<Instruction>, u64>,
+}
+
+impl Default for FuelCosts {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl FuelCosts {
+    pub fn new(default: u64) -> Self {
+        Self {
+            default,
+            costs: HashMap::new(),
+        }
+    }
+
+    // 命令の消費量を指定する
+    // 即値の値によらず、同じ種類の命令は同じ量を消費する
+    pub fn set(&mut self, i: &Instruction, cost: u64) -> &mut Self {
+        self.costs.insert(discriminant(i), cost);
+        self
+    }
+
+    pub fn cost(&self, i: &Instruction) -> u64 {
+        if self.costs.is_empty() {
+            return self.default;
+        }
+        self.costs
+            .get(&discriminant(i))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+// 燃料による実行の制限
+// 燃料を設定したストアでは、命令を実行するたびに燃料を消費し、足りなくなるとOutOfFuelでトラップする
+// 燃料が足りなくなった実行は中断され、燃料を補給してからresumeで再開できる
+impl Store {
+    // 燃料を設定し、燃料の消費を有効にする
+    pub fn set_fuel(&mut self, fuel: u64) {
+        self.fuel = Some(fuel);
+    }
+
+    // 燃料を補給する
+    // 燃料の消費が有効でない場合は、有効にする
+    pub fn add_fuel(&mut self, fuel: u64) {
+        self.fuel = Some(self.fuel.unwrap_or(0).saturating_add(fuel));
+    }
+
+    // 残りの燃料
+    // 燃料の消費が有効でない場合はNone
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    pub fn set_fuel_costs(&mut self, costs: FuelCosts) {
+        self.fuel_costs = costs;
+    }
+
+    // 燃料が足りなくなって中断した実行があるか
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.is_some()
+    }
+
+    // 中断した実行を、燃料が足りなくなった命令から再開する
+    // 戻り値は中断した関数呼び出しの戻り値で、再び燃料が足りなくなると再び中断する
+    // ホスト関数の中で燃料が足りなくなった実行は、再開できない
+    pub fn resume(&mut self) -> Result<Vec<Value>, RuntimeError> {
+        match self.suspended.take() {
+            Some(s) => Interpreter::resume(self, s),
+            None => Err(Trap::host("no suspended execution").into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{exec::Instance, wat::module::module};
+
+    const SUM: &str = "(module
+        (func (export \"sum\") (param $n i32) (result i32) (local $acc i32)
+          block $done
+            loop $next
+              local.get $n
+              i32.eqz
+              br_if $done
+              local.get $acc
+              local.get $n
+              i32.add
+              local.set $acc
+              local.get $n
+              i32.const 1
+              i32.sub
+              local.set $n
+              br $next
+            end
+          end
+          local.get $acc))";
+
+    fn instantiate(store: &mut Store) -> Instance {
+        let (_, m) = module(SUM).unwrap();
+        Instance::new(store, &m, &[]).unwrap()
+    }
+
+    #[test]
+    fn consume_and_resume() {
+        let mut store = Store::new();
+        let inst = instantiate(&mut store);
+        assert_eq!(store.fuel(), None);
+
+        // 1回のループで12命令、ループに入るまでに2命令、抜けるときに3命令と、最後に1命令を実行する
+        store.set_fuel(1000);
+        assert_eq!(
+            inst.invoke(&mut store, "sum", &[Value::I32(10)]),
+            Ok(vec![Value::I32(55)])
+        );
+        assert_eq!(store.fuel(), Some(1000 - (2 + 10 * 12 + 3 + 1)));
+
+        store.set_fuel(50);
+        let e = inst
+            .invoke(&mut store, "sum", &[Value::I32(100)])
+            .unwrap_err();
+        assert_eq!(e.trap, Trap::OutOfFuel);
+        assert_eq!(store.fuel(), Some(0));
+        assert!(store.is_suspended());
+
+        // 燃料を少しずつ補給しながら再開する
+        let mut resumed = 0;
+        let result = loop {
+            store.add_fuel(100);
+            match store.resume() {
+                Err(e) if e.trap == Trap::OutOfFuel => resumed += 1,
+                result => break result,
+            }
+        };
+        assert_eq!(result, Ok(vec![Value::I32(5050)]));
+        assert_eq!(resumed, 11);
+        assert!(!store.is_suspended());
+        assert_eq!(
+            store.resume().map_err(|e| e.trap),
+            Err(Trap::host("no suspended execution"))
+        );
+    }
+
+    #[test]
+    fn cost_table() {
+        let mut costs = FuelCosts::new(0);
+        costs
+            .set(&Instruction::I32Add, 10)
+            .set(&Instruction::LocalGet(0), 1);
+        assert_eq!(costs.cost(&Instruction::I32Add), 10);
+        assert_eq!(costs.cost(&Instruction::LocalGet(3)), 1);
+        assert_eq!(costs.cost(&Instruction::I32Sub), 0);
+
+        let mut store = Store::new();
+        store.set_fuel_costs(costs);
+        let inst = instantiate(&mut store);
+        store.set_fuel(142);
+        assert_eq!(
+            inst.invoke(&mut store, "sum", &[Value::I32(10)]),
+            Ok(vec![Value::I32(55)])
+        );
+        assert_eq!(store.fuel(), Some(0));
+    }
+}
@@ -0,0 +1,202 @@
+use std::{marker::PhantomData, rc::Rc};
+
+use crate::ast::FuncType;
+
+use super::{
+    host::{Caller, HostFunc, IntoFunc, WasmTyList},
+    instance::Instance,
+    interpreter::{Code, Interpreter},
+    store::Store,
+    trap::{Result, RuntimeError, Trap},
+    value::Value,
+};
+
+// 関数のインスタンス
+// Wasmの関数は、定義したモジュールのインスタンスを持つ
+// https://webassembly.github.io/spec/core/exec/runtime.html#function-instances
+#[derive(Clone)]
+pub enum FuncInst {
+    Wasm {
+        ty: FuncType,
+        inst: Instance,
+        code: Rc<Code>,
+    },
+    Host {
+        ty: FuncType,
+        func: HostFunc,
+    },
+}
+
+impl FuncInst {
+    pub fn ty(&self) -> &FuncType {
+        match self {
+            Self::Wasm { ty, .. } | Self::Host { ty, .. } => ty,
+        }
+    }
+}
+
+// 関数のハンドル
+#[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
+pub struct Func(pub(crate) u32);
+
+impl Func {
+    // 型を指定してホスト関数を作成する
+    // 戻り値が関数の型と一致しない場合は、呼び出し元でトラップする
+    pub fn new(
+        store: &mut Store,
+        ty: FuncType,
+        func: impl Fn(&mut Caller<'_>, &[Value]) -> Result<Vec<Value>> + 'static,
+    ) -> Self {
+        store.funcs.push(FuncInst::Host {
+            ty,
+            func: Rc::new(func),
+        });
+        Self(store.funcs.len() as u32 - 1)
+    }
+
+    // クロージャからホスト関数を作成する
+    // 関数の型はクロージャの引数と戻り値の型から求める
+    pub fn wrap<Params, Results>(store: &mut Store, func: impl IntoFunc<Params, Results>) -> Self {
+        let (ty, func) = func.into_func();
+        store.funcs.push(FuncInst::Host { ty, func });
+        Self(store.funcs.len() as u32 - 1)
+    }
+
+    pub fn ty<'a>(&self, store: &'a Store) -> &'a FuncType {
+        store.funcs[self.0 as usize].ty()
+    }
+
+    // 関数を呼び出す
+    // 引数の数と型が関数の型と一致しない場合はトラップする
+    // https://webassembly.github.io/spec/core/exec/modules.html#invocation
+    pub fn call(
+        &self,
+        store: &mut Store,
+        args: &[Value],
+    ) -> std::result::Result<Vec<Value>, RuntimeError> {
+        let params = &self.ty(store).0;
+        if params.len() != args.len() || params.iter().zip(args).any(|(t, a)| *t != a.ty()) {
+            return Err(Trap::TypeMismatch.into());
+        }
+        Interpreter::new(store).call(*self, args)
+    }
+
+    // 引数と戻り値の型をRustの型で指定した関数にする
+    // 関数の型はここで一度だけ照合し、呼び出すときには照合しない
+    pub fn typed<Params: WasmTyList, Results: WasmTyList>(
+        &self,
+        store: &Store,
+    ) -> std::result::Result<TypedFunc<Params, Results>, RuntimeError> {
+        let (params, results) = self.ty(store);
+        if *params != Params::types() || *results != Results::types() {
+            return Err(Trap::TypeMismatch.into());
+        }
+        Ok(TypedFunc {
+            func: *self,
+            _marker: PhantomData,
+        })
+    }
+}
+
+// 型を照合済みの関数のハンドル
+// 値の列はWasmTyListで受け渡す
+pub struct TypedFunc<Params, Results> {
+    func: Func,
+    _marker: PhantomData<fn(Params) -> Results>,
+}
+
+impl<Params, Results> Clone for TypedFunc<Params, Results> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Params, Results> Copy for TypedFunc<Params, Results> {}
+
+impl<Params: WasmTyList, Results: WasmTyList> TypedFunc<Params, Results> {
+    pub fn func(&self) -> Func {
+        self.func
+    }
+
+    pub fn call(
+        &self,
+        store: &mut Store,
+        params: Params,
+    ) -> std::result::Result<Results, RuntimeError> {
+        let results = Interpreter::new(store).call(self.func, &params.into_values())?;
+        Ok(Results::from_values(&results))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wat::module::module;
+
+    #[test]
+    fn typed_funcs() {
+        let (_, m) = module(
+            "(module
+                (func (export \"add\") (param i32 i32) (result i32)
+                  local.get 0
+                  local.get 1
+                  i32.add)
+                (func (export \"split\") (param i64) (result i32 i32)
+                  local.get 0
+                  i32.wrap_i64
+                  local.get 0
+                  i64.const 32
+                  i64.shr_u
+                  i32.wrap_i64)
+                (func (export \"div\") (param i32) (result i32)
+                  i32.const 1
+                  local.get 0
+                  i32.div_u)
+                (func (export \"self\") (result funcref)
+                  ref.func 0)
+                (func (export \"nop\")))",
+        )
+        .unwrap();
+        let mut store = Store::new();
+        let inst = Instance::new(&mut store, &m, &[]).unwrap();
+        let add = inst
+            .get_typed_func::<(i32, i32), i32>(&store, "add")
+            .unwrap();
+        assert_eq!(add.call(&mut store, (1, 2)), Ok(3));
+        let split = inst
+            .get_typed_func::<i64, (i32, i32)>(&store, "split")
+            .unwrap();
+        assert_eq!(split.call(&mut store, 0x1_0000_0002), Ok((2, 1)));
+        let div = inst.get_typed_func::<i32, i32>(&store, "div").unwrap();
+        assert_eq!(
+            div.call(&mut store, 0).map_err(|e| e.trap),
+            Err(Trap::IntegerDivideByZero)
+        );
+        let f = inst
+            .get_typed_func::<(), Option<Func>>(&store, "self")
+            .unwrap();
+        assert_eq!(f.call(&mut store, ()), Ok(Some(add.func())));
+        let nop = inst.get_typed_func::<(), ()>(&store, "nop").unwrap();
+        assert_eq!(nop.call(&mut store, ()), Ok(()));
+
+        // 型が合わない場合は取得するときにエラーになる
+        assert_eq!(
+            inst.get_typed_func::<(i32, i64), i32>(&store, "add")
+                .map(|f| f.func())
+                .map_err(|e| e.trap),
+            Err(Trap::TypeMismatch)
+        );
+        assert_eq!(
+            inst.get_typed_func::<(i32, i32), ()>(&store, "add")
+                .map(|f| f.func())
+                .map_err(|e| e.trap),
+            Err(Trap::TypeMismatch)
+        );
+        assert_eq!(
+            inst.get_typed_func::<(), ()>(&store, "missing")
+                .map(|f| f.func())
+                .map_err(|e| e.to_string()),
+            Err("unknown export function \"missing\"".to_string())
+        );
+    }
+}
@@ -0,0 +1,408 @@
+use std::rc::Rc;
+
+use crate::ast::{FuncType, ValueType};
+
+use super::{
+    func::Func,
+    instance::Instance,
+    memory::Memory,
+    store::{Extern, Store},
+    trap::{Result, Trap},
+    value::Value,
+};
+
+// 仕様
+// https://webassembly.github.io/spec/core/exec/runtime.html#function-instances
+
+// ホスト関数の本体
+// 引数の型は呼び出す前に、戻り値の型は呼び出した後に関数の型と照合する
+pub type HostFunc = Rc<dyn Fn(&mut Caller<'_>, &[Value]) -> Result<Vec<Value>>>;
+
+// ホスト関数に渡す、呼び出し元の情報
+// ストアと、呼び出し元の関数を定義したモジュールのインスタンスを持つ
+// 埋め込み側から直接呼び出した場合、インスタンスはない
+pub struct Caller<'a> {
+    pub(crate) store: &'a mut Store,
+    pub(crate) inst: Option<Instance>,
+}
+
+impl Caller<'_> {
+    pub fn store(&mut self) -> &mut Store {
+        self.store
+    }
+
+    // 呼び出し元のインスタンスのエクスポート
+    pub fn get_export(&self, name: &str) -> Option<Extern> {
+        self.inst?.get_export(self.store, name)
+    }
+
+    // 呼び出し元のメモリ
+    // 慣例では、メモリは"memory"という名前でエクスポートされる
+    pub fn get_memory(&self, name: &str) -> Option<Memory> {
+        match self.get_export(name)? {
+            Extern::Memory(m) => Some(m),
+            _ => None,
+        }
+    }
+}
+
+// Wasmの値として受け渡しできるRustの型
+pub trait WasmTy: Sized {
+    fn ty() -> ValueType;
+    fn from_value(v: Value) -> Self;
+    fn into_value(self) -> Value;
+}
+
+macro_rules! wasm_ty {
+    ($t:ty, $vt:ident, $get:ident) => {
+        impl WasmTy for $t {
+            fn ty() -> ValueType {
+                ValueType::$vt
+            }
+
+            fn from_value(v: Value) -> Self {
+                v.$get()
+            }
+
+            fn into_value(self) -> Value {
+                Value::$vt(self)
+            }
+        }
+    };
+}
+
+wasm_ty!(i32, I32, i32);
+wasm_ty!(i64, I64, i64);
+wasm_ty!(f32, F32, f32);
+wasm_ty!(f64, F64, f64);
+wasm_ty!(u128, V128, v128);
+
+// 関数への参照で、nullはNoneで表す
+impl WasmTy for Option<Func> {
+    fn ty() -> ValueType {
+        ValueType::FuncRef
+    }
+
+    fn from_value(v: Value) -> Self {
+        match v {
+            Value::FuncRef(f) => f,
+            v => panic!("expected funcref, got {v:?}"),
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::FuncRef(self)
+    }
+}
+
+// Wasmの値の列として受け渡しできるRustの型
+// 値が1つの場合はその型、それ以外はタプルで表す
+pub trait WasmTyList: Sized {
+    fn types() -> Vec<ValueType>;
+    fn from_values(vs: &[Value]) -> Self;
+    fn into_values(self) -> Vec<Value>;
+}
+
+impl<T: WasmTy> WasmTyList for T {
+    fn types() -> Vec<ValueType> {
+        vec![T::ty()]
+    }
+
+    fn from_values(vs: &[Value]) -> Self {
+        T::from_value(vs[0])
+    }
+
+    fn into_values(self) -> Vec<Value> {
+        vec![self.into_value()]
+    }
+}
+
+macro_rules! wasm_ty_list {
+    ($($t:ident),*) => {
+        impl<$($t: WasmTy),*> WasmTyList for ($($t,)*) {
+            fn types() -> Vec<ValueType> {
+                vec![$($t::ty()),*]
+            }
+
+            #[allow(unused_variables, unused_mut, unused_assignments, clippy::unused_unit)]
+            fn from_values(vs: &[Value]) -> Self {
+                let mut i = 0;
+                ($({
+                    i += 1;
+                    $t::from_value(vs[i - 1])
+                },)*)
+            }
+
+            #[allow(non_snake_case)]
+            fn into_values(self) -> Vec<Value> {
+                let ($($t,)*) = self;
+                vec![$($t.into_value()),*]
+            }
+        }
+    };
+}
+
+wasm_ty_list!();
+wasm_ty_list!(A1);
+wasm_ty_list!(A1, A2);
+wasm_ty_list!(A1, A2, A3);
+wasm_ty_list!(A1, A2, A3, A4);
+wasm_ty_list!(A1, A2, A3, A4, A5);
+wasm_ty_list!(A1, A2, A3, A4, A5, A6);
+wasm_ty_list!(A1, A2, A3, A4, A5, A6, A7);
+wasm_ty_list!(A1, A2, A3, A4, A5, A6, A7, A8);
+
+// ホスト関数の戻り値にできる型
+// Errを返すとトラップする
+pub trait HostResult {
+    fn types() -> Vec<ValueType>;
+    fn into_result(self) -> Result<Vec<Value>>;
+}
+
+impl<T: WasmTyList> HostResult for T {
+    fn types() -> Vec<ValueType> {
+        T::types()
+    }
+
+    fn into_result(self) -> Result<Vec<Value>> {
+        Ok(self.into_values())
+    }
+}
+
+impl<T: WasmTyList> HostResult for std::result::Result<T, Trap> {
+    fn types() -> Vec<ValueType> {
+        T::types()
+    }
+
+    fn into_result(self) -> Result<Vec<Value>> {
+        self.map(T::into_values)
+    }
+}
+
+// 関数の型をシグネチャから求められるクロージャ
+// 最初の引数に&mut Callerを取るクロージャは、呼び出し元にアクセスできる
+// Paramsはシグネチャを区別するための型で、呼び出し側では推論に任せる
+pub trait IntoFunc<Params, Results> {
+    fn into_func(self) -> (FuncType, HostFunc);
+}
+
+macro_rules! into_func {
+    ($($t:ident),*) => {
+        impl<F, R, $($t,)*> IntoFunc<($($t,)*), R> for F
+        where
+            F: Fn($($t),*) -> R + 'static,
+            $($t: WasmTy,)*
+            R: HostResult,
+        {
+            fn into_func(self) -> (FuncType, HostFunc) {
+                let ty = (<($($t,)*) as WasmTyList>::types(), R::types());
+                let f = move |_: &mut Caller<'_>, args: &[Value]| {
+                    #[allow(non_snake_case)]
+                    let ($($t,)*) = <($($t,)*) as WasmTyList>::from_values(args);
+                    self($($t),*).into_result()
+                };
+                (ty, Rc::new(f))
+            }
+        }
+
+        impl<F, R, $($t,)*> IntoFunc<(Caller<'static>, $($t,)*), R> for F
+        where
+            F: Fn(&mut Caller<'_>, $($t),*) -> R + 'static,
+            $($t: WasmTy,)*
+            R: HostResult,
+        {
+            fn into_func(self) -> (FuncType, HostFunc) {
+                let ty = (<($($t,)*) as WasmTyList>::types(), R::types());
+                let f = move |caller: &mut Caller<'_>, args: &[Value]| {
+                    #[allow(non_snake_case)]
+                    let ($($t,)*) = <($($t,)*) as WasmTyList>::from_values(args);
+                    self(caller, $($t),*).into_result()
+                };
+                (ty, Rc::new(f))
+            }
+        }
+    };
+}
+
+into_func!();
+into_func!(A1);
+into_func!(A1, A2);
+into_func!(A1, A2, A3);
+into_func!(A1, A2, A3, A4);
+into_func!(A1, A2, A3, A4, A5);
+into_func!(A1, A2, A3, A4, A5, A6);
+into_func!(A1, A2, A3, A4, A5, A6, A7);
+into_func!(A1, A2, A3, A4, A5, A6, A7, A8);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{exec::Error, wat::module::module};
+
+    fn instantiate(store: &mut Store, wat: &str, imports: &[Extern]) -> Instance {
+        let (_, m) = module(wat).unwrap();
+        Instance::new(store, &m, imports).unwrap()
+    }
+
+    #[test]
+    fn wrap_closures() {
+        let mut store = Store::new();
+        let add = Func::wrap(&mut store, |a: i32, b: i32| -> i32 { a + b });
+        assert_eq!(
+            *add.ty(&store),
+            (vec![ValueType::I32, ValueType::I32], vec![ValueType::I32])
+        );
+        let swap = Func::wrap(&mut store, |a: i64, b: f64| (b, a));
+        assert_eq!(
+            *swap.ty(&store),
+            (
+                vec![ValueType::I64, ValueType::F64],
+                vec![ValueType::F64, ValueType::I64]
+            )
+        );
+        let nop = Func::wrap(&mut store, || {});
+        assert_eq!(*nop.ty(&store), (vec![], vec![]));
+
+        let inst = instantiate(
+            &mut store,
+            "(module
+                (import \"env\" \"add\" (func $add (param i32 i32) (result i32)))
+                (func (export \"twice\") (param i32) (result i32)
+                  local.get 0
+                  local.get 0
+                  call $add))",
+            &[Extern::Func(add)],
+        );
+        assert_eq!(
+            inst.invoke(&mut store, "twice", &[Value::I32(21)]),
+            Ok(vec![Value::I32(42)])
+        );
+        assert_eq!(
+            add.call(&mut store, &[Value::I32(1), Value::I32(2)]),
+            Ok(vec![Value::I32(3)])
+        );
+    }
+
+    #[test]
+    fn caller_memory() {
+        let mut store = Store::new();
+        // 呼び出し元のメモリから文字列を読み出し、先頭を大文字にする
+        let upper = Func::wrap(&mut store, |caller: &mut Caller<'_>, ptr: i32, len: i32| {
+            let mem = caller.get_memory("memory").unwrap();
+            let data = mem.data_mut(caller.store());
+            let s = &mut data[ptr as usize..(ptr + len) as usize];
+            s[0] = s[0].to_ascii_uppercase();
+            s.iter().filter(|c| c.is_ascii_lowercase()).count() as i32
+        });
+        let inst = instantiate(
+            &mut store,
+            "(module
+                (import \"env\" \"upper\" (func $upper (param i32 i32) (result i32)))
+                (memory (export \"memory\") 1)
+                (data (i32.const 8) \"hello\")
+                (func (export \"run\") (result i32)
+                  i32.const 8
+                  i32.const 5
+                  call $upper))",
+            &[Extern::Func(upper)],
+        );
+        assert_eq!(inst.invoke(&mut store, "run", &[]), Ok(vec![Value::I32(4)]));
+        let mem = inst.get_memory(&store, "memory").unwrap();
+        assert_eq!(&mem.data(&store)[8..13], b"Hello");
+    }
+
+    #[test]
+    fn tail_called_host() {
+        let mut store = Store::new();
+        // 末尾呼び出しでも、呼び出し元は末尾呼び出しをしたインスタンスになる
+        let peek = Func::wrap(&mut store, |caller: &mut Caller<'_>, addr: i32| {
+            let mem = caller.get_memory("memory").unwrap();
+            mem.data(caller.store())[addr as usize] as i32
+        });
+        let inst = instantiate(
+            &mut store,
+            "(module
+                (import \"env\" \"peek\" (func $peek (param i32) (result i32)))
+                (memory (export \"memory\") 1)
+                (data (i32.const 4) \"\\2a\")
+                (func $tail (export \"tail\") (param i32) (result i32)
+                  local.get 0
+                  return_call $peek)
+                (func (export \"nested\") (result i32)
+                  i32.const 4
+                  call $tail))",
+            &[Extern::Func(peek)],
+        );
+        let tail = inst.get_func(&store, "tail").unwrap();
+        let other = instantiate(
+            &mut store,
+            "(module
+                (import \"env\" \"tail\" (func $tail (param i32) (result i32)))
+                (memory (export \"memory\") 1)
+                (func (export \"run\") (result i32)
+                  i32.const 4
+                  call $tail))",
+            &[Extern::Func(tail)],
+        );
+        assert_eq!(
+            inst.invoke(&mut store, "tail", &[Value::I32(4)]),
+            Ok(vec![Value::I32(42)])
+        );
+        assert_eq!(
+            inst.invoke(&mut store, "nested", &[]),
+            Ok(vec![Value::I32(42)])
+        );
+        assert_eq!(
+            other.invoke(&mut store, "run", &[]),
+            Ok(vec![Value::I32(42)])
+        );
+    }
+
+    #[test]
+    fn host_traps() {
+        let mut store = Store::new();
+        let check = Func::wrap(&mut store, |a: i32| {
+            if a < 0 {
+                return Err(Trap::host("negative argument"));
+            }
+            Ok(a)
+        });
+        let wrong = Func::new(&mut store, (vec![], vec![ValueType::I32]), |_, _| {
+            Ok(vec![Value::I64(0)])
+        });
+        let inst = instantiate(
+            &mut store,
+            "(module
+                (import \"env\" \"check\" (func $check (param i32) (result i32)))
+                (import \"env\" \"wrong\" (func $wrong (result i32)))
+                (func (export \"check\") (param i32) (result i32)
+                  local.get 0
+                  call $check)
+                (func (export \"wrong\") (result i32)
+                  call $wrong))",
+            &[Extern::Func(check), Extern::Func(wrong)],
+        );
+        assert_eq!(
+            inst.invoke(&mut store, "check", &[Value::I32(3)]),
+            Ok(vec![Value::I32(3)])
+        );
+        assert_eq!(
+            inst.invoke(&mut store, "check", &[Value::I32(-1)])
+                .map_err(|e| e.trap),
+            Err(Trap::host("negative argument"))
+        );
+        assert_eq!(
+            inst.invoke(&mut store, "wrong", &[]).map_err(|e| e.trap),
+            Err(Trap::TypeMismatch)
+        );
+
+        // シグネチャが合わない関数はインポートできない
+        let (_, m) = module("(module (import \"env\" \"check\" (func (param i64))))").unwrap();
+        assert_eq!(
+            Instance::new(&mut store, &m, &[Extern::Func(check)]),
+            Err(Error::Link(
+                "incompatible import type env.check".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,556 @@
+use std::rc::Rc;
+
+use crate::{
+    ast::{
+        DataMode, ElemMode, ExportDesc, Expr, FuncType, ImportDesc, Instruction, Module, NameMap,
+    },
+    valid,
+};
+
+use super::{
+    func::{Func, FuncInst, TypedFunc},
+    host::WasmTyList,
+    interpreter::Code,
+    memory::Memory,
+    store::{match_limits, DataInst, ElemInst, Extern, Global, Store, Table, Tag},
+    trap::{Result, RuntimeError, Trap},
+    value::Value,
+    Error,
+};
+
+// 仕様
+// https://webassembly.github.io/spec/core/exec/modules.html
+
+// モジュールのインスタンス
+// 各インデックス空間を、インポートしたものから順にストアのハンドルに対応付ける
+// elemsとdatasは、ストアでのセグメントの位置を持つ
+// https://webassembly.github.io/spec/core/exec/runtime.html#module-instances
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ModuleInst {
+    pub types: Vec<FuncType>,
+    pub funcs: Vec<Func>,
+    pub tables: Vec<Table>,
+    pub mems: Vec<Memory>,
+    pub globals: Vec<Global>,
+    pub tags: Vec<Tag>,
+    pub elems: Vec<u32>,
+    pub datas: Vec<u32>,
+    pub exports: Vec<(String, Extern)>,
+    pub func_names: NameMap,
+}
+
+// モジュールのインスタンスのハンドル
+#[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
+pub struct Instance(pub(crate) u32);
+
+impl Instance {
+    // モジュールを、ストアで有効にした提案で検証してインスタンス化する
+    // importsはモジュールのインポートと同じ順序で並べる
+    // セグメントの初期化と開始関数のトラップは、インスタンス化のエラーになる
+    // https://webassembly.github.io/spec/core/exec/modules.html#instantiation
+    pub fn new(
+        store: &mut Store,
+        module: &Module,
+        imports: &[Extern],
+    ) -> std::result::Result<Self, Error> {
+        valid::validate_with_features(module, &store.features)?;
+        if imports.len() != module.imports.len() {
+            return Err(Error::Link(format!(
+                "expected {} imports, found {}",
+                module.imports.len(),
+                imports.len()
+            )));
+        }
+
+        store.check_limits(module)?;
+        let id = Self(store.instances.len() as u32);
+        let mut mi = ModuleInst {
+            types: module.types.clone(),
+            func_names: module.names.funcs.clone(),
+            ..Default::default()
+        };
+        for (import, ext) in module.imports.iter().zip(imports) {
+            if !matches_import(store, module, &import.desc, ext) {
+                return Err(Error::Link(format!(
+                    "incompatible import type {}.{}",
+                    import.module, import.name
+                )));
+            }
+            match *ext {
+                Extern::Func(f) => mi.funcs.push(f),
+                Extern::Table(t) => mi.tables.push(t),
+                Extern::Memory(m) => mi.mems.push(m),
+                Extern::Global(g) => mi.globals.push(g),
+                Extern::Tag(t) => mi.tags.push(t),
+            }
+        }
+
+        // グローバルとエレメントの初期値から参照できるように、関数を先に割り当てる
+        // https://webassembly.github.io/spec/core/exec/modules.html#alloc-module
+        for f in &module.funcs {
+            let ty = module.types[f.f_type as usize].clone();
+            store.funcs.push(FuncInst::Wasm {
+                code: Rc::new(Code::new(ty.clone(), f)),
+                ty,
+                inst: id,
+            });
+            mi.funcs.push(Func(store.funcs.len() as u32 - 1));
+        }
+        for g in &module.globals {
+            let v = const_expr(store, &mi, &g.init);
+            mi.globals.push(Global::new(store, g.g_type, v));
+        }
+        for t in &module.tables {
+            mi.tables
+                .push(Table::new(store, *t, Value::default_of(t.elem)));
+        }
+        for m in &module.mems {
+            mi.mems.push(Memory::new(store, *m));
+        }
+        for t in &module.tags {
+            let ty = module.types[t.f_type as usize].clone();
+            mi.tags.push(Tag::new(store, ty));
+        }
+        for e in &module.elems {
+            let elems = e.init.iter().map(|i| const_expr(store, &mi, i)).collect();
+            store.elems.push(ElemInst { elems });
+            mi.elems.push(store.elems.len() as u32 - 1);
+        }
+        for d in &module.datas {
+            store.datas.push(DataInst {
+                data: d.init.clone(),
+            });
+            mi.datas.push(store.datas.len() as u32 - 1);
+        }
+        for e in &module.exports {
+            let ext = match e.desc {
+                ExportDesc::Func(x) => Extern::Func(mi.funcs[x as usize]),
+                ExportDesc::Table(x) => Extern::Table(mi.tables[x as usize]),
+                ExportDesc::Memory(x) => Extern::Memory(mi.mems[x as usize]),
+                ExportDesc::Global(x) => Extern::Global(mi.globals[x as usize]),
+                ExportDesc::Tag(x) => Extern::Tag(mi.tags[x as usize]),
+            };
+            mi.exports.push((e.name.clone(), ext));
+        }
+        store.instances.push(mi);
+
+        id.initialize(store, module)?;
+        if let Some(start) = module.start {
+            let start = id.module(store).funcs[start as usize];
+            start.call(store, &[])?;
+        }
+        Ok(id)
+    }
+
+    // 能動的なセグメントをテーブルとメモリにコピーし、使い終わったセグメントを破棄する
+    // 範囲外へのコピーはトラップし、それまでのコピーは残る
+    // https://webassembly.github.io/spec/core/exec/modules.html#instantiation
+    fn initialize(&self, store: &mut Store, module: &Module) -> Result<()> {
+        for (i, e) in module.elems.iter().enumerate() {
+            let addr = self.module(store).elems[i] as usize;
+            match &e.mode {
+                ElemMode::Active { table, offset } => {
+                    let offset = const_expr(store, self.module(store), offset).i32() as u32;
+                    let table = self.module(store).tables[*table as usize];
+                    let elems = std::mem::take(&mut store.elems[addr].elems);
+                    let dst = &mut store.tables[table.0 as usize].elems;
+                    let Some(dst) = dst.get_mut(offset as usize..offset as usize + elems.len())
+                    else {
+                        return Err(Trap::TableOutOfBounds);
+                    };
+                    dst.copy_from_slice(&elems);
+                }
+                ElemMode::Declarative => store.elems[addr].elems.clear(),
+                ElemMode::Passive => (),
+            }
+        }
+        for (i, d) in module.datas.iter().enumerate() {
+            let addr = self.module(store).datas[i] as usize;
+            if let DataMode::Active { memory, offset } = &d.mode {
+                let offset = const_expr(store, self.module(store), offset).i32() as u32;
+                let mem = self.module(store).mems[*memory as usize];
+                let data = std::mem::take(&mut store.datas[addr].data);
+                let dst = &mut store.mems[mem.0 as usize].data;
+                let Some(dst) = dst.get_mut(offset as usize..offset as usize + data.len()) else {
+                    return Err(Trap::MemoryOutOfBounds);
+                };
+                dst.copy_from_slice(&data);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn module<'a>(&self, store: &'a Store) -> &'a ModuleInst {
+        &store.instances[self.0 as usize]
+    }
+
+    // エクスポートを、モジュールで定義された順に返す
+    pub fn exports<'a>(&self, store: &'a Store) -> impl Iterator<Item = (&'a str, Extern)> {
+        self.module(store)
+            .exports
+            .iter()
+            .map(|(name, ext)| (name.as_str(), *ext))
+    }
+
+    pub fn get_export(&self, store: &Store, name: &str) -> Option<Extern> {
+        self.exports(store)
+            .find(|(n, _)| *n == name)
+            .map(|(_, e)| e)
+    }
+
+    pub fn get_func(&self, store: &Store, name: &str) -> Option<Func> {
+        match self.get_export(store, name)? {
+            Extern::Func(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    pub fn get_table(&self, store: &Store, name: &str) -> Option<Table> {
+        match self.get_export(store, name)? {
+            Extern::Table(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    pub fn get_memory(&self, store: &Store, name: &str) -> Option<Memory> {
+        match self.get_export(store, name)? {
+            Extern::Memory(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    pub fn get_global(&self, store: &Store, name: &str) -> Option<Global> {
+        match self.get_export(store, name)? {
+            Extern::Global(g) => Some(g),
+            _ => None,
+        }
+    }
+
+    // エクスポートした関数を、引数と戻り値の型を指定して取得する
+    pub fn get_typed_func<Params: WasmTyList, Results: WasmTyList>(
+        &self,
+        store: &Store,
+        name: &str,
+    ) -> std::result::Result<TypedFunc<Params, Results>, RuntimeError> {
+        match self.get_func(store, name) {
+            Some(f) => f.typed(store),
+            None => Err(Trap::host(format!("unknown export function \"{name}\"")).into()),
+        }
+    }
+
+    // エクスポートした関数を呼び出す
+    pub fn invoke(
+        &self,
+        store: &mut Store,
+        name: &str,
+        args: &[Value],
+    ) -> std::result::Result<Vec<Value>, RuntimeError> {
+        match self.get_func(store, name) {
+            Some(f) => f.call(store, args),
+            None => Err(Trap::host(format!("unknown export function \"{name}\"")).into()),
+        }
+    }
+}
+
+// インポートする値が、モジュールのインポートの型に合うか
+// テーブルとメモリは、現在の大きさが制限に収まればよい
+// https://webassembly.github.io/spec/core/exec/modules.html#import-matching
+fn matches_import(store: &Store, module: &Module, desc: &ImportDesc, ext: &Extern) -> bool {
+    match (desc, ext) {
+        (ImportDesc::Func(t), Extern::Func(f)) => *f.ty(store) == module.types[*t as usize],
+        (ImportDesc::Table(expected), Extern::Table(t)) => {
+            let actual = t.ty(store);
+            actual.elem == expected.elem && match_limits(&actual.limits, &expected.limits)
+        }
+        (ImportDesc::Memory(expected), Extern::Memory(m)) => {
+            let actual = m.ty(store);
+            actual.shared == expected.shared && match_limits(&actual.limits, &expected.limits)
+        }
+        (ImportDesc::Global(expected), Extern::Global(g)) => g.ty(store) == *expected,
+        (ImportDesc::Tag(expected), Extern::Tag(t)) => {
+            *t.ty(store) == module.types[expected.f_type as usize]
+        }
+        _ => false,
+    }
+}
+
+// 定数式を評価する
+// 検証済みの定数式は、1つの値を積む命令だけからなる
+// https://webassembly.github.io/spec/core/valid/instructions.html#constant-expressions
+fn const_expr(store: &Store, mi: &ModuleInst, expr: &Expr) -> Value {
+    match expr.as_slice() {
+        [Instruction::I32Const(v)] => Value::I32(*v),
+        [Instruction::I64Const(v)] => Value::I64(*v),
+        [Instruction::F32Const(v)] => Value::F32(f32::from_bits(*v)),
+        [Instruction::F64Const(v)] => Value::F64(f64::from_bits(*v)),
+        [Instruction::V128Const(v)] => Value::V128(*v),
+        [Instruction::RefNull(t)] => Value::default_of(*t),
+        [Instruction::RefFunc(f)] => Value::FuncRef(Some(mi.funcs[*f as usize])),
+        [Instruction::GlobalGet(g)] => mi.globals[*g as usize].get(store),
+        e => unreachable!("invalid constant expression {e:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{GlobalType, Limits, MemType, TableType, ValueType},
+        features::Features,
+        wat::module::module,
+    };
+
+    fn instantiate(
+        store: &mut Store,
+        wat: &str,
+        imports: &[Extern],
+    ) -> std::result::Result<Instance, RuntimeError> {
+        let (_, m) = module(wat).unwrap();
+        Instance::new(store, &m, imports).map_err(|e| match e {
+            Error::Trap(t) => t,
+            e => panic!("{e}"),
+        })
+    }
+
+    #[test]
+    fn invoke_add() {
+        let mut store = Store::new();
+        let inst = instantiate(&mut store, include_str!("../../test.wat"), &[]).unwrap();
+        assert_eq!(
+            inst.invoke(&mut store, "add", &[Value::I32(1), Value::I32(2)]),
+            Ok(vec![Value::I32(3)])
+        );
+        assert_eq!(
+            inst.invoke(&mut store, "add", &[Value::I32(1)])
+                .map_err(|e| e.trap),
+            Err(Trap::TypeMismatch)
+        );
+        assert_eq!(
+            inst.invoke(&mut store, "sub", &[]).map_err(|e| e.trap),
+            Err(Trap::host("unknown export function \"sub\""))
+        );
+    }
+
+    #[test]
+    fn globals_and_start() {
+        let mut store = Store::new();
+        let imported = Global::new(
+            &mut store,
+            GlobalType {
+                value_type: ValueType::I32,
+                mutable: false,
+            },
+            Value::I32(5),
+        );
+        let inst = instantiate(
+            &mut store,
+            "(module
+                (import \"env\" \"base\" (global $base i32))
+                (global $g (export \"g\") (mut i32) (global.get $base))
+                (global $c i64 (i64.const 7))
+                (func $start
+                  global.get $g
+                  i32.const 10
+                  i32.mul
+                  global.set $g)
+                (func (export \"get\") (result i32 i64)
+                  global.get $g
+                  global.get $c)
+                (start $start))",
+            &[Extern::Global(imported)],
+        )
+        .unwrap();
+        assert_eq!(
+            inst.invoke(&mut store, "get", &[]),
+            Ok(vec![Value::I32(50), Value::I64(7)])
+        );
+        let g = inst.get_global(&store, "g").unwrap();
+        assert_eq!(g.set(&mut store, Value::I32(1)), Ok(()));
+        assert_eq!(
+            inst.invoke(&mut store, "get", &[]),
+            Ok(vec![Value::I32(1), Value::I64(7)])
+        );
+        assert_eq!(
+            imported.set(&mut store, Value::I32(0)),
+            Err(Trap::host("global is immutable"))
+        );
+    }
+
+    #[test]
+    fn shared_instances() {
+        // 2つのインスタンスが、エクスポートしたテーブルと関数を共有する
+        let mut store = Store::new();
+        let a = instantiate(
+            &mut store,
+            "(module
+                (table (export \"t\") 2 funcref)
+                (func $seven (export \"seven\") (result i32) i32.const 7)
+                (elem (i32.const 0) $seven))",
+            &[],
+        )
+        .unwrap();
+        let t = a.get_table(&store, "t").unwrap();
+        let seven = a.get_func(&store, "seven").unwrap();
+        let b = instantiate(
+            &mut store,
+            "(module
+                (import \"a\" \"t\" (table 1 funcref))
+                (import \"a\" \"seven\" (func $seven (result i32)))
+                (func $eight (result i32) i32.const 8)
+                (elem (i32.const 1) $eight)
+                (func (export \"call\") (param i32) (result i32)
+                  local.get 0
+                  call_indirect (result i32)))",
+            &[Extern::Table(t), Extern::Func(seven)],
+        )
+        .unwrap();
+        assert_eq!(
+            b.invoke(&mut store, "call", &[Value::I32(0)]),
+            Ok(vec![Value::I32(7)])
+        );
+        assert_eq!(
+            b.invoke(&mut store, "call", &[Value::I32(1)]),
+            Ok(vec![Value::I32(8)])
+        );
+        assert_eq!(t.get(&store, 0), Some(Value::FuncRef(Some(seven))));
+    }
+
+    #[test]
+    fn data_and_elem_initialization() {
+        let mut store = Store::new();
+        let inst = instantiate(
+            &mut store,
+            "(module
+                (memory (export \"m\") 1)
+                (data (i32.const 65534) \"hi\")
+                (data $p \"passive\"))",
+            &[],
+        )
+        .unwrap();
+        let m = inst.get_memory(&store, "m").unwrap();
+        assert_eq!(&store.mems[m.0 as usize].data[65534..], b"hi");
+        let mi = inst.module(&store);
+        assert_eq!(store.datas[mi.datas[0] as usize].data, b"");
+        assert_eq!(store.datas[mi.datas[1] as usize].data, b"passive");
+
+        assert_eq!(
+            instantiate(
+                &mut store,
+                "(module (memory 1) (data (i32.const 65535) \"hi\"))",
+                &[]
+            )
+            .map_err(|e| e.trap),
+            Err(Trap::MemoryOutOfBounds)
+        );
+        assert_eq!(
+            instantiate(
+                &mut store,
+                "(module (table 1 funcref) (func $f) (elem (i32.const 1) $f))",
+                &[]
+            )
+            .map_err(|e| e.trap),
+            Err(Trap::TableOutOfBounds)
+        );
+    }
+
+    #[test]
+    fn import_matching() {
+        let mut store = Store::new();
+        let mem = Memory::new(
+            &mut store,
+            MemType {
+                limits: Limits {
+                    min: 2,
+                    max: Some(3),
+                },
+                shared: false,
+            },
+        );
+        let table = Table::new(
+            &mut store,
+            TableType {
+                limits: Limits { min: 1, max: None },
+                elem: ValueType::FuncRef,
+            },
+            Value::FuncRef(None),
+        );
+        let link = |store: &mut Store, wat: &str, ext: Extern| {
+            let (_, m) = module(wat).unwrap();
+            Instance::new(store, &m, &[ext])
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        };
+        assert_eq!(
+            link(
+                &mut store,
+                "(module (import \"env\" \"m\" (memory 1 4)))",
+                Extern::Memory(mem)
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            link(
+                &mut store,
+                "(module (import \"env\" \"m\" (memory 3)))",
+                Extern::Memory(mem)
+            ),
+            Err("incompatible import type env.m".to_string())
+        );
+        assert_eq!(
+            link(
+                &mut store,
+                "(module (import \"env\" \"m\" (memory 1 2)))",
+                Extern::Memory(mem)
+            ),
+            Err("incompatible import type env.m".to_string())
+        );
+        assert_eq!(
+            link(
+                &mut store,
+                "(module (import \"env\" \"t\" (table 1 2 funcref)))",
+                Extern::Table(table)
+            ),
+            Err("incompatible import type env.t".to_string())
+        );
+        assert_eq!(
+            link(
+                &mut store,
+                "(module (import \"env\" \"f\" (func)))",
+                Extern::Table(table)
+            ),
+            Err("incompatible import type env.f".to_string())
+        );
+        let (_, m) = module("(module (import \"env\" \"f\" (func)))").unwrap();
+        assert_eq!(
+            Instance::new(&mut store, &m, &[]).unwrap_err().to_string(),
+            "expected 1 imports, found 0"
+        );
+    }
+
+    #[test]
+    fn features() {
+        let (_, m) = module(
+            "(module
+                (memory 1)
+                (memory (export \"m\") 1)
+                (data (memory 1) (i32.const 0) \"a\"))",
+        )
+        .unwrap();
+        let mut store = Store::new();
+        assert_eq!(
+            Instance::new(&mut store, &m, &[]).unwrap_err().to_string(),
+            "multiple memories used but multi_memory feature disabled"
+        );
+        store.set_features(Features {
+            multi_memory: true,
+            ..Features::default()
+        });
+        let inst = Instance::new(&mut store, &m, &[]).unwrap();
+        let mem = inst.get_memory(&store, "m").unwrap();
+        assert_eq!(mem.data(&store)[0], b'a');
+
+        // バイナリ形式の既定の提案は、インスタンス化の既定と同じ
+        let bytes = crate::binary::encode(&m);
+        assert!(crate::binary::decode(&bytes).is_err());
+    }
+}
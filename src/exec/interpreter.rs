@@ -0,0 +1,1786 @@
+use std::{collections::HashMap, ops::Range, rc::Rc};
+
+use crate::ast::{
+    instr, BlockType, Catch, Func as FuncBody, FuncType, Instruction as I, Instruction, MemArg,
+    ValueType,
+};
+
+use super::{
+    func::{Func, FuncInst},
+    host::{Caller, HostFunc},
+    instance::{Instance, ModuleInst},
+    numeric, simd,
+    store::{ExnInst, Store, Tag},
+    trap::{FrameInfo, Result, RuntimeError, Trap},
+    value::Value,
+};
+
+// 仕様
+// https://webassembly.github.io/spec/core/exec/instructions.html
+
+// 関数本体と、ブロックの対応を前もって求めた表
+// endsはブロックの開始とelseの位置から対応するendの位置を、elsesはifの位置から対応するelseの位置を引く
+#[derive(Debug, PartialEq, Clone)]
+pub struct Code {
+    pub ty: FuncType,
+    pub locals: Vec<ValueType>,
+    pub body: Vec<Instruction>,
+    ends: HashMap<usize, usize>,
+    elses: HashMap<usize, usize>,
+}
+
+impl Code {
+    pub fn new(ty: FuncType, func: &FuncBody) -> Self {
+        let mut ends = HashMap::new();
+        let mut elses = HashMap::new();
+        let mut opens = Vec::new();
+        for (pc, i) in func.body.iter().enumerate() {
+            match i {
+                I::Block(_) | I::Loop(_) | I::If(_) | I::TryTable(..) => opens.push(pc),
+                I::Else => {
+                    let start = *opens.last().expect("else has a matching if");
+                    elses.insert(start, pc);
+                    opens.push(pc);
+                }
+                I::End => {
+                    let start = opens.pop().expect("end has a matching block");
+                    ends.insert(start, pc);
+                    // elseのendは、対応するifのendでもある
+                    if matches!(func.body[start], I::Else) {
+                        let start = opens.pop().expect("else has a matching if");
+                        ends.insert(start, pc);
+                    }
+                }
+                _ => (),
+            }
+        }
+        Self {
+            ty,
+            locals: func.locals.clone(),
+            body: func.body.clone(),
+            ends,
+            elses,
+        }
+    }
+}
+
+// ブロックのラベル
+// arityは分岐で持ち出す値の数、heightはブロックに入る前のオペランドスタックの高さ、
+// contは分岐した後に実行を続ける位置
+// try_tableのラベルは、handlerにtry_table命令の位置を持つ
+// https://webassembly.github.io/spec/core/exec/runtime.html#labels
+#[derive(Debug, PartialEq, Clone)]
+struct Label {
+    arity: usize,
+    height: usize,
+    cont: usize,
+    is_loop: bool,
+    handler: Option<usize>,
+}
+
+// 関数の呼び出しフレーム
+// 関数本体の全体も1つのラベルとして扱い、最初のラベルへの分岐は関数からのreturnになる
+// instは関数を定義したモジュールのインスタンスで、インデックスの解決に使う
+// https://webassembly.github.io/spec/core/exec/runtime.html#frames
+#[derive(Debug, PartialEq, Clone)]
+struct Frame {
+    inst: Instance,
+    func: Func,
+    code: Rc<Code>,
+    pc: usize,
+    locals: Vec<Value>,
+    labels: Vec<Label>,
+    height: usize,
+}
+
+// オペランドスタックとフレームのスタックを持つ、スタックマシン
+// Wasmの関数呼び出しはホストの再帰呼び出しにせず、フレームのスタックに積む
+// out_of_fuelは、この実行の中で燃料が足りなくなったかを表す
+pub struct Interpreter<'a> {
+    store: &'a mut Store,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    out_of_fuel: bool,
+}
+
+// 呼び出しの深さとオペランドスタックの大きさの上限
+// フレームはヒープに確保するため、Wasmの再帰呼び出しでホストのスタックは消費しない
+// ホスト関数からWasmの関数を呼び出すとホストのスタックを消費するため、その入れ子の深さも制限する
+// 上限を超えるとStackExhaustedでトラップする
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub struct StackLimits {
+    pub max_call_depth: usize,
+    pub max_value_stack: usize,
+    pub max_host_nesting: usize,
+}
+
+impl Default for StackLimits {
+    fn default() -> Self {
+        Self {
+            max_call_depth: 65536,
+            max_value_stack: 1 << 20,
+            max_host_nesting: 100,
+        }
+    }
+}
+
+// ホスト関数の呼び出しで入れ子になった、外側の実行が使っている分
+// 内側の実行は、外側の分も含めて上限と比べる
+#[derive(Debug, PartialEq, Clone, Copy, Eq, Default)]
+pub(crate) struct Nesting {
+    frames: usize,
+    values: usize,
+    level: usize,
+}
+
+// 燃料が足りなくなって中断した実行
+// 燃料が足りなかった命令から再開できるように、スタックとフレームを残す
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Suspended {
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+}
+
+// 2つのオペランドを取り出し、演算の結果を積む
+macro_rules! binop {
+    ($self:ident, $get:ident, |$a:ident, $b:ident| $e:expr) => {{
+        let $b = $self.pop().$get();
+        let $a = $self.pop().$get();
+        $self.push($e);
+    }};
+}
+
+// 1つのオペランドを取り出し、演算の結果を積む
+macro_rules! unop {
+    ($self:ident, $get:ident, |$a:ident| $e:expr) => {{
+        let $a = $self.pop().$get();
+        $self.push($e);
+    }};
+}
+
+// メモリから読み出した値を積む
+// 2つ目の型を指定すると、読み出した値をその型に拡張する
+macro_rules! load {
+    ($self:ident, $m:expr, $t:ty) => {{
+        let b = $self.load_bytes($m)?;
+        $self.push(<$t>::from_le_bytes(b));
+    }};
+    ($self:ident, $m:expr, $t:ty => $u:ty) => {{
+        let b = $self.load_bytes($m)?;
+        $self.push(<$t>::from_le_bytes(b) as $u);
+    }};
+}
+
+// 値を取り出し、$t型に切り詰めてメモリに書き込む
+macro_rules! store {
+    ($self:ident, $m:expr, $get:ident as $t:ty) => {{
+        let v = $self.pop().$get() as $t;
+        $self.store_bytes($m, v.to_le_bytes())?;
+    }};
+}
+
+// 64ビットを読み出し、$n個のレーンをそれぞれ2倍の幅に拡張する
+macro_rules! load_extend {
+    ($self:ident, $m:expr, $t:ty => $u:ty, $n:literal) => {{
+        let v = u64::from_le_bytes($self.load_bytes($m)?) as u128;
+        let l: [$t; $n * 2] = simd::lanes(v);
+        let r: [$u; $n] = std::array::from_fn(|i| l[i] as $u);
+        $self.push(simd::from_lanes(r));
+    }};
+}
+
+// 1つの値を読み出し、全てのレーンに複製する
+macro_rules! load_splat {
+    ($self:ident, $m:expr, $t:ty, $n:literal) => {{
+        let x = <$t>::from_le_bytes($self.load_bytes($m)?);
+        $self.push(simd::from_lanes([x; $n]));
+    }};
+}
+
+// 1つの値を読み出し、ベクタのレーンを置き換える
+macro_rules! load_lane {
+    ($self:ident, $m:expr, $l:expr, $t:ty, $n:literal) => {{
+        let v = $self.pop().v128();
+        let x = <$t>::from_le_bytes($self.load_bytes($m)?);
+        let mut l: [$t; $n] = simd::lanes(v);
+        l[*$l as usize] = x;
+        $self.push(simd::from_lanes(l));
+    }};
+}
+
+// ベクタのレーンを1つ、メモリに書き込む
+macro_rules! store_lane {
+    ($self:ident, $m:expr, $l:expr, $t:ty, $n:literal) => {{
+        let l: [$t; $n] = simd::lanes($self.pop().v128());
+        $self.store_bytes($m, l[*$l as usize].to_le_bytes())?;
+    }};
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(store: &'a mut Store) -> Self {
+        Self {
+            store,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            out_of_fuel: false,
+        }
+    }
+
+    // 関数を呼び出し、戻り値を返す
+    // 引数の型は呼び出し元で確認する
+    // トラップした場合は、その時点のフレームをバックトレースとして返す
+    pub fn call(
+        &mut self,
+        func: Func,
+        args: &[Value],
+    ) -> std::result::Result<Vec<Value>, RuntimeError> {
+        self.stack.extend_from_slice(args);
+        let result = self.call_func(func);
+        self.run(result)
+    }
+
+    // 中断した実行を再開する
+    pub(crate) fn resume(
+        store: &'a mut Store,
+        suspended: Suspended,
+    ) -> std::result::Result<Vec<Value>, RuntimeError> {
+        let mut interp = Self {
+            stack: suspended.stack,
+            frames: suspended.frames,
+            ..Self::new(store)
+        };
+        interp.run(Ok(()))
+    }
+
+    // フレームがなくなるまで実行する
+    // 燃料が足りなくなった場合は、その命令から再開できるようにストアに実行を残す
+    fn run(&mut self, mut result: Result<()>) -> std::result::Result<Vec<Value>, RuntimeError> {
+        while let (Ok(()), Some(frame)) = (&result, self.frames.last()) {
+            let code = Rc::clone(&frame.code);
+            result = self.execute(&code);
+        }
+        if let Err(trap) = result {
+            let backtrace = self.backtrace();
+            if self.out_of_fuel {
+                self.frame().pc -= 1;
+                self.store.suspended = Some(Suspended {
+                    stack: std::mem::take(&mut self.stack),
+                    frames: std::mem::take(&mut self.frames),
+                });
+            } else {
+                // ホスト関数の中で中断した実行は、ホスト関数の続きを再開できない
+                if trap == Trap::OutOfFuel {
+                    self.store.suspended = None;
+                }
+                self.stack.clear();
+                self.frames.clear();
+            }
+            return Err(RuntimeError { trap, backtrace });
+        }
+        let results = std::mem::take(&mut self.stack);
+        results.iter().for_each(|v| self.store.exns.pin(v));
+        Ok(results)
+    }
+
+    // 関数の中で積んだ値も含めて、オペランドスタックの大きさが上限に収まるか
+    // 1つの命令で積む値の数は限られるため、命令ごとに実行する前に確認する
+    fn check_value_stack(&self) -> Result<()> {
+        if self.store.nesting.values + self.stack.len() > self.store.stack_limits.max_value_stack {
+            return Err(Trap::StackExhausted);
+        }
+        Ok(())
+    }
+
+    // 命令の実行に必要な燃料を消費する
+    // 燃料が足りない場合は消費せずにトラップする
+    fn consume_fuel(&mut self, i: &Instruction) -> Result<()> {
+        if let Some(fuel) = self.store.fuel {
+            let cost = self.store.fuel_costs.cost(i);
+            if fuel < cost {
+                self.out_of_fuel = true;
+                return Err(Trap::OutOfFuel);
+            }
+            self.store.fuel = Some(fuel - cost);
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, v: impl Into<Value>) {
+        self.stack.push(v.into());
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("operand stack is validated")
+    }
+
+    // スタックの上からn個の値を、積まれた順で取り出す
+    fn pop_n(&mut self, n: usize) -> Vec<Value> {
+        self.stack.split_off(self.stack.len() - n)
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("a frame is active")
+    }
+
+    // 現在のフレームの関数を定義したモジュールのインスタンス
+    fn module(&self) -> &ModuleInst {
+        let frame = self.frames.last().expect("a frame is active");
+        frame.inst.module(self.store)
+    }
+
+    // 関数を呼び出す
+    // Wasmの関数は、引数をスタックから取り出してローカル変数を初期化し、新しいフレームに入る
+    // ホスト関数は、その場で呼び出して戻り値をスタックに積む
+    // https://webassembly.github.io/spec/core/exec/instructions.html#function-calls
+    fn call_func(&mut self, func: Func) -> Result<()> {
+        let (inst, code) = match &self.store.funcs[func.0 as usize] {
+            FuncInst::Wasm { inst, code, .. } => (*inst, Rc::clone(code)),
+            FuncInst::Host { ty, func } => {
+                let (ty, func) = (ty.clone(), Rc::clone(func));
+                let inst = self.frames.last().map(|f| f.inst);
+                return self.call_host(&ty, func, inst);
+            }
+        };
+        // オペランドスタックの大きさは、ローカル変数も含めて関数に入るときに確認する
+        let limits = self.store.stack_limits;
+        let outer = self.store.nesting;
+        if outer.frames + self.frames.len() >= limits.max_call_depth
+            || outer.values + self.stack.len() + code.locals.len() > limits.max_value_stack
+        {
+            return Err(Trap::StackExhausted);
+        }
+        let mut locals = self.pop_n(code.ty.0.len());
+        locals.extend(code.locals.iter().map(|t| Value::default_of(*t)));
+        let height = self.stack.len();
+        let label = Label {
+            arity: code.ty.1.len(),
+            height,
+            cont: code.body.len(),
+            is_loop: false,
+            handler: None,
+        };
+        self.frames.push(Frame {
+            inst,
+            func,
+            code,
+            pc: 0,
+            locals,
+            labels: vec![label],
+            height,
+        });
+        Ok(())
+    }
+
+    // ホスト関数には、呼び出し元のインスタンスとストアを渡す
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-invoke-host
+    fn call_host(&mut self, ty: &FuncType, func: HostFunc, inst: Option<Instance>) -> Result<()> {
+        let args = self.pop_n(ty.0.len());
+        // 引数と、ホスト関数の中で受け取ったexnrefの固定は、呼び出しから戻ると外す
+        let pins = self.store.exns.pins();
+        args.iter().for_each(|v| self.store.exns.pin(v));
+        let outer = self.store.nesting;
+        if outer.level >= self.store.stack_limits.max_host_nesting {
+            return Err(Trap::StackExhausted);
+        }
+        self.store.nesting = Nesting {
+            frames: outer.frames + self.frames.len(),
+            values: outer.values + self.stack.len(),
+            level: outer.level + 1,
+        };
+        let mut caller = Caller {
+            store: self.store,
+            inst,
+        };
+        let results = func(&mut caller, &args);
+        self.store.nesting = outer;
+        self.store.exns.unpin_to(pins);
+        let results = results?;
+        if results.len() != ty.1.len() || results.iter().zip(&ty.1).any(|(r, t)| r.ty() != *t) {
+            return Err(Trap::TypeMismatch);
+        }
+        self.stack.extend(results);
+        Ok(())
+    }
+
+    // 戻り値をスタックに残してフレームから出る
+    fn pop_frame(&mut self) {
+        let frame = self.frames.pop().expect("a frame is active");
+        let results = self.pop_n(frame.code.ty.1.len());
+        self.stack.truncate(frame.height);
+        self.stack.extend(results);
+    }
+
+    // 末尾呼び出しは、呼び出し元のフレームを取り除いてから呼び出す
+    // ホスト関数の呼び出し元は、取り除いたフレームのインスタンスになる
+    // https://github.com/WebAssembly/tail-call/blob/main/proposals/tail-call/Overview.md
+    fn tail_call(&mut self, func: Func) -> Result<()> {
+        let n = func.ty(self.store).0.len();
+        let args = self.pop_n(n);
+        let frame = self.frames.pop().expect("a frame is active");
+        self.stack.truncate(frame.height);
+        self.stack.extend(args);
+        if let FuncInst::Host { ty, func } = &self.store.funcs[func.0 as usize] {
+            let (ty, func) = (ty.clone(), Rc::clone(func));
+            return self.call_host(&ty, func, Some(frame.inst));
+        }
+        self.call_func(func)
+    }
+
+    // call_indirectで呼び出す関数を、テーブルから取り出して型を確認する
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-call-indirect
+    fn indirect(&mut self, ty: u32, table: u32) -> Result<Func> {
+        let i = self.pop().i32() as u32;
+        let table = self.module().tables[table as usize];
+        let func = match table.get(self.store, i) {
+            None => return Err(Trap::UndefinedElement),
+            Some(Value::FuncRef(None)) => return Err(Trap::UninitializedElement),
+            Some(Value::FuncRef(Some(f))) => f,
+            Some(v) => unreachable!("funcref table holds {v:?}"),
+        };
+        if *func.ty(self.store) != self.module().types[ty as usize] {
+            return Err(Trap::IndirectCallTypeMismatch);
+        }
+        Ok(func)
+    }
+
+    fn block_type(&self, bt: &BlockType) -> (usize, usize) {
+        match bt {
+            BlockType::Empty => (0, 0),
+            BlockType::Value(_) => (0, 1),
+            BlockType::Type(idx) => {
+                let (params, results) = &self.module().types[*idx as usize];
+                (params.len(), results.len())
+            }
+        }
+    }
+
+    // ブロックに入る
+    // パラメータはスタックに残したまま、その下の高さをラベルに記録する
+    fn enter(&mut self, bt: &BlockType, cont: usize, is_loop: bool, handler: Option<usize>) {
+        let (params, results) = self.block_type(bt);
+        let height = self.stack.len() - params;
+        let arity = if is_loop { params } else { results };
+        self.frame().labels.push(Label {
+            arity,
+            height,
+            cont,
+            is_loop,
+            handler,
+        });
+    }
+
+    // n番目の外側のラベルに分岐する
+    // ループへの分岐はラベルを残して先頭から繰り返す
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-br
+    fn br(&mut self, n: u32) {
+        let frame = self.frame();
+        let idx = frame.labels.len() - 1 - n as usize;
+        let label = frame.labels[idx].clone();
+        frame
+            .labels
+            .truncate(if label.is_loop { idx + 1 } else { idx });
+        frame.pc = label.cont;
+        let vals = self.pop_n(label.arity);
+        self.stack.truncate(label.height);
+        self.stack.extend(vals);
+    }
+
+    // 例外を投げ、内側のtry_tableから順に捕捉するcatch節を探す
+    // 捕捉されるとtry_tableのブロックから出てcatch節のラベルに分岐し、
+    // 捕捉されずに全てのフレームから出るとトラップする
+    // https://webassembly.github.io/exception-handling/core/exec/instructions.html#exec-throw-ref
+    fn throw(&mut self, exn: u32) -> Result<()> {
+        let Some(ExnInst { tag, fields }) = self.store.exns.get(exn).cloned() else {
+            return Err(Trap::host("released exception reference"));
+        };
+        let Some((depth, idx, c)) = self.handler(tag) else {
+            // バックトレースに残すため、フレームは取り除かずにトラップする
+            return Err(Trap::UncaughtException);
+        };
+        if let Some(frame) = self.frames.get(depth + 1) {
+            let height = frame.height;
+            self.frames.truncate(depth + 1);
+            self.stack.truncate(height);
+        }
+        let (t, l, with_ref) = match c {
+            Catch::Catch(t, l) => (Some(t), l, false),
+            Catch::CatchRef(t, l) => (Some(t), l, true),
+            Catch::CatchAll(l) => (None, l, false),
+            Catch::CatchAllRef(l) => (None, l, true),
+        };
+        let height = self.frame().labels[idx].height;
+        self.frame().labels.truncate(idx);
+        self.stack.truncate(height);
+        if t.is_some() {
+            self.stack.extend_from_slice(&fields);
+        }
+        if with_ref {
+            self.push(Value::ExnRef(Some(exn)));
+        }
+        self.br(l);
+        Ok(())
+    }
+
+    // 到達できない例外を回収する
+    // オペランドスタックとローカル変数、グローバル、テーブル、セグメント、中断した実行の値から、例外をたどる
+    // ホスト関数から呼び出された実行では、外側の実行の値が見えないため回収しない
+    fn collect_exns(&mut self) {
+        let store = &mut *self.store;
+        let suspended = store.suspended.iter().flat_map(|s| {
+            s.stack
+                .iter()
+                .chain(s.frames.iter().flat_map(|f| &f.locals))
+        });
+        let roots = self
+            .stack
+            .iter()
+            .chain(self.frames.iter().flat_map(|f| &f.locals))
+            .chain(store.globals.iter().map(|g| &g.value))
+            .chain(store.tables.iter().flat_map(|t| &t.elems))
+            .chain(store.elems.iter().flat_map(|e| &e.elems))
+            .chain(suspended);
+        store.exns.collect(roots);
+    }
+
+    // tagの例外を捕捉するcatch節と、そのtry_tableのフレームとラベルの位置を探す
+    fn handler(&self, tag: Tag) -> Option<(usize, usize, Catch)> {
+        for (depth, frame) in self.frames.iter().enumerate().rev() {
+            let tags = &frame.inst.module(self.store).tags;
+            for (idx, label) in frame.labels.iter().enumerate().rev() {
+                let Some(pc) = label.handler else {
+                    continue;
+                };
+                let I::TryTable(_, catches) = &frame.code.body[pc] else {
+                    unreachable!("handler points to try_table");
+                };
+                let found = catches.iter().find(|c| match c {
+                    Catch::Catch(t, _) | Catch::CatchRef(t, _) => tags[*t as usize] == tag,
+                    Catch::CatchAll(_) | Catch::CatchAllRef(_) => true,
+                });
+                if let Some(c) = found {
+                    return Some((depth, idx, *c));
+                }
+            }
+        }
+        None
+    }
+
+    // 内側のフレームから順に、関数と実行中の命令の位置を集める
+    fn backtrace(&self) -> Vec<FrameInfo> {
+        let frames = self.frames.iter().rev().map(|frame| {
+            let module = frame.inst.module(self.store);
+            let func = module
+                .funcs
+                .iter()
+                .position(|f| *f == frame.func)
+                .expect("function is defined in its instance") as u32;
+            let name = module
+                .func_names
+                .iter()
+                .find(|(i, _)| *i == func)
+                .map(|(_, name)| name.clone());
+            FrameInfo {
+                func,
+                name,
+                offset: frame.pc - 1,
+            }
+        });
+        frames.collect()
+    }
+
+    // 現在のフレームのインスタンスで、memidxが指すメモリのストアでの位置
+    fn mem(&self, x: u32) -> usize {
+        self.module().mems[x as usize].0 as usize
+    }
+
+    // スタックから取り出したアドレスとmemargのオフセットが指す位置から、Nバイトを読み出す
+    // ロードとストアは、常に0番目のメモリを使う
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-load
+    fn load_bytes<const N: usize>(&mut self, m: &MemArg) -> Result<[u8; N]> {
+        let addr = self.pop().i32() as u32;
+        let mem = self.mem(0);
+        self.store.mems[mem].load(addr, m.offset)
+    }
+
+    // 書き込む値は、呼び出し元で先に取り出しておく
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-store
+    fn store_bytes<const N: usize>(&mut self, m: &MemArg, bytes: [u8; N]) -> Result<()> {
+        let addr = self.pop().i32() as u32;
+        let mem = self.mem(0);
+        self.store.mems[mem].store(addr, m.offset, bytes)
+    }
+
+    // メモリの範囲[offset, offset + n)が、大きさsizeに収まるか
+    // 範囲の終わりはu32で桁あふれしないように、確認した値から求める
+    fn check_memory(offset: u32, n: u32, size: usize) -> Result<Range<usize>> {
+        let end = offset as u64 + n as u64;
+        if end > size as u64 {
+            return Err(Trap::MemoryOutOfBounds);
+        }
+        Ok(offset as usize..end as usize)
+    }
+
+    // テーブルの範囲[offset, offset + n)が、大きさsizeに収まるか
+    fn check_table(offset: u32, n: u32, size: usize) -> Result<Range<usize>> {
+        let end = offset as u64 + n as u64;
+        if end > size as u64 {
+            return Err(Trap::TableOutOfBounds);
+        }
+        Ok(offset as usize..end as usize)
+    }
+
+    // 現在のフレームの命令を順に実行する
+    // 関数の呼び出しと関数からの復帰でフレームが変わると、呼び出し元に戻る
+    fn execute(&mut self, code: &Code) -> Result<()> {
+        loop {
+            let pc = self.frame().pc;
+            let Some(i) = code.body.get(pc) else {
+                self.pop_frame();
+                return Ok(());
+            };
+            self.frame().pc = pc + 1;
+            self.consume_fuel(i)?;
+            self.check_value_stack()?;
+            match i {
+                // Control Instructions
+                // https://webassembly.github.io/spec/core/exec/instructions.html#control-instructions
+                I::Unreachable => return Err(Trap::Unreachable),
+                I::Nop => (),
+                I::Block(bt) => self.enter(bt, code.ends[&pc] + 1, false, None),
+                I::Loop(bt) => self.enter(bt, pc + 1, true, None),
+                I::If(bt) => {
+                    let c = self.pop().i32();
+                    self.enter(bt, code.ends[&pc] + 1, false, None);
+                    if c == 0 {
+                        // elseがない場合はendに進み、ラベルを取り除く
+                        self.frame().pc = match code.elses.get(&pc) {
+                            Some(e) => e + 1,
+                            None => code.ends[&pc],
+                        };
+                    }
+                }
+                I::Else => self.frame().pc = code.ends[&pc],
+                I::End => {
+                    self.frame().labels.pop();
+                }
+                I::Br(l) => self.br(*l),
+                I::BrIf(l) => {
+                    if self.pop().i32() != 0 {
+                        self.br(*l);
+                    }
+                }
+                I::BrTable(ls, default) => {
+                    let i = self.pop().i32() as u32 as usize;
+                    self.br(*ls.get(i).unwrap_or(default));
+                }
+                I::Return => {
+                    self.pop_frame();
+                    return Ok(());
+                }
+                I::Call(f) => {
+                    let f = self.module().funcs[*f as usize];
+                    return self.call_func(f);
+                }
+                I::CallIndirect(ty, table) => {
+                    let f = self.indirect(*ty, *table)?;
+                    return self.call_func(f);
+                }
+                I::ReturnCall(f) => {
+                    let f = self.module().funcs[*f as usize];
+                    return self.tail_call(f);
+                }
+                I::ReturnCallIndirect(ty, table) => {
+                    let f = self.indirect(*ty, *table)?;
+                    return self.tail_call(f);
+                }
+
+                // Exception Handling
+                // https://webassembly.github.io/exception-handling/core/exec/instructions.html
+                // 例外を投げるとフレームが変わることがあるため、呼び出し元に戻る
+                I::TryTable(bt, _) => self.enter(bt, code.ends[&pc] + 1, false, Some(pc)),
+                I::Throw(x) => {
+                    // フィールドの値がスタックにあるうちに回収する
+                    if self.store.nesting.level == 0 && self.store.exns.should_collect() {
+                        self.collect_exns();
+                    }
+                    let tag = self.module().tags[*x as usize];
+                    let n = tag.ty(self.store).0.len();
+                    let fields = self.pop_n(n);
+                    let exn = self.store.exns.alloc(ExnInst { tag, fields });
+                    return self.throw(exn);
+                }
+                I::ThrowRef => match self.pop() {
+                    Value::ExnRef(Some(exn)) => return self.throw(exn),
+                    _ => return Err(Trap::NullReference),
+                },
+
+                // Reference Instructions
+                // https://webassembly.github.io/spec/core/exec/instructions.html#reference-instructions
+                I::RefNull(t) => self.push(Value::default_of(*t)),
+                I::RefIsNull => unop!(self, is_null, |a| a),
+                I::RefFunc(f) => {
+                    let f = self.module().funcs[*f as usize];
+                    self.push(Value::FuncRef(Some(f)));
+                }
+
+                // Parametric Instructions
+                // https://webassembly.github.io/spec/core/exec/instructions.html#parametric-instructions
+                I::Drop => {
+                    self.pop();
+                }
+                I::Select(_) => {
+                    let c = self.pop().i32();
+                    let b = self.pop();
+                    let a = self.pop();
+                    self.push(if c != 0 { a } else { b });
+                }
+
+                // Variable Instructions
+                // https://webassembly.github.io/spec/core/exec/instructions.html#variable-instructions
+                I::LocalGet(x) => {
+                    let v = self.frame().locals[*x];
+                    self.push(v);
+                }
+                I::LocalSet(x) => {
+                    let v = self.pop();
+                    self.frame().locals[*x] = v;
+                }
+                I::LocalTee(x) => {
+                    let v = *self.stack.last().expect("operand stack is validated");
+                    self.frame().locals[*x] = v;
+                }
+                I::GlobalGet(x) => {
+                    let v = self.module().globals[*x as usize].get(self.store);
+                    self.push(v);
+                }
+                I::GlobalSet(x) => {
+                    let v = self.pop();
+                    let g = self.module().globals[*x as usize];
+                    self.store.globals[g.0 as usize].value = v;
+                }
+
+                // Table Instructions
+                // https://webassembly.github.io/spec/core/exec/instructions.html#table-instructions
+                I::TableGet(x) => {
+                    let i = self.pop().i32() as u32;
+                    let t = self.module().tables[*x as usize];
+                    match t.get(self.store, i) {
+                        Some(v) => self.push(v),
+                        None => return Err(Trap::TableOutOfBounds),
+                    }
+                }
+                I::TableSet(x) => {
+                    let v = self.pop();
+                    let i = self.pop().i32() as u32;
+                    let t = self.module().tables[*x as usize];
+                    t.set(self.store, i, v)?;
+                }
+                I::TableSize(x) => {
+                    let t = self.module().tables[*x as usize];
+                    self.push(t.size(self.store) as i32);
+                }
+                // 広げられない場合は-1を積む
+                I::TableGrow(x) => {
+                    let n = self.pop().i32() as u32;
+                    let init = self.pop();
+                    let t = self.module().tables[*x as usize];
+                    let Store {
+                        tables, limiter, ..
+                    } = &mut *self.store;
+                    let old = tables[t.0 as usize].grow(n, init, &mut *limiter.0);
+                    self.push(old.map_or(-1, |old| old as i32));
+                }
+                I::TableFill(x) => {
+                    let n = self.pop().i32() as u32;
+                    let v = self.pop();
+                    let i = self.pop().i32() as u32;
+                    let t = self.module().tables[*x as usize];
+                    let elems = &mut self.store.tables[t.0 as usize].elems;
+                    let range = Self::check_table(i, n, elems.len())?;
+                    elems[range].fill(v);
+                }
+                // 範囲が重なる場合も、コピー元を先に読み出したように振る舞う
+                I::TableCopy(dst, src) => {
+                    let n = self.pop().i32() as u32;
+                    let s = self.pop().i32() as u32;
+                    let d = self.pop().i32() as u32;
+                    let dst = self.module().tables[*dst as usize].0 as usize;
+                    let src = self.module().tables[*src as usize].0 as usize;
+                    let s = Self::check_table(s, n, self.store.tables[src].elems.len())?;
+                    let d = Self::check_table(d, n, self.store.tables[dst].elems.len())?;
+                    if dst == src {
+                        self.store.tables[dst].elems.copy_within(s, d.start);
+                    } else {
+                        let vals = self.store.tables[src].elems[s].to_vec();
+                        self.store.tables[dst].elems[d].copy_from_slice(&vals);
+                    }
+                }
+                I::TableInit(e, x) => {
+                    let n = self.pop().i32() as u32;
+                    let s = self.pop().i32() as u32;
+                    let d = self.pop().i32() as u32;
+                    let e = self.module().elems[*e as usize] as usize;
+                    let t = self.module().tables[*x as usize].0 as usize;
+                    let s = Self::check_table(s, n, self.store.elems[e].elems.len())?;
+                    let d = Self::check_table(d, n, self.store.tables[t].elems.len())?;
+                    let vals = self.store.elems[e].elems[s].to_vec();
+                    self.store.tables[t].elems[d].copy_from_slice(&vals);
+                }
+                I::ElemDrop(e) => {
+                    let e = self.module().elems[*e as usize] as usize;
+                    self.store.elems[e].elems.clear();
+                }
+
+                // Memory Instructions
+                // https://webassembly.github.io/spec/core/exec/instructions.html#memory-instructions
+                I::I32Load(m) => load!(self, m, i32),
+                I::I64Load(m) => load!(self, m, i64),
+                I::F32Load(m) => load!(self, m, f32),
+                I::F64Load(m) => load!(self, m, f64),
+                I::I32Load8S(m) => load!(self, m, i8 => i32),
+                I::I32Load8U(m) => load!(self, m, u8 => i32),
+                I::I32Load16S(m) => load!(self, m, i16 => i32),
+                I::I32Load16U(m) => load!(self, m, u16 => i32),
+                I::I64Load8S(m) => load!(self, m, i8 => i64),
+                I::I64Load8U(m) => load!(self, m, u8 => i64),
+                I::I64Load16S(m) => load!(self, m, i16 => i64),
+                I::I64Load16U(m) => load!(self, m, u16 => i64),
+                I::I64Load32S(m) => load!(self, m, i32 => i64),
+                I::I64Load32U(m) => load!(self, m, u32 => i64),
+                I::I32Store(m) => store!(self, m, i32 as i32),
+                I::I64Store(m) => store!(self, m, i64 as i64),
+                I::F32Store(m) => store!(self, m, f32 as f32),
+                I::F64Store(m) => store!(self, m, f64 as f64),
+                I::I32Store8(m) => store!(self, m, i32 as u8),
+                I::I32Store16(m) => store!(self, m, i32 as u16),
+                I::I64Store8(m) => store!(self, m, i64 as u8),
+                I::I64Store16(m) => store!(self, m, i64 as u16),
+                I::I64Store32(m) => store!(self, m, i64 as u32),
+                I::MemorySize(x) => {
+                    let mem = self.module().mems[*x as usize];
+                    self.push(mem.size(self.store) as i32);
+                }
+                // 広げられない場合は-1を積む
+                I::MemoryGrow(x) => {
+                    let n = self.pop().i32() as u32;
+                    let mem = self.module().mems[*x as usize];
+                    let old = mem.grow(self.store, n);
+                    self.push(old.map_or(-1, |old| old as i32));
+                }
+                I::MemoryFill(x) => {
+                    let n = self.pop().i32() as u32;
+                    let v = self.pop().i32() as u8;
+                    let d = self.pop().i32() as u32;
+                    let mem = self.mem(*x);
+                    let data = &mut self.store.mems[mem].data;
+                    let range = Self::check_memory(d, n, data.len())?;
+                    data[range].fill(v);
+                }
+                // 範囲が重なる場合も、コピー元を先に読み出したように振る舞う
+                I::MemoryCopy(dst, src) => {
+                    let n = self.pop().i32() as u32;
+                    let s = self.pop().i32() as u32;
+                    let d = self.pop().i32() as u32;
+                    let (dst, src) = (self.mem(*dst), self.mem(*src));
+                    let s = Self::check_memory(s, n, self.store.mems[src].data.len())?;
+                    let d = Self::check_memory(d, n, self.store.mems[dst].data.len())?;
+                    if dst == src {
+                        self.store.mems[dst].data.copy_within(s, d.start);
+                    } else {
+                        let bytes = self.store.mems[src].data[s].to_vec();
+                        self.store.mems[dst].data[d].copy_from_slice(&bytes);
+                    }
+                }
+                I::MemoryInit(x, mem) => {
+                    let n = self.pop().i32() as u32;
+                    let s = self.pop().i32() as u32;
+                    let d = self.pop().i32() as u32;
+                    let x = self.module().datas[*x as usize] as usize;
+                    let mem = self.mem(*mem);
+                    let s = Self::check_memory(s, n, self.store.datas[x].data.len())?;
+                    let d = Self::check_memory(d, n, self.store.mems[mem].data.len())?;
+                    let bytes = &self.store.datas[x].data[s];
+                    self.store.mems[mem].data[d].copy_from_slice(bytes);
+                }
+                I::DataDrop(x) => {
+                    let x = self.module().datas[*x as usize] as usize;
+                    self.store.datas[x].data.clear();
+                }
+
+                // Numeric Instructions
+                // https://webassembly.github.io/spec/core/exec/instructions.html#numeric-instructions
+                I::I32Const(v) => self.push(*v),
+                I::I64Const(v) => self.push(*v),
+                I::F32Const(v) => self.push(f32::from_bits(*v)),
+                I::F64Const(v) => self.push(f64::from_bits(*v)),
+
+                I::I32Eqz => unop!(self, i32, |a| a == 0),
+                I::I32Eq => binop!(self, i32, |a, b| a == b),
+                I::I32Ne => binop!(self, i32, |a, b| a != b),
+                I::I32LtS => binop!(self, i32, |a, b| a < b),
+                I::I32LtU => binop!(self, i32, |a, b| (a as u32) < (b as u32)),
+                I::I32GtS => binop!(self, i32, |a, b| a > b),
+                I::I32GtU => binop!(self, i32, |a, b| (a as u32) > (b as u32)),
+                I::I32LeS => binop!(self, i32, |a, b| a <= b),
+                I::I32LeU => binop!(self, i32, |a, b| (a as u32) <= (b as u32)),
+                I::I32GeS => binop!(self, i32, |a, b| a >= b),
+                I::I32GeU => binop!(self, i32, |a, b| (a as u32) >= (b as u32)),
+                I::I64Eqz => unop!(self, i64, |a| a == 0),
+                I::I64Eq => binop!(self, i64, |a, b| a == b),
+                I::I64Ne => binop!(self, i64, |a, b| a != b),
+                I::I64LtS => binop!(self, i64, |a, b| a < b),
+                I::I64LtU => binop!(self, i64, |a, b| (a as u64) < (b as u64)),
+                I::I64GtS => binop!(self, i64, |a, b| a > b),
+                I::I64GtU => binop!(self, i64, |a, b| (a as u64) > (b as u64)),
+                I::I64LeS => binop!(self, i64, |a, b| a <= b),
+                I::I64LeU => binop!(self, i64, |a, b| (a as u64) <= (b as u64)),
+                I::I64GeS => binop!(self, i64, |a, b| a >= b),
+                I::I64GeU => binop!(self, i64, |a, b| (a as u64) >= (b as u64)),
+                I::F32Eq => binop!(self, f32, |a, b| a == b),
+                I::F32Ne => binop!(self, f32, |a, b| a != b),
+                I::F32Lt => binop!(self, f32, |a, b| a < b),
+                I::F32Gt => binop!(self, f32, |a, b| a > b),
+                I::F32Le => binop!(self, f32, |a, b| a <= b),
+                I::F32Ge => binop!(self, f32, |a, b| a >= b),
+                I::F64Eq => binop!(self, f64, |a, b| a == b),
+                I::F64Ne => binop!(self, f64, |a, b| a != b),
+                I::F64Lt => binop!(self, f64, |a, b| a < b),
+                I::F64Gt => binop!(self, f64, |a, b| a > b),
+                I::F64Le => binop!(self, f64, |a, b| a <= b),
+                I::F64Ge => binop!(self, f64, |a, b| a >= b),
+
+                I::I32Clz => unop!(self, i32, |a| a.leading_zeros() as i32),
+                I::I32Ctz => unop!(self, i32, |a| a.trailing_zeros() as i32),
+                I::I32Popcnt => unop!(self, i32, |a| a.count_ones() as i32),
+                I::I32Add => binop!(self, i32, |a, b| a.wrapping_add(b)),
+                I::I32Sub => binop!(self, i32, |a, b| a.wrapping_sub(b)),
+                I::I32Mul => binop!(self, i32, |a, b| a.wrapping_mul(b)),
+                I::I32DivS => binop!(self, i32, |a, b| numeric::i32_div_s(a, b)?),
+                I::I32DivU => binop!(self, i32, |a, b| numeric::i32_div_u(a, b)?),
+                I::I32RemS => binop!(self, i32, |a, b| numeric::i32_rem_s(a, b)?),
+                I::I32RemU => binop!(self, i32, |a, b| numeric::i32_rem_u(a, b)?),
+                I::I32And => binop!(self, i32, |a, b| a & b),
+                I::I32Or => binop!(self, i32, |a, b| a | b),
+                I::I32Xor => binop!(self, i32, |a, b| a ^ b),
+                I::I32Shl => binop!(self, i32, |a, b| a.wrapping_shl(b as u32)),
+                I::I32ShrS => binop!(self, i32, |a, b| a.wrapping_shr(b as u32)),
+                I::I32ShrU => binop!(self, i32, |a, b| (a as u32).wrapping_shr(b as u32) as i32),
+                I::I32Rotl => binop!(self, i32, |a, b| a.rotate_left(b as u32)),
+                I::I32Rotr => binop!(self, i32, |a, b| a.rotate_right(b as u32)),
+                I::I64Clz => unop!(self, i64, |a| a.leading_zeros() as i64),
+                I::I64Ctz => unop!(self, i64, |a| a.trailing_zeros() as i64),
+                I::I64Popcnt => unop!(self, i64, |a| a.count_ones() as i64),
+                I::I64Add => binop!(self, i64, |a, b| a.wrapping_add(b)),
+                I::I64Sub => binop!(self, i64, |a, b| a.wrapping_sub(b)),
+                I::I64Mul => binop!(self, i64, |a, b| a.wrapping_mul(b)),
+                I::I64DivS => binop!(self, i64, |a, b| numeric::i64_div_s(a, b)?),
+                I::I64DivU => binop!(self, i64, |a, b| numeric::i64_div_u(a, b)?),
+                I::I64RemS => binop!(self, i64, |a, b| numeric::i64_rem_s(a, b)?),
+                I::I64RemU => binop!(self, i64, |a, b| numeric::i64_rem_u(a, b)?),
+                I::I64And => binop!(self, i64, |a, b| a & b),
+                I::I64Or => binop!(self, i64, |a, b| a | b),
+                I::I64Xor => binop!(self, i64, |a, b| a ^ b),
+                I::I64Shl => binop!(self, i64, |a, b| a.wrapping_shl(b as u32)),
+                I::I64ShrS => binop!(self, i64, |a, b| a.wrapping_shr(b as u32)),
+                I::I64ShrU => binop!(self, i64, |a, b| (a as u64).wrapping_shr(b as u32) as i64),
+                I::I64Rotl => binop!(self, i64, |a, b| a.rotate_left(b as u32)),
+                I::I64Rotr => binop!(self, i64, |a, b| a.rotate_right(b as u32)),
+
+                // 符号の操作はビット演算で、NaNのペイロードを保つ
+                I::F32Abs => unop!(self, f32, |a| a.abs()),
+                I::F32Neg => unop!(self, f32, |a| -a),
+                I::F32Ceil => unop!(self, f32, |a| a.ceil()),
+                I::F32Floor => unop!(self, f32, |a| a.floor()),
+                I::F32Trunc => unop!(self, f32, |a| a.trunc()),
+                I::F32Nearest => unop!(self, f32, |a| a.round_ties_even()),
+                I::F32Sqrt => unop!(self, f32, |a| a.sqrt()),
+                I::F32Add => binop!(self, f32, |a, b| a + b),
+                I::F32Sub => binop!(self, f32, |a, b| a - b),
+                I::F32Mul => binop!(self, f32, |a, b| a * b),
+                I::F32Div => binop!(self, f32, |a, b| a / b),
+                I::F32Min => binop!(self, f32, |a, b| numeric::f32_min(a, b)),
+                I::F32Max => binop!(self, f32, |a, b| numeric::f32_max(a, b)),
+                I::F32Copysign => binop!(self, f32, |a, b| a.copysign(b)),
+                I::F64Abs => unop!(self, f64, |a| a.abs()),
+                I::F64Neg => unop!(self, f64, |a| -a),
+                I::F64Ceil => unop!(self, f64, |a| a.ceil()),
+                I::F64Floor => unop!(self, f64, |a| a.floor()),
+                I::F64Trunc => unop!(self, f64, |a| a.trunc()),
+                I::F64Nearest => unop!(self, f64, |a| a.round_ties_even()),
+                I::F64Sqrt => unop!(self, f64, |a| a.sqrt()),
+                I::F64Add => binop!(self, f64, |a, b| a + b),
+                I::F64Sub => binop!(self, f64, |a, b| a - b),
+                I::F64Mul => binop!(self, f64, |a, b| a * b),
+                I::F64Div => binop!(self, f64, |a, b| a / b),
+                I::F64Min => binop!(self, f64, |a, b| numeric::f64_min(a, b)),
+                I::F64Max => binop!(self, f64, |a, b| numeric::f64_max(a, b)),
+                I::F64Copysign => binop!(self, f64, |a, b| a.copysign(b)),
+
+                I::I32WrapI64 => unop!(self, i64, |a| a as i32),
+                I::I32TruncF32S => unop!(self, f32, |a| numeric::i32_trunc_s(a as f64)?),
+                I::I32TruncF32U => unop!(self, f32, |a| numeric::i32_trunc_u(a as f64)?),
+                I::I32TruncF64S => unop!(self, f64, |a| numeric::i32_trunc_s(a)?),
+                I::I32TruncF64U => unop!(self, f64, |a| numeric::i32_trunc_u(a)?),
+                I::I64ExtendI32S => unop!(self, i32, |a| a as i64),
+                I::I64ExtendI32U => unop!(self, i32, |a| a as u32 as i64),
+                I::I64TruncF32S => unop!(self, f32, |a| numeric::i64_trunc_s(a as f64)?),
+                I::I64TruncF32U => unop!(self, f32, |a| numeric::i64_trunc_u(a as f64)?),
+                I::I64TruncF64S => unop!(self, f64, |a| numeric::i64_trunc_s(a)?),
+                I::I64TruncF64U => unop!(self, f64, |a| numeric::i64_trunc_u(a)?),
+                I::F32ConvertI32S => unop!(self, i32, |a| a as f32),
+                I::F32ConvertI32U => unop!(self, i32, |a| a as u32 as f32),
+                I::F32ConvertI64S => unop!(self, i64, |a| a as f32),
+                I::F32ConvertI64U => unop!(self, i64, |a| a as u64 as f32),
+                I::F32DemoteF64 => unop!(self, f64, |a| a as f32),
+                I::F64ConvertI32S => unop!(self, i32, |a| a as f64),
+                I::F64ConvertI32U => unop!(self, i32, |a| a as u32 as f64),
+                I::F64ConvertI64S => unop!(self, i64, |a| a as f64),
+                I::F64ConvertI64U => unop!(self, i64, |a| a as u64 as f64),
+                I::F64PromoteF32 => unop!(self, f32, |a| a as f64),
+                I::I32ReinterpretF32 => unop!(self, f32, |a| a.to_bits() as i32),
+                I::I64ReinterpretF64 => unop!(self, f64, |a| a.to_bits() as i64),
+                I::F32ReinterpretI32 => unop!(self, i32, |a| f32::from_bits(a as u32)),
+                I::F64ReinterpretI64 => unop!(self, i64, |a| f64::from_bits(a as u64)),
+                I::I32Extend8S => unop!(self, i32, |a| a as i8 as i32),
+                I::I32Extend16S => unop!(self, i32, |a| a as i16 as i32),
+                I::I64Extend8S => unop!(self, i64, |a| a as i8 as i64),
+                I::I64Extend16S => unop!(self, i64, |a| a as i16 as i64),
+                I::I64Extend32S => unop!(self, i64, |a| a as i32 as i64),
+                // Rustの浮動小数点数から整数へのasは、飽和させてNaNを0にする
+                // https://webassembly.github.io/spec/core/exec/numerics.html#op-trunc-sat-s
+                I::I32TruncSatF32S => unop!(self, f32, |a| a as i32),
+                I::I32TruncSatF32U => unop!(self, f32, |a| a as u32 as i32),
+                I::I32TruncSatF64S => unop!(self, f64, |a| a as i32),
+                I::I32TruncSatF64U => unop!(self, f64, |a| a as u32 as i32),
+                I::I64TruncSatF32S => unop!(self, f32, |a| a as i64),
+                I::I64TruncSatF32U => unop!(self, f32, |a| a as u64 as i64),
+                I::I64TruncSatF64S => unop!(self, f64, |a| a as i64),
+                I::I64TruncSatF64U => unop!(self, f64, |a| a as u64 as i64),
+
+                // Vector Instructions
+                // https://webassembly.github.io/spec/core/exec/instructions.html#vector-instructions
+                I::V128Const(v) => self.push(*v),
+                I::V128Load(m) => load!(self, m, u128),
+                I::V128Load8x8S(m) => load_extend!(self, m, i8 => i16, 8),
+                I::V128Load8x8U(m) => load_extend!(self, m, u8 => u16, 8),
+                I::V128Load16x4S(m) => load_extend!(self, m, i16 => i32, 4),
+                I::V128Load16x4U(m) => load_extend!(self, m, u16 => u32, 4),
+                I::V128Load32x2S(m) => load_extend!(self, m, i32 => i64, 2),
+                I::V128Load32x2U(m) => load_extend!(self, m, u32 => u64, 2),
+                I::V128Load8Splat(m) => load_splat!(self, m, u8, 16),
+                I::V128Load16Splat(m) => load_splat!(self, m, u16, 8),
+                I::V128Load32Splat(m) => load_splat!(self, m, u32, 4),
+                I::V128Load64Splat(m) => load_splat!(self, m, u64, 2),
+                I::V128Load32Zero(m) => load!(self, m, u32 => u128),
+                I::V128Load64Zero(m) => load!(self, m, u64 => u128),
+                I::V128Load8Lane(m, l) => load_lane!(self, m, l, u8, 16),
+                I::V128Load16Lane(m, l) => load_lane!(self, m, l, u16, 8),
+                I::V128Load32Lane(m, l) => load_lane!(self, m, l, u32, 4),
+                I::V128Load64Lane(m, l) => load_lane!(self, m, l, u64, 2),
+                I::V128Store(m) => store!(self, m, v128 as u128),
+                I::V128Store8Lane(m, l) => store_lane!(self, m, l, u8, 16),
+                I::V128Store16Lane(m, l) => store_lane!(self, m, l, u16, 8),
+                I::V128Store32Lane(m, l) => store_lane!(self, m, l, u32, 4),
+                I::V128Store64Lane(m, l) => store_lane!(self, m, l, u64, 2),
+                i => {
+                    if !simd::execute(i, &mut self.stack) {
+                        let name = instr::find(i).map_or("instruction", |e| e.name);
+                        return Err(Trap::Unsupported(name));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{exec::Extern, wat::module::module};
+
+    // 関数をエクスポートしたモジュールをインスタンス化して呼び出す
+    fn run(wat: &str, name: &str, args: &[Value]) -> std::result::Result<Vec<Value>, RuntimeError> {
+        let (_, m) = module(wat).unwrap();
+        let mut store = Store::new();
+        let inst = Instance::new(&mut store, &m, &[]).unwrap();
+        inst.invoke(&mut store, name, args)
+    }
+
+    #[test]
+    fn code_ends() {
+        let (_, m) = module(
+            "(module (func
+                block
+                  i32.const 0
+                  if
+                    loop
+                    end
+                  else
+                  end
+                end))",
+        )
+        .unwrap();
+        let code = Code::new((vec![], vec![]), &m.funcs[0]);
+        assert_eq!(code.ends, HashMap::from([(0, 7), (2, 6), (3, 4), (5, 6)]));
+        assert_eq!(code.elses, HashMap::from([(2, 5)]));
+    }
+
+    #[test]
+    fn control_flow() {
+        let wat = "(module
+            (func (export \"abs\") (param i32) (result i32)
+              local.get 0
+              i32.const 0
+              i32.lt_s
+              if (result i32)
+                i32.const 0
+                local.get 0
+                i32.sub
+              else
+                local.get 0
+              end)
+            (func (export \"sum\") (param $n i32) (result i32) (local $acc i32)
+              block $done
+                loop $next
+                  local.get $n
+                  i32.eqz
+                  br_if $done
+                  local.get $acc
+                  local.get $n
+                  i32.add
+                  local.set $acc
+                  local.get $n
+                  i32.const 1
+                  i32.sub
+                  local.set $n
+                  br $next
+                end
+              end
+              local.get $acc)
+            (func (export \"switch\") (param i32) (result i32)
+              block
+                block
+                  block
+                    local.get 0
+                    br_table 0 1 2
+                  end
+                  i32.const 10
+                  return
+                end
+                i32.const 20
+                return
+              end
+              i32.const 30)
+            (func (export \"early\") (result i32)
+              i32.const 1
+              block (result i32)
+                i32.const 2
+                i32.const 3
+                br 0
+              end
+              i32.add))";
+        assert_eq!(run(wat, "abs", &[Value::I32(-5)]), Ok(vec![Value::I32(5)]));
+        assert_eq!(run(wat, "abs", &[Value::I32(7)]), Ok(vec![Value::I32(7)]));
+        assert_eq!(
+            run(wat, "sum", &[Value::I32(100)]),
+            Ok(vec![Value::I32(5050)])
+        );
+        assert_eq!(
+            run(wat, "switch", &[Value::I32(0)]),
+            Ok(vec![Value::I32(10)])
+        );
+        assert_eq!(
+            run(wat, "switch", &[Value::I32(1)]),
+            Ok(vec![Value::I32(20)])
+        );
+        assert_eq!(
+            run(wat, "switch", &[Value::I32(9)]),
+            Ok(vec![Value::I32(30)])
+        );
+        assert_eq!(run(wat, "early", &[]), Ok(vec![Value::I32(4)]));
+    }
+
+    #[test]
+    fn block_params() {
+        let wat = "(module
+            (type $t (func (param i32) (result i32)))
+            (func (export \"f\") (param i32) (result i32)
+              local.get 0
+              loop $l (type $t)
+                i32.const 1
+                i32.add
+                local.tee 0
+                local.get 0
+                i32.const 5
+                i32.lt_s
+                br_if $l
+              end))";
+        assert_eq!(run(wat, "f", &[Value::I32(0)]), Ok(vec![Value::I32(5)]));
+    }
+
+    #[test]
+    fn calls() {
+        let wat = "(module
+            (func $fac (export \"fac\") (param i64) (result i64)
+              local.get 0
+              i64.eqz
+              if (result i64)
+                i64.const 1
+              else
+                local.get 0
+                local.get 0
+                i64.const 1
+                i64.sub
+                call $fac
+                i64.mul
+              end)
+            (func $count (export \"count\") (param i32 i32) (result i32)
+              local.get 0
+              i32.eqz
+              if
+                local.get 1
+                return
+              end
+              local.get 0
+              i32.const 1
+              i32.sub
+              local.get 1
+              i32.const 1
+              i32.add
+              return_call $count))";
+        assert_eq!(
+            run(wat, "fac", &[Value::I64(20)]),
+            Ok(vec![Value::I64(2432902008176640000)])
+        );
+        // 末尾呼び出しはフレームを増やさない
+        let args = [Value::I32(1_000_000), Value::I32(0)];
+        assert_eq!(run(wat, "count", &args), Ok(vec![Value::I32(1_000_000)]));
+    }
+
+    #[test]
+    fn stack_limits() {
+        // 無限の再帰はホストのスタックを溢れさせずにトラップする
+        let wat = "(module (func $f (export \"f\") call $f))";
+        let e = run(wat, "f", &[]).unwrap_err();
+        assert_eq!(e.trap, Trap::StackExhausted);
+        assert_eq!(e.backtrace.len(), StackLimits::default().max_call_depth);
+
+        let (_, m) = module(
+            "(module
+                (import \"env\" \"host\" (func $host (param i32) (result i32)))
+                (func $depth (export \"depth\") (param i32) (result i32)
+                  local.get 0
+                  i32.eqz
+                  if (result i32)
+                    i32.const 0
+                  else
+                    local.get 0
+                    i32.const 1
+                    i32.sub
+                    call $depth
+                  end)
+                (func (export \"locals\") (local i64 i64 i64 i64))
+                (func (export \"values\") (param i32)
+                  i32.const 1
+                  i32.const 2
+                  i32.const 3
+                  local.get 0
+                  br_if 0
+                  i32.const 4
+                  i32.const 5
+                  drop
+                  drop
+                  drop
+                  drop
+                  drop)
+                (func (export \"nest\") (param i32) (result i32)
+                  local.get 0
+                  call $host))",
+        )
+        .unwrap();
+        let mut store = Store::new();
+        store.set_stack_limits(StackLimits {
+            max_call_depth: 10,
+            max_value_stack: 4,
+            max_host_nesting: 3,
+        });
+        // ホスト関数からWasmの関数を呼び出して入れ子にする
+        let host = Func::wrap(&mut store, |caller: &mut Caller<'_>, n: i32| {
+            if n == 0 {
+                return Ok(0);
+            }
+            let Some(Extern::Func(nest)) = caller.get_export("nest") else {
+                unreachable!()
+            };
+            match nest.call(caller.store(), &[Value::I32(n - 1)]) {
+                Ok(vs) => Ok(vs[0].i32() + 1),
+                Err(e) => Err(e.trap),
+            }
+        });
+        let inst = Instance::new(&mut store, &m, &[Extern::Func(host)]).unwrap();
+        assert_eq!(
+            inst.invoke(&mut store, "depth", &[Value::I32(2)]),
+            Ok(vec![Value::I32(0)])
+        );
+        let e = inst
+            .invoke(&mut store, "depth", &[Value::I32(10)])
+            .unwrap_err();
+        assert_eq!(e.trap, Trap::StackExhausted);
+        assert_eq!(e.backtrace.len(), 10);
+        assert_eq!(
+            inst.invoke(&mut store, "locals", &[]).map_err(|e| e.trap),
+            Ok(vec![])
+        );
+        store.set_stack_limits(StackLimits {
+            max_value_stack: 3,
+            ..StackLimits::default()
+        });
+        assert_eq!(
+            inst.invoke(&mut store, "locals", &[]).map_err(|e| e.trap),
+            Err(Trap::StackExhausted)
+        );
+        // 関数の本体で積んだ値も上限と比べる
+        store.set_stack_limits(StackLimits {
+            max_value_stack: 4,
+            ..StackLimits::default()
+        });
+        assert_eq!(
+            inst.invoke(&mut store, "values", &[Value::I32(1)]),
+            Ok(vec![])
+        );
+        let e = inst
+            .invoke(&mut store, "values", &[Value::I32(0)])
+            .unwrap_err();
+        assert_eq!(e.trap, Trap::StackExhausted);
+        assert_eq!(e.backtrace[0].offset, 7);
+
+        store.set_stack_limits(StackLimits {
+            max_host_nesting: 3,
+            ..StackLimits::default()
+        });
+        assert_eq!(
+            inst.invoke(&mut store, "nest", &[Value::I32(2)]),
+            Ok(vec![Value::I32(2)])
+        );
+        assert_eq!(
+            inst.invoke(&mut store, "nest", &[Value::I32(3)])
+                .map_err(|e| e.trap),
+            Err(Trap::StackExhausted)
+        );
+        // 入れ子の深さはトラップした後も元に戻る
+        assert_eq!(
+            inst.invoke(&mut store, "nest", &[Value::I32(2)]),
+            Ok(vec![Value::I32(2)])
+        );
+    }
+
+    #[test]
+    fn numeric() {
+        let wat = "(module
+            (func (export \"div\") (param i32 i32) (result i32)
+              local.get 0
+              local.get 1
+              i32.div_s)
+            (func (export \"trunc\") (param f64) (result i32)
+              local.get 0
+              i32.trunc_f64_u)
+            (func (export \"sat\") (param f32) (result i64)
+              local.get 0
+              i64.trunc_sat_f32_s)
+            (func (export \"float\") (param f32) (result f32)
+              local.get 0
+              f32.nearest
+              f32.const -0.0
+              f32.min)
+            (func (export \"bits\") (param i64) (result i32)
+              local.get 0
+              i64.const 56
+              i64.rotl
+              i32.wrap_i64
+              i32.extend8_s))";
+        assert_eq!(
+            run(wat, "div", &[Value::I32(-7), Value::I32(2)]),
+            Ok(vec![Value::I32(-3)])
+        );
+        assert_eq!(
+            run(wat, "div", &[Value::I32(1), Value::I32(0)]).map_err(|e| e.trap),
+            Err(Trap::IntegerDivideByZero)
+        );
+        assert_eq!(
+            run(wat, "trunc", &[Value::F64(3e9)]),
+            Ok(vec![Value::I32(-1294967296)])
+        );
+        assert_eq!(
+            run(wat, "trunc", &[Value::F64(-1.0)]).map_err(|e| e.trap),
+            Err(Trap::IntegerOverflow)
+        );
+        assert_eq!(
+            run(wat, "sat", &[Value::F32(f32::NAN)]),
+            Ok(vec![Value::I64(0)])
+        );
+        assert_eq!(
+            run(wat, "sat", &[Value::F32(1e30)]),
+            Ok(vec![Value::I64(i64::MAX)])
+        );
+        assert_eq!(
+            run(wat, "float", &[Value::F32(2.5)]),
+            Ok(vec![Value::F32(-0.0)])
+        );
+        assert_eq!(
+            run(wat, "float", &[Value::F32(-3.5)]),
+            Ok(vec![Value::F32(-4.0)])
+        );
+        assert_eq!(
+            run(wat, "bits", &[Value::I64(0x80ff)]),
+            Ok(vec![Value::I32(-128)])
+        );
+    }
+
+    #[test]
+    fn references_and_select() {
+        let wat = "(module
+            (func $f)
+            (elem declare func $f)
+            (func (export \"pick\") (param i32) (result funcref)
+              ref.func $f
+              ref.null func
+              local.get 0
+              select (result funcref))
+            (func (export \"null\") (result i32)
+              ref.null extern
+              ref.is_null))";
+        assert_eq!(
+            run(wat, "pick", &[Value::I32(1)]),
+            Ok(vec![Value::FuncRef(Some(Func(0)))])
+        );
+        assert_eq!(
+            run(wat, "pick", &[Value::I32(0)]),
+            Ok(vec![Value::FuncRef(None)])
+        );
+        assert_eq!(run(wat, "null", &[]), Ok(vec![Value::I32(1)]));
+    }
+
+    #[test]
+    fn tables() {
+        let wat = "(module
+            (table $t 2 4 funcref)
+            (table $u 3 externref)
+            (func $a (result i32) i32.const 1)
+            (func $b (result i32) i32.const 2)
+            (elem $e func $a $b)
+            (func (export \"init\") (param i32 i32 i32)
+              local.get 0
+              local.get 1
+              local.get 2
+              table.init $t $e)
+            (func (export \"call\") (param i32) (result i32)
+              local.get 0
+              call_indirect $t (result i32))
+            (func (export \"call_i64\") (param i32) (result i64)
+              local.get 0
+              call_indirect $t (result i64))
+            (func (export \"grow\") (param i32) (result i32)
+              ref.null func
+              local.get 0
+              table.grow $t)
+            (func (export \"copy\") (result i32)
+              i32.const 1
+              i32.const 0
+              i32.const 1
+              table.copy $t $t
+              i32.const 1
+              call_indirect $t (result i32))
+            (func (export \"fill\") (result i32)
+              i32.const 1
+              ref.null extern
+              i32.const 2
+              table.fill $u
+              i32.const 2
+              table.get $u
+              ref.is_null))";
+        let (_, m) = module(wat).unwrap();
+        let mut store = Store::new();
+        let inst = Instance::new(&mut store, &m, &[]).unwrap();
+        let mut invoke = |name: &str, args: &[i32]| {
+            let args: Vec<Value> = args.iter().map(|a| Value::I32(*a)).collect();
+            inst.invoke(&mut store, name, &args).map_err(|e| e.trap)
+        };
+        assert_eq!(invoke("call", &[0]), Err(Trap::UninitializedElement));
+        assert_eq!(invoke("init", &[0, 0, 2]), Ok(vec![]));
+        assert_eq!(invoke("call", &[1]), Ok(vec![Value::I32(2)]));
+        assert_eq!(
+            invoke("call_i64", &[0]),
+            Err(Trap::IndirectCallTypeMismatch)
+        );
+        assert_eq!(invoke("call", &[2]), Err(Trap::UndefinedElement));
+        assert_eq!(invoke("init", &[1, 1, 2]), Err(Trap::TableOutOfBounds));
+        assert_eq!(invoke("copy", &[]), Ok(vec![Value::I32(1)]));
+        assert_eq!(invoke("grow", &[2]), Ok(vec![Value::I32(2)]));
+        assert_eq!(invoke("grow", &[1]), Ok(vec![Value::I32(-1)]));
+        assert_eq!(invoke("fill", &[]), Ok(vec![Value::I32(1)]));
+    }
+
+    #[test]
+    fn exceptions() {
+        let wat = "(module
+            (tag $e (param i32))
+            (tag $f)
+            (func $throw (param i32)
+              local.get 0
+              i32.eqz
+              if
+                throw $f
+              end
+              local.get 0
+              throw $e)
+            (func (export \"catch\") (param i32) (result i32)
+              block $h (result i32)
+                try_table (catch $e $h)
+                  local.get 0
+                  call $throw
+                end
+                i32.const 0
+              end)
+            (func (export \"catch_all\") (param i32) (result i32)
+              block $all
+                try_table (catch_all $all)
+                  local.get 0
+                  call $throw
+                end
+              end
+              i32.const -1)
+            (func (export \"rethrow\") (param i32) (result i32)
+              block $h (result i32)
+                try_table (catch $e $h)
+                  block $r (result exnref)
+                    try_table (catch_all_ref $r)
+                      local.get 0
+                      call $throw
+                    end
+                    unreachable
+                  end
+                  throw_ref
+                end
+                i32.const 0
+              end))";
+        assert_eq!(run(wat, "catch", &[Value::I32(7)]), Ok(vec![Value::I32(7)]));
+        assert_eq!(
+            run(wat, "catch", &[Value::I32(0)]).map_err(|e| e.trap),
+            Err(Trap::UncaughtException)
+        );
+        assert_eq!(
+            run(wat, "catch_all", &[Value::I32(0)]),
+            Ok(vec![Value::I32(-1)])
+        );
+        assert_eq!(
+            run(wat, "rethrow", &[Value::I32(9)]),
+            Ok(vec![Value::I32(9)])
+        );
+    }
+
+    #[test]
+    fn exception_collection() {
+        let (_, m) = module(
+            "(module
+                (import \"env\" \"log\" (func $log (param exnref) (result i32)))
+                (tag $e (param i32))
+                (global $kept (mut exnref) (ref.null exn))
+                (func $catch (param i32) (result i32)
+                  block $h (result i32)
+                    try_table (catch $e $h)
+                      local.get 0
+                      throw $e
+                    end
+                    i32.const 0
+                  end)
+                (func $make (export \"make\") (param i32) (result exnref)
+                  block $r (result exnref)
+                    try_table (catch_all_ref $r)
+                      local.get 0
+                      throw $e
+                    end
+                    unreachable
+                  end)
+                (func (export \"keep\") (param i32)
+                  local.get 0
+                  call $make
+                  global.set $kept)
+                (func (export \"loop\") (param i32) (result i32)
+                  (local $sum i32)
+                  loop $l
+                    local.get $sum
+                    local.get 0
+                    call $catch
+                    i32.add
+                    local.set $sum
+                    local.get 0
+                    i32.const 1
+                    i32.sub
+                    local.tee 0
+                    br_if $l
+                  end
+                  local.get $sum)
+                (func (export \"host_loop\") (param i32) (result i32)
+                  (local $sum i32)
+                  loop $l
+                    local.get $sum
+                    local.get 0
+                    call $make
+                    call $log
+                    i32.add
+                    local.set $sum
+                    local.get 0
+                    i32.const 1
+                    i32.sub
+                    local.tee 0
+                    br_if $l
+                  end
+                  local.get $sum)
+                (func $rethrow (export \"rethrow\") (param exnref) (result i32)
+                  block $h (result i32)
+                    try_table (catch $e $h)
+                      local.get 0
+                      throw_ref
+                    end
+                    i32.const 0
+                  end)
+                (func (export \"rethrow_kept\") (result i32)
+                  global.get $kept
+                  call $rethrow))",
+        )
+        .unwrap();
+        let mut store = Store::new();
+        let log = Func::new(
+            &mut store,
+            (vec![ValueType::ExnRef], vec![ValueType::I32]),
+            |_, args| Ok(vec![Value::I32(args[0].is_null() as i32 ^ 1)]),
+        );
+        let inst = Instance::new(&mut store, &m, &[Extern::Func(log)]).unwrap();
+        let bounded = |store: &Store| assert!(store.exns.len() <= 128, "{}", store.exns.len());
+        assert_eq!(
+            inst.invoke(&mut store, "keep", &[Value::I32(7)]),
+            Ok(vec![])
+        );
+        // 捕捉した例外は回収され、ストアは大きくならない
+        for _ in 0..2 {
+            assert_eq!(
+                inst.invoke(&mut store, "loop", &[Value::I32(10000)]),
+                Ok(vec![Value::I32(50005000)])
+            );
+            bounded(&store);
+        }
+        // ホスト関数に渡した例外も、呼び出しから戻ると回収できる
+        assert_eq!(
+            inst.invoke(&mut store, "host_loop", &[Value::I32(10000)]),
+            Ok(vec![Value::I32(10000)])
+        );
+        bounded(&store);
+
+        // 埋め込み側に渡した例外は、手放すまで回収されない
+        let kept = inst.invoke(&mut store, "make", &[Value::I32(5)]).unwrap();
+        for i in 0..200 {
+            let exn = inst.invoke(&mut store, "make", &[Value::I32(i)]).unwrap();
+            store.release_exnref(&exn[0]);
+        }
+        inst.invoke(&mut store, "loop", &[Value::I32(100)]).unwrap();
+        bounded(&store);
+        assert_eq!(
+            inst.invoke(&mut store, "rethrow", &kept),
+            Ok(vec![Value::I32(5)])
+        );
+        // グローバルから参照する例外は回収されない
+        assert_eq!(
+            inst.invoke(&mut store, "rethrow_kept", &[]),
+            Ok(vec![Value::I32(7)])
+        );
+    }
+
+    #[test]
+    fn memory() {
+        let wat = "(module
+            (memory 1 2)
+            (data (i32.const 0) \"\\01\\02\\03\\04\\ff\")
+            (data $d \"abc\")
+            (func (export \"load\") (param i32) (result i32)
+              local.get 0
+              i32.load offset=1)
+            (func (export \"load8_s\") (param i32) (result i64)
+              local.get 0
+              i64.load8_s)
+            (func (export \"store\") (param i32 i64) (result i32)
+              local.get 0
+              local.get 1
+              i64.store16
+              local.get 0
+              i32.load)
+            (func (export \"grow\") (param i32) (result i32)
+              local.get 0
+              memory.grow)
+            (func (export \"copy\") (result i64)
+              i32.const 1
+              i32.const 0
+              i32.const 4
+              memory.copy
+              i32.const 0
+              i64.load)
+            (func (export \"init\") (param i32) (result i32)
+              local.get 0
+              i32.const 0
+              i32.const 3
+              memory.init $d
+              data.drop $d
+              local.get 0
+              i32.load)
+            (func (export \"extend\") (result i32)
+              i32.const 0
+              v128.load8x8_s
+              i16x8.extract_lane_s 4))";
+        let (_, m) = module(wat).unwrap();
+        let mut store = Store::new();
+        let inst = Instance::new(&mut store, &m, &[]).unwrap();
+        let mut invoke =
+            |name: &str, args: &[Value]| inst.invoke(&mut store, name, args).map_err(|e| e.trap);
+        let oob = Err(Trap::MemoryOutOfBounds);
+        assert_eq!(
+            invoke("load", &[Value::I32(0)]),
+            Ok(vec![Value::I32(0xff040302u32 as i32)])
+        );
+        assert_eq!(invoke("load", &[Value::I32(65532)]), oob);
+        assert_eq!(invoke("load", &[Value::I32(-1)]), oob);
+        assert_eq!(
+            invoke("load8_s", &[Value::I32(4)]),
+            Ok(vec![Value::I64(-1)])
+        );
+        assert_eq!(
+            invoke("store", &[Value::I32(8), Value::I64(0x12345678)]),
+            Ok(vec![Value::I32(0x5678)])
+        );
+        assert_eq!(invoke("extend", &[]), Ok(vec![Value::I32(-1)]));
+        assert_eq!(invoke("copy", &[]), Ok(vec![Value::I64(0x0403020101)]));
+        assert_eq!(
+            invoke("init", &[Value::I32(16)]),
+            Ok(vec![Value::I32(0x636261)])
+        );
+        assert_eq!(invoke("init", &[Value::I32(16)]), oob);
+        assert_eq!(invoke("grow", &[Value::I32(1)]), Ok(vec![Value::I32(1)]));
+        assert_eq!(invoke("grow", &[Value::I32(1)]), Ok(vec![Value::I32(-1)]));
+        assert_eq!(
+            invoke("load", &[Value::I32(131067)]),
+            Ok(vec![Value::I32(0)])
+        );
+    }
+
+    #[test]
+    fn vectors() {
+        let wat = "(module
+            (func (export \"dot\") (param i32) (result i32)
+              v128.const i16x8 1 2 3 4 5 6 7 8
+              local.get 0
+              i16x8.splat
+              i32x4.dot_i16x8_s
+              v128.const i32x4 0 0 0 0
+              i32x4.add
+              i32x4.extract_lane 3))";
+        assert_eq!(run(wat, "dot", &[Value::I32(2)]), Ok(vec![Value::I32(30)]));
+    }
+}
@@ -0,0 +1,252 @@
+use crate::ast::Module;
+
+use super::{memory::PAGE_SIZE, store::Store, Error};
+
+// メモリやテーブルの確保を制限する、埋め込み側の方針
+// メモリとテーブルを広げる前と、モジュールをインスタンス化する前に問い合わせる
+// 広げることを拒否すると、memory.growとtable.growは-1を返す
+pub trait ResourceLimiter {
+    // メモリをcurrentバイトからdesiredバイトに広げてよいか
+    // maximumはメモリの型で指定された最大のバイト数
+    fn memory_growing(&mut self, current: usize, desired: usize, maximum: Option<usize>) -> bool;
+
+    // テーブルをcurrent要素からdesired要素に広げてよいか
+    fn table_growing(&mut self, current: u32, desired: u32, maximum: Option<u32>) -> bool;
+
+    // ストアに作成できるインスタンス、メモリ、テーブルの数
+    fn instances(&self) -> usize {
+        DEFAULT_COUNT
+    }
+
+    fn memories(&self) -> usize {
+        DEFAULT_COUNT
+    }
+
+    fn tables(&self) -> usize {
+        DEFAULT_COUNT
+    }
+}
+
+// インスタンス、メモリ、テーブルの数の既定の上限
+pub const DEFAULT_COUNT: usize = 10000;
+
+// 1つのメモリの既定の最大のバイト数で、256MiB (4096ページ)
+pub const DEFAULT_MEMORY_SIZE: usize = 4096 * PAGE_SIZE;
+
+// 1つのテーブルの既定の最大の要素数
+pub const DEFAULT_TABLE_ELEMENTS: u32 = 1 << 20;
+
+// 上限を固定した、ストアの既定のResourceLimiter
+// memory_sizeは1つのメモリの最大のバイト数、table_elementsは1つのテーブルの最大の要素数で、
+// Noneの場合はメモリやテーブルの型の最大値だけで制限する
+// 既定では、信頼できないモジュールが4GiBのメモリを確保できないように
+// DEFAULT_MEMORY_SIZEとDEFAULT_TABLE_ELEMENTSで制限する
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub struct StoreLimits {
+    pub memory_size: Option<usize>,
+    pub table_elements: Option<u32>,
+    pub instances: usize,
+    pub memories: usize,
+    pub tables: usize,
+}
+
+impl Default for StoreLimits {
+    fn default() -> Self {
+        Self {
+            memory_size: Some(DEFAULT_MEMORY_SIZE),
+            table_elements: Some(DEFAULT_TABLE_ELEMENTS),
+            instances: DEFAULT_COUNT,
+            memories: DEFAULT_COUNT,
+            tables: DEFAULT_COUNT,
+        }
+    }
+}
+
+impl ResourceLimiter for StoreLimits {
+    fn memory_growing(&mut self, _: usize, desired: usize, _: Option<usize>) -> bool {
+        self.memory_size.is_none_or(|max| desired <= max)
+    }
+
+    fn table_growing(&mut self, _: u32, desired: u32, _: Option<u32>) -> bool {
+        self.table_elements.is_none_or(|max| desired <= max)
+    }
+
+    fn instances(&self) -> usize {
+        self.instances
+    }
+
+    fn memories(&self) -> usize {
+        self.memories
+    }
+
+    fn tables(&self) -> usize {
+        self.tables
+    }
+}
+
+// ストアが持つResourceLimiter
+pub(crate) struct Limiter(pub(crate) Box<dyn ResourceLimiter>);
+
+impl Default for Limiter {
+    fn default() -> Self {
+        Self(Box::new(StoreLimits::default()))
+    }
+}
+
+impl Store {
+    // ストアのResourceLimiterを置き換える
+    pub fn set_limiter(&mut self, limiter: impl ResourceLimiter + 'static) {
+        self.limiter = Limiter(Box::new(limiter));
+    }
+
+    // モジュールをインスタンス化する前に、作成するインスタンス、メモリ、テーブルの数と、
+    // メモリとテーブルの最小の大きさが上限に収まるかを確認する
+    pub(crate) fn check_limits(&mut self, module: &Module) -> Result<(), Error> {
+        let limiter = &mut self.limiter.0;
+        if self.instances.len() + 1 > limiter.instances() {
+            return Err(Error::Limit("instance count limit exceeded".to_string()));
+        }
+        if self.mems.len() + module.mems.len() > limiter.memories() {
+            return Err(Error::Limit("memory count limit exceeded".to_string()));
+        }
+        if self.tables.len() + module.tables.len() > limiter.tables() {
+            return Err(Error::Limit("table count limit exceeded".to_string()));
+        }
+        for m in &module.mems {
+            let desired = m.limits.min as usize * PAGE_SIZE;
+            let maximum = m.limits.max.map(|max| max as usize * PAGE_SIZE);
+            if !limiter.memory_growing(0, desired, maximum) {
+                return Err(Error::Limit(format!(
+                    "memory minimum size of {} pages exceeds memory limits",
+                    m.limits.min
+                )));
+            }
+        }
+        for t in &module.tables {
+            if !limiter.table_growing(0, t.limits.min, t.limits.max) {
+                return Err(Error::Limit(format!(
+                    "table minimum size of {} elements exceeds table limits",
+                    t.limits.min
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{
+        exec::{Instance, Value},
+        wat::module::module,
+    };
+
+    const GROW: &str = "(module
+        (memory 1)
+        (table 1 funcref)
+        (func (export \"memory\") (param i32) (result i32)
+          local.get 0
+          memory.grow)
+        (func (export \"table\") (param i32) (result i32)
+          ref.null func
+          local.get 0
+          table.grow))";
+
+    // 問い合わせを記録し、2ページを超えるメモリと1要素を超えるテーブルを拒否する
+    struct Recorder(Rc<RefCell<Vec<(usize, usize)>>>);
+
+    impl ResourceLimiter for Recorder {
+        fn memory_growing(&mut self, current: usize, desired: usize, _: Option<usize>) -> bool {
+            self.0.borrow_mut().push((current, desired));
+            desired <= 2 * PAGE_SIZE
+        }
+
+        fn table_growing(&mut self, _: u32, desired: u32, _: Option<u32>) -> bool {
+            desired <= 1
+        }
+    }
+
+    #[test]
+    fn custom_limiter() {
+        let mut store = Store::new();
+        let log = Rc::new(RefCell::new(Vec::new()));
+        store.set_limiter(Recorder(Rc::clone(&log)));
+        let (_, m) = module(GROW).unwrap();
+        let inst = Instance::new(&mut store, &m, &[]).unwrap();
+        let mut grow = |name: &str, n: i32| inst.invoke(&mut store, name, &[Value::I32(n)]);
+        assert_eq!(grow("memory", 1), Ok(vec![Value::I32(1)]));
+        assert_eq!(grow("memory", 1), Ok(vec![Value::I32(-1)]));
+        assert_eq!(grow("table", 1), Ok(vec![Value::I32(-1)]));
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                (0, PAGE_SIZE),
+                (PAGE_SIZE, 2 * PAGE_SIZE),
+                (2 * PAGE_SIZE, 3 * PAGE_SIZE)
+            ]
+        );
+    }
+
+    #[test]
+    fn store_limits() {
+        let mut store = Store::new();
+        store.set_limiter(StoreLimits {
+            memory_size: Some(4 * PAGE_SIZE),
+            table_elements: Some(3),
+            instances: 2,
+            ..Default::default()
+        });
+        let instantiate = |store: &mut Store, wat: &str| {
+            let (_, m) = module(wat).unwrap();
+            Instance::new(store, &m, &[]).map_err(|e| e.to_string())
+        };
+        assert_eq!(
+            instantiate(&mut store, "(module (memory 65536))").map(|_| ()),
+            Err("memory minimum size of 65536 pages exceeds memory limits".to_string())
+        );
+        assert_eq!(
+            instantiate(&mut store, "(module (table 4 funcref))").map(|_| ()),
+            Err("table minimum size of 4 elements exceeds table limits".to_string())
+        );
+
+        let inst = instantiate(&mut store, GROW).unwrap();
+        let mut grow = |name: &str, n: i32| inst.invoke(&mut store, name, &[Value::I32(n)]);
+        assert_eq!(grow("memory", 4), Ok(vec![Value::I32(-1)]));
+        assert_eq!(grow("memory", 3), Ok(vec![Value::I32(1)]));
+        assert_eq!(grow("table", 2), Ok(vec![Value::I32(1)]));
+        assert_eq!(grow("table", 1), Ok(vec![Value::I32(-1)]));
+
+        instantiate(&mut store, "(module)").unwrap();
+        assert_eq!(
+            instantiate(&mut store, "(module)").map(|_| ()),
+            Err("instance count limit exceeded".to_string())
+        );
+    }
+
+    #[test]
+    fn default_limits() {
+        // 既定のストアでは、メモリとテーブルの大きさも制限する
+        let mut store = Store::new();
+        let (_, m) = module(GROW).unwrap();
+        let inst = Instance::new(&mut store, &m, &[]).unwrap();
+        let mut grow = |name: &str, n: i32| inst.invoke(&mut store, name, &[Value::I32(n)]);
+        let pages = (DEFAULT_MEMORY_SIZE / PAGE_SIZE) as i32;
+        assert_eq!(grow("memory", pages), Ok(vec![Value::I32(-1)]));
+        assert_eq!(grow("memory", 65535), Ok(vec![Value::I32(-1)]));
+        assert_eq!(grow("memory", 1), Ok(vec![Value::I32(1)]));
+        let elements = DEFAULT_TABLE_ELEMENTS as i32;
+        assert_eq!(grow("table", elements), Ok(vec![Value::I32(-1)]));
+        assert_eq!(grow("table", 1), Ok(vec![Value::I32(1)]));
+
+        let (_, m) = module("(module (memory 65536))").unwrap();
+        assert_eq!(
+            Instance::new(&mut store, &m, &[]).map(|_| ()),
+            Err(Error::Limit(
+                "memory minimum size of 65536 pages exceeds memory limits".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use crate::ast::Module;
+
+use super::{
+    func::Func,
+    host::IntoFunc,
+    instance::Instance,
+    store::{Extern, Store},
+    Error,
+};
+
+// インポートを(モジュール名, 名前)で解決するリンカー
+// ホスト関数やメモリ、インスタンス化したモジュールのエクスポートを名前空間ごとに登録し、
+// モジュールのインポートを登録したものから探してインスタンス化する
+#[derive(Debug, Default, Clone)]
+pub struct Linker {
+    defs: HashMap<(String, String), Extern>,
+}
+
+impl Linker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 同じ名前を2回登録するとエラーになる
+    pub fn define(
+        &mut self,
+        module: &str,
+        name: &str,
+        ext: impl Into<Extern>,
+    ) -> Result<&mut Self, Error> {
+        let key = (module.to_string(), name.to_string());
+        if self.defs.contains_key(&key) {
+            return Err(Error::Link(format!("duplicate definition {module}.{name}")));
+        }
+        self.defs.insert(key, ext.into());
+        Ok(self)
+    }
+
+    // クロージャからホスト関数を作成して登録する
+    pub fn func_wrap<Params, Results>(
+        &mut self,
+        store: &mut Store,
+        module: &str,
+        name: &str,
+        func: impl IntoFunc<Params, Results>,
+    ) -> Result<&mut Self, Error> {
+        let func = Func::wrap(store, func);
+        self.define(module, name, func)
+    }
+
+    // インスタンスの全てのエクスポートを、moduleの名前空間に登録する
+    pub fn instance(
+        &mut self,
+        store: &Store,
+        module: &str,
+        inst: Instance,
+    ) -> Result<&mut Self, Error> {
+        for (name, ext) in inst.exports(store) {
+            self.define(module, name, ext)?;
+        }
+        Ok(self)
+    }
+
+    pub fn get(&self, module: &str, name: &str) -> Option<Extern> {
+        self.defs
+            .get(&(module.to_string(), name.to_string()))
+            .copied()
+    }
+
+    // インポートを解決してモジュールをインスタンス化する
+    // 型の照合はインスタンス化で行う
+    pub fn instantiate(&self, store: &mut Store, module: &Module) -> Result<Instance, Error> {
+        let imports = module
+            .imports
+            .iter()
+            .map(|i| {
+                self.get(&i.module, &i.name)
+                    .ok_or_else(|| Error::Link(format!("unknown import {}.{}", i.module, i.name)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Instance::new(store, module, &imports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{
+        ast::{GlobalType, Limits, MemType, ValueType},
+        exec::{Global, Memory, Value},
+        wat::module::module,
+    };
+
+    #[test]
+    fn resolve_imports() {
+        let mut store = Store::new();
+        let mut linker = Linker::new();
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let logged = Rc::clone(&log);
+        let mem = Memory::new(
+            &mut store,
+            MemType {
+                limits: Limits { min: 1, max: None },
+                shared: false,
+            },
+        );
+        let base = Global::new(
+            &mut store,
+            GlobalType {
+                value_type: ValueType::I32,
+                mutable: false,
+            },
+            Value::I32(100),
+        );
+        linker
+            .func_wrap(&mut store, "env", "log", move |v: i32| {
+                logged.borrow_mut().push(v)
+            })
+            .unwrap()
+            .define("env", "memory", mem)
+            .unwrap()
+            .define("env", "base", base)
+            .unwrap();
+
+        let (_, lib) = module(
+            "(module
+                (import \"env\" \"base\" (global $base i32))
+                (func (export \"offset\") (param i32) (result i32)
+                  local.get 0
+                  global.get $base
+                  i32.add))",
+        )
+        .unwrap();
+        let lib = linker.instantiate(&mut store, &lib).unwrap();
+        linker.instance(&store, "lib", lib).unwrap();
+
+        let (_, app) = module(
+            "(module
+                (import \"env\" \"log\" (func $log (param i32)))
+                (import \"lib\" \"offset\" (func $offset (param i32) (result i32)))
+                (import \"env\" \"memory\" (memory 1))
+                (func (export \"main\")
+                  i32.const 1
+                  call $offset
+                  call $log))",
+        )
+        .unwrap();
+        let app = linker.instantiate(&mut store, &app).unwrap();
+        assert_eq!(app.invoke(&mut store, "main", &[]), Ok(vec![]));
+        assert_eq!(*log.borrow(), vec![101]);
+    }
+
+    #[test]
+    fn link_errors() {
+        let mut store = Store::new();
+        let mut linker = Linker::new();
+        linker
+            .func_wrap(&mut store, "env", "f", |a: i32| a)
+            .unwrap();
+        let link = |store: &mut Store, wat: &str| {
+            let (_, m) = module(wat).unwrap();
+            linker
+                .instantiate(store, &m)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        };
+        assert_eq!(
+            link(&mut store, "(module (import \"env\" \"log\" (func)))"),
+            Err("unknown import env.log".to_string())
+        );
+        assert_eq!(
+            link(
+                &mut store,
+                "(module (import \"env\" \"f\" (func (param i64))))"
+            ),
+            Err("incompatible import type env.f".to_string())
+        );
+        assert_eq!(
+            link(&mut store, "(module (import \"env\" \"f\" (memory 1)))"),
+            Err("incompatible import type env.f".to_string())
+        );
+        let f = linker.get("env", "f").unwrap();
+        assert_eq!(
+            linker.define("env", "f", f).map(|_| ()),
+            Err(Error::Link("duplicate definition env.f".to_string()))
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use std::ops::Range;
+
+use crate::ast::MemType;
+
+use super::{
+    limits::ResourceLimiter,
+    store::Store,
+    trap::{Result, Trap},
+};
+
+// 仕様
+// https://webassembly.github.io/spec/core/exec/runtime.html#memory-instances
+
+// ページの大きさ
+pub const PAGE_SIZE: usize = 65536;
+
+// 32ビットのアドレスで扱えるページ数の上限
+pub const MAX_PAGES: u32 = 65536;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MemInst {
+    pub ty: MemType,
+    pub data: Vec<u8>,
+}
+
+impl MemInst {
+    // 実効アドレスeaからnバイトの範囲
+    // アドレスとオフセットの和は32ビットを超えうるため、u64で計算する
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-load
+    fn range(&self, ea: u64, n: usize) -> Result<Range<usize>> {
+        if ea + n as u64 > self.data.len() as u64 {
+            return Err(Trap::MemoryOutOfBounds);
+        }
+        Ok(ea as usize..ea as usize + n)
+    }
+
+    // アドレスaddrにオフセットを足した位置から、Nバイトを読み出す
+    // 値はリトルエンディアンで格納する
+    pub fn load<const N: usize>(&self, addr: u32, offset: u32) -> Result<[u8; N]> {
+        let range = self.range(addr as u64 + offset as u64, N)?;
+        Ok(self.data[range].try_into().expect("range has N bytes"))
+    }
+
+    pub fn store<const N: usize>(&mut self, addr: u32, offset: u32, bytes: [u8; N]) -> Result<()> {
+        let range = self.range(addr as u64 + offset as u64, N)?;
+        self.data[range].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    // メモリをnページだけ広げ、広げる前のページ数を返す
+    // 最大値を超える場合や、limiterが拒否した場合は広げずにNoneを返す
+    // https://webassembly.github.io/spec/core/exec/modules.html#grow-mem
+    pub fn grow(&mut self, n: u32, limiter: &mut dyn ResourceLimiter) -> Option<u32> {
+        let old = (self.data.len() / PAGE_SIZE) as u32;
+        let new = old.checked_add(n)?;
+        let max = self.ty.limits.max;
+        if new > max.unwrap_or(MAX_PAGES).min(MAX_PAGES) {
+            return None;
+        }
+        let maximum = max.map(|max| max as usize * PAGE_SIZE);
+        if !limiter.memory_growing(self.data.len(), new as usize * PAGE_SIZE, maximum) {
+            return None;
+        }
+        self.data.resize(new as usize * PAGE_SIZE, 0);
+        self.ty.limits.min = new;
+        Some(old)
+    }
+}
+
+// メモリのハンドル
+#[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
+pub struct Memory(pub(crate) u32);
+
+impl Memory {
+    // 最小のページ数を0で埋めたメモリを作成する
+    pub fn new(store: &mut Store, ty: MemType) -> Self {
+        store.mems.push(MemInst {
+            ty,
+            data: vec![0; ty.limits.min as usize * PAGE_SIZE],
+        });
+        Self(store.mems.len() as u32 - 1)
+    }
+
+    pub fn ty(&self, store: &Store) -> MemType {
+        store.mems[self.0 as usize].ty
+    }
+
+    // ページ数
+    pub fn size(&self, store: &Store) -> u32 {
+        (store.mems[self.0 as usize].data.len() / PAGE_SIZE) as u32
+    }
+
+    // ストアのResourceLimiterに問い合わせてから広げる
+    pub fn grow(&self, store: &mut Store, n: u32) -> Option<u32> {
+        store.mems[self.0 as usize].grow(n, &mut *store.limiter.0)
+    }
+
+    // メモリの中身
+    pub fn data<'a>(&self, store: &'a Store) -> &'a [u8] {
+        &store.mems[self.0 as usize].data
+    }
+
+    pub fn data_mut<'a>(&self, store: &'a mut Store) -> &'a mut [u8] {
+        &mut store.mems[self.0 as usize].data
+    }
+
+    // offsetからbufの長さだけ読み出す
+    // 範囲外の場合は何も読み出さずにトラップする
+    pub fn read(&self, store: &Store, offset: usize, buf: &mut [u8]) -> Result<()> {
+        let mem = &store.mems[self.0 as usize];
+        let range = mem.range(offset as u64, buf.len())?;
+        buf.copy_from_slice(&mem.data[range]);
+        Ok(())
+    }
+
+    // offsetからdataを書き込む
+    // 範囲外の場合は何も書き込まずにトラップする
+    pub fn write(&self, store: &mut Store, offset: usize, data: &[u8]) -> Result<()> {
+        let mem = &mut store.mems[self.0 as usize];
+        let range = mem.range(offset as u64, data.len())?;
+        mem.data[range].copy_from_slice(data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Limits;
+
+    fn memory(store: &mut Store, min: u32, max: Option<u32>) -> Memory {
+        Memory::new(
+            store,
+            MemType {
+                limits: Limits { min, max },
+                shared: false,
+            },
+        )
+    }
+
+    #[test]
+    fn load_store() {
+        let mut store = Store::new();
+        let m = memory(&mut store, 1, None);
+        let mem = &mut store.mems[m.0 as usize];
+        assert_eq!(mem.store(8, 4, 0x12345678u32.to_le_bytes()), Ok(()));
+        assert_eq!(mem.load::<1>(12, 0), Ok([0x78]));
+        assert_eq!(mem.load::<4>(0, 12), Ok([0x78, 0x56, 0x34, 0x12]));
+        assert_eq!(mem.load::<2>(65534, 0), Ok([0, 0]));
+        assert_eq!(mem.load::<4>(65534, 0), Err(Trap::MemoryOutOfBounds));
+        // アドレスとオフセットの和は桁あふれしない
+        assert_eq!(mem.load::<1>(u32::MAX, 1), Err(Trap::MemoryOutOfBounds));
+    }
+
+    #[test]
+    fn grow_and_slices() {
+        let mut store = Store::new();
+        let m = memory(&mut store, 1, Some(2));
+        assert_eq!(m.grow(&mut store, 1), Some(1));
+        assert_eq!(m.grow(&mut store, 1), None);
+        assert_eq!(m.size(&store), 2);
+        assert_eq!(m.ty(&store).limits.min, 2);
+        let unbounded = memory(&mut store, 0, None);
+        assert_eq!(unbounded.grow(&mut store, MAX_PAGES + 1), None);
+        assert_eq!(unbounded.grow(&mut store, 0), Some(0));
+
+        assert_eq!(m.write(&mut store, 65536, b"wasm"), Ok(()));
+        let mut buf = [0; 4];
+        assert_eq!(m.read(&store, 65536, &mut buf), Ok(()));
+        assert_eq!(&buf, b"wasm");
+        assert_eq!(
+            m.write(&mut store, 2 * PAGE_SIZE - 1, b"xy"),
+            Err(Trap::MemoryOutOfBounds)
+        );
+        assert_eq!(m.data(&store)[2 * PAGE_SIZE - 1], 0);
+    }
+}
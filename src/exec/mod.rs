@@ -0,0 +1,72 @@
+use std::fmt;
+
+use crate::valid::ValidationError;
+
+pub mod fuel;
+pub mod func;
+pub mod host;
+pub mod instance;
+pub mod interpreter;
+pub mod limits;
+pub mod linker;
+pub mod memory;
+mod numeric;
+pub mod simd;
+pub mod store;
+pub mod trap;
+pub mod value;
+
+pub use fuel::FuelCosts;
+pub use func::{Func, TypedFunc};
+pub use host::Caller;
+pub use instance::Instance;
+pub use interpreter::StackLimits;
+pub use limits::{ResourceLimiter, StoreLimits};
+pub use linker::Linker;
+pub use memory::Memory;
+pub use store::{Extern, Global, Store, Table, Tag};
+pub use trap::{FrameInfo, RuntimeError, Trap};
+pub use value::Value;
+
+// 仕様
+// https://webassembly.github.io/spec/core/exec/index.html
+
+// インスタンス化のエラー
+// 検証のエラー、インポートを解決できないエラー、資源の制限を超えるエラー、開始関数のトラップのいずれか
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub enum Error {
+    Validation(ValidationError),
+    Link(String),
+    Limit(String),
+    Trap(RuntimeError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Validation(e) => write!(f, "{e}"),
+            Self::Link(message) | Self::Limit(message) => write!(f, "{message}"),
+            Self::Trap(t) => write!(f, "{t}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ValidationError> for Error {
+    fn from(e: ValidationError) -> Self {
+        Self::Validation(e)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(e: RuntimeError) -> Self {
+        Self::Trap(e)
+    }
+}
+
+impl From<Trap> for Error {
+    fn from(t: Trap) -> Self {
+        Self::Trap(t.into())
+    }
+}
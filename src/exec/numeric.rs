@@ -0,0 +1,174 @@
+use super::trap::{Result, Trap};
+
+// 仕様
+// https://webassembly.github.io/spec/core/exec/numerics.html
+
+// https://webassembly.github.io/spec/core/exec/numerics.html#op-idiv-s
+pub fn i32_div_s(a: i32, b: i32) -> Result<i32> {
+    match (a, b) {
+        (_, 0) => Err(Trap::IntegerDivideByZero),
+        (i32::MIN, -1) => Err(Trap::IntegerOverflow),
+        _ => Ok(a / b),
+    }
+}
+
+pub fn i32_div_u(a: i32, b: i32) -> Result<i32> {
+    match b {
+        0 => Err(Trap::IntegerDivideByZero),
+        _ => Ok(((a as u32) / (b as u32)) as i32),
+    }
+}
+
+// 符号付きの剰余は、i32::MIN % -1でもオーバーフローせず0になる
+// https://webassembly.github.io/spec/core/exec/numerics.html#op-irem-s
+pub fn i32_rem_s(a: i32, b: i32) -> Result<i32> {
+    match b {
+        0 => Err(Trap::IntegerDivideByZero),
+        _ => Ok(a.wrapping_rem(b)),
+    }
+}
+
+pub fn i32_rem_u(a: i32, b: i32) -> Result<i32> {
+    match b {
+        0 => Err(Trap::IntegerDivideByZero),
+        _ => Ok(((a as u32) % (b as u32)) as i32),
+    }
+}
+
+pub fn i64_div_s(a: i64, b: i64) -> Result<i64> {
+    match (a, b) {
+        (_, 0) => Err(Trap::IntegerDivideByZero),
+        (i64::MIN, -1) => Err(Trap::IntegerOverflow),
+        _ => Ok(a / b),
+    }
+}
+
+pub fn i64_div_u(a: i64, b: i64) -> Result<i64> {
+    match b {
+        0 => Err(Trap::IntegerDivideByZero),
+        _ => Ok(((a as u64) / (b as u64)) as i64),
+    }
+}
+
+pub fn i64_rem_s(a: i64, b: i64) -> Result<i64> {
+    match b {
+        0 => Err(Trap::IntegerDivideByZero),
+        _ => Ok(a.wrapping_rem(b)),
+    }
+}
+
+pub fn i64_rem_u(a: i64, b: i64) -> Result<i64> {
+    match b {
+        0 => Err(Trap::IntegerDivideByZero),
+        _ => Ok(((a as u64) % (b as u64)) as i64),
+    }
+}
+
+// どちらかがNaNであればNaNになり、-0は+0より小さいものとして扱う
+// https://webassembly.github.io/spec/core/exec/numerics.html#op-fmin
+pub fn f32_min(a: f32, b: f32) -> f32 {
+    if a.is_nan() || b.is_nan() {
+        return f32::NAN;
+    }
+    if a == b {
+        return if a.is_sign_negative() { a } else { b };
+    }
+    a.min(b)
+}
+
+// https://webassembly.github.io/spec/core/exec/numerics.html#op-fmax
+pub fn f32_max(a: f32, b: f32) -> f32 {
+    if a.is_nan() || b.is_nan() {
+        return f32::NAN;
+    }
+    if a == b {
+        return if a.is_sign_positive() { a } else { b };
+    }
+    a.max(b)
+}
+
+pub fn f64_min(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        return f64::NAN;
+    }
+    if a == b {
+        return if a.is_sign_negative() { a } else { b };
+    }
+    a.min(b)
+}
+
+pub fn f64_max(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        return f64::NAN;
+    }
+    if a == b {
+        return if a.is_sign_positive() { a } else { b };
+    }
+    a.max(b)
+}
+
+// 小数部分を切り捨てた値が整数の範囲に収まらなければトラップする
+// minとmaxは、切り捨てた値が取れる範囲の両端を含まない境界
+// https://webassembly.github.io/spec/core/exec/numerics.html#op-trunc-s
+fn check_trunc(v: f64, min: f64, max: f64) -> Result<f64> {
+    if v.is_nan() {
+        return Err(Trap::InvalidConversionToInteger);
+    }
+    let t = v.trunc();
+    if t <= min || t >= max {
+        return Err(Trap::IntegerOverflow);
+    }
+    Ok(t)
+}
+
+pub fn i32_trunc_s(v: f64) -> Result<i32> {
+    check_trunc(v, -2147483649.0, 2147483648.0).map(|t| t as i32)
+}
+
+pub fn i32_trunc_u(v: f64) -> Result<i32> {
+    check_trunc(v, -1.0, 4294967296.0).map(|t| t as u32 as i32)
+}
+
+// f32からの変換は、f64に広げてから同じ境界で調べる
+pub fn i64_trunc_s(v: f64) -> Result<i64> {
+    check_trunc(v, -9223372036854777856.0, 9223372036854775808.0).map(|t| t as i64)
+}
+
+pub fn i64_trunc_u(v: f64) -> Result<i64> {
+    check_trunc(v, -1.0, 18446744073709551616.0).map(|t| t as u64 as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_division() {
+        assert_eq!(i32_div_s(7, -2), Ok(-3));
+        assert_eq!(i32_div_u(-1, 2), Ok(i32::MAX));
+        assert_eq!(i32_rem_s(i32::MIN, -1), Ok(0));
+        assert_eq!(i64_rem_u(-1, 10), Ok(5));
+        assert_eq!(i32_div_s(1, 0), Err(Trap::IntegerDivideByZero));
+        assert_eq!(i64_div_s(i64::MIN, -1), Err(Trap::IntegerOverflow));
+    }
+
+    #[test]
+    fn float_min_max() {
+        assert!(f32_min(f32::NAN, 1.0).is_nan());
+        assert!(f64_max(1.0, f64::NAN).is_nan());
+        assert!(f32_min(0.0, -0.0).is_sign_negative());
+        assert!(f64_max(-0.0, 0.0).is_sign_positive());
+        assert_eq!(f32_max(1.0, 2.0), 2.0);
+    }
+
+    #[test]
+    fn truncation() {
+        assert_eq!(i32_trunc_s(-2147483648.9), Ok(i32::MIN));
+        assert_eq!(i32_trunc_u(-0.9), Ok(0));
+        assert_eq!(i32_trunc_u(4294967295.0), Ok(-1));
+        assert_eq!(i32_trunc_s(2147483648.0), Err(Trap::IntegerOverflow));
+        assert_eq!(i64_trunc_s(f64::NAN), Err(Trap::InvalidConversionToInteger));
+        assert_eq!(i64_trunc_s(-9223372036854775808.0), Ok(i64::MIN));
+        assert_eq!(i64_trunc_u(18446744073709549568.0), Ok(-2048));
+    }
+}
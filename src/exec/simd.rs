@@ -0,0 +1,614 @@
+use crate::ast::Instruction as I;
+
+use super::{numeric, value::Value};
+
+// 仕様
+// https://webassembly.github.io/spec/core/exec/instructions.html#vector-instructions
+
+// v128のレーンになる数値の型
+// レーンはリトルエンディアンのバイト列として並ぶ
+pub trait Lane: Copy {
+    const SIZE: usize;
+    fn read(b: &[u8]) -> Self;
+    fn write(self, b: &mut [u8]);
+}
+
+macro_rules! lane {
+    ($($t:ty),*) => {
+        $(impl Lane for $t {
+            const SIZE: usize = std::mem::size_of::<$t>();
+
+            fn read(b: &[u8]) -> Self {
+                <$t>::from_le_bytes(b.try_into().expect("lane size"))
+            }
+
+            fn write(self, b: &mut [u8]) {
+                b.copy_from_slice(&self.to_le_bytes());
+            }
+        })*
+    };
+}
+
+lane!(i8, u8, i16, u16, i32, u32, i64, u64, f32, f64);
+
+// v128をN個のレーンに分ける
+pub fn lanes<T: Lane, const N: usize>(v: u128) -> [T; N] {
+    let b = v.to_le_bytes();
+    std::array::from_fn(|i| T::read(&b[i * T::SIZE..(i + 1) * T::SIZE]))
+}
+
+// N個のレーンをv128にまとめる
+pub fn from_lanes<T: Lane, const N: usize>(l: [T; N]) -> u128 {
+    let mut b = [0; 16];
+    for (i, x) in l.into_iter().enumerate() {
+        x.write(&mut b[i * T::SIZE..(i + 1) * T::SIZE]);
+    }
+    u128::from_le_bytes(b)
+}
+
+// レーンごとに演算する
+fn map<T: Lane, U: Lane, const N: usize>(v: u128, f: impl Fn(T) -> U) -> u128 {
+    from_lanes::<U, N>(lanes::<T, N>(v).map(f))
+}
+
+// 2つのv128の同じ位置のレーンごとに演算する
+fn zip<T: Lane, U: Lane, const N: usize>(a: u128, b: u128, f: impl Fn(T, T) -> U) -> u128 {
+    let (a, b) = (lanes::<T, N>(a), lanes::<T, N>(b));
+    from_lanes::<U, N>(std::array::from_fn(|i| f(a[i], b[i])))
+}
+
+// 比較の結果は、真のレーンの全ビットを1にする
+fn mask8(c: bool) -> i8 {
+    -(c as i8)
+}
+
+fn mask16(c: bool) -> i16 {
+    -(c as i16)
+}
+
+fn mask32(c: bool) -> i32 {
+    -(c as i32)
+}
+
+fn mask64(c: bool) -> i64 {
+    -(c as i64)
+}
+
+// 各レーンの最上位ビットを集める
+fn bitmask<T: Lane + PartialOrd + Default, const N: usize>(v: u128) -> i32 {
+    lanes::<T, N>(v)
+        .iter()
+        .enumerate()
+        .fold(0, |m, (i, x)| m | (((*x < T::default()) as i32) << i))
+}
+
+fn all_true<T: Lane + PartialEq + Default, const N: usize>(v: u128) -> bool {
+    lanes::<T, N>(v).iter().all(|x| *x != T::default())
+}
+
+// 幅の広い2つのv128の各レーンを、飽和させて幅の狭いレーンに詰める
+// aのレーンが下位に、bのレーンが上位に並ぶ
+fn narrow<T: Lane, U: Lane + Default, const N: usize, const M: usize>(
+    a: u128,
+    b: u128,
+    f: impl Fn(T) -> U,
+) -> u128 {
+    let (a, b) = (lanes::<T, N>(a), lanes::<T, N>(b));
+    from_lanes::<U, M>(std::array::from_fn(|i| {
+        if i < N {
+            f(a[i])
+        } else {
+            f(b[i - N])
+        }
+    }))
+}
+
+// レーンの半分を幅の広いレーンに広げる
+// highがtrueの場合は上位の半分を使う
+fn extend<T: Lane, U: Lane, const N: usize, const M: usize>(
+    v: u128,
+    high: bool,
+    f: impl Fn(T) -> U,
+) -> u128 {
+    let l = lanes::<T, N>(v);
+    let base = if high { M } else { 0 };
+    from_lanes::<U, M>(std::array::from_fn(|i| f(l[base + i])))
+}
+
+// 広げたレーン同士を掛ける
+fn extmul<T: Lane, U: Lane, const N: usize, const M: usize>(
+    a: u128,
+    b: u128,
+    high: bool,
+    f: impl Fn(T, T) -> U,
+) -> u128 {
+    let (a, b) = (lanes::<T, N>(a), lanes::<T, N>(b));
+    let base = if high { M } else { 0 };
+    from_lanes::<U, M>(std::array::from_fn(|i| f(a[base + i], b[base + i])))
+}
+
+// 隣り合う2つのレーンを広げて足す
+fn extadd_pairwise<T: Lane, U: Lane, const N: usize, const M: usize>(
+    v: u128,
+    f: impl Fn(T, T) -> U,
+) -> u128 {
+    let l = lanes::<T, N>(v);
+    from_lanes::<U, M>(std::array::from_fn(|i| f(l[2 * i], l[2 * i + 1])))
+}
+
+// https://webassembly.github.io/spec/core/exec/numerics.html#op-fpmin
+fn pmin<T: PartialOrd>(a: T, b: T) -> T {
+    if b < a {
+        b
+    } else {
+        a
+    }
+}
+
+fn pmax<T: PartialOrd>(a: T, b: T) -> T {
+    if a < b {
+        b
+    } else {
+        a
+    }
+}
+
+// https://webassembly.github.io/spec/core/exec/numerics.html#op-iq15mulrsat-s
+fn q15mulr_sat(a: i16, b: i16) -> i16 {
+    ((a as i32 * b as i32 + 0x4000) >> 15).clamp(i16::MIN as i32, i16::MAX as i32) as i16
+}
+
+// メモリを使わないSIMD命令を実行する
+// 対象の命令でなければfalseを返し、スタックは変更しない
+pub fn execute(i: &I, stack: &mut Vec<Value>) -> bool {
+    macro_rules! pop {
+        ($get:ident) => {
+            stack.pop().expect("operand stack is validated").$get()
+        };
+    }
+    // v128を1つ取り出して、v128を積む
+    macro_rules! v1 {
+        (|$a:ident| $e:expr) => {{
+            let $a = pop!(v128);
+            stack.push(Value::V128($e));
+        }};
+    }
+    // v128を2つ取り出して、v128を積む
+    macro_rules! v2 {
+        (|$a:ident, $b:ident| $e:expr) => {{
+            let $b = pop!(v128);
+            let $a = pop!(v128);
+            stack.push(Value::V128($e));
+        }};
+    }
+    // v128を1つ取り出して、i32を積む
+    macro_rules! test {
+        (|$a:ident| $e:expr) => {{
+            let $a = pop!(v128);
+            stack.push(Value::from($e));
+        }};
+    }
+    // シフト量のi32とv128を取り出して、v128を積む
+    // シフト量はレーンのビット数で剰余を取る
+    macro_rules! shift {
+        ($t:ty, $n:expr, |$a:ident, $s:ident| $e:expr) => {{
+            let $s = pop!(i32) as u32 % (<$t>::BITS);
+            let v = pop!(v128);
+            stack.push(Value::V128(map::<$t, $t, $n>(v, |$a| $e)));
+        }};
+    }
+    // スカラーを取り出して、全てのレーンに複製する
+    macro_rules! splat {
+        ($get:ident, $t:ty, $n:expr) => {{
+            let x = pop!($get) as $t;
+            stack.push(Value::V128(from_lanes::<$t, $n>([x; $n])));
+        }};
+    }
+    macro_rules! extract {
+        ($t:ty, $n:expr, $l:expr, $to:ty) => {{
+            let v = pop!(v128);
+            stack.push(Value::from(lanes::<$t, $n>(v)[*$l as usize] as $to));
+        }};
+    }
+    macro_rules! replace {
+        ($get:ident, $t:ty, $n:expr, $l:expr) => {{
+            let x = pop!($get) as $t;
+            let mut l = lanes::<$t, $n>(pop!(v128));
+            l[*$l as usize] = x;
+            stack.push(Value::V128(from_lanes::<$t, $n>(l)));
+        }};
+    }
+
+    match i {
+        I::I8x16Shuffle(s) => v2!(|a, b| {
+            let (a, b) = (lanes::<u8, 16>(a), lanes::<u8, 16>(b));
+            from_lanes::<u8, 16>(s.map(|x| {
+                let x = x as usize;
+                if x < 16 {
+                    a[x]
+                } else {
+                    b[x - 16]
+                }
+            }))
+        }),
+        I::I8x16Swizzle => v2!(|a, b| {
+            let a = lanes::<u8, 16>(a);
+            map::<u8, u8, 16>(b, |x| a.get(x as usize).copied().unwrap_or(0))
+        }),
+        I::I8x16Splat => splat!(i32, i8, 16),
+        I::I16x8Splat => splat!(i32, i16, 8),
+        I::I32x4Splat => splat!(i32, i32, 4),
+        I::I64x2Splat => splat!(i64, i64, 2),
+        I::F32x4Splat => splat!(f32, f32, 4),
+        I::F64x2Splat => splat!(f64, f64, 2),
+        I::I8x16ExtractLaneS(l) => extract!(i8, 16, l, i32),
+        I::I8x16ExtractLaneU(l) => extract!(u8, 16, l, i32),
+        I::I16x8ExtractLaneS(l) => extract!(i16, 8, l, i32),
+        I::I16x8ExtractLaneU(l) => extract!(u16, 8, l, i32),
+        I::I32x4ExtractLane(l) => extract!(i32, 4, l, i32),
+        I::I64x2ExtractLane(l) => extract!(i64, 2, l, i64),
+        I::F32x4ExtractLane(l) => extract!(f32, 4, l, f32),
+        I::F64x2ExtractLane(l) => extract!(f64, 2, l, f64),
+        I::I8x16ReplaceLane(l) => replace!(i32, i8, 16, l),
+        I::I16x8ReplaceLane(l) => replace!(i32, i16, 8, l),
+        I::I32x4ReplaceLane(l) => replace!(i32, i32, 4, l),
+        I::I64x2ReplaceLane(l) => replace!(i64, i64, 2, l),
+        I::F32x4ReplaceLane(l) => replace!(f32, f32, 4, l),
+        I::F64x2ReplaceLane(l) => replace!(f64, f64, 2, l),
+
+        I::I8x16Eq => v2!(|a, b| zip::<i8, i8, 16>(a, b, |x, y| mask8(x == y))),
+        I::I8x16Ne => v2!(|a, b| zip::<i8, i8, 16>(a, b, |x, y| mask8(x != y))),
+        I::I8x16LtS => v2!(|a, b| zip::<i8, i8, 16>(a, b, |x, y| mask8(x < y))),
+        I::I8x16LtU => v2!(|a, b| zip::<u8, i8, 16>(a, b, |x, y| mask8(x < y))),
+        I::I8x16GtS => v2!(|a, b| zip::<i8, i8, 16>(a, b, |x, y| mask8(x > y))),
+        I::I8x16GtU => v2!(|a, b| zip::<u8, i8, 16>(a, b, |x, y| mask8(x > y))),
+        I::I8x16LeS => v2!(|a, b| zip::<i8, i8, 16>(a, b, |x, y| mask8(x <= y))),
+        I::I8x16LeU => v2!(|a, b| zip::<u8, i8, 16>(a, b, |x, y| mask8(x <= y))),
+        I::I8x16GeS => v2!(|a, b| zip::<i8, i8, 16>(a, b, |x, y| mask8(x >= y))),
+        I::I8x16GeU => v2!(|a, b| zip::<u8, i8, 16>(a, b, |x, y| mask8(x >= y))),
+        I::I16x8Eq => v2!(|a, b| zip::<i16, i16, 8>(a, b, |x, y| mask16(x == y))),
+        I::I16x8Ne => v2!(|a, b| zip::<i16, i16, 8>(a, b, |x, y| mask16(x != y))),
+        I::I16x8LtS => v2!(|a, b| zip::<i16, i16, 8>(a, b, |x, y| mask16(x < y))),
+        I::I16x8LtU => v2!(|a, b| zip::<u16, i16, 8>(a, b, |x, y| mask16(x < y))),
+        I::I16x8GtS => v2!(|a, b| zip::<i16, i16, 8>(a, b, |x, y| mask16(x > y))),
+        I::I16x8GtU => v2!(|a, b| zip::<u16, i16, 8>(a, b, |x, y| mask16(x > y))),
+        I::I16x8LeS => v2!(|a, b| zip::<i16, i16, 8>(a, b, |x, y| mask16(x <= y))),
+        I::I16x8LeU => v2!(|a, b| zip::<u16, i16, 8>(a, b, |x, y| mask16(x <= y))),
+        I::I16x8GeS => v2!(|a, b| zip::<i16, i16, 8>(a, b, |x, y| mask16(x >= y))),
+        I::I16x8GeU => v2!(|a, b| zip::<u16, i16, 8>(a, b, |x, y| mask16(x >= y))),
+        I::I32x4Eq => v2!(|a, b| zip::<i32, i32, 4>(a, b, |x, y| mask32(x == y))),
+        I::I32x4Ne => v2!(|a, b| zip::<i32, i32, 4>(a, b, |x, y| mask32(x != y))),
+        I::I32x4LtS => v2!(|a, b| zip::<i32, i32, 4>(a, b, |x, y| mask32(x < y))),
+        I::I32x4LtU => v2!(|a, b| zip::<u32, i32, 4>(a, b, |x, y| mask32(x < y))),
+        I::I32x4GtS => v2!(|a, b| zip::<i32, i32, 4>(a, b, |x, y| mask32(x > y))),
+        I::I32x4GtU => v2!(|a, b| zip::<u32, i32, 4>(a, b, |x, y| mask32(x > y))),
+        I::I32x4LeS => v2!(|a, b| zip::<i32, i32, 4>(a, b, |x, y| mask32(x <= y))),
+        I::I32x4LeU => v2!(|a, b| zip::<u32, i32, 4>(a, b, |x, y| mask32(x <= y))),
+        I::I32x4GeS => v2!(|a, b| zip::<i32, i32, 4>(a, b, |x, y| mask32(x >= y))),
+        I::I32x4GeU => v2!(|a, b| zip::<u32, i32, 4>(a, b, |x, y| mask32(x >= y))),
+        I::I64x2Eq => v2!(|a, b| zip::<i64, i64, 2>(a, b, |x, y| mask64(x == y))),
+        I::I64x2Ne => v2!(|a, b| zip::<i64, i64, 2>(a, b, |x, y| mask64(x != y))),
+        I::I64x2LtS => v2!(|a, b| zip::<i64, i64, 2>(a, b, |x, y| mask64(x < y))),
+        I::I64x2GtS => v2!(|a, b| zip::<i64, i64, 2>(a, b, |x, y| mask64(x > y))),
+        I::I64x2LeS => v2!(|a, b| zip::<i64, i64, 2>(a, b, |x, y| mask64(x <= y))),
+        I::I64x2GeS => v2!(|a, b| zip::<i64, i64, 2>(a, b, |x, y| mask64(x >= y))),
+        I::F32x4Eq => v2!(|a, b| zip::<f32, i32, 4>(a, b, |x, y| mask32(x == y))),
+        I::F32x4Ne => v2!(|a, b| zip::<f32, i32, 4>(a, b, |x, y| mask32(x != y))),
+        I::F32x4Lt => v2!(|a, b| zip::<f32, i32, 4>(a, b, |x, y| mask32(x < y))),
+        I::F32x4Gt => v2!(|a, b| zip::<f32, i32, 4>(a, b, |x, y| mask32(x > y))),
+        I::F32x4Le => v2!(|a, b| zip::<f32, i32, 4>(a, b, |x, y| mask32(x <= y))),
+        I::F32x4Ge => v2!(|a, b| zip::<f32, i32, 4>(a, b, |x, y| mask32(x >= y))),
+        I::F64x2Eq => v2!(|a, b| zip::<f64, i64, 2>(a, b, |x, y| mask64(x == y))),
+        I::F64x2Ne => v2!(|a, b| zip::<f64, i64, 2>(a, b, |x, y| mask64(x != y))),
+        I::F64x2Lt => v2!(|a, b| zip::<f64, i64, 2>(a, b, |x, y| mask64(x < y))),
+        I::F64x2Gt => v2!(|a, b| zip::<f64, i64, 2>(a, b, |x, y| mask64(x > y))),
+        I::F64x2Le => v2!(|a, b| zip::<f64, i64, 2>(a, b, |x, y| mask64(x <= y))),
+        I::F64x2Ge => v2!(|a, b| zip::<f64, i64, 2>(a, b, |x, y| mask64(x >= y))),
+
+        I::V128Not => v1!(|a| !a),
+        I::V128And => v2!(|a, b| a & b),
+        I::V128Andnot => v2!(|a, b| a & !b),
+        I::V128Or => v2!(|a, b| a | b),
+        I::V128Xor => v2!(|a, b| a ^ b),
+        I::V128Bitselect => {
+            let c = pop!(v128);
+            v2!(|a, b| (a & c) | (b & !c))
+        }
+        I::V128AnyTrue => test!(|a| a != 0),
+
+        I::I8x16Abs => v1!(|a| map::<i8, i8, 16>(a, |x| x.wrapping_abs())),
+        I::I8x16Neg => v1!(|a| map::<i8, i8, 16>(a, |x| x.wrapping_neg())),
+        I::I8x16Popcnt => v1!(|a| map::<u8, u8, 16>(a, |x| x.count_ones() as u8)),
+        I::I8x16AllTrue => test!(|a| all_true::<u8, 16>(a)),
+        I::I8x16Bitmask => test!(|a| bitmask::<i8, 16>(a)),
+        I::I8x16NarrowI16x8S => {
+            v2!(|a, b| narrow::<i16, i8, 8, 16>(a, b, |x| x.clamp(-128, 127) as i8))
+        }
+        I::I8x16NarrowI16x8U => {
+            v2!(|a, b| narrow::<i16, u8, 8, 16>(a, b, |x| x.clamp(0, 255) as u8))
+        }
+        I::I8x16Shl => shift!(i8, 16, |x, s| x.wrapping_shl(s)),
+        I::I8x16ShrS => shift!(i8, 16, |x, s| x.wrapping_shr(s)),
+        I::I8x16ShrU => shift!(u8, 16, |x, s| x.wrapping_shr(s)),
+        I::I8x16Add => v2!(|a, b| zip::<i8, i8, 16>(a, b, |x, y| x.wrapping_add(y))),
+        I::I8x16AddSatS => v2!(|a, b| zip::<i8, i8, 16>(a, b, |x, y| x.saturating_add(y))),
+        I::I8x16AddSatU => v2!(|a, b| zip::<u8, u8, 16>(a, b, |x, y| x.saturating_add(y))),
+        I::I8x16Sub => v2!(|a, b| zip::<i8, i8, 16>(a, b, |x, y| x.wrapping_sub(y))),
+        I::I8x16SubSatS => v2!(|a, b| zip::<i8, i8, 16>(a, b, |x, y| x.saturating_sub(y))),
+        I::I8x16SubSatU => v2!(|a, b| zip::<u8, u8, 16>(a, b, |x, y| x.saturating_sub(y))),
+        I::I8x16MinS => v2!(|a, b| zip::<i8, i8, 16>(a, b, |x, y| x.min(y))),
+        I::I8x16MinU => v2!(|a, b| zip::<u8, u8, 16>(a, b, |x, y| x.min(y))),
+        I::I8x16MaxS => v2!(|a, b| zip::<i8, i8, 16>(a, b, |x, y| x.max(y))),
+        I::I8x16MaxU => v2!(|a, b| zip::<u8, u8, 16>(a, b, |x, y| x.max(y))),
+        I::I8x16AvgrU => {
+            v2!(|a, b| zip::<u8, u8, 16>(a, b, |x, y| (x as u16 + y as u16).div_ceil(2) as u8))
+        }
+
+        I::I16x8ExtaddPairwiseI8x16S => {
+            v1!(|a| extadd_pairwise::<i8, i16, 16, 8>(a, |x, y| x as i16 + y as i16))
+        }
+        I::I16x8ExtaddPairwiseI8x16U => {
+            v1!(|a| extadd_pairwise::<u8, u16, 16, 8>(a, |x, y| x as u16 + y as u16))
+        }
+        I::I32x4ExtaddPairwiseI16x8S => {
+            v1!(|a| extadd_pairwise::<i16, i32, 8, 4>(a, |x, y| x as i32 + y as i32))
+        }
+        I::I32x4ExtaddPairwiseI16x8U => {
+            v1!(|a| extadd_pairwise::<u16, u32, 8, 4>(a, |x, y| x as u32 + y as u32))
+        }
+
+        I::I16x8Abs => v1!(|a| map::<i16, i16, 8>(a, |x| x.wrapping_abs())),
+        I::I16x8Neg => v1!(|a| map::<i16, i16, 8>(a, |x| x.wrapping_neg())),
+        I::I16x8Q15mulrSatS => v2!(|a, b| zip::<i16, i16, 8>(a, b, q15mulr_sat)),
+        I::I16x8AllTrue => test!(|a| all_true::<u16, 8>(a)),
+        I::I16x8Bitmask => test!(|a| bitmask::<i16, 8>(a)),
+        I::I16x8NarrowI32x4S => {
+            v2!(|a, b| narrow::<i32, i16, 4, 8>(a, b, |x| x.clamp(-32768, 32767) as i16))
+        }
+        I::I16x8NarrowI32x4U => {
+            v2!(|a, b| narrow::<i32, u16, 4, 8>(a, b, |x| x.clamp(0, 65535) as u16))
+        }
+        I::I16x8ExtendLowI8x16S => v1!(|a| extend::<i8, i16, 16, 8>(a, false, |x| x as i16)),
+        I::I16x8ExtendHighI8x16S => v1!(|a| extend::<i8, i16, 16, 8>(a, true, |x| x as i16)),
+        I::I16x8ExtendLowI8x16U => v1!(|a| extend::<u8, u16, 16, 8>(a, false, |x| x as u16)),
+        I::I16x8ExtendHighI8x16U => v1!(|a| extend::<u8, u16, 16, 8>(a, true, |x| x as u16)),
+        I::I16x8Shl => shift!(i16, 8, |x, s| x.wrapping_shl(s)),
+        I::I16x8ShrS => shift!(i16, 8, |x, s| x.wrapping_shr(s)),
+        I::I16x8ShrU => shift!(u16, 8, |x, s| x.wrapping_shr(s)),
+        I::I16x8Add => v2!(|a, b| zip::<i16, i16, 8>(a, b, |x, y| x.wrapping_add(y))),
+        I::I16x8AddSatS => v2!(|a, b| zip::<i16, i16, 8>(a, b, |x, y| x.saturating_add(y))),
+        I::I16x8AddSatU => v2!(|a, b| zip::<u16, u16, 8>(a, b, |x, y| x.saturating_add(y))),
+        I::I16x8Sub => v2!(|a, b| zip::<i16, i16, 8>(a, b, |x, y| x.wrapping_sub(y))),
+        I::I16x8SubSatS => v2!(|a, b| zip::<i16, i16, 8>(a, b, |x, y| x.saturating_sub(y))),
+        I::I16x8SubSatU => v2!(|a, b| zip::<u16, u16, 8>(a, b, |x, y| x.saturating_sub(y))),
+        I::I16x8Mul => v2!(|a, b| zip::<i16, i16, 8>(a, b, |x, y| x.wrapping_mul(y))),
+        I::I16x8MinS => v2!(|a, b| zip::<i16, i16, 8>(a, b, |x, y| x.min(y))),
+        I::I16x8MinU => v2!(|a, b| zip::<u16, u16, 8>(a, b, |x, y| x.min(y))),
+        I::I16x8MaxS => v2!(|a, b| zip::<i16, i16, 8>(a, b, |x, y| x.max(y))),
+        I::I16x8MaxU => v2!(|a, b| zip::<u16, u16, 8>(a, b, |x, y| x.max(y))),
+        I::I16x8AvgrU => {
+            v2!(|a, b| zip::<u16, u16, 8>(a, b, |x, y| (x as u32 + y as u32).div_ceil(2) as u16))
+        }
+        I::I16x8ExtmulLowI8x16S => {
+            v2!(|a, b| extmul::<i8, i16, 16, 8>(a, b, false, |x, y| x as i16 * y as i16))
+        }
+        I::I16x8ExtmulHighI8x16S => {
+            v2!(|a, b| extmul::<i8, i16, 16, 8>(a, b, true, |x, y| x as i16 * y as i16))
+        }
+        I::I16x8ExtmulLowI8x16U => {
+            v2!(|a, b| extmul::<u8, u16, 16, 8>(a, b, false, |x, y| x as u16 * y as u16))
+        }
+        I::I16x8ExtmulHighI8x16U => {
+            v2!(|a, b| extmul::<u8, u16, 16, 8>(a, b, true, |x, y| x as u16 * y as u16))
+        }
+
+        I::I32x4Abs => v1!(|a| map::<i32, i32, 4>(a, |x| x.wrapping_abs())),
+        I::I32x4Neg => v1!(|a| map::<i32, i32, 4>(a, |x| x.wrapping_neg())),
+        I::I32x4AllTrue => test!(|a| all_true::<u32, 4>(a)),
+        I::I32x4Bitmask => test!(|a| bitmask::<i32, 4>(a)),
+        I::I32x4ExtendLowI16x8S => v1!(|a| extend::<i16, i32, 8, 4>(a, false, |x| x as i32)),
+        I::I32x4ExtendHighI16x8S => v1!(|a| extend::<i16, i32, 8, 4>(a, true, |x| x as i32)),
+        I::I32x4ExtendLowI16x8U => v1!(|a| extend::<u16, u32, 8, 4>(a, false, |x| x as u32)),
+        I::I32x4ExtendHighI16x8U => v1!(|a| extend::<u16, u32, 8, 4>(a, true, |x| x as u32)),
+        I::I32x4Shl => shift!(i32, 4, |x, s| x.wrapping_shl(s)),
+        I::I32x4ShrS => shift!(i32, 4, |x, s| x.wrapping_shr(s)),
+        I::I32x4ShrU => shift!(u32, 4, |x, s| x.wrapping_shr(s)),
+        I::I32x4Add => v2!(|a, b| zip::<i32, i32, 4>(a, b, |x, y| x.wrapping_add(y))),
+        I::I32x4Sub => v2!(|a, b| zip::<i32, i32, 4>(a, b, |x, y| x.wrapping_sub(y))),
+        I::I32x4Mul => v2!(|a, b| zip::<i32, i32, 4>(a, b, |x, y| x.wrapping_mul(y))),
+        I::I32x4MinS => v2!(|a, b| zip::<i32, i32, 4>(a, b, |x, y| x.min(y))),
+        I::I32x4MinU => v2!(|a, b| zip::<u32, u32, 4>(a, b, |x, y| x.min(y))),
+        I::I32x4MaxS => v2!(|a, b| zip::<i32, i32, 4>(a, b, |x, y| x.max(y))),
+        I::I32x4MaxU => v2!(|a, b| zip::<u32, u32, 4>(a, b, |x, y| x.max(y))),
+        I::I32x4DotI16x8S => v2!(|a, b| {
+            let (a, b) = (lanes::<i16, 8>(a), lanes::<i16, 8>(b));
+            from_lanes::<i32, 4>(std::array::from_fn(|i| {
+                let lo = a[2 * i] as i32 * b[2 * i] as i32;
+                let hi = a[2 * i + 1] as i32 * b[2 * i + 1] as i32;
+                lo.wrapping_add(hi)
+            }))
+        }),
+        I::I32x4ExtmulLowI16x8S => {
+            v2!(|a, b| extmul::<i16, i32, 8, 4>(a, b, false, |x, y| x as i32 * y as i32))
+        }
+        I::I32x4ExtmulHighI16x8S => {
+            v2!(|a, b| extmul::<i16, i32, 8, 4>(a, b, true, |x, y| x as i32 * y as i32))
+        }
+        I::I32x4ExtmulLowI16x8U => {
+            v2!(|a, b| extmul::<u16, u32, 8, 4>(a, b, false, |x, y| x as u32 * y as u32))
+        }
+        I::I32x4ExtmulHighI16x8U => {
+            v2!(|a, b| extmul::<u16, u32, 8, 4>(a, b, true, |x, y| x as u32 * y as u32))
+        }
+
+        I::I64x2Abs => v1!(|a| map::<i64, i64, 2>(a, |x| x.wrapping_abs())),
+        I::I64x2Neg => v1!(|a| map::<i64, i64, 2>(a, |x| x.wrapping_neg())),
+        I::I64x2AllTrue => test!(|a| all_true::<u64, 2>(a)),
+        I::I64x2Bitmask => test!(|a| bitmask::<i64, 2>(a)),
+        I::I64x2ExtendLowI32x4S => v1!(|a| extend::<i32, i64, 4, 2>(a, false, |x| x as i64)),
+        I::I64x2ExtendHighI32x4S => v1!(|a| extend::<i32, i64, 4, 2>(a, true, |x| x as i64)),
+        I::I64x2ExtendLowI32x4U => v1!(|a| extend::<u32, u64, 4, 2>(a, false, |x| x as u64)),
+        I::I64x2ExtendHighI32x4U => v1!(|a| extend::<u32, u64, 4, 2>(a, true, |x| x as u64)),
+        I::I64x2Shl => shift!(i64, 2, |x, s| x.wrapping_shl(s)),
+        I::I64x2ShrS => shift!(i64, 2, |x, s| x.wrapping_shr(s)),
+        I::I64x2ShrU => shift!(u64, 2, |x, s| x.wrapping_shr(s)),
+        I::I64x2Add => v2!(|a, b| zip::<i64, i64, 2>(a, b, |x, y| x.wrapping_add(y))),
+        I::I64x2Sub => v2!(|a, b| zip::<i64, i64, 2>(a, b, |x, y| x.wrapping_sub(y))),
+        I::I64x2Mul => v2!(|a, b| zip::<i64, i64, 2>(a, b, |x, y| x.wrapping_mul(y))),
+        I::I64x2ExtmulLowI32x4S => {
+            v2!(|a, b| extmul::<i32, i64, 4, 2>(a, b, false, |x, y| x as i64 * y as i64))
+        }
+        I::I64x2ExtmulHighI32x4S => {
+            v2!(|a, b| extmul::<i32, i64, 4, 2>(a, b, true, |x, y| x as i64 * y as i64))
+        }
+        I::I64x2ExtmulLowI32x4U => {
+            v2!(|a, b| extmul::<u32, u64, 4, 2>(a, b, false, |x, y| x as u64 * y as u64))
+        }
+        I::I64x2ExtmulHighI32x4U => {
+            v2!(|a, b| extmul::<u32, u64, 4, 2>(a, b, true, |x, y| x as u64 * y as u64))
+        }
+
+        I::F32x4Ceil => v1!(|a| map::<f32, f32, 4>(a, |x| x.ceil())),
+        I::F32x4Floor => v1!(|a| map::<f32, f32, 4>(a, |x| x.floor())),
+        I::F32x4Trunc => v1!(|a| map::<f32, f32, 4>(a, |x| x.trunc())),
+        I::F32x4Nearest => v1!(|a| map::<f32, f32, 4>(a, |x| x.round_ties_even())),
+        I::F32x4Abs => v1!(|a| map::<f32, f32, 4>(a, |x| x.abs())),
+        I::F32x4Neg => v1!(|a| map::<f32, f32, 4>(a, |x| -x)),
+        I::F32x4Sqrt => v1!(|a| map::<f32, f32, 4>(a, |x| x.sqrt())),
+        I::F32x4Add => v2!(|a, b| zip::<f32, f32, 4>(a, b, |x, y| x + y)),
+        I::F32x4Sub => v2!(|a, b| zip::<f32, f32, 4>(a, b, |x, y| x - y)),
+        I::F32x4Mul => v2!(|a, b| zip::<f32, f32, 4>(a, b, |x, y| x * y)),
+        I::F32x4Div => v2!(|a, b| zip::<f32, f32, 4>(a, b, |x, y| x / y)),
+        I::F32x4Min => v2!(|a, b| zip::<f32, f32, 4>(a, b, numeric::f32_min)),
+        I::F32x4Max => v2!(|a, b| zip::<f32, f32, 4>(a, b, numeric::f32_max)),
+        I::F32x4Pmin => v2!(|a, b| zip::<f32, f32, 4>(a, b, pmin)),
+        I::F32x4Pmax => v2!(|a, b| zip::<f32, f32, 4>(a, b, pmax)),
+        I::F64x2Ceil => v1!(|a| map::<f64, f64, 2>(a, |x| x.ceil())),
+        I::F64x2Floor => v1!(|a| map::<f64, f64, 2>(a, |x| x.floor())),
+        I::F64x2Trunc => v1!(|a| map::<f64, f64, 2>(a, |x| x.trunc())),
+        I::F64x2Nearest => v1!(|a| map::<f64, f64, 2>(a, |x| x.round_ties_even())),
+        I::F64x2Abs => v1!(|a| map::<f64, f64, 2>(a, |x| x.abs())),
+        I::F64x2Neg => v1!(|a| map::<f64, f64, 2>(a, |x| -x)),
+        I::F64x2Sqrt => v1!(|a| map::<f64, f64, 2>(a, |x| x.sqrt())),
+        I::F64x2Add => v2!(|a, b| zip::<f64, f64, 2>(a, b, |x, y| x + y)),
+        I::F64x2Sub => v2!(|a, b| zip::<f64, f64, 2>(a, b, |x, y| x - y)),
+        I::F64x2Mul => v2!(|a, b| zip::<f64, f64, 2>(a, b, |x, y| x * y)),
+        I::F64x2Div => v2!(|a, b| zip::<f64, f64, 2>(a, b, |x, y| x / y)),
+        I::F64x2Min => v2!(|a, b| zip::<f64, f64, 2>(a, b, numeric::f64_min)),
+        I::F64x2Max => v2!(|a, b| zip::<f64, f64, 2>(a, b, numeric::f64_max)),
+        I::F64x2Pmin => v2!(|a, b| zip::<f64, f64, 2>(a, b, pmin)),
+        I::F64x2Pmax => v2!(|a, b| zip::<f64, f64, 2>(a, b, pmax)),
+
+        // 浮動小数点数から整数へのasは、飽和させてNaNを0にする
+        I::I32x4TruncSatF32x4S => v1!(|a| map::<f32, i32, 4>(a, |x| x as i32)),
+        I::I32x4TruncSatF32x4U => v1!(|a| map::<f32, u32, 4>(a, |x| x as u32)),
+        I::F32x4ConvertI32x4S => v1!(|a| map::<i32, f32, 4>(a, |x| x as f32)),
+        I::F32x4ConvertI32x4U => v1!(|a| map::<u32, f32, 4>(a, |x| x as f32)),
+        // 結果のレーンが足りない部分は0にする
+        I::I32x4TruncSatF64x2SZero => v1!(|a| {
+            let l = lanes::<f64, 2>(a);
+            from_lanes::<i32, 4>([l[0] as i32, l[1] as i32, 0, 0])
+        }),
+        I::I32x4TruncSatF64x2UZero => v1!(|a| {
+            let l = lanes::<f64, 2>(a);
+            from_lanes::<u32, 4>([l[0] as u32, l[1] as u32, 0, 0])
+        }),
+        I::F64x2ConvertLowI32x4S => v1!(|a| extend::<i32, f64, 4, 2>(a, false, |x| x as f64)),
+        I::F64x2ConvertLowI32x4U => v1!(|a| extend::<u32, f64, 4, 2>(a, false, |x| x as f64)),
+        I::F32x4DemoteF64x2Zero => v1!(|a| {
+            let l = lanes::<f64, 2>(a);
+            from_lanes::<f32, 4>([l[0] as f32, l[1] as f32, 0.0, 0.0])
+        }),
+        I::F64x2PromoteLowF32x4 => v1!(|a| extend::<f32, f64, 4, 2>(a, false, |x| x as f64)),
+
+        _ => return false,
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(i: I, args: &[Value]) -> Value {
+        let mut stack = args.to_vec();
+        assert!(execute(&i, &mut stack));
+        assert_eq!(stack.len(), 1);
+        stack[0]
+    }
+
+    fn i32x4(l: [i32; 4]) -> Value {
+        Value::V128(from_lanes::<i32, 4>(l))
+    }
+
+    #[test]
+    fn lane_layout() {
+        let v = 0x0f0e0d0c_0b0a0908_07060504_03020100u128;
+        assert_eq!(lanes::<u8, 16>(v)[1], 1);
+        assert_eq!(lanes::<u32, 4>(v)[3], 0x0f0e0d0c);
+        assert_eq!(from_lanes::<u16, 8>(lanes::<u16, 8>(v)), v);
+    }
+
+    #[test]
+    fn integer_lanes() {
+        let a = i32x4([1, -2, i32::MAX, 4]);
+        let b = i32x4([10, 20, 1, -4]);
+        assert_eq!(run(I::I32x4Add, &[a, b]), i32x4([11, 18, i32::MIN, 0]));
+        assert_eq!(run(I::I32x4LtS, &[a, b]), i32x4([-1, -1, 0, 0]));
+        assert_eq!(run(I::I32x4Bitmask, &[a]), Value::I32(0b0010));
+        assert_eq!(run(I::I32x4AllTrue, &[a]), Value::I32(1));
+        assert_eq!(
+            run(I::I32x4Shl, &[a, Value::I32(33)]),
+            i32x4([2, -4, -2, 8])
+        );
+        assert_eq!(run(I::I32x4ExtractLane(1), &[a]), Value::I32(-2));
+        assert_eq!(
+            run(I::I32x4ReplaceLane(3), &[a, Value::I32(7)]),
+            i32x4([1, -2, i32::MAX, 7])
+        );
+        let bytes = Value::V128(from_lanes::<i8, 16>([-1; 16]));
+        assert_eq!(
+            run(I::I16x8ExtendLowI8x16U, &[bytes]),
+            Value::V128(from_lanes::<u16, 8>([255; 8]))
+        );
+        assert_eq!(
+            run(
+                I::I8x16NarrowI16x8U,
+                &[i32x4([-1; 4]), i32x4([0x7fff_0100; 4])]
+            ),
+            Value::V128(from_lanes::<u8, 16>([
+                0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255
+            ]))
+        );
+        let s = from_lanes::<u8, 16>(std::array::from_fn(|i| i as u8));
+        let idx = from_lanes::<u8, 16>(std::array::from_fn(
+            |i| if i == 0 { 99 } else { 15 - i as u8 },
+        ));
+        assert_eq!(
+            run(I::I8x16Swizzle, &[Value::V128(s), Value::V128(idx)]),
+            Value::V128(from_lanes::<u8, 16>(std::array::from_fn(|i| {
+                if i == 0 {
+                    0
+                } else {
+                    15 - i as u8
+                }
+            })))
+        );
+    }
+
+    #[test]
+    fn float_lanes() {
+        let a = Value::V128(from_lanes::<f32, 4>([1.5, -0.0, f32::NAN, 2.5]));
+        let b = Value::V128(from_lanes::<f32, 4>([1.0, 0.0, 1.0, 3.0]));
+        let min = run(I::F32x4Min, &[a, b]).v128();
+        let l = lanes::<f32, 4>(min);
+        assert_eq!((l[0], l[3]), (1.0, 2.5));
+        assert!(l[1].is_sign_negative() && l[2].is_nan());
+        let l = lanes::<f32, 4>(run(I::F32x4Pmin, &[a, b]).v128());
+        assert_eq!((l[0], l[3]), (1.0, 2.5));
+        assert!(l[1].is_sign_negative() && l[2].is_nan());
+        assert_eq!(run(I::I32x4TruncSatF32x4U, &[a]), i32x4([1, 0, 0, 2]));
+        assert_eq!(run(I::F32x4Nearest, &[a]).v128() as u32, 2.0f32.to_bits());
+        assert!(!execute(&I::I32Add, &mut vec![]));
+    }
+}
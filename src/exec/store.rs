@@ -0,0 +1,367 @@
+use std::cell::RefCell;
+
+use crate::{
+    ast::{FuncType, GlobalType, Limits, TableType},
+    features::Features,
+};
+
+use super::{
+    fuel::FuelCosts,
+    func::{Func, FuncInst},
+    instance::ModuleInst,
+    interpreter::{Nesting, StackLimits, Suspended},
+    limits::{Limiter, ResourceLimiter},
+    memory::{MemInst, Memory},
+    trap::{Result, Trap},
+    value::Value,
+};
+
+// 仕様
+// https://webassembly.github.io/spec/core/exec/runtime.html#store
+
+// 関数、テーブル、メモリ、グローバル、タグ、セグメント、例外とモジュールのインスタンスを持つストア
+// 各インスタンスはストアでの位置を表すハンドルで参照し、ハンドルは作成したストアでだけ有効
+// 燃料と、燃料が足りなくなって中断した実行、資源とスタックの制限、
+// インスタンス化するモジュールを検証するときに有効にする提案もストアごとに持つ
+#[derive(Default)]
+pub struct Store {
+    pub(crate) funcs: Vec<FuncInst>,
+    pub(crate) tables: Vec<TableInst>,
+    pub(crate) mems: Vec<MemInst>,
+    pub(crate) globals: Vec<GlobalInst>,
+    pub(crate) tags: Vec<TagInst>,
+    pub(crate) elems: Vec<ElemInst>,
+    pub(crate) datas: Vec<DataInst>,
+    pub(crate) exns: Exns,
+    pub(crate) instances: Vec<ModuleInst>,
+    pub(crate) fuel: Option<u64>,
+    pub(crate) fuel_costs: FuelCosts,
+    pub(crate) suspended: Option<Suspended>,
+    pub(crate) limiter: Limiter,
+    pub(crate) stack_limits: StackLimits,
+    pub(crate) nesting: Nesting,
+    pub(crate) features: Features,
+}
+
+impl Store {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_stack_limits(&mut self, limits: StackLimits) {
+        self.stack_limits = limits;
+    }
+
+    pub fn set_features(&mut self, features: Features) {
+        self.features = features;
+    }
+
+    // 関数の戻り値やGlobal::get、Table::getで受け取ったexnrefを、使い終わったときに手放す
+    // 手放すまで、その例外は回収されない
+    pub fn release_exnref(&mut self, v: &Value) {
+        self.exns.release(v);
+    }
+}
+
+// インポートとエクスポートの対象になる値
+// https://webassembly.github.io/spec/core/exec/runtime.html#external-values
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub enum Extern {
+    Func(Func),
+    Table(Table),
+    Memory(Memory),
+    Global(Global),
+    Tag(Tag),
+}
+
+macro_rules! from_extern {
+    ($($t:ident),*) => {
+        $(
+            impl From<$t> for Extern {
+                fn from(x: $t) -> Self {
+                    Self::$t(x)
+                }
+            }
+        )*
+    };
+}
+
+from_extern!(Func, Table, Memory, Global, Tag);
+
+// https://webassembly.github.io/spec/core/exec/runtime.html#table-instances
+#[derive(Debug, PartialEq, Clone)]
+pub struct TableInst {
+    pub ty: TableType,
+    pub elems: Vec<Value>,
+}
+
+impl TableInst {
+    // テーブルをn要素だけ広げ、広げる前の要素数を返す
+    // 最大値を超える場合や、limiterが拒否した場合は広げずにNoneを返す
+    // https://webassembly.github.io/spec/core/exec/modules.html#grow-table
+    pub fn grow(&mut self, n: u32, init: Value, limiter: &mut dyn ResourceLimiter) -> Option<u32> {
+        let old = self.elems.len() as u32;
+        let new = old.checked_add(n)?;
+        let max = self.ty.limits.max;
+        if max.is_some_and(|max| new > max) || !limiter.table_growing(old, new, max) {
+            return None;
+        }
+        self.elems.resize(new as usize, init);
+        self.ty.limits.min = new;
+        Some(old)
+    }
+}
+
+// テーブルのハンドル
+#[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
+pub struct Table(pub(crate) u32);
+
+impl Table {
+    // 全ての要素をinitにしたテーブルを作成する
+    pub fn new(store: &mut Store, ty: TableType, init: Value) -> Self {
+        store.tables.push(TableInst {
+            ty,
+            elems: vec![init; ty.limits.min as usize],
+        });
+        Self(store.tables.len() as u32 - 1)
+    }
+
+    pub fn ty(&self, store: &Store) -> TableType {
+        store.tables[self.0 as usize].ty
+    }
+
+    pub fn size(&self, store: &Store) -> u32 {
+        store.tables[self.0 as usize].elems.len() as u32
+    }
+
+    // ストアのResourceLimiterに問い合わせてから広げる
+    pub fn grow(&self, store: &mut Store, n: u32, init: Value) -> Result<Option<u32>> {
+        let table = &mut store.tables[self.0 as usize];
+        if init.ty() != table.ty.elem {
+            return Err(Trap::TypeMismatch);
+        }
+        Ok(table.grow(n, init, &mut *store.limiter.0))
+    }
+
+    pub fn get(&self, store: &Store, idx: u32) -> Option<Value> {
+        let v = store.tables[self.0 as usize]
+            .elems
+            .get(idx as usize)
+            .copied()?;
+        store.exns.pin(&v);
+        Some(v)
+    }
+
+    pub fn set(&self, store: &mut Store, idx: u32, v: Value) -> Result<()> {
+        let table = &mut store.tables[self.0 as usize];
+        if v.ty() != table.ty.elem {
+            return Err(Trap::TypeMismatch);
+        }
+        match table.elems.get_mut(idx as usize) {
+            Some(e) => *e = v,
+            None => return Err(Trap::TableOutOfBounds),
+        }
+        Ok(())
+    }
+}
+
+// https://webassembly.github.io/spec/core/exec/runtime.html#global-instances
+#[derive(Debug, PartialEq, Clone)]
+pub struct GlobalInst {
+    pub ty: GlobalType,
+    pub value: Value,
+}
+
+// グローバルのハンドル
+#[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
+pub struct Global(pub(crate) u32);
+
+impl Global {
+    pub fn new(store: &mut Store, ty: GlobalType, value: Value) -> Self {
+        store.globals.push(GlobalInst { ty, value });
+        Self(store.globals.len() as u32 - 1)
+    }
+
+    pub fn ty(&self, store: &Store) -> GlobalType {
+        store.globals[self.0 as usize].ty
+    }
+
+    pub fn get(&self, store: &Store) -> Value {
+        let v = store.globals[self.0 as usize].value;
+        store.exns.pin(&v);
+        v
+    }
+
+    // 埋め込み側からも、不変のグローバルは変更できない
+    pub fn set(&self, store: &mut Store, value: Value) -> Result<()> {
+        let global = &mut store.globals[self.0 as usize];
+        if !global.ty.mutable {
+            return Err(Trap::host("global is immutable"));
+        }
+        if value.ty() != global.ty.value_type {
+            return Err(Trap::TypeMismatch);
+        }
+        global.value = value;
+        Ok(())
+    }
+}
+
+// 例外のタグ
+// 同じ型を持つタグでも、インスタンスが異なれば別のタグとして扱う
+// https://webassembly.github.io/exception-handling/core/exec/runtime.html#tag-instances
+#[derive(Debug, PartialEq, Clone)]
+pub struct TagInst {
+    pub ty: FuncType,
+}
+
+// タグのハンドル
+#[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
+pub struct Tag(pub(crate) u32);
+
+impl Tag {
+    pub fn new(store: &mut Store, ty: FuncType) -> Self {
+        store.tags.push(TagInst { ty });
+        Self(store.tags.len() as u32 - 1)
+    }
+
+    pub fn ty<'a>(&self, store: &'a Store) -> &'a FuncType {
+        &store.tags[self.0 as usize].ty
+    }
+}
+
+// エレメントセグメントとデータセグメント
+// elem.dropとdata.dropで中身を空にする
+// https://webassembly.github.io/spec/core/exec/runtime.html#element-instances
+#[derive(Debug, PartialEq, Clone)]
+pub struct ElemInst {
+    pub elems: Vec<Value>,
+}
+
+// https://webassembly.github.io/spec/core/exec/runtime.html#data-instances
+#[derive(Debug, PartialEq, Clone)]
+pub struct DataInst {
+    pub data: Vec<u8>,
+}
+
+// 投げられた例外
+// exnrefはストアでの例外の位置を持つ
+// https://webassembly.github.io/exception-handling/core/exec/runtime.html#exception-instances
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExnInst {
+    pub tag: Tag,
+    pub fields: Vec<Value>,
+}
+
+// 例外を回収する目安にする、使用中の例外の数の最小値
+const MIN_EXN_THRESHOLD: usize = 64;
+
+// 例外のインスタンスを持つ領域
+// 到達できなくなった例外は回収して、その位置を次の例外に再利用する
+// ホストや埋め込み側に渡したexnrefは追跡できないため、固定して回収しない
+// 固定は後に固定したものから外す
+// ホスト関数の呼び出し中に固定したものは、呼び出しから戻ると外し、
+// 埋め込み側に渡したものは、埋め込み側がStore::release_exnrefで外す
+#[derive(Debug, Default)]
+pub(crate) struct Exns {
+    slots: Vec<Option<ExnInst>>,
+    free: Vec<u32>,
+    pinned: RefCell<Vec<u32>>,
+    threshold: usize,
+}
+
+impl Exns {
+    pub(crate) fn alloc(&mut self, exn: ExnInst) -> u32 {
+        match self.free.pop() {
+            Some(i) => {
+                self.slots[i as usize] = Some(exn);
+                i
+            }
+            None => {
+                self.slots.push(Some(exn));
+                self.slots.len() as u32 - 1
+            }
+        }
+    }
+
+    // 埋め込み側が固定を外した後に使ったexnrefは、回収済みのためNoneになることがある
+    pub(crate) fn get(&self, i: u32) -> Option<&ExnInst> {
+        self.slots.get(i as usize)?.as_ref()
+    }
+
+    // 確保した位置の数で、回収した位置も含む
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    // 値がexnrefの場合は、ホストや埋め込み側に渡すため固定する
+    pub(crate) fn pin(&self, v: &Value) {
+        if let Value::ExnRef(Some(i)) = v {
+            self.pinned.borrow_mut().push(*i);
+        }
+    }
+
+    // 固定した数で、unpin_toに渡すと、それより後に固定したものを外す
+    pub(crate) fn pins(&self) -> usize {
+        self.pinned.borrow().len()
+    }
+
+    pub(crate) fn unpin_to(&self, pins: usize) {
+        self.pinned.borrow_mut().truncate(pins);
+    }
+
+    // 値がexnrefの場合は、最後に固定したものを1つ外す
+    pub(crate) fn release(&mut self, v: &Value) {
+        let Value::ExnRef(Some(i)) = v else {
+            return;
+        };
+        let pinned = self.pinned.get_mut();
+        if let Some(pos) = pinned.iter().rposition(|p| p == i) {
+            pinned.remove(pos);
+        }
+    }
+
+    // 使用中の例外が前回の回収後の2倍に増えたら回収する
+    pub(crate) fn should_collect(&self) -> bool {
+        self.slots.len() - self.free.len() >= self.threshold.max(MIN_EXN_THRESHOLD)
+    }
+
+    // rootsと固定した例外から、例外のフィールドをたどって到達できない例外を回収する
+    pub(crate) fn collect<'v>(&mut self, roots: impl Iterator<Item = &'v Value>) {
+        let mut marked = vec![false; self.slots.len()];
+        let mut work: Vec<u32> = self.pinned.borrow().iter().copied().collect();
+        work.extend(roots.filter_map(|v| match v {
+            Value::ExnRef(Some(i)) => Some(*i),
+            _ => None,
+        }));
+        while let Some(i) = work.pop() {
+            let Some(exn) = self.get(i) else {
+                continue;
+            };
+            if std::mem::replace(&mut marked[i as usize], true) {
+                continue;
+            }
+            work.extend(exn.fields.iter().filter_map(|v| match v {
+                Value::ExnRef(Some(i)) => Some(*i),
+                _ => None,
+            }));
+        }
+        for (i, marked) in marked.into_iter().enumerate() {
+            if !marked && self.slots[i].take().is_some() {
+                self.free.push(i as u32);
+            }
+        }
+        self.threshold = 2 * (self.slots.len() - self.free.len());
+    }
+}
+
+// インポートするテーブルやメモリの制限が、インポート先の制限に収まるか
+// https://webassembly.github.io/spec/core/valid/types.html#match-limits
+pub(crate) fn match_limits(actual: &Limits, expected: &Limits) -> bool {
+    actual.min >= expected.min
+        && match (actual.max, expected.max) {
+            (_, None) => true,
+            (Some(a), Some(e)) => a <= e,
+            (None, Some(_)) => false,
+        }
+}
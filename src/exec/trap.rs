@@ -0,0 +1,193 @@
+use std::fmt;
+
+// 実行を中断するトラップ
+// メッセージは仕様のテストスイートで使われているものに合わせる
+// https://webassembly.github.io/spec/core/exec/runtime.html#syntax-trap
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub enum Trap {
+    Unreachable,
+    IntegerOverflow,
+    IntegerDivideByZero,
+    InvalidConversionToInteger,
+    MemoryOutOfBounds,
+    TableOutOfBounds,
+    UndefinedElement,
+    UninitializedElement,
+    IndirectCallTypeMismatch,
+    NullReference,
+    UncaughtException,
+    StackExhausted,
+    OutOfFuel,
+    // 埋め込み側から渡した値や、ホスト関数の戻り値の型が合わない
+    TypeMismatch,
+    // 実行できない命令の名前
+    Unsupported(&'static str),
+    // ホスト関数や埋め込み側のAPIで発生したエラー
+    Host(String),
+}
+
+impl Trap {
+    pub fn host(message: impl Into<String>) -> Self {
+        Self::Host(message.into())
+    }
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreachable => write!(f, "unreachable"),
+            Self::IntegerOverflow => write!(f, "integer overflow"),
+            Self::IntegerDivideByZero => write!(f, "integer divide by zero"),
+            Self::InvalidConversionToInteger => write!(f, "invalid conversion to integer"),
+            Self::MemoryOutOfBounds => write!(f, "out of bounds memory access"),
+            Self::TableOutOfBounds => write!(f, "out of bounds table access"),
+            Self::UndefinedElement => write!(f, "undefined element"),
+            Self::UninitializedElement => write!(f, "uninitialized element"),
+            Self::IndirectCallTypeMismatch => write!(f, "indirect call type mismatch"),
+            Self::NullReference => write!(f, "null exception reference"),
+            Self::UncaughtException => write!(f, "uncaught exception"),
+            Self::StackExhausted => write!(f, "call stack exhausted"),
+            Self::OutOfFuel => write!(f, "all fuel consumed"),
+            Self::TypeMismatch => write!(f, "type mismatch"),
+            Self::Unsupported(name) => write!(f, "unsupported instruction {name}"),
+            Self::Host(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for Trap {}
+
+pub type Result<T> = std::result::Result<T, Trap>;
+
+// バックトレースの1つのフレーム
+// funcはモジュールでの関数のインデックス、nameは名前セクションでの関数の名前、
+// offsetは関数本体での実行中の命令の位置 (バイトオフセットではなく命令の番号)
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct FrameInfo {
+    pub func: u32,
+    pub name: Option<String>,
+    pub offset: usize,
+}
+
+impl fmt::Display for FrameInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "func[{}]", self.func)?;
+        if let Some(name) = &self.name {
+            write!(f, " <{name}>")?;
+        }
+        write!(f, " at instruction {}", self.offset)
+    }
+}
+
+// Wasmの関数の呼び出しで発生したトラップ
+// backtraceはトラップした関数から順に、呼び出し元のWasmのフレームを並べる
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct RuntimeError {
+    pub trap: Trap,
+    pub backtrace: Vec<FrameInfo>,
+}
+
+impl From<Trap> for RuntimeError {
+    fn from(trap: Trap) -> Self {
+        Self {
+            trap,
+            backtrace: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.trap)?;
+        if !self.backtrace.is_empty() {
+            write!(f, "\nwasm backtrace:")?;
+            for (i, frame) in self.backtrace.iter().enumerate() {
+                write!(f, "\n  {i}: {frame}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        exec::{Extern, Func, Instance, Store, Value},
+        wat::module::module,
+    };
+
+    #[test]
+    fn backtrace() {
+        let mut store = Store::new();
+        let nop = Func::wrap(&mut store, || {});
+        let (_, m) = module(
+            "(module
+                (import \"env\" \"nop\" (func $nop))
+                (func $div (param i32) (result i32)
+                  call $nop
+                  i32.const 1
+                  local.get 0
+                  i32.div_u)
+                (func $run (export \"run\") (param i32) (result i32)
+                  nop
+                  local.get 0
+                  call $div)
+                (tag $e)
+                (func (export \"throw\")
+                  throw $e))",
+        )
+        .unwrap();
+        let inst = Instance::new(&mut store, &m, &[Extern::Func(nop)]).unwrap();
+        assert_eq!(
+            inst.invoke(&mut store, "run", &[Value::I32(2)]),
+            Ok(vec![Value::I32(0)])
+        );
+
+        let e = inst
+            .invoke(&mut store, "run", &[Value::I32(0)])
+            .unwrap_err();
+        assert_eq!(e.trap, Trap::IntegerDivideByZero);
+        assert_eq!(
+            e.backtrace,
+            vec![
+                FrameInfo {
+                    func: 1,
+                    name: Some("div".to_string()),
+                    offset: 3,
+                },
+                FrameInfo {
+                    func: 2,
+                    name: Some("run".to_string()),
+                    offset: 2,
+                },
+            ]
+        );
+        assert_eq!(
+            e.to_string(),
+            "integer divide by zero
+wasm backtrace:
+  0: func[1] <div> at instruction 3
+  1: func[2] <run> at instruction 2"
+        );
+
+        let e = inst.invoke(&mut store, "throw", &[]).unwrap_err();
+        assert_eq!(e.trap, Trap::UncaughtException);
+        assert_eq!(
+            e.backtrace,
+            vec![FrameInfo {
+                func: 3,
+                name: None,
+                offset: 0,
+            }]
+        );
+
+        // Wasmのフレームがない場合、バックトレースは空になる
+        let e = inst
+            .invoke(&mut store, "run", &[Value::I64(0)])
+            .unwrap_err();
+        assert_eq!(e.to_string(), "type mismatch");
+    }
+}
@@ -0,0 +1,129 @@
+use crate::ast::ValueType;
+
+use super::func::Func;
+
+// 実行時の値
+// 参照は、関数を指す場合は関数のハンドル、
+// 例外を指す場合はストアでの例外の位置を持ち、nullの場合はNoneになる
+// https://webassembly.github.io/spec/core/exec/runtime.html#values
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Value {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    V128(u128),
+    FuncRef(Option<Func>),
+    ExternRef(Option<u32>),
+    ExnRef(Option<u32>),
+}
+
+impl Value {
+    // 値の型のデフォルト値で、ローカル変数の初期値に使う
+    // https://webassembly.github.io/spec/core/exec/runtime.html#default-val
+    pub fn default_of(t: ValueType) -> Self {
+        match t {
+            ValueType::I32 => Self::I32(0),
+            ValueType::I64 => Self::I64(0),
+            ValueType::F32 => Self::F32(0.0),
+            ValueType::F64 => Self::F64(0.0),
+            ValueType::V128 => Self::V128(0),
+            ValueType::FuncRef => Self::FuncRef(None),
+            ValueType::ExternRef => Self::ExternRef(None),
+            ValueType::ExnRef => Self::ExnRef(None),
+        }
+    }
+
+    pub fn ty(&self) -> ValueType {
+        match self {
+            Self::I32(_) => ValueType::I32,
+            Self::I64(_) => ValueType::I64,
+            Self::F32(_) => ValueType::F32,
+            Self::F64(_) => ValueType::F64,
+            Self::V128(_) => ValueType::V128,
+            Self::FuncRef(_) => ValueType::FuncRef,
+            Self::ExternRef(_) => ValueType::ExternRef,
+            Self::ExnRef(_) => ValueType::ExnRef,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(
+            self,
+            Self::FuncRef(None) | Self::ExternRef(None) | Self::ExnRef(None)
+        )
+    }
+
+    // 検証済みの命令列では型が一致するため、一致しない場合はパニックする
+    pub fn i32(&self) -> i32 {
+        match self {
+            Self::I32(v) => *v,
+            v => panic!("expected i32, got {v:?}"),
+        }
+    }
+
+    pub fn i64(&self) -> i64 {
+        match self {
+            Self::I64(v) => *v,
+            v => panic!("expected i64, got {v:?}"),
+        }
+    }
+
+    pub fn f32(&self) -> f32 {
+        match self {
+            Self::F32(v) => *v,
+            v => panic!("expected f32, got {v:?}"),
+        }
+    }
+
+    pub fn f64(&self) -> f64 {
+        match self {
+            Self::F64(v) => *v,
+            v => panic!("expected f64, got {v:?}"),
+        }
+    }
+
+    pub fn v128(&self) -> u128 {
+        match self {
+            Self::V128(v) => *v,
+            v => panic!("expected v128, got {v:?}"),
+        }
+    }
+}
+
+impl From<i32> for Value {
+    fn from(v: i32) -> Self {
+        Self::I32(v)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(v: i64) -> Self {
+        Self::I64(v)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(v: f32) -> Self {
+        Self::F32(v)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Self::F64(v)
+    }
+}
+
+// 比較の結果はi32の0か1で表す
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Self::I32(v as i32)
+    }
+}
+
+impl From<u128> for Value {
+    fn from(v: u128) -> Self {
+        Self::V128(v)
+    }
+}
@@ -0,0 +1,273 @@
+use crate::ast::{
+    instr::{self, Opcode},
+    BlockType, ImportDesc, Instruction, MemType, Module, ValueType,
+};
+
+// 有効にするWebAssemblyの提案
+// 無効な提案の機能を使ったモジュールは、パース、デコード、検証でエラーになる
+// https://github.com/WebAssembly/proposals/blob/main/finished-proposals.md
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub struct Features {
+    // 128ビットのベクトル型とSIMD命令
+    // https://github.com/WebAssembly/simd/blob/main/proposals/simd/SIMD.md
+    pub simd: bool,
+    // 共有メモリとアトミック命令
+    // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md
+    pub threads: bool,
+    // return_callとreturn_call_indirect
+    // https://github.com/WebAssembly/tail-call/blob/main/proposals/tail-call/Overview.md
+    pub tail_call: bool,
+    // タグ、throw、throw_ref、try_tableとexnref
+    // https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/Exceptions.md
+    pub exceptions: bool,
+    // 複数のメモリを持つモジュール
+    // https://github.com/WebAssembly/multi-memory/blob/main/proposals/multi-memory/Overview.md
+    pub multi_memory: bool,
+}
+
+// これまでパーサーとデコーダーが受け付けていた提案は、既定で有効にする
+impl Default for Features {
+    fn default() -> Self {
+        Self {
+            simd: true,
+            threads: true,
+            tail_call: true,
+            exceptions: true,
+            multi_memory: false,
+        }
+    }
+}
+
+// 無効な提案の機能が使われたことを表すエラーメッセージを作成する
+fn disabled(what: &str, feature: &str) -> Result<(), String> {
+    Err(format!("{what} used but {feature} feature disabled"))
+}
+
+impl Features {
+    // 全ての提案を有効にする
+    pub fn all() -> Self {
+        Self {
+            simd: true,
+            threads: true,
+            tail_call: true,
+            exceptions: true,
+            multi_memory: true,
+        }
+    }
+
+    // 全ての提案を無効にする
+    pub fn none() -> Self {
+        Self {
+            simd: false,
+            threads: false,
+            tail_call: false,
+            exceptions: false,
+            multi_memory: false,
+        }
+    }
+
+    pub fn value_type(&self, t: ValueType) -> Result<(), String> {
+        match t {
+            ValueType::V128 if !self.simd => disabled("v128 value type", "simd"),
+            ValueType::ExnRef if !self.exceptions => disabled("exnref value type", "exceptions"),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn mem_type(&self, m: &MemType) -> Result<(), String> {
+        if m.shared && !self.threads {
+            return disabled("shared memory", "threads");
+        }
+        Ok(())
+    }
+
+    // タグは例外処理の提案で導入された
+    pub fn tag(&self) -> Result<(), String> {
+        if !self.exceptions {
+            return disabled("tag", "exceptions");
+        }
+        Ok(())
+    }
+
+    // インポートしたものを含むメモリの数を調べる
+    pub fn memories(&self, n: usize) -> Result<(), String> {
+        if n > 1 && !self.multi_memory {
+            return disabled("multiple memories", "multi_memory");
+        }
+        Ok(())
+    }
+
+    // 命令と、その即値に含まれる値の型を調べる
+    // SIMD命令とアトミック命令は、命令表のオペコードの接頭辞で判断する
+    pub fn instruction(&self, i: &Instruction) -> Result<(), String> {
+        use Instruction as I;
+
+        match i {
+            I::ReturnCall(_) | I::ReturnCallIndirect(..) if !self.tail_call => {
+                disabled("tail-call instruction", "tail_call")
+            }
+            I::Throw(_) | I::ThrowRef | I::TryTable(..) if !self.exceptions => {
+                disabled("exception-handling instruction", "exceptions")
+            }
+            I::V128Const(_) | I::I8x16Shuffle(_) if !self.simd => {
+                disabled("SIMD instruction", "simd")
+            }
+            I::Block(BlockType::Value(t))
+            | I::Loop(BlockType::Value(t))
+            | I::If(BlockType::Value(t))
+            | I::TryTable(BlockType::Value(t), _)
+            | I::Select(Some(t))
+            | I::RefNull(t) => self.value_type(*t),
+            _ => match instr::find(i).map(|e| e.opcode) {
+                Some(Opcode::Prefixed(0xfd, _)) if !self.simd => {
+                    disabled("SIMD instruction", "simd")
+                }
+                Some(Opcode::Prefixed(0xfe, _)) if !self.threads => {
+                    disabled("atomic instruction", "threads")
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+
+    // 関数本体以外のモジュールの要素が、有効な提案だけを使っているかを調べる
+    pub fn fields(&self, module: &Module) -> Result<(), String> {
+        let mut mems = 0;
+        for import in &module.imports {
+            match &import.desc {
+                ImportDesc::Func(_) => (),
+                ImportDesc::Table(t) => self.value_type(t.elem)?,
+                ImportDesc::Memory(m) => {
+                    self.mem_type(m)?;
+                    mems += 1;
+                }
+                ImportDesc::Global(g) => self.value_type(g.value_type)?,
+                ImportDesc::Tag(_) => self.tag()?,
+            }
+        }
+        for (params, results) in &module.types {
+            for t in params.iter().chain(results) {
+                self.value_type(*t)?;
+            }
+        }
+        for t in &module.tables {
+            self.value_type(t.elem)?;
+        }
+        for m in &module.mems {
+            self.mem_type(m)?;
+        }
+        self.memories(mems + module.mems.len())?;
+        if !module.tags.is_empty() {
+            self.tag()?;
+        }
+        for g in &module.globals {
+            self.value_type(g.g_type.value_type)?;
+        }
+        let exprs = module
+            .globals
+            .iter()
+            .map(|g| &g.init)
+            .chain(module.elems.iter().flat_map(|e| &e.init));
+        for i in exprs.flatten() {
+            self.instruction(i)?;
+        }
+        Ok(())
+    }
+
+    // モジュール全体が、有効な提案だけを使っているかを調べる
+    pub fn module(&self, module: &Module) -> Result<(), String> {
+        self.fields(module)?;
+        for f in &module.funcs {
+            for t in &f.locals {
+                self.value_type(*t)?;
+            }
+            for i in &f.body {
+                self.instruction(i)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wat::module::module;
+
+    fn check(wat: &str, features: Features) -> Result<(), String> {
+        let (_, m) = module(wat).unwrap();
+        features.module(&m)
+    }
+
+    #[test]
+    fn proposals() {
+        let cases = [
+            (
+                "(module (func return_call 0))",
+                Features {
+                    tail_call: false,
+                    ..Features::all()
+                },
+                "tail-call instruction used but tail_call feature disabled",
+            ),
+            (
+                "(module (func (result v128) v128.const i64x2 0 0))",
+                Features {
+                    simd: false,
+                    ..Features::all()
+                },
+                "v128 value type used but simd feature disabled",
+            ),
+            (
+                "(module (func i32.const 0 i8x16.splat drop))",
+                Features {
+                    simd: false,
+                    ..Features::all()
+                },
+                "SIMD instruction used but simd feature disabled",
+            ),
+            (
+                "(module (memory 1 1 shared))",
+                Features {
+                    threads: false,
+                    ..Features::all()
+                },
+                "shared memory used but threads feature disabled",
+            ),
+            (
+                "(module (memory 1) (func i32.const 0 i32.atomic.load drop))",
+                Features {
+                    threads: false,
+                    ..Features::all()
+                },
+                "atomic instruction used but threads feature disabled",
+            ),
+            (
+                "(module (tag))",
+                Features {
+                    exceptions: false,
+                    ..Features::all()
+                },
+                "tag used but exceptions feature disabled",
+            ),
+            (
+                "(module (func (block (result exnref) ref.null exn)))",
+                Features {
+                    exceptions: false,
+                    ..Features::all()
+                },
+                "exnref value type used but exceptions feature disabled",
+            ),
+            (
+                "(module (memory 1) (memory 1))",
+                Features::default(),
+                "multiple memories used but multi_memory feature disabled",
+            ),
+        ];
+        for (wat, features, message) in cases {
+            assert_eq!(check(wat, features), Err(message.to_string()), "{wat}");
+            assert_eq!(check(wat, Features::all()), Ok(()), "{wat}");
+        }
+        assert_eq!(check("(module (func nop))", Features::none()), Ok(()));
+    }
+}
@@ -1,2 +1,8 @@
-mod ast;
+pub mod ast;
+pub mod binary;
+pub mod exec;
+pub mod features;
+pub mod valid;
 pub mod wat;
+
+pub use features::Features;
@@ -0,0 +1,180 @@
+use std::collections::HashSet;
+
+use crate::{
+    ast::{
+        ExportDesc, FuncType, GlobalType, ImportDesc, Instruction, MemType, Module, TableType,
+        ValueType,
+    },
+    features::Features,
+};
+
+use super::{error, Result};
+
+// 仕様
+// https://webassembly.github.io/spec/core/valid/conventions.html#contexts
+
+// 検証で参照する、モジュールの各要素の型
+// 関数、テーブル、メモリ、グローバル、タグは、インポートしたものから順に並べる
+// funcsとtagsは型のインデックスで持ち、参照するときに型を取り出す
+// ローカル変数、ラベル、戻り値の型は、関数を検証するfunc::Validatorが持つ
+// refsは関数の外で参照されている関数のインデックスで、ref.funcで参照できる
+// featuresは有効な提案で、無効な提案の型や命令はエラーになる
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct Context {
+    pub types: Vec<FuncType>,
+    pub funcs: Vec<u32>,
+    pub tables: Vec<TableType>,
+    pub mems: Vec<MemType>,
+    pub globals: Vec<GlobalType>,
+    pub tags: Vec<u32>,
+    pub elems: Vec<ValueType>,
+    pub datas: usize,
+    pub refs: HashSet<u32>,
+    pub features: Features,
+}
+
+impl Context {
+    pub fn new(module: &Module) -> Self {
+        let mut c = Self {
+            types: module.types.clone(),
+            funcs: Vec::new(),
+            tables: Vec::new(),
+            mems: Vec::new(),
+            globals: Vec::new(),
+            tags: Vec::new(),
+            elems: module.elems.iter().map(|e| e.e_type).collect(),
+            datas: module.datas.len(),
+            refs: HashSet::new(),
+            features: Features::default(),
+        };
+        for import in &module.imports {
+            match &import.desc {
+                ImportDesc::Func(t) => c.funcs.push(*t),
+                ImportDesc::Table(t) => c.tables.push(*t),
+                ImportDesc::Memory(m) => c.mems.push(*m),
+                ImportDesc::Global(g) => c.globals.push(*g),
+                ImportDesc::Tag(t) => c.tags.push(t.f_type),
+            }
+        }
+        c.funcs.extend(module.funcs.iter().map(|f| f.f_type));
+        c.tables.extend(&module.tables);
+        c.mems.extend(&module.mems);
+        c.globals.extend(module.globals.iter().map(|g| g.g_type));
+        c.tags.extend(module.tags.iter().map(|t| t.f_type));
+
+        // 関数本体と開始関数以外で参照されている関数を集める
+        // https://webassembly.github.io/spec/core/valid/modules.html#valid-module
+        let exprs = module
+            .globals
+            .iter()
+            .map(|g| &g.init)
+            .chain(module.elems.iter().flat_map(|e| &e.init));
+        for i in exprs.flatten() {
+            if let Instruction::RefFunc(f) = i {
+                c.refs.insert(*f);
+            }
+        }
+        for e in &module.exports {
+            if let ExportDesc::Func(f) = e.desc {
+                c.refs.insert(f);
+            }
+        }
+        c
+    }
+
+    pub fn type_at(&self, idx: u32) -> Result<&FuncType> {
+        match self.types.get(idx as usize) {
+            Some(t) => Ok(t),
+            None => error(format!("unknown type {idx}")),
+        }
+    }
+
+    // 関数の型を取得する
+    pub fn func(&self, idx: u32) -> Result<&FuncType> {
+        match self.funcs.get(idx as usize) {
+            Some(t) => self.type_at(*t),
+            None => error(format!("unknown function {idx}")),
+        }
+    }
+
+    pub fn table(&self, idx: u32) -> Result<&TableType> {
+        match self.tables.get(idx as usize) {
+            Some(t) => Ok(t),
+            None => error(format!("unknown table {idx}")),
+        }
+    }
+
+    pub fn mem(&self, idx: u32) -> Result<&MemType> {
+        match self.mems.get(idx as usize) {
+            Some(m) => Ok(m),
+            None => error(format!("unknown memory {idx}")),
+        }
+    }
+
+    pub fn global(&self, idx: u32) -> Result<&GlobalType> {
+        match self.globals.get(idx as usize) {
+            Some(g) => Ok(g),
+            None => error(format!("unknown global {idx}")),
+        }
+    }
+
+    // タグの型を取得する
+    pub fn tag(&self, idx: u32) -> Result<&FuncType> {
+        match self.tags.get(idx as usize) {
+            Some(t) => self.type_at(*t),
+            None => error(format!("unknown tag {idx}")),
+        }
+    }
+
+    // エレメントセグメントの参照型を取得する
+    pub fn elem(&self, idx: u32) -> Result<ValueType> {
+        match self.elems.get(idx as usize) {
+            Some(t) => Ok(*t),
+            None => error(format!("unknown elem segment {idx}")),
+        }
+    }
+
+    pub fn data(&self, idx: u32) -> Result<()> {
+        if idx as usize >= self.datas {
+            return error(format!("unknown data segment {idx}"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wat::module::module;
+
+    #[test]
+    fn context_new() {
+        let (_, m) = module(
+            "(module
+                (import \"env\" \"f\" (func (param i32)))
+                (import \"env\" \"g\" (global i64))
+                (global (mut i32) (i32.const 0))
+                (func $f)
+                (elem declare func $f)
+                (export \"f0\" (func 0)))",
+        )
+        .unwrap();
+        let c = Context::new(&m);
+        assert_eq!(c.funcs, vec![0, 1]);
+        assert_eq!(c.func(0), Ok(&(vec![ValueType::I32], vec![])));
+        assert_eq!(
+            c.globals.iter().map(|g| g.mutable).collect::<Vec<_>>(),
+            vec![false, true]
+        );
+        assert_eq!(c.elems, vec![ValueType::FuncRef]);
+        assert_eq!(c.refs, HashSet::from([0, 1]));
+        assert_eq!(
+            c.func(2).map_err(|e| e.message),
+            Err("unknown function 2".to_string())
+        );
+        assert_eq!(
+            c.data(0).map_err(|e| e.message),
+            Err("unknown data segment 0".to_string())
+        );
+    }
+}
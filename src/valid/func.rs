@@ -0,0 +1,914 @@
+use crate::ast::{
+    instr::{self, Opcode, Shape},
+    BlockType, Catch, Func, FuncType, Instruction as I, Instruction, StackType, ValueType,
+};
+
+use super::{context::Context, error, Result, ValidationError};
+
+// 仕様
+// https://webassembly.github.io/spec/core/valid/instructions.html
+// https://webassembly.github.io/spec/core/appendix/algorithm.html
+
+// オペランドスタックの値の型
+// unreachableやbrの後では、スタックから任意の型の値を取り出せる
+// そのような型の分からない値をNoneで表す
+type Operand = Option<ValueType>;
+
+// 制御フレームを作った命令の種類
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+enum Kind {
+    Func,
+    Block,
+    Loop,
+    If,
+    Else,
+    TryTable,
+}
+
+// 制御フレーム
+// heightはフレームに入ったときのオペランドスタックの高さ
+// unreachableはフレームの残りが到達不能で、スタックが多相になっていることを表す
+#[derive(Debug, PartialEq, Clone, Eq)]
+struct Frame {
+    kind: Kind,
+    params: StackType,
+    results: StackType,
+    height: usize,
+    unreachable: bool,
+}
+
+impl Frame {
+    // 分岐先としてのラベルの型
+    // loopへの分岐は先頭に戻るため、パラメータの型になる
+    fn label_types(&self) -> &StackType {
+        match self.kind {
+            Kind::Loop => &self.params,
+            _ => &self.results,
+        }
+    }
+}
+
+// 関数本体の命令列を先頭から1つずつ検証する
+// 関数全体を1つの制御フレームとし、本体の末尾のendはinstructionに渡さずfinishで閉じる
+pub struct Validator<'a> {
+    ctx: &'a Context,
+    locals: Vec<ValueType>,
+    results: StackType,
+    vals: Vec<Operand>,
+    ctrls: Vec<Frame>,
+}
+
+impl<'a> Validator<'a> {
+    // localsは引数を含むローカル変数の型、resultsは関数の戻り値の型
+    pub fn new(ctx: &'a Context, locals: Vec<ValueType>, results: StackType) -> Self {
+        let mut v = Self {
+            ctx,
+            locals,
+            results: results.clone(),
+            vals: Vec::new(),
+            ctrls: Vec::new(),
+        };
+        v.push_ctrl(Kind::Func, vec![], results);
+        v
+    }
+
+    fn push_val(&mut self, t: Operand) {
+        self.vals.push(t);
+    }
+
+    fn push_vals(&mut self, ts: &[ValueType]) {
+        self.vals.extend(ts.iter().map(|t| Some(*t)));
+    }
+
+    // 値を1つ取り出す
+    // 到達不能なフレームでは、フレームに入ったときより下の値の代わりに型の分からない値を返す
+    fn pop_val(&mut self) -> Result<Operand> {
+        let frame = self.ctrls.last().expect("function frame");
+        if self.vals.len() == frame.height {
+            if frame.unreachable {
+                return Ok(None);
+            }
+            return error("type mismatch");
+        }
+        Ok(self.vals.pop().flatten())
+    }
+
+    // 期待する型の値を1つ取り出す
+    fn pop_expect(&mut self, expect: ValueType) -> Result<Operand> {
+        match self.pop_val()? {
+            Some(actual) if actual != expect => error("type mismatch"),
+            _ => Ok(Some(expect)),
+        }
+    }
+
+    // 期待する型の値を末尾から取り出し、スタックに積まれていた順に返す
+    fn pop_vals(&mut self, ts: &[ValueType]) -> Result<Vec<Operand>> {
+        let mut popped = Vec::with_capacity(ts.len());
+        for t in ts.iter().rev() {
+            popped.push(self.pop_expect(*t)?);
+        }
+        popped.reverse();
+        Ok(popped)
+    }
+
+    // フレームに入り、パラメータの値をフレームの中に積む
+    fn push_ctrl(&mut self, kind: Kind, params: StackType, results: StackType) {
+        let height = self.vals.len();
+        self.push_vals(&params);
+        self.ctrls.push(Frame {
+            kind,
+            params,
+            results,
+            height,
+            unreachable: false,
+        });
+    }
+
+    // フレームを閉じる
+    // 戻り値の型の値がちょうど積まれている必要がある
+    fn pop_ctrl(&mut self) -> Result<Frame> {
+        let frame = self.ctrls.last().expect("function frame").clone();
+        self.pop_vals(&frame.results)?;
+        if self.vals.len() != frame.height {
+            return error("type mismatch");
+        }
+        self.ctrls.pop();
+        Ok(frame)
+    }
+
+    // 内側から数えてl番目のラベルのフレーム
+    fn label(&self, l: u32) -> Result<&Frame> {
+        match self.ctrls.iter().rev().nth(l as usize) {
+            Some(frame) => Ok(frame),
+            None => error(format!("unknown label {l}")),
+        }
+    }
+
+    fn label_types(&self, l: u32) -> Result<StackType> {
+        Ok(self.label(l)?.label_types().clone())
+    }
+
+    // フレームの残りを到達不能にし、スタックを多相にする
+    fn unreachable(&mut self) {
+        let frame = self.ctrls.last_mut().expect("function frame");
+        self.vals.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    fn local(&self, idx: usize) -> Result<ValueType> {
+        match self.locals.get(idx) {
+            Some(t) => Ok(*t),
+            None => error(format!("unknown local {idx}")),
+        }
+    }
+
+    // ブロックの型を、パラメータと戻り値の型に変換する
+    // https://webassembly.github.io/spec/core/valid/types.html#block-types
+    fn block_type(&self, bt: &BlockType) -> Result<FuncType> {
+        match bt {
+            BlockType::Empty => Ok((vec![], vec![])),
+            BlockType::Value(t) => Ok((vec![], vec![*t])),
+            BlockType::Type(idx) => self.ctx.type_at(*idx).cloned(),
+        }
+    }
+
+    // ブロックに入る
+    // パラメータの値を取り出し、新しいフレームの中に積み直す
+    fn enter(&mut self, kind: Kind, bt: &BlockType) -> Result<()> {
+        let (params, results) = self.block_type(bt)?;
+        self.pop_vals(&params)?;
+        self.push_ctrl(kind, params, results);
+        Ok(())
+    }
+
+    // 関数を呼び出す
+    fn call(&mut self, (params, results): &FuncType) -> Result<()> {
+        self.pop_vals(params)?;
+        self.push_vals(results);
+        Ok(())
+    }
+
+    // 末尾呼び出しをする
+    // 呼び出し先の戻り値が、そのまま関数の戻り値になる
+    // https://github.com/WebAssembly/tail-call/blob/main/proposals/tail-call/Overview.md
+    fn return_call(&mut self, (params, results): &FuncType) -> Result<()> {
+        if *results != self.results {
+            return error("type mismatch");
+        }
+        self.pop_vals(params)?;
+        self.unreachable();
+        Ok(())
+    }
+
+    // try_tableのcatch節を検証する
+    // 分岐先のラベルは、try_tableのフレームに入る前の制御スタックで数える
+    // https://webassembly.github.io/exception-handling/core/valid/instructions.html#control-instructions
+    fn catch(&self, catch: &Catch) -> Result<()> {
+        let (types, l) = match catch {
+            Catch::Catch(tag, l) => (self.ctx.tag(*tag)?.0.clone(), l),
+            Catch::CatchRef(tag, l) => {
+                let mut types = self.ctx.tag(*tag)?.0.clone();
+                types.push(ValueType::ExnRef);
+                (types, l)
+            }
+            Catch::CatchAll(l) => (vec![], l),
+            Catch::CatchAllRef(l) => (vec![ValueType::ExnRef], l),
+        };
+        if types != self.label_types(*l)? {
+            return error("type mismatch");
+        }
+        Ok(())
+    }
+
+    // テーブルの参照型を取得する
+    fn table(&self, idx: u32) -> Result<ValueType> {
+        Ok(self.ctx.table(idx)?.elem)
+    }
+
+    // 命令を1つ検証する
+    pub fn instruction(&mut self, i: &Instruction) -> Result<()> {
+        use ValueType::{ExnRef, F32, F64, I32, I64, V128};
+
+        self.ctx.features.instruction(i).or_else(error)?;
+        match i {
+            I::Unreachable => self.unreachable(),
+            I::Block(bt) => self.enter(Kind::Block, bt)?,
+            I::Loop(bt) => self.enter(Kind::Loop, bt)?,
+            I::If(bt) => {
+                self.pop_expect(I32)?;
+                self.enter(Kind::If, bt)?;
+            }
+            I::Else => {
+                let frame = self.pop_ctrl()?;
+                if frame.kind != Kind::If {
+                    return error("else without if");
+                }
+                self.push_ctrl(Kind::Else, frame.params, frame.results);
+            }
+            I::End => {
+                if self.ctrls.len() == 1 {
+                    return error("unexpected end");
+                }
+                let frame = self.pop_ctrl()?;
+                // elseのないifは、パラメータをそのまま戻り値として返す
+                if frame.kind == Kind::If && frame.params != frame.results {
+                    return error("type mismatch");
+                }
+                self.push_vals(&frame.results);
+            }
+            I::TryTable(bt, catches) => {
+                for c in catches {
+                    self.catch(c)?;
+                }
+                self.enter(Kind::TryTable, bt)?;
+            }
+            I::Br(l) => {
+                let types = self.label_types(*l)?;
+                self.pop_vals(&types)?;
+                self.unreachable();
+            }
+            I::BrIf(l) => {
+                self.pop_expect(I32)?;
+                let types = self.label_types(*l)?;
+                self.pop_vals(&types)?;
+                self.push_vals(&types);
+            }
+            I::BrTable(ls, default) => {
+                self.pop_expect(I32)?;
+                let arity = self.label_types(*default)?.len();
+                for l in ls {
+                    let types = self.label_types(*l)?;
+                    if types.len() != arity {
+                        return error("type mismatch");
+                    }
+                    let popped = self.pop_vals(&types)?;
+                    self.vals.extend(popped);
+                }
+                let types = self.label_types(*default)?;
+                self.pop_vals(&types)?;
+                self.unreachable();
+            }
+            I::Return => {
+                let results = self.results.clone();
+                self.pop_vals(&results)?;
+                self.unreachable();
+            }
+            I::Call(f) => self.call(&self.ctx.func(*f)?.clone())?,
+            I::CallIndirect(t, table) | I::ReturnCallIndirect(t, table) => {
+                if self.table(*table)? != ValueType::FuncRef {
+                    return error("type mismatch");
+                }
+                self.pop_expect(I32)?;
+                let ft = self.ctx.type_at(*t)?.clone();
+                match i {
+                    I::CallIndirect(..) => self.call(&ft)?,
+                    _ => self.return_call(&ft)?,
+                }
+            }
+            I::ReturnCall(f) => self.return_call(&self.ctx.func(*f)?.clone())?,
+            I::Throw(tag) => {
+                let params = self.ctx.tag(*tag)?.0.clone();
+                self.pop_vals(&params)?;
+                self.unreachable();
+            }
+            I::ThrowRef => {
+                self.pop_expect(ExnRef)?;
+                self.unreachable();
+            }
+            I::RefNull(t) => self.push_val(Some(*t)),
+            I::RefIsNull => {
+                if matches!(self.pop_val()?, Some(t) if !t.is_ref()) {
+                    return error("type mismatch");
+                }
+                self.push_val(Some(I32));
+            }
+            I::RefFunc(f) => {
+                self.ctx.func(*f)?;
+                if !self.ctx.refs.contains(f) {
+                    return error(format!("undeclared function reference {f}"));
+                }
+                self.push_val(Some(ValueType::FuncRef));
+            }
+            I::Drop => {
+                self.pop_val()?;
+            }
+            I::Select(None) => {
+                self.pop_expect(I32)?;
+                let t1 = self.pop_val()?;
+                let t2 = self.pop_val()?;
+                // 型を指定しないselectは、数値型とベクタ型の値しか選べない
+                if t1.is_some_and(|t| t.is_ref()) || t2.is_some_and(|t| t.is_ref()) {
+                    return error("type mismatch");
+                }
+                if t1.is_some() && t2.is_some() && t1 != t2 {
+                    return error("type mismatch");
+                }
+                self.push_val(t1.or(t2));
+            }
+            I::Select(Some(t)) => {
+                self.pop_expect(I32)?;
+                self.pop_expect(*t)?;
+                self.pop_expect(*t)?;
+                self.push_val(Some(*t));
+            }
+            I::LocalGet(x) => {
+                let t = self.local(*x)?;
+                self.push_val(Some(t));
+            }
+            I::LocalSet(x) => {
+                let t = self.local(*x)?;
+                self.pop_expect(t)?;
+            }
+            I::LocalTee(x) => {
+                let t = self.local(*x)?;
+                self.pop_expect(t)?;
+                self.push_val(Some(t));
+            }
+            I::GlobalGet(x) => {
+                let g = self.ctx.global(*x)?;
+                self.push_val(Some(g.value_type));
+            }
+            I::GlobalSet(x) => {
+                let g = *self.ctx.global(*x)?;
+                if !g.mutable {
+                    return error("global is immutable");
+                }
+                self.pop_expect(g.value_type)?;
+            }
+            I::TableGet(x) => {
+                let t = self.table(*x)?;
+                self.pop_expect(I32)?;
+                self.push_val(Some(t));
+            }
+            I::TableSet(x) => {
+                let t = self.table(*x)?;
+                self.pop_expect(t)?;
+                self.pop_expect(I32)?;
+            }
+            I::TableSize(x) => {
+                self.table(*x)?;
+                self.push_val(Some(I32));
+            }
+            I::TableGrow(x) => {
+                let t = self.table(*x)?;
+                self.pop_expect(I32)?;
+                self.pop_expect(t)?;
+                self.push_val(Some(I32));
+            }
+            I::TableFill(x) => {
+                let t = self.table(*x)?;
+                self.pop_vals(&[I32, t, I32])?;
+            }
+            I::TableCopy(dst, src) => {
+                if self.table(*dst)? != self.table(*src)? {
+                    return error("type mismatch");
+                }
+                self.pop_vals(&[I32, I32, I32])?;
+            }
+            I::TableInit(elem, table) => {
+                if self.ctx.elem(*elem)? != self.table(*table)? {
+                    return error("type mismatch");
+                }
+                self.pop_vals(&[I32, I32, I32])?;
+            }
+            I::ElemDrop(elem) => {
+                self.ctx.elem(*elem)?;
+            }
+            I::MemorySize(m) => {
+                self.ctx.mem(*m)?;
+                self.push_val(Some(I32));
+            }
+            I::MemoryGrow(m) => {
+                self.ctx.mem(*m)?;
+                self.pop_expect(I32)?;
+                self.push_val(Some(I32));
+            }
+            I::MemoryFill(m) => {
+                self.ctx.mem(*m)?;
+                self.pop_vals(&[I32, I32, I32])?;
+            }
+            I::MemoryCopy(dst, src) => {
+                self.ctx.mem(*dst)?;
+                self.ctx.mem(*src)?;
+                self.pop_vals(&[I32, I32, I32])?;
+            }
+            I::MemoryInit(data, m) => {
+                self.ctx.mem(*m)?;
+                self.ctx.data(*data)?;
+                self.pop_vals(&[I32, I32, I32])?;
+            }
+            I::DataDrop(data) => self.ctx.data(*data)?,
+            I::I32Const(_) => self.push_val(Some(I32)),
+            I::I64Const(_) => self.push_val(Some(I64)),
+            I::F32Const(_) => self.push_val(Some(F32)),
+            I::F64Const(_) => self.push_val(Some(F64)),
+            I::V128Const(_) => self.push_val(Some(V128)),
+            I::I8x16Shuffle(lanes) => {
+                if lanes.iter().any(|l| *l >= 32) {
+                    return error("invalid lane index");
+                }
+                self.pop_vals(&[V128, V128])?;
+                self.push_val(Some(V128));
+            }
+            _ => self.table_instruction(i)?,
+        }
+        Ok(())
+    }
+
+    // 型が固定の命令を、命令表の型と即値の形を使って検証する
+    fn table_instruction(&mut self, i: &Instruction) -> Result<()> {
+        let Some(entry) = instr::find(i) else {
+            return error("unknown instruction");
+        };
+        let natural = match entry.shape {
+            Shape::Memory(_, align) | Shape::MemoryLane(_, align, _) => Some(align),
+            _ => None,
+        };
+        if let (Some(natural), Some(m)) = (natural, i.memarg()) {
+            self.ctx.mem(0)?;
+            // アトミック命令は、自然なアラインメントちょうどでなければならない
+            // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md
+            let atomic = matches!(entry.opcode, Opcode::Prefixed(0xfe, _));
+            if atomic && m.align != natural {
+                return error("atomic alignment must be natural");
+            }
+            if m.align > natural {
+                return error("alignment must not be larger than natural");
+            }
+        }
+        let lanes = match entry.shape {
+            Shape::Lane(_, lanes) | Shape::MemoryLane(_, _, lanes) => Some(lanes),
+            _ => None,
+        };
+        if let (Some(lanes), Some(lane)) = (lanes, i.lane()) {
+            if lane >= lanes {
+                return error("invalid lane index");
+            }
+        }
+        let Some((params, results)) = entry.ty else {
+            return error("unknown instruction");
+        };
+        self.pop_vals(params)?;
+        self.push_vals(results);
+        Ok(())
+    }
+
+    // 命令列の終わりで関数のフレームを閉じる
+    pub fn finish(mut self) -> Result<()> {
+        if self.ctrls.len() != 1 {
+            return error("unclosed block");
+        }
+        self.pop_ctrl()?;
+        Ok(())
+    }
+}
+
+// 関数を検証する
+// 無効な提案のローカル変数の型と命令もエラーにする
+// エラーには、見つかった命令の位置を付ける
+// 本体の末尾で見つかったエラーは、本体の長さを位置とする
+// https://webassembly.github.io/spec/core/valid/modules.html#functions
+pub fn validate_func(ctx: &Context, f: &Func) -> Result<()> {
+    let (params, results) = ctx.type_at(f.f_type)?.clone();
+    for t in &f.locals {
+        ctx.features.value_type(*t).or_else(error)?;
+    }
+    let locals = [params, f.locals.clone()].concat();
+    let mut v = Validator::new(ctx, locals, results);
+    let at = |pos: usize| {
+        move |e: ValidationError| ValidationError {
+            instr: Some(pos),
+            ..e
+        }
+    };
+    for (pos, i) in f.body.iter().enumerate() {
+        v.instruction(i).map_err(at(pos))?;
+    }
+    v.finish().map_err(at(f.body.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wat::module::module;
+
+    // 最後の関数を検証し、エラーのメッセージを返す
+    fn check(wat: &str) -> std::result::Result<(), String> {
+        let (_, m) = module(wat).unwrap();
+        let c = Context::new(&m);
+        validate_func(&c, m.funcs.last().unwrap()).map_err(|e| e.message)
+    }
+
+    // 1つの関数だけを持つモジュールで検証する
+    fn check_func(func: &str) -> std::result::Result<(), String> {
+        check(&format!("(module (memory 1) (table 1 funcref) {func})"))
+    }
+
+    #[test]
+    fn operand_types() {
+        assert_eq!(
+            check_func("(func (param i32 i32) (result i32) local.get 0 local.get 1 i32.add)"),
+            Ok(())
+        );
+        assert_eq!(
+            check_func("(func (param f64 f64) (result i32) local.get 0 local.get 1 i32.add)"),
+            Err("type mismatch".to_string())
+        );
+        // 値が足りない、または余る
+        assert_eq!(
+            check_func("(func (result i32) i32.const 1 i32.add)"),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check_func("(func (result i32) i32.const 1 i32.const 2)"),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check_func("(func (result i64) (i64.extend_i32_u (i32.wrap_i64 (i64.const 1))))"),
+            Ok(())
+        );
+        assert_eq!(
+            check_func("(func (local f32) (local.set 0 (f64.const 1)))"),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check_func("(func local.get 0 drop)"),
+            Err("unknown local 0".to_string())
+        );
+    }
+
+    #[test]
+    fn polymorphic_stack() {
+        // unreachableやbrの後は、どの型の値でも取り出せる
+        assert_eq!(
+            check_func("(func (result i32) unreachable i32.add)"),
+            Ok(())
+        );
+        assert_eq!(
+            check_func("(func (result i32) (block (result i32) (br 0 (i32.const 1)) f32.add drop i32.const 0))"),
+            Ok(())
+        );
+        assert_eq!(check_func("(func (result f64) unreachable return)"), Ok(()));
+        // 到達不能でも、積んだ値の型は検査する
+        assert_eq!(
+            check_func("(func (result i32) unreachable f32.const 0 i32.add)"),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(check_func("(func unreachable select drop)"), Ok(()));
+        // フレームを抜けると多相ではなくなる
+        assert_eq!(
+            check_func("(func (block unreachable) i32.add drop)"),
+            Err("type mismatch".to_string())
+        );
+    }
+
+    #[test]
+    fn control_instructions() {
+        assert_eq!(
+            check_func(
+                "(func (param i32) (result i32)
+                  (if (result i32) (local.get 0) (then (i32.const 1)) (else (i32.const 2))))"
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check_func(
+                "(func (param i32) (result i32)
+                  (if (result i32) (local.get 0) (then (i32.const 1))))"
+            ),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check_func("(func (if (f32.const 0) (then)))"),
+            Err("type mismatch".to_string())
+        );
+        // loopへの分岐は、パラメータの型の値を渡す
+        assert_eq!(
+            check(
+                "(module (type (func (param i32) (result i64)))
+                  (func (result i64) (i32.const 0) (loop (type 0) (br_if 0 (i32.const 1)) drop (i64.const 1))))"
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check_func(
+                "(func (result i32) (block (result i32) (br_if 0 (i32.const 1) (i32.const 0))))"
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check_func("(func (block (br 1)) (br 2))"),
+            Err("unknown label 2".to_string())
+        );
+        assert_eq!(
+            check_func(
+                "(func (param i32) (result i32)
+                  (block (result i32) (block (result f32) (br_table 0 1 (f32.const 0) (local.get 0))) drop i32.const 0))"
+            ),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check_func(
+                "(func (param i32) (result i32)
+                  (block (result i32) (br_table 0 1 (i32.const 7) (local.get 0))))"
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check_func("(func (result i32) (return (i64.const 0)))"),
+            Err("type mismatch".to_string())
+        );
+    }
+
+    #[test]
+    fn flat_blocks() {
+        let func = |body: Vec<Instruction>| {
+            let c = Context::new(&Default::default());
+            let f = Func {
+                body,
+                ..Default::default()
+            };
+            validate_func(
+                &Context {
+                    types: vec![(vec![], vec![])],
+                    ..c
+                },
+                &f,
+            )
+        };
+        assert_eq!(func(vec![I::Block(BlockType::Empty), I::End]), Ok(()));
+        assert_eq!(
+            func(vec![I::Block(BlockType::Empty)]),
+            Err(ValidationError {
+                func: None,
+                instr: Some(1),
+                message: "unclosed block".to_string()
+            })
+        );
+        assert_eq!(
+            func(vec![I::Nop, I::End]).map_err(|e| (e.instr, e.message)),
+            Err((Some(1), "unexpected end".to_string()))
+        );
+        assert_eq!(
+            func(vec![I::Block(BlockType::Empty), I::Else, I::End]).map_err(|e| e.message),
+            Err("else without if".to_string())
+        );
+    }
+
+    #[test]
+    fn calls() {
+        let module = |body: &str| {
+            format!(
+                "(module
+                  (type $t (func (param i32) (result i32)))
+                  (type $u (func (result i64)))
+                  (table 1 funcref)
+                  (table 1 externref)
+                  (func $f (type $t) local.get 0)
+                  (func (result i32) {body}))"
+            )
+        };
+        assert_eq!(check(&module("(call $f (i32.const 1))")), Ok(()));
+        assert_eq!(
+            check(&module("(call $f (i64.const 1))")),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check(&module("(call 5)")),
+            Err("unknown function 5".to_string())
+        );
+        assert_eq!(
+            check(&module(
+                "(call_indirect (type $t) (i32.const 1) (i32.const 0))"
+            )),
+            Ok(())
+        );
+        assert_eq!(
+            check(&module(
+                "(call_indirect 1 (type $t) (i32.const 1) (i32.const 0))"
+            )),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check(&module("(return_call $f (i32.const 1)) i64.add drop")),
+            Ok(())
+        );
+        assert_eq!(
+            check(&module("(return_call_indirect (type $u) (i32.const 0))")),
+            Err("type mismatch".to_string())
+        );
+    }
+
+    #[test]
+    fn exceptions() {
+        let module = |body: &str| {
+            format!(
+                "(module
+                  (tag $e (param i32))
+                  (func (result i32) {body}))"
+            )
+        };
+        assert_eq!(
+            check(&module(
+                "(block $h (result i32) (try_table (result i32) (catch $e $h) (throw $e (i32.const 1))))"
+            )),
+            Ok(())
+        );
+        assert_eq!(
+            check(&module(
+                "(block $h (result i64) (try_table (catch $e $h) (throw $e (i32.const 1)))) drop i32.const 0"
+            )),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check(&module(
+                "(block $h (result exnref) (try_table (catch_all_ref $h) (nop)) unreachable) throw_ref"
+            )),
+            Ok(())
+        );
+        assert_eq!(
+            check(&module("(throw 3)")),
+            Err("unknown tag 3".to_string())
+        );
+    }
+
+    #[test]
+    fn variables_and_references() {
+        let module = |body: &str| {
+            format!(
+                "(module
+                  (global $c i32 (i32.const 0))
+                  (global $m (mut i64) (i64.const 0))
+                  (table $t 1 funcref)
+                  (func $f)
+                  (func $g)
+                  (elem declare func $f)
+                  (func {body}))"
+            )
+        };
+        assert_eq!(check(&module("(global.set $m (i64.const 1))")), Ok(()));
+        assert_eq!(
+            check(&module("(global.set $c (i32.const 1))")),
+            Err("global is immutable".to_string())
+        );
+        assert_eq!(
+            check(&module("(table.set $t (i32.const 0) (ref.func $f))")),
+            Ok(())
+        );
+        assert_eq!(
+            check(&module("(drop (ref.func $g))")),
+            Err("undeclared function reference 1".to_string())
+        );
+        assert_eq!(
+            check(&module("(drop (ref.is_null (ref.null extern)))")),
+            Ok(())
+        );
+        assert_eq!(
+            check(&module("(drop (ref.is_null (i32.const 0)))")),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check(&module(
+                "(drop (select (ref.null func) (ref.null func) (i32.const 0)))"
+            )),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check(&module(
+                "(drop (select (result funcref) (ref.null func) (ref.null func) (i32.const 0)))"
+            )),
+            Ok(())
+        );
+        assert_eq!(
+            check(&module(
+                "(table.fill $t (i32.const 0) (ref.null extern) (i32.const 1))"
+            )),
+            Err("type mismatch".to_string())
+        );
+    }
+
+    #[test]
+    fn memory_instructions() {
+        assert_eq!(
+            check("(module (func (drop (i32.load (i32.const 0)))))"),
+            Err("unknown memory 0".to_string())
+        );
+        assert_eq!(
+            check_func("(func (i64.store offset=8 align=8 (i32.const 0) (i64.const 1)))"),
+            Ok(())
+        );
+        assert_eq!(
+            check_func("(func (i32.store align=8 (i32.const 0) (i32.const 1)))"),
+            Err("alignment must not be larger than natural".to_string())
+        );
+        assert_eq!(
+            check_func("(func (drop (i32.atomic.load align=4 (i32.const 0))))"),
+            Ok(())
+        );
+        assert_eq!(
+            check_func("(func (drop (i32.atomic.load align=2 (i32.const 0))))"),
+            Err("atomic alignment must be natural".to_string())
+        );
+        assert_eq!(
+            check_func("(func (drop (memory.grow (i32.const 1))))"),
+            Ok(())
+        );
+        assert_eq!(
+            check_func("(func (memory.fill (i32.const 0) (i32.const 0) (i64.const 0)))"),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check_func("(func (data.drop 0))"),
+            Err("unknown data segment 0".to_string())
+        );
+    }
+
+    #[test]
+    fn vector_instructions() {
+        // 命令列を、v128を返す関数の本体として検証する
+        let body = |instrs: &[Instruction]| {
+            let c = Context::new(&Default::default());
+            let mut v = Validator::new(&c, vec![], vec![ValueType::V128]);
+            instrs
+                .iter()
+                .try_for_each(|i| v.instruction(i))
+                .and_then(|_| v.finish())
+                .map_err(|e| e.message)
+        };
+        let lanes = |lane: u8| {
+            let mut lanes = [0; 16];
+            lanes[15] = lane;
+            lanes
+        };
+        assert_eq!(
+            body(&[I::V128Const(0), I::V128Const(0), I::I8x16Shuffle(lanes(31))]),
+            Ok(())
+        );
+        assert_eq!(
+            body(&[I::V128Const(0), I::V128Const(0), I::I8x16Shuffle(lanes(32))]),
+            Err("invalid lane index".to_string())
+        );
+        assert_eq!(
+            body(&[I::V128Const(0), I::I32x4ExtractLane(3), I::I32x4Splat]),
+            Ok(())
+        );
+        assert_eq!(
+            body(&[I::V128Const(0), I::I32x4ExtractLane(4), I::I32x4Splat]),
+            Err("invalid lane index".to_string())
+        );
+        assert_eq!(
+            body(&[I::V128Const(0), I::I32Const(1), I::I8x16Shl]),
+            Ok(())
+        );
+        assert_eq!(
+            body(&[I::V128Const(0), I::V128Const(0), I::I8x16Shl]),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check_func(
+                "(func (result v128) (v128.load8_lane 15 (i32.const 0) (v128.const i64x2 0 0)))"
+            ),
+            Ok(())
+        );
+    }
+}
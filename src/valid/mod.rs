@@ -0,0 +1,158 @@
+use std::fmt;
+
+use crate::{ast::Module, features::Features};
+
+pub mod context;
+pub mod func;
+pub mod module;
+
+pub use context::Context;
+
+// 仕様
+// https://webassembly.github.io/spec/core/valid/index.html
+
+// 検証のエラー
+// funcはエラーが見つかった関数のインデックス、instrはその関数本体での命令の位置
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct ValidationError {
+    pub func: Option<u32>,
+    pub instr: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(func) = self.func {
+            write!(f, " (in func {func}")?;
+            if let Some(instr) = self.instr {
+                write!(f, " at instruction {instr}")?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+pub type Result<T> = std::result::Result<T, ValidationError>;
+
+// 位置を持たないエラーを作成する
+// 位置は、呼び出し元で関数や命令を検証するときに付け加える
+fn error<T>(message: impl Into<String>) -> Result<T> {
+    Err(ValidationError {
+        func: None,
+        instr: None,
+        message: message.into(),
+    })
+}
+
+// モジュールを、既定の提案を有効にして検証する
+pub fn validate(module: &Module) -> Result<()> {
+    validate_with_features(module, &Features::default())
+}
+
+// モジュールを検証する
+// モジュールの構造を調べた後、関数本体の命令列をオペランドスタックと制御スタックを使って型検査する
+// https://webassembly.github.io/spec/core/valid/modules.html#valid-module
+pub fn validate_with_features(module: &Module, features: &Features) -> Result<()> {
+    let c = Context {
+        features: *features,
+        ..Context::new(module)
+    };
+    module::validate_module(&c, module)?;
+    let imported = c.funcs.len() - module.funcs.len();
+    for (i, f) in module.funcs.iter().enumerate() {
+        func::validate_func(&c, f).map_err(|e| ValidationError {
+            func: Some((imported + i) as u32),
+            ..e
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{binary::decode, wat::module::module};
+
+    fn validate_wat(wat: &str) -> Result<()> {
+        let (_, m) = module(wat).unwrap();
+        validate(&m)
+    }
+
+    #[test]
+    fn test_wat_validate() {
+        assert_eq!(validate_wat(include_str!("../../test.wat")), Ok(()));
+        let m = decode(include_bytes!("../../test.wasm")).unwrap();
+        assert_eq!(validate(&m), Ok(()));
+    }
+
+    #[test]
+    fn error_location() {
+        let err = validate_wat(
+            "(module
+                (func)
+                (func (param f64 f64) (result i32)
+                  local.get 0
+                  local.get 1
+                  i32.add))",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ValidationError {
+                func: Some(1),
+                instr: Some(2),
+                message: "type mismatch".to_string()
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "type mismatch (in func 1 at instruction 2)"
+        );
+    }
+
+    #[test]
+    fn imported_func_index() {
+        let err = validate_wat(
+            "(module
+                (import \"env\" \"f\" (func))
+                (func (result i32)))",
+        )
+        .unwrap_err();
+        assert_eq!(err.func, Some(1));
+        assert_eq!(err.instr, Some(0));
+    }
+
+    #[test]
+    fn disabled_features() {
+        let (_, m) = module(
+            "(module
+                (func (local v128))
+                (func (param i32) (result i32)
+                  local.get 0
+                  return_call 1))",
+        )
+        .unwrap();
+        let err = validate_with_features(
+            &m,
+            &Features {
+                tail_call: false,
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "tail-call instruction used but tail_call feature disabled (in func 1 at instruction 1)"
+        );
+        let err = validate_with_features(&m, &Features::none()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "v128 value type used but simd feature disabled (in func 0)"
+        );
+        assert_eq!(validate(&m), Ok(()));
+    }
+}
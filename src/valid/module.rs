@@ -0,0 +1,381 @@
+use std::collections::HashSet;
+
+use crate::ast::{
+    DataMode, ElemMode, ExportDesc, Expr, ImportDesc, Instruction as I, Limits, MemType, Module,
+    TableType, ValueType,
+};
+
+use super::{context::Context, error, func::Validator, Result, ValidationError};
+
+// 仕様
+// https://webassembly.github.io/spec/core/valid/modules.html
+
+// メモリの最大のページ数 (64KiBのページが65536個で4GiB)
+// https://webassembly.github.io/spec/core/valid/types.html#memory-types
+pub const MAX_PAGES: u32 = 65536;
+
+// 関数本体以外のモジュールの構造を検証する
+// 無効な提案の機能、インデックスの範囲、型の制限、定数式、エクスポート名の重複、開始関数の型を調べる
+pub fn validate_module(c: &Context, module: &Module) -> Result<()> {
+    c.features.fields(module).or_else(error)?;
+    for import in &module.imports {
+        match &import.desc {
+            ImportDesc::Func(t) => {
+                c.type_at(*t)?;
+            }
+            ImportDesc::Table(t) => table_type(t)?,
+            ImportDesc::Memory(m) => mem_type(m)?,
+            ImportDesc::Global(_) => (),
+            ImportDesc::Tag(t) => tag_type(c, t.f_type)?,
+        }
+    }
+
+    let imported = c.funcs.len() - module.funcs.len();
+    for (i, f) in module.funcs.iter().enumerate() {
+        c.type_at(f.f_type).map_err(|e| ValidationError {
+            func: Some((imported + i) as u32),
+            ..e
+        })?;
+    }
+    for t in &module.tables {
+        table_type(t)?;
+    }
+    for m in &module.mems {
+        mem_type(m)?;
+    }
+    for t in &module.tags {
+        tag_type(c, t.f_type)?;
+    }
+
+    // グローバルの初期値は、それより前に定義された不変のグローバルを参照できる
+    let imported = c.globals.len() - module.globals.len();
+    for (i, g) in module.globals.iter().enumerate() {
+        const_expr(c, &g.init, g.g_type.value_type, imported + i)?;
+    }
+
+    for e in &module.elems {
+        for init in &e.init {
+            const_expr(c, init, e.e_type, c.globals.len())?;
+        }
+        if let ElemMode::Active { table, offset } = &e.mode {
+            if c.table(*table)?.elem != e.e_type {
+                return error("type mismatch");
+            }
+            const_expr(c, offset, ValueType::I32, c.globals.len())?;
+        }
+    }
+    for d in &module.datas {
+        if let DataMode::Active { memory, offset } = &d.mode {
+            c.mem(*memory)?;
+            const_expr(c, offset, ValueType::I32, c.globals.len())?;
+        }
+    }
+
+    if let Some(start) = module.start {
+        if *c.func(start)? != (vec![], vec![]) {
+            return error("start function must have type [] -> []");
+        }
+    }
+
+    let mut names = HashSet::new();
+    for e in &module.exports {
+        match e.desc {
+            ExportDesc::Func(x) => {
+                c.func(x)?;
+            }
+            ExportDesc::Table(x) => {
+                c.table(x)?;
+            }
+            ExportDesc::Memory(x) => {
+                c.mem(x)?;
+            }
+            ExportDesc::Global(x) => {
+                c.global(x)?;
+            }
+            ExportDesc::Tag(x) => {
+                c.tag(x)?;
+            }
+        }
+        if !names.insert(&e.name) {
+            return error(format!("duplicate export name \"{}\"", e.name));
+        }
+    }
+    Ok(())
+}
+
+// https://webassembly.github.io/spec/core/valid/types.html#limits
+fn limits(limits: &Limits, max: u32, message: &str) -> Result<()> {
+    if limits.min > max || limits.max.is_some_and(|m| m > max) {
+        return error(message);
+    }
+    if limits.max.is_some_and(|m| limits.min > m) {
+        return error("size minimum must not be greater than maximum");
+    }
+    Ok(())
+}
+
+// https://webassembly.github.io/spec/core/valid/types.html#table-types
+fn table_type(t: &TableType) -> Result<()> {
+    limits(&t.limits, u32::MAX, "table size must be at most 2^32-1")
+}
+
+// 共有メモリは最大のページ数を持つ必要がある
+// https://webassembly.github.io/spec/core/valid/types.html#memory-types
+// https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md
+fn mem_type(m: &MemType) -> Result<()> {
+    limits(
+        &m.limits,
+        MAX_PAGES,
+        "memory size must be at most 65536 pages (4GiB)",
+    )?;
+    if m.shared && m.limits.max.is_none() {
+        return error("shared memory must have maximum");
+    }
+    Ok(())
+}
+
+// タグの型は戻り値を持たない
+// https://webassembly.github.io/exception-handling/core/valid/types.html#tag-types
+fn tag_type(c: &Context, t: u32) -> Result<()> {
+    if !c.type_at(t)?.1.is_empty() {
+        return error("non-empty tag result type");
+    }
+    Ok(())
+}
+
+// 定数式を検証する
+// 定数、ref.null、ref.funcと、不変のグローバルのglobal.getだけを使うことができる
+// globalsは参照できるグローバルの数
+// https://webassembly.github.io/spec/core/valid/instructions.html#constant-expressions
+fn const_expr(c: &Context, expr: &Expr, expect: ValueType, globals: usize) -> Result<()> {
+    for i in expr {
+        match i {
+            I::I32Const(_)
+            | I::I64Const(_)
+            | I::F32Const(_)
+            | I::F64Const(_)
+            | I::V128Const(_)
+            | I::RefNull(_)
+            | I::RefFunc(_) => (),
+            I::GlobalGet(x) => {
+                if *x as usize >= globals {
+                    return error(format!("unknown global {x}"));
+                }
+                if c.global(*x)?.mutable {
+                    return error("constant expression required");
+                }
+            }
+            _ => return error("constant expression required"),
+        }
+    }
+    let mut v = Validator::new(c, vec![], vec![expect]);
+    for i in expr {
+        v.instruction(i)?;
+    }
+    v.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{Global, GlobalType, Mem},
+        features::Features,
+        wat::module::module,
+    };
+
+    fn check(wat: &str) -> std::result::Result<(), String> {
+        check_with(wat, &Features::default())
+    }
+
+    fn check_with(wat: &str, features: &Features) -> std::result::Result<(), String> {
+        let (_, m) = module(wat).unwrap();
+        let c = Context {
+            features: *features,
+            ..Context::new(&m)
+        };
+        validate_module(&c, &m).map_err(|e| e.message)
+    }
+
+    #[test]
+    fn indices() {
+        let m = Module {
+            funcs: vec![Default::default()],
+            ..Default::default()
+        };
+        assert_eq!(
+            validate_module(&Context::new(&m), &m),
+            Err(ValidationError {
+                func: Some(0),
+                instr: None,
+                message: "unknown type 0".to_string()
+            })
+        );
+        assert_eq!(
+            check("(module (func) (export \"f\" (func 1)))"),
+            Err("unknown function 1".to_string())
+        );
+        assert_eq!(
+            check("(module (export \"m\" (memory 0)))"),
+            Err("unknown memory 0".to_string())
+        );
+        assert_eq!(
+            check("(module (data (memory 1) (i32.const 0) \"\") (memory 1))"),
+            Err("unknown memory 1".to_string())
+        );
+        assert_eq!(
+            check("(module (elem (i32.const 0) func))"),
+            Err("unknown table 0".to_string())
+        );
+    }
+
+    #[test]
+    fn exports() {
+        assert_eq!(
+            check(
+                "(module (func) (global i32 (i32.const 0))
+                  (export \"a\" (func 0)) (export \"b\" (global 0)))"
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check("(module (func) (export \"a\" (func 0)) (export \"a\" (func 0)))"),
+            Err("duplicate export name \"a\"".to_string())
+        );
+    }
+
+    #[test]
+    fn memory_limits() {
+        assert_eq!(check("(module (memory 1 65536))"), Ok(()));
+        assert_eq!(
+            check("(module (memory 65537))"),
+            Err("memory size must be at most 65536 pages (4GiB)".to_string())
+        );
+        assert_eq!(
+            check("(module (memory 0 65537))"),
+            Err("memory size must be at most 65536 pages (4GiB)".to_string())
+        );
+        assert_eq!(
+            check("(module (memory 2 1))"),
+            Err("size minimum must not be greater than maximum".to_string())
+        );
+        assert_eq!(
+            check("(module (table 2 1 funcref))"),
+            Err("size minimum must not be greater than maximum".to_string())
+        );
+        let shared = Module {
+            mems: vec![Mem {
+                limits: Limits { min: 1, max: None },
+                shared: true,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            validate_module(&Context::new(&shared), &shared).map_err(|e| e.message),
+            Err("shared memory must have maximum".to_string())
+        );
+    }
+
+    #[test]
+    fn multiple_memories() {
+        let wat = "(module (import \"env\" \"m\" (memory 1)) (memory 1))";
+        assert_eq!(
+            check(wat),
+            Err("multiple memories used but multi_memory feature disabled".to_string())
+        );
+        let features = Features {
+            multi_memory: true,
+            ..Default::default()
+        };
+        assert_eq!(check_with(wat, &features), Ok(()));
+    }
+
+    #[test]
+    fn constant_expressions() {
+        let wat = |fields: &str| {
+            format!(
+                "(module
+                  (import \"env\" \"c\" (global $c i32))
+                  (import \"env\" \"m\" (global $m (mut i32)))
+                  (table 1 funcref)
+                  (memory 1)
+                  (func $f)
+                  {fields})"
+            )
+        };
+        assert_eq!(
+            check(&wat("(global $g i32 (global.get $c))
+                 (global funcref (ref.func $f))
+                 (global i32 (global.get $g))
+                 (elem (offset (global.get $c)) funcref (ref.func $f) (ref.null func))
+                 (data (i32.const 0) \"\")")),
+            Ok(())
+        );
+        assert_eq!(
+            check(&wat("(global i32 (global.get $m))")),
+            Err("constant expression required".to_string())
+        );
+        assert_eq!(
+            check(&wat("(global i32 (i32.add (i32.const 1) (i32.const 2)))")),
+            Err("constant expression required".to_string())
+        );
+        assert_eq!(
+            check(&wat("(global i64 (i32.const 0))")),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check(&wat("(global i32)")),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check(&wat("(data (i64.const 0) \"\")")),
+            Err("type mismatch".to_string())
+        );
+        assert_eq!(
+            check(&wat("(elem (i32.const 0) externref (ref.null extern))")),
+            Err("type mismatch".to_string())
+        );
+
+        // 後で定義されるグローバルは参照できない
+        let m = Module {
+            globals: vec![
+                Global {
+                    g_type: GlobalType {
+                        value_type: ValueType::I32,
+                        mutable: false,
+                    },
+                    init: vec![I::GlobalGet(1)],
+                },
+                Global {
+                    g_type: GlobalType {
+                        value_type: ValueType::I32,
+                        mutable: false,
+                    },
+                    init: vec![I::I32Const(0)],
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            validate_module(&Context::new(&m), &m).map_err(|e| e.message),
+            Err("unknown global 1".to_string())
+        );
+    }
+
+    #[test]
+    fn start_and_tags() {
+        assert_eq!(check("(module (func $s) (start $s))"), Ok(()));
+        assert_eq!(
+            check("(module (func $s (param i32)) (start $s))"),
+            Err("start function must have type [] -> []".to_string())
+        );
+        assert_eq!(
+            check("(module (start 0))"),
+            Err("unknown function 0".to_string())
+        );
+        assert_eq!(
+            check("(module (type (func (result i32))) (tag (type 0)))"),
+            Err("non-empty tag result type".to_string())
+        );
+    }
+}
@@ -1,19 +1,39 @@
 use super::types::Index;
-use crate::ast::{Export, Func, FuncType, Type};
+use crate::ast::{
+    Custom, Data, Elem, Export, Func, FuncType, Global, Import, ImportDesc, Mem, NameMap, Names,
+    Table, Tag, Type,
+};
 
 // 仕様
 // https://webassembly.github.io/spec/core/valid/conventions.html?highlight=context#contexts
 
-// パースしたローカル変数、型、関数、エクスポートを保持する
+// パースしたローカル変数、ラベル、モジュールの各フィールドを保持する
+// ラベルは内側のブロックほど後ろに積まれる
+// blocksとlabel_namesは関数の中のブロックの数とラベルの名前で、関数ごとにリセットする
+// namesは関数ごとのローカル変数とラベルの名前を集める
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Context {
     pub locals: Vec<Option<String>>,
+    pub labels: Vec<Option<String>>,
+    pub blocks: u32,
+    pub label_names: NameMap,
+    pub names: Names,
     pub types: Field<Type>,
     pub funcs: Field<Func>,
+    pub tables: Field<Table>,
+    pub mems: Field<Mem>,
+    pub globals: Field<Global>,
+    pub tags: Field<Tag>,
+    pub elems: Field<Elem>,
+    pub datas: Field<Data>,
+    pub start: Option<u32>,
+    pub imports: Vec<Import>,
     pub exports: Field<Export>,
+    pub customs: Vec<Custom>,
 }
 
 // 識別子と型と関数のペアを保持する
+// インポートがあるフィールドでは、idsはインポートしたものを含むインデックス空間全体の識別子を持つ
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Field<T> {
     pub ids: Vec<Option<String>>,
@@ -43,58 +63,148 @@ impl<T> Field<T> {
     fn add_id(&mut self, id: Option<String>) {
         self.ids.push(id);
     }
+
+    // idx番目の識別子を宣言する
+    // モジュールの解析前に宣言済みの場合は何もしない
+    fn declare(&mut self, idx: usize, id: Option<String>) {
+        if self.ids.len() == idx {
+            self.add_id(id);
+        }
+    }
+
+    // 識別子からインデックスを取得する
+    // 識別子が存在しない場合はNoneを返し、呼び出し側でパースのエラーにする
+    fn get_idx(&self, index: &Index) -> Option<usize> {
+        match index {
+            Index::Idx(idx) => Some(*idx),
+            Index::Id(id) => self.ids.iter().position(|x| x == &Some(id.to_owned())),
+        }
+    }
 }
 
 impl Context {
     pub fn new() -> Self {
         Self {
             locals: Vec::new(),
+            labels: Vec::new(),
+            blocks: 0,
+            label_names: Vec::new(),
+            names: Names::default(),
             types: Field::new(),
             funcs: Field::new(),
+            tables: Field::new(),
+            mems: Field::new(),
+            globals: Field::new(),
+            tags: Field::new(),
+            elems: Field::new(),
+            datas: Field::new(),
+            start: None,
+            imports: Vec::new(),
             exports: Field::new(),
+            customs: Vec::new(),
         }
     }
 
-    // 関数のリストから関数のインデックスを取得する
-    // まだ関数が存在しない場合は、panicする
-    pub fn get_func_idx(&self, idx: &Index) -> usize {
-        match idx {
-            Index::Idx(idx) => *idx,
-            Index::Id(id) => self
-                .funcs
-                .ids
-                .iter()
-                .position(|x| x == &Some(id.to_owned()))
-                .expect("Func id has to exists"),
+    // 条件に一致するインポートの数を数える
+    fn imported(&self, kind: fn(&ImportDesc) -> bool) -> usize {
+        self.imports.iter().filter(|i| kind(&i.desc)).count()
+    }
+
+    // モジュールの解析前に、フィールドの識別子を宣言する
+    // 後で定義されるフィールドを、識別子で参照できるようにする
+    pub fn predeclare(&mut self, field: &str, id: Option<String>) {
+        match field {
+            "func" => self.funcs.add_id(id),
+            "table" => self.tables.add_id(id),
+            "memory" => self.mems.add_id(id),
+            "global" => self.globals.add_id(id),
+            "tag" => self.tags.add_id(id),
+            "elem" => self.elems.add_id(id),
+            "data" => self.datas.add_id(id),
+            _ => (),
         }
     }
 
+    // 関数のリストから関数のインデックスを取得する
+    pub fn get_func_idx(&self, idx: &Index) -> Option<usize> {
+        self.funcs.get_idx(idx)
+    }
+
     // ローカル変数をリストに追加する
-    // すでに同じローカル変数が存在する場合は、panicする
-    pub fn insert_local_id(&mut self, id: &Option<String>) {
+    // すでに同じローカル変数が存在する場合は、追加せずにfalseを返す
+    pub fn insert_local_id(&mut self, id: &Option<String>) -> bool {
         if id.is_some() && self.locals.contains(id) {
-            panic!("Local id has to be unique");
-        } else {
-            self.locals.push(id.clone());
+            return false;
         }
+        self.locals.push(id.clone());
+        true
     }
 
     // ローカル変数のリストからローカル変数のインデックスを取得する
-    pub fn get_local_idx(&self, index: &Index) -> usize {
+    pub fn get_local_idx(&self, index: &Index) -> Option<usize> {
         match index {
-            Index::Idx(idx) => *idx,
+            Index::Idx(idx) => Some(*idx),
+            Index::Id(id) => self.locals.iter().position(|x| x == &Some(id.clone())),
+        }
+    }
+
+    // ブロックに入るときにラベルを積む
+    // 識別子があれば、関数の中で何番目のブロックかと共に名前を記録する
+    pub fn push_label(&mut self, id: Option<String>) {
+        if let Some(id) = &id {
+            self.label_names.push((self.blocks, name(id)));
+        }
+        self.blocks += 1;
+        self.labels.push(id);
+    }
+
+    // ブロックを抜けるときにラベルを取り除く
+    pub fn pop_label(&mut self) {
+        self.labels.pop();
+    }
+
+    // ラベルのインデックスを取得する
+    // ラベルのインデックスは最も内側のブロックを0として数える
+    pub fn get_label_idx(&self, index: &Index) -> Option<usize> {
+        match index {
+            Index::Idx(idx) => Some(*idx),
             Index::Id(id) => self
-                .locals
+                .labels
                 .iter()
-                .position(|x| x == &Some(id.clone()))
-                .expect("Identifier not found"),
+                .rev()
+                .position(|x| x == &Some(id.clone())),
+        }
+    }
+
+    // 関数の識別子を登録する
+    // モジュールの解析前に宣言済みの場合は何もしない
+    pub fn declare_func_id(&mut self, id: Option<String>) {
+        let idx = self.next_func_idx();
+        self.funcs.declare(idx, id);
+    }
+
+    // 関数の解析を始める前に、ローカル変数とラベルをリセットする
+    pub fn begin_func(&mut self) {
+        self.locals.clear();
+        self.blocks = 0;
+        self.label_names.clear();
+    }
+
+    // 関数の解析を終えたときに、ローカル変数とラベルの名前を記録する
+    pub fn end_func(&mut self, idx: u32) {
+        let locals = name_map(&self.locals);
+        if !locals.is_empty() {
+            self.names.locals.push((idx, locals));
+        }
+        if !self.label_names.is_empty() {
+            let labels = std::mem::take(&mut self.label_names);
+            self.names.labels.push((idx, labels));
         }
     }
-    // 関数のリストに関数の識別子を追加する
-    // 追加した関数のインデックスを返す
-    pub fn insert_func_id(&mut self, id: Option<String>) -> usize {
-        self.funcs.add_id(id);
-        self.funcs.ids.len() - 1
+
+    // 次に定義される関数のインデックスを取得する
+    pub fn next_func_idx(&self) -> usize {
+        self.imported(|d| matches!(d, ImportDesc::Func(_))) + self.funcs.list.len()
     }
 
     // 型のリストに関数型を追加する
@@ -102,6 +212,11 @@ impl Context {
         self.types.add(id, t.clone());
     }
 
+    // 型のリストから型のインデックスを取得する
+    pub fn get_type_idx(&self, index: &Index) -> Option<usize> {
+        self.types.get_idx(index)
+    }
+
     // 型のリストから関数型のインデックスを取得する
     pub fn get_idx_from_func_type(&self, ft: &FuncType) -> Option<usize> {
         self.types.list.iter().position(|t| t == ft)
@@ -128,8 +243,120 @@ impl Context {
         self.funcs.add_item(func.clone());
     }
 
+    // テーブルのリストにテーブルを追加し、そのインデックスを返す
+    pub fn insert_table(&mut self, id: Option<String>, table: &Table) -> usize {
+        let idx = self.imported(|d| matches!(d, ImportDesc::Table(_))) + self.tables.list.len();
+        self.tables.declare(idx, id);
+        self.tables.add_item(*table);
+        idx
+    }
+
+    // テーブルのリストからテーブルのインデックスを取得する
+    pub fn get_table_idx(&self, index: &Index) -> Option<usize> {
+        self.tables.get_idx(index)
+    }
+
+    // メモリのリストにメモリを追加し、そのインデックスを返す
+    pub fn insert_mem(&mut self, id: Option<String>, mem: &Mem) -> usize {
+        let idx = self.imported(|d| matches!(d, ImportDesc::Memory(_))) + self.mems.list.len();
+        self.mems.declare(idx, id);
+        self.mems.add_item(*mem);
+        idx
+    }
+
+    // メモリのリストからメモリのインデックスを取得する
+    pub fn get_mem_idx(&self, index: &Index) -> Option<usize> {
+        self.mems.get_idx(index)
+    }
+
+    // グローバルのリストにグローバルを追加し、そのインデックスを返す
+    pub fn insert_global(&mut self, id: Option<String>, global: &Global) -> usize {
+        let idx = self.imported(|d| matches!(d, ImportDesc::Global(_))) + self.globals.list.len();
+        self.globals.declare(idx, id);
+        self.globals.add_item(global.clone());
+        idx
+    }
+
+    // グローバルのリストからグローバルのインデックスを取得する
+    pub fn get_global_idx(&self, index: &Index) -> Option<usize> {
+        self.globals.get_idx(index)
+    }
+
+    // タグのリストにタグを追加し、そのインデックスを返す
+    pub fn insert_tag(&mut self, id: Option<String>, tag: &Tag) -> usize {
+        let idx = self.imported(|d| matches!(d, ImportDesc::Tag(_))) + self.tags.list.len();
+        self.tags.declare(idx, id);
+        self.tags.add_item(*tag);
+        idx
+    }
+
+    // タグのリストからタグのインデックスを取得する
+    pub fn get_tag_idx(&self, index: &Index) -> Option<usize> {
+        self.tags.get_idx(index)
+    }
+
+    // エレメントセグメントのリストにセグメントを追加する
+    pub fn insert_elem(&mut self, id: Option<String>, elem: &Elem) {
+        self.elems.declare(self.elems.list.len(), id);
+        self.elems.add_item(elem.clone());
+    }
+
+    // エレメントセグメントのリストからセグメントのインデックスを取得する
+    pub fn get_elem_idx(&self, index: &Index) -> Option<usize> {
+        self.elems.get_idx(index)
+    }
+
+    // データセグメントのリストにセグメントを追加する
+    pub fn insert_data(&mut self, id: Option<String>, data: &Data) {
+        self.datas.declare(self.datas.list.len(), id);
+        self.datas.add_item(data.clone());
+    }
+
+    // データセグメントのリストからセグメントのインデックスを取得する
+    pub fn get_data_idx(&self, index: &Index) -> Option<usize> {
+        self.datas.get_idx(index)
+    }
+
+    // インポートのリストにインポートを追加する
+    // インポートしたものの識別子は、その種類のインデックス空間に宣言する
+    pub fn insert_import(&mut self, id: Option<String>, import: &Import) {
+        match import.desc {
+            ImportDesc::Func(_) => self.declare_func_id(id),
+            ImportDesc::Table(_) => {
+                let idx = self.imported(|d| matches!(d, ImportDesc::Table(_)));
+                self.tables.declare(idx, id);
+            }
+            ImportDesc::Memory(_) => {
+                let idx = self.imported(|d| matches!(d, ImportDesc::Memory(_)));
+                self.mems.declare(idx, id);
+            }
+            ImportDesc::Global(_) => {
+                let idx = self.imported(|d| matches!(d, ImportDesc::Global(_)));
+                self.globals.declare(idx, id);
+            }
+            ImportDesc::Tag(_) => {
+                let idx = self.imported(|d| matches!(d, ImportDesc::Tag(_)));
+                self.tags.declare(idx, id);
+            }
+        }
+        self.imports.push(import.clone());
+    }
+
     // エクスポートのリストにエクスポートを追加する
     pub fn insert_export(&mut self, name: &Option<String>, export: &Export) {
         self.exports.add((*name).clone(), export.clone());
     }
 }
+
+// 識別子から先頭の"$"を除いて名前にする
+pub fn name(id: &str) -> String {
+    id.strip_prefix('$').unwrap_or(id).to_string()
+}
+
+// インデックス空間の識別子のリストから、識別子のあるものだけをNameMapにする
+pub fn name_map(ids: &[Option<String>]) -> NameMap {
+    ids.iter()
+        .enumerate()
+        .filter_map(|(i, id)| Some((i as u32, name(id.as_ref()?))))
+        .collect()
+}
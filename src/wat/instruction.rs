@@ -1,43 +1,456 @@
 use std::{cell::RefCell, rc::Rc};
 
 use nom::{
-    branch::alt, bytes::streaming::tag, combinator::map, multi::many1, sequence::preceded, IResult,
+    branch::alt,
+    bytes::{complete::tag, streaming},
+    combinator::{cut, fail, map, map_opt, opt, verify},
+    multi::{count, many0, many1, many_m_n},
+    sequence::{pair, preceded},
+    IResult,
 };
 
-use crate::ast::Instruction;
+use crate::ast::{
+    instr::{self, Shape},
+    Catch, Instruction, MemArg,
+};
 
-use super::{context::Context, token::bws, types::index};
+use super::{
+    context::Context,
+    token::{self, bws, keyword as kw, pt, ws},
+    types::{self, index},
+    values,
+};
 
 // local.getとその先に続く文字列からIndexを取得する
 fn local_get<'a>(input: &'a str, ctx: &Rc<RefCell<Context>>) -> IResult<&'a str, Instruction> {
-    let local_get = bws(tag("local.get"));
+    let local_get = bws(streaming::tag("local.get"));
     let (input, i) = preceded(local_get, index)(input)?;
 
     // IndexからContextのIndexを取得する
-    let i = ctx.borrow().get_local_idx(&i);
+    let Some(i) = ctx.borrow().get_local_idx(&i) else {
+        return fail(input);
+    };
 
     Ok((input, Instruction::LocalGet(i)))
 }
 
-// 文字列からInstruction型へ変換する
-fn i32_add(input: &str) -> IResult<&str, Instruction> {
-    map(bws(tag("i32.add")), |_| Instruction::I32Add)(input)
+// "offset=4 align=2"のようなmemargをパースする
+// 省略された場合、offsetは0、alignは命令の自然なアラインメントになる
+// https://webassembly.github.io/spec/core/text/instructions.html#memory-instructions
+fn memarg(natural: u32) -> impl FnMut(&str) -> IResult<&str, MemArg> {
+    move |input| {
+        let offset = preceded(tag("offset="), values::u32);
+        // alignはバイト数で書かれるため、2の指数に変換する
+        // 2の累乗でない場合は、他の解釈を試さずにエラーにする
+        let align = map(
+            preceded(
+                tag("align="),
+                cut(verify(values::u32, |a: &u32| a.is_power_of_two())),
+            ),
+            |a| a.trailing_zeros(),
+        );
+        let (input, offset) = opt(preceded(ws, offset))(input)?;
+        let (input, align) = opt(preceded(ws, align))(input)?;
+        let arg = MemArg {
+            offset: offset.unwrap_or(0),
+            align: align.unwrap_or(natural),
+        };
+        Ok((input, arg))
+    }
+}
+
+// レーン数未満のレーン番号をパースする
+fn lane_idx(lanes: u8) -> impl FnMut(&str) -> IResult<&str, u8> {
+    move |input| {
+        map(
+            verify(preceded(ws, values::u32), |l| *l < lanes as u32),
+            |l| l as u8,
+        )(input)
+    }
+}
+
+// 命令表に登録された命令をパースする
+// 即値は命令表の形に従ってパースする
+fn table_instruction(input: &str) -> IResult<&str, Instruction> {
+    let (input, entry) = map_opt(values::keyword, instr::find_by_name)(input)?;
+    match entry.shape {
+        Shape::Plain(ref i) => Ok((input, i.clone())),
+        Shape::Memory(ctor, align) => map(memarg(align), ctor)(input),
+        Shape::Lane(ctor, lanes) => map(lane_idx(lanes), ctor)(input),
+        Shape::MemoryLane(ctor, align, lanes) => {
+            map(pair(memarg(align), lane_idx(lanes)), |(m, l)| ctor(m, l))(input)
+        }
+    }
+}
+
+// n個のレーンの値をパースし、リトルエンディアンで1つのv128の値にまとめる
+fn lanes<'a, F>(input: &'a str, n: usize, width: u32, lane: F) -> IResult<&'a str, u128>
+where
+    F: FnMut(&'a str) -> IResult<&'a str, u64>,
+{
+    let (input, values) = count(preceded(ws, lane), n)(input)?;
+    let v = values.iter().enumerate().fold(0u128, |acc, (i, v)| {
+        acc | (*v as u128) << (i as u32 * width)
+    });
+    Ok((input, v))
+}
+
+// "v128.const i32x4 1 2 3 4"のようなv128の定数をパースする
+// https://webassembly.github.io/spec/core/text/instructions.html#vector-instructions
+fn v128_const(input: &str) -> IResult<&str, Instruction> {
+    let (input, shape) = preceded(pair(kw("v128.const"), ws), values::keyword)(input)?;
+    let (input, v) = match shape {
+        "i8x16" => lanes(input, 16, 8, map(values::i8, |n| n as u8 as u64))?,
+        "i16x8" => lanes(input, 8, 16, map(values::i16, |n| n as u16 as u64))?,
+        "i32x4" => lanes(input, 4, 32, map(values::i32, |n| n as u32 as u64))?,
+        "i64x2" => lanes(input, 2, 64, map(values::i64, |n| n as u64))?,
+        "f32x4" => lanes(input, 4, 32, map(values::f32, |n| n as u64))?,
+        "f64x2" => lanes(input, 2, 64, values::f64)?,
+        _ => return fail(input),
+    };
+    Ok((input, Instruction::V128Const(v)))
+}
+
+// "i8x16.shuffle"と16個のレーン番号をパースする
+fn i8x16_shuffle(input: &str) -> IResult<&str, Instruction> {
+    let (input, idx) = preceded(kw("i8x16.shuffle"), count(lane_idx(32), 16))(input)?;
+    let mut lanes = [0; 16];
+    lanes.copy_from_slice(&idx);
+    Ok((input, Instruction::I8x16Shuffle(lanes)))
+}
+
+// "local.set $x"や"global.get 0"のような変数命令をパースする
+// https://webassembly.github.io/spec/core/text/instructions.html#variable-instructions
+fn variable<'a>(input: &'a str, ctx: &Rc<RefCell<Context>>) -> IResult<&'a str, Instruction> {
+    let (input, name) = values::keyword(input)?;
+    match name {
+        "local.set" | "local.tee" | "global.get" | "global.set" => {
+            let (input, i) = preceded(ws, index)(input)?;
+            let c = ctx.borrow();
+            let instr = match name {
+                "local.set" => c.get_local_idx(&i).map(Instruction::LocalSet),
+                "local.tee" => c.get_local_idx(&i).map(Instruction::LocalTee),
+                "global.get" => c
+                    .get_global_idx(&i)
+                    .map(|g| Instruction::GlobalGet(g as u32)),
+                _ => c
+                    .get_global_idx(&i)
+                    .map(|g| Instruction::GlobalSet(g as u32)),
+            };
+            match instr {
+                Some(instr) => Ok((input, instr)),
+                None => fail(input),
+            }
+        }
+        _ => fail(input),
+    }
+}
+
+// "ref.null func"や"ref.func $f"のような参照命令をパースする
+// https://webassembly.github.io/spec/core/text/instructions.html#reference-instructions
+fn reference<'a>(input: &'a str, ctx: &Rc<RefCell<Context>>) -> IResult<&'a str, Instruction> {
+    let (input, name) = values::keyword(input)?;
+    match name {
+        "ref.null" => map(types::heap_type, Instruction::RefNull)(input),
+        "ref.func" => {
+            let (input, i) = preceded(ws, index)(input)?;
+            let Some(f) = ctx.borrow().get_func_idx(&i) else {
+                return fail(input);
+            };
+            Ok((input, Instruction::RefFunc(f as u32)))
+        }
+        _ => fail(input),
+    }
+}
+
+// "table.get $t"や"memory.grow"のようなテーブルとメモリの命令をパースする
+// 省略可能なテーブルとメモリのインデックスは0になる
+// https://webassembly.github.io/spec/core/text/instructions.html#table-instructions
+fn table_memory<'a>(input: &'a str, ctx: &Rc<RefCell<Context>>) -> IResult<&'a str, Instruction> {
+    let (input, name) = values::keyword(input)?;
+    let ctor: fn(u32) -> Instruction = match name {
+        "table.get" => Instruction::TableGet,
+        "table.set" => Instruction::TableSet,
+        "table.size" => Instruction::TableSize,
+        "table.grow" => Instruction::TableGrow,
+        "memory.size" => Instruction::MemorySize,
+        "memory.grow" => Instruction::MemoryGrow,
+        _ => return fail(input),
+    };
+    let (input, i) = opt(preceded(ws, index))(input)?;
+    let idx = match i {
+        None => Some(0),
+        Some(i) if name.starts_with("table") => ctx.borrow().get_table_idx(&i),
+        Some(i) => ctx.borrow().get_mem_idx(&i),
+    };
+    let Some(idx) = idx else {
+        return fail(input);
+    };
+    Ok((input, ctor(idx as u32)))
+}
+
+// "memory.copy"や"table.init"のようなバルクメモリ命令をパースする
+// 省略可能なメモリとテーブルのインデックスは0になる
+// https://webassembly.github.io/spec/core/text/instructions.html#memory-instructions
+// https://webassembly.github.io/spec/core/text/instructions.html#table-instructions
+fn bulk<'a>(input: &'a str, ctx: &Rc<RefCell<Context>>) -> IResult<&'a str, Instruction> {
+    let (rest, name) = values::keyword(input)?;
+    let (rest, idxs) = many_m_n(0, 2, preceded(ws, index))(rest)?;
+
+    let c = ctx.borrow();
+    let mem = |i| c.get_mem_idx(i).map(|i| i as u32);
+    let table = |i| c.get_table_idx(i).map(|i| i as u32);
+    let data = |i| c.get_data_idx(i).map(|i| i as u32);
+    let elem = |i| c.get_elem_idx(i).map(|i| i as u32);
+
+    // "memory.init $mem $data"のようにdataidxとelemidxは最後に書かれる
+    // 識別子が存在しない場合はNoneになる
+    let instr = || {
+        Some(match (name, idxs.as_slice()) {
+            ("memory.init", [d]) => Instruction::MemoryInit(data(d)?, 0),
+            ("memory.init", [m, d]) => Instruction::MemoryInit(data(d)?, mem(m)?),
+            ("data.drop", [d]) => Instruction::DataDrop(data(d)?),
+            ("memory.copy", []) => Instruction::MemoryCopy(0, 0),
+            ("memory.copy", [d, s]) => Instruction::MemoryCopy(mem(d)?, mem(s)?),
+            ("memory.fill", []) => Instruction::MemoryFill(0),
+            ("memory.fill", [m]) => Instruction::MemoryFill(mem(m)?),
+            ("table.init", [e]) => Instruction::TableInit(elem(e)?, 0),
+            ("table.init", [t, e]) => Instruction::TableInit(elem(e)?, table(t)?),
+            ("elem.drop", [e]) => Instruction::ElemDrop(elem(e)?),
+            ("table.copy", []) => Instruction::TableCopy(0, 0),
+            ("table.copy", [d, s]) => Instruction::TableCopy(table(d)?, table(s)?),
+            ("table.fill", []) => Instruction::TableFill(0),
+            ("table.fill", [t]) => Instruction::TableFill(table(t)?),
+            _ => return None,
+        })
+    };
+    match instr() {
+        Some(instr) => Ok((rest, instr)),
+        None => fail(input),
+    }
+}
+
+// "i32.const 1"や"f64.const nan"のような定数をパースする
+// https://webassembly.github.io/spec/core/text/instructions.html#numeric-instructions
+fn constant(input: &str) -> IResult<&str, Instruction> {
+    let (input, name) = values::keyword(input)?;
+    match name {
+        "i32.const" => map(preceded(ws, values::i32), Instruction::I32Const)(input),
+        "i64.const" => map(preceded(ws, values::i64), Instruction::I64Const)(input),
+        "f32.const" => map(preceded(ws, values::f32), Instruction::F32Const)(input),
+        "f64.const" => map(preceded(ws, values::f64), Instruction::F64Const)(input),
+        _ => fail(input),
+    }
+}
+
+// "select (result i32)"のようなselect命令をパースする
+// https://webassembly.github.io/spec/core/text/instructions.html#parametric-instructions
+fn select(input: &str) -> IResult<&str, Instruction> {
+    let result = preceded(ws, pt(preceded(token::result, types::value_type)));
+    map(preceded(kw("select"), opt(result)), Instruction::Select)(input)
+}
+
+// "call $f"や"return_call_indirect (param i32)"のような関数呼び出しをパースする
+// https://webassembly.github.io/spec/core/text/instructions.html#control-instructions
+fn call<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Instruction> {
+    let (input, name) = values::keyword(input)?;
+    match name {
+        "call" | "return_call" => {
+            let (input, idx) = preceded(ws, index)(input)?;
+            let Some(f) = ctx.borrow().get_func_idx(&idx).map(|f| f as u32) else {
+                return fail(input);
+            };
+            let instr = match name {
+                "call" => Instruction::Call(f),
+                _ => Instruction::ReturnCall(f),
+            };
+            Ok((input, instr))
+        }
+        "call_indirect" | "return_call_indirect" => {
+            let (input, table) = opt(preceded(ws, index))(input)?;
+            let Some(table) = table.map_or(Some(0), |t| ctx.borrow().get_table_idx(&t)) else {
+                return fail(input);
+            };
+            let (input, ty) = types::anonymous_type_use(input, ctx)?;
+
+            let instr = match name {
+                "call_indirect" => Instruction::CallIndirect(ty as u32, table as u32),
+                _ => Instruction::ReturnCallIndirect(ty as u32, table as u32),
+            };
+            Ok((input, instr))
+        }
+        _ => fail(input),
+    }
+}
+
+// 空白に続くラベルのインデックスをパースする
+fn label_idx<'a>(input: &'a str, ctx: &Rc<RefCell<Context>>) -> IResult<&'a str, u32> {
+    let (input, i) = preceded(ws, index)(input)?;
+    match ctx.borrow().get_label_idx(&i) {
+        Some(i) => Ok((input, i as u32)),
+        None => fail(input),
+    }
+}
+
+// 空白に続くタグのインデックスをパースする
+fn tag_idx<'a>(input: &'a str, ctx: &Rc<RefCell<Context>>) -> IResult<&'a str, u32> {
+    let (input, i) = preceded(ws, index)(input)?;
+    match ctx.borrow().get_tag_idx(&i) {
+        Some(i) => Ok((input, i as u32)),
+        None => fail(input),
+    }
+}
+
+// "(catch $e $l)"のようなtry_tableのcatch節をパースする
+// https://webassembly.github.io/exception-handling/core/text/instructions.html#control-instructions
+fn catch<'a>(input: &'a str, ctx: &Rc<RefCell<Context>>) -> IResult<&'a str, Catch> {
+    let inner = |input: &'a str| {
+        let tag = |i| tag_idx(i, ctx);
+        let label = |i| label_idx(i, ctx);
+        let (input, name) = values::keyword(input)?;
+        match name {
+            "catch" => map(pair(tag, label), |(x, l)| Catch::Catch(x, l))(input),
+            "catch_ref" => map(pair(tag, label), |(x, l)| Catch::CatchRef(x, l))(input),
+            "catch_all" => map(label, Catch::CatchAll)(input),
+            "catch_all_ref" => map(label, Catch::CatchAllRef)(input),
+            _ => fail(input),
+        }
+    };
+    preceded(ws, pt(bws(inner)))(input)
+}
+
+// ブロックや分岐、例外などの制御命令をパースする
+// ブロックに入るときにラベルを積み、endで取り除く
+// https://webassembly.github.io/spec/core/text/instructions.html#control-instructions
+fn control<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Instruction> {
+    let label = |i| label_idx(i, &ctx.clone());
+    let (input, name) = values::keyword(input)?;
+    match name {
+        "block" | "loop" | "if" => {
+            let (input, id) = opt(values::id)(input)?;
+            let (input, bt) = types::block_type(input, ctx)?;
+            ctx.borrow_mut().push_label(id.map(|id| id.to_string()));
+            let instr = match name {
+                "block" => Instruction::Block(bt),
+                "loop" => Instruction::Loop(bt),
+                _ => Instruction::If(bt),
+            };
+            Ok((input, instr))
+        }
+        "try_table" => {
+            let (input, id) = opt(values::id)(input)?;
+            let (input, bt) = types::block_type(input, ctx)?;
+            // catch節のラベルは、try_tableの外側のブロックから数える
+            let (input, catches) = many0(|i| catch(i, ctx))(input)?;
+            ctx.borrow_mut().push_label(id.map(|id| id.to_string()));
+            Ok((input, Instruction::TryTable(bt, catches)))
+        }
+        "else" => map(opt(values::id), |_| Instruction::Else)(input),
+        "end" => {
+            let (input, _) = opt(values::id)(input)?;
+            ctx.borrow_mut().pop_label();
+            Ok((input, Instruction::End))
+        }
+        "br" => map(label, Instruction::Br)(input),
+        "br_if" => map(label, Instruction::BrIf)(input),
+        "br_table" => {
+            // 最後のラベルはデフォルトの分岐先になる
+            let (input, mut labels) = many1(label)(input)?;
+            let default = labels.pop().expect("br_table has at least one label");
+            Ok((input, Instruction::BrTable(labels, default)))
+        }
+        "throw" => map(|i| tag_idx(i, ctx), Instruction::Throw)(input),
+        _ => fail(input),
+    }
+}
+
+// 1つの命令をパースする
+fn instruction<'a>(
+    input: &'a str,
+    ctx: &mut Rc<RefCell<Context>>,
+) -> IResult<&'a str, Instruction> {
+    let lg = |i| local_get(i, ctx);
+    let variable = |i| variable(i, ctx);
+    let call = |i| call(i, &mut ctx.clone());
+    let control = |i| control(i, &mut ctx.clone());
+    let reference = |i| reference(i, ctx);
+    let table_memory = |i| table_memory(i, ctx);
+    let bulk = |i| bulk(i, ctx);
+    alt((
+        lg,
+        variable,
+        control,
+        call,
+        reference,
+        table_memory,
+        bulk,
+        constant,
+        select,
+        v128_const,
+        i8x16_shuffle,
+        table_instruction,
+    ))(input)
+}
+
+// "(i32.add (local.get 0) (i32.const 1))"のような折り畳まれた命令をパースする
+// 被演算子の命令を先に並べ、その後に命令自身を置く
+// https://webassembly.github.io/spec/core/text/instructions.html#folded-instructions
+pub fn folded<'a>(
+    input: &'a str,
+    ctx: &mut Rc<RefCell<Context>>,
+) -> IResult<&'a str, Vec<Instruction>> {
+    let inner = |input: &'a str| -> IResult<&'a str, Vec<Instruction>> {
+        let operands = |i| folded(i, &mut ctx.clone());
+        let body = |i| instructions(i, &mut ctx.clone());
+        let (input, instr) = instruction(input, &mut ctx.clone())?;
+        match instr {
+            Instruction::Block(_) | Instruction::Loop(_) | Instruction::TryTable(..) => {
+                let (input, body) = body(input)?;
+                ctx.borrow_mut().pop_label();
+                Ok((input, [vec![instr], body, vec![Instruction::End]].concat()))
+            }
+            Instruction::If(_) => {
+                // 条件、then節、省略可能なelse節の順に書かれる
+                let (input, cond) = many0(operands)(input)?;
+                let (input, then) = preceded(ws, pt(preceded(kw("then"), body)))(input)?;
+                let (input, els) = opt(preceded(ws, pt(preceded(kw("else"), body))))(input)?;
+                ctx.borrow_mut().pop_label();
+
+                let mut instrs = cond.concat();
+                instrs.push(instr);
+                instrs.extend(then);
+                if let Some(els) = els {
+                    instrs.push(Instruction::Else);
+                    instrs.extend(els);
+                }
+                instrs.push(Instruction::End);
+                Ok((input, instrs))
+            }
+            Instruction::Else | Instruction::End => fail(input),
+            _ => {
+                let (input, operands) = many0(operands)(input)?;
+                Ok((input, [operands.concat(), vec![instr]].concat()))
+            }
+        }
+    };
+    preceded(ws, pt(bws(inner)))(input)
 }
 
 // 文字列からInstructionのVec型へ変換する
+// 平らな命令と折り畳まれた命令を混在させることができる
 pub fn instructions<'a>(
     input: &'a str,
     ctx: &mut Rc<RefCell<Context>>,
 ) -> IResult<&'a str, Vec<Instruction>> {
-    let lg = |i| local_get(i, ctx);
-    let instruction = alt((lg, i32_add));
-    many1(bws(instruction))(input)
+    let plain = map(|i| instruction(i, &mut ctx.clone()), |i| vec![i]);
+    let folded = |i| folded(i, &mut ctx.clone());
+    map(many0(bws(alt((plain, folded)))), |v| v.concat())(input)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ast::Instruction;
+    use crate::ast::{BlockType, Instruction, Tag, ValueType};
 
     #[test]
     fn local_get_parse() {
@@ -57,8 +470,458 @@ mod tests {
 
     #[test]
     fn i32_add_parse() {
-        assert_eq!(i32_add("i32.add"), Ok(("", Instruction::I32Add)));
-        assert!(i32_add("local.get").is_err());
+        assert_eq!(table_instruction("i32.add"), Ok(("", Instruction::I32Add)));
+        assert!(table_instruction("local.get").is_err());
+    }
+
+    #[test]
+    fn simd_plain_parse() {
+        assert_eq!(
+            table_instruction("i8x16.add_sat_s"),
+            Ok(("", Instruction::I8x16AddSatS))
+        );
+        assert_eq!(
+            table_instruction("i8x16.add)"),
+            Ok((")", Instruction::I8x16Add))
+        );
+        assert_eq!(
+            table_instruction("i32x4.trunc_sat_f64x2_s_zero"),
+            Ok(("", Instruction::I32x4TruncSatF64x2SZero))
+        );
+        assert!(table_instruction("i8x16.add_sat").is_err());
+    }
+
+    #[test]
+    fn memarg_parse() {
+        assert_eq!(
+            table_instruction("v128.load"),
+            Ok((
+                "",
+                Instruction::V128Load(MemArg {
+                    offset: 0,
+                    align: 4
+                })
+            ))
+        );
+        assert_eq!(
+            table_instruction("v128.load offset=16 align=8"),
+            Ok((
+                "",
+                Instruction::V128Load(MemArg {
+                    offset: 16,
+                    align: 3
+                })
+            ))
+        );
+        assert_eq!(
+            table_instruction("v128.load32_splat align=1"),
+            Ok((
+                "",
+                Instruction::V128Load32Splat(MemArg {
+                    offset: 0,
+                    align: 0
+                })
+            ))
+        );
+        assert!(table_instruction("v128.load align=3").is_err());
+    }
+
+    #[test]
+    fn lane_parse() {
+        assert_eq!(
+            table_instruction("i8x16.extract_lane_s 15"),
+            Ok(("", Instruction::I8x16ExtractLaneS(15)))
+        );
+        assert_eq!(
+            table_instruction("f64x2.replace_lane 1"),
+            Ok(("", Instruction::F64x2ReplaceLane(1)))
+        );
+        assert!(table_instruction("i64x2.extract_lane 2").is_err());
+        assert_eq!(
+            table_instruction("v128.load16_lane offset=2 7"),
+            Ok((
+                "",
+                Instruction::V128Load16Lane(
+                    MemArg {
+                        offset: 2,
+                        align: 1
+                    },
+                    7
+                )
+            ))
+        );
+        assert!(table_instruction("v128.store8_lane 16").is_err());
+    }
+
+    #[test]
+    fn v128_const_parse() {
+        assert_eq!(
+            v128_const("v128.const i32x4 1 2 3 -1"),
+            Ok((
+                "",
+                Instruction::V128Const(0xffffffff_00000003_00000002_00000001)
+            ))
+        );
+        assert_eq!(
+            v128_const("v128.const i8x16 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 255"),
+            Ok((
+                "",
+                Instruction::V128Const(0xff0e0d0c_0b0a0908_07060504_03020100)
+            ))
+        );
+        assert_eq!(
+            v128_const("v128.const i16x8 0 0 0 0 0 0 0 0x8000"),
+            Ok(("", Instruction::V128Const(0x8000 << 112)))
+        );
+        assert_eq!(
+            v128_const("v128.const i64x2 -1 0"),
+            Ok(("", Instruction::V128Const(u64::MAX as u128)))
+        );
+        assert_eq!(
+            v128_const("v128.const f32x4 1.0 0 0 -0"),
+            Ok(("", Instruction::V128Const(0x80000000 << 96 | 0x3f800000)))
+        );
+        assert_eq!(
+            v128_const("v128.const f64x2 0 1"),
+            Ok(("", Instruction::V128Const(0x3ff00000_00000000 << 64)))
+        );
+        assert!(v128_const("v128.const i32x4 1 2 3").is_err());
+        assert!(v128_const("v128.const i32x3 1 2 3").is_err());
+    }
+
+    #[test]
+    fn i8x16_shuffle_parse() {
+        let mut expected = [0; 16];
+        expected[15] = 31;
+        assert_eq!(
+            i8x16_shuffle("i8x16.shuffle 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 31"),
+            Ok(("", Instruction::I8x16Shuffle(expected)))
+        );
+        assert!(i8x16_shuffle("i8x16.shuffle 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 32").is_err());
+    }
+
+    #[test]
+    fn bulk_parse() {
+        let ctx = Rc::new(RefCell::new(Context::new()));
+        assert_eq!(
+            bulk("memory.copy", &ctx),
+            Ok(("", Instruction::MemoryCopy(0, 0)))
+        );
+        assert_eq!(
+            bulk("memory.copy 1 2", &ctx),
+            Ok(("", Instruction::MemoryCopy(1, 2)))
+        );
+        assert_eq!(
+            bulk("memory.fill)", &ctx),
+            Ok((")", Instruction::MemoryFill(0)))
+        );
+        assert_eq!(
+            bulk("memory.init 3", &ctx),
+            Ok(("", Instruction::MemoryInit(3, 0)))
+        );
+        assert_eq!(
+            bulk("memory.init 1 3", &ctx),
+            Ok(("", Instruction::MemoryInit(3, 1)))
+        );
+        assert_eq!(
+            bulk("data.drop 2", &ctx),
+            Ok(("", Instruction::DataDrop(2)))
+        );
+        assert!(bulk("data.drop", &ctx).is_err());
+        assert_eq!(
+            bulk("table.copy 0 1", &ctx),
+            Ok(("", Instruction::TableCopy(0, 1)))
+        );
+        assert_eq!(
+            bulk("table.init 4", &ctx),
+            Ok(("", Instruction::TableInit(4, 0)))
+        );
+        assert_eq!(
+            bulk("table.init 1 4", &ctx),
+            Ok(("", Instruction::TableInit(4, 1)))
+        );
+        assert_eq!(
+            bulk("elem.drop 0", &ctx),
+            Ok(("", Instruction::ElemDrop(0)))
+        );
+        assert_eq!(
+            bulk("table.fill 2", &ctx),
+            Ok(("", Instruction::TableFill(2)))
+        );
+        assert!(bulk("memory.grow", &ctx).is_err());
+        assert!(bulk("memory.copy 1", &ctx).is_err());
+
+        ctx.borrow_mut()
+            .predeclare("memory", Some("$m".to_string()));
+        ctx.borrow_mut().predeclare("data", Some("$a".to_string()));
+        ctx.borrow_mut().predeclare("data", Some("$b".to_string()));
+        assert_eq!(
+            bulk("memory.init $m $b", &ctx),
+            Ok(("", Instruction::MemoryInit(1, 0)))
+        );
+        assert_eq!(
+            bulk("data.drop $a", &ctx),
+            Ok(("", Instruction::DataDrop(0)))
+        );
+    }
+
+    #[test]
+    fn variable_parse() {
+        let ctx = Rc::new(RefCell::new(Context {
+            locals: vec![None, Some("$x".to_string())],
+            ..Context::new()
+        }));
+        ctx.borrow_mut()
+            .predeclare("global", Some("$g".to_string()));
+        assert_eq!(
+            variable("local.set $x", &ctx),
+            Ok(("", Instruction::LocalSet(1)))
+        );
+        assert_eq!(
+            variable("local.tee 0", &ctx),
+            Ok(("", Instruction::LocalTee(0)))
+        );
+        assert_eq!(
+            variable("global.get $g", &ctx),
+            Ok(("", Instruction::GlobalGet(0)))
+        );
+        assert_eq!(
+            variable("global.set 3", &ctx),
+            Ok(("", Instruction::GlobalSet(3)))
+        );
+        assert!(variable("local.get 0", &ctx).is_err());
+    }
+
+    #[test]
+    fn reference_parse() {
+        let ctx = Rc::new(RefCell::new(Context::new()));
+        ctx.borrow_mut().predeclare("func", Some("$f".to_string()));
+        assert_eq!(
+            reference("ref.null func", &ctx),
+            Ok(("", Instruction::RefNull(ValueType::FuncRef)))
+        );
+        assert_eq!(
+            reference("ref.null extern", &ctx),
+            Ok(("", Instruction::RefNull(ValueType::ExternRef)))
+        );
+        assert_eq!(
+            reference("ref.func $f", &ctx),
+            Ok(("", Instruction::RefFunc(0)))
+        );
+        assert!(reference("ref.null i32", &ctx).is_err());
+    }
+
+    #[test]
+    fn table_memory_parse() {
+        let ctx = Rc::new(RefCell::new(Context::new()));
+        ctx.borrow_mut().predeclare("table", Some("$t".to_string()));
+        ctx.borrow_mut().predeclare("table", Some("$u".to_string()));
+        assert_eq!(
+            table_memory("table.get $u", &ctx),
+            Ok(("", Instruction::TableGet(1)))
+        );
+        assert_eq!(
+            table_memory("table.grow)", &ctx),
+            Ok((")", Instruction::TableGrow(0)))
+        );
+        assert_eq!(
+            table_memory("memory.size", &ctx),
+            Ok(("", Instruction::MemorySize(0)))
+        );
+        assert_eq!(
+            table_memory("memory.grow 1", &ctx),
+            Ok(("", Instruction::MemoryGrow(1)))
+        );
+    }
+
+    #[test]
+    fn constant_parse() {
+        assert_eq!(
+            constant("i32.const -1"),
+            Ok(("", Instruction::I32Const(-1)))
+        );
+        assert_eq!(
+            constant("i64.const 0xffff_ffff_ffff_ffff"),
+            Ok(("", Instruction::I64Const(-1)))
+        );
+        assert_eq!(
+            constant("f32.const 1.5"),
+            Ok(("", Instruction::F32Const(1.5f32.to_bits())))
+        );
+        assert_eq!(
+            constant("f64.const -inf"),
+            Ok(("", Instruction::F64Const(f64::NEG_INFINITY.to_bits())))
+        );
+        assert!(constant("i32.const 4294967296").is_err());
+    }
+
+    #[test]
+    fn select_parse() {
+        assert_eq!(select("select"), Ok(("", Instruction::Select(None))));
+        assert_eq!(
+            select("select (result f64)"),
+            Ok(("", Instruction::Select(Some(ValueType::F64))))
+        );
+    }
+
+    #[test]
+    fn folded_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        assert_eq!(
+            instructions(
+                "(i32.add (local.get 0) (i32.const 1))
+                (block $b (result i32)
+                  (br_if $b (i32.const 1) (local.get 1)))
+                (if (result i32) (local.get 0)
+                  (then (i32.const 1))
+                  (else (i32.const 2)))
+                drop)",
+                &mut ctx
+            ),
+            Ok((
+                ")",
+                vec![
+                    Instruction::LocalGet(0),
+                    Instruction::I32Const(1),
+                    Instruction::I32Add,
+                    Instruction::Block(BlockType::Value(ValueType::I32)),
+                    Instruction::I32Const(1),
+                    Instruction::LocalGet(1),
+                    Instruction::BrIf(0),
+                    Instruction::End,
+                    Instruction::LocalGet(0),
+                    Instruction::If(BlockType::Value(ValueType::I32)),
+                    Instruction::I32Const(1),
+                    Instruction::Else,
+                    Instruction::I32Const(2),
+                    Instruction::End,
+                    Instruction::Drop,
+                ]
+            ))
+        );
+        assert!(ctx.borrow().labels.is_empty());
+    }
+
+    #[test]
+    fn call_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        ctx.borrow_mut().predeclare("func", Some("$f".to_string()));
+        ctx.borrow_mut().predeclare("func", Some("$g".to_string()));
+
+        assert_eq!(call("call $g", &mut ctx), Ok(("", Instruction::Call(1))));
+        assert_eq!(call("call 0", &mut ctx), Ok(("", Instruction::Call(0))));
+        assert_eq!(
+            call("return_call $f", &mut ctx),
+            Ok(("", Instruction::ReturnCall(0)))
+        );
+        assert!(call("return_call", &mut ctx).is_err());
+    }
+
+    #[test]
+    fn call_indirect_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        ctx.borrow_mut().insert_local_id(&Some("$x".to_string()));
+
+        assert_eq!(
+            call("call_indirect (param i32) (result i32)", &mut ctx),
+            Ok(("", Instruction::CallIndirect(0, 0)))
+        );
+        assert_eq!(
+            call("return_call_indirect 1 (param i64)", &mut ctx),
+            Ok(("", Instruction::ReturnCallIndirect(1, 1)))
+        );
+        assert_eq!(
+            call("return_call_indirect (param i32) (result i32)", &mut ctx),
+            Ok(("", Instruction::ReturnCallIndirect(0, 0)))
+        );
+        assert_eq!(ctx.borrow().locals, vec![Some("$x".to_string())]);
+        assert_eq!(
+            ctx.borrow().types.list,
+            vec![
+                (vec![ValueType::I32], vec![ValueType::I32]),
+                (vec![ValueType::I64], vec![])
+            ]
+        );
+    }
+
+    #[test]
+    fn control_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        assert_eq!(
+            control("block $outer (result i32)", &mut ctx),
+            Ok(("", Instruction::Block(BlockType::Value(ValueType::I32))))
+        );
+        assert_eq!(
+            control("loop $inner", &mut ctx),
+            Ok(("", Instruction::Loop(BlockType::Empty)))
+        );
+        assert_eq!(control("br $outer", &mut ctx), Ok(("", Instruction::Br(1))));
+        assert_eq!(
+            control("br_if $inner", &mut ctx),
+            Ok(("", Instruction::BrIf(0)))
+        );
+        assert_eq!(
+            control("br_table $inner 1 $outer", &mut ctx),
+            Ok(("", Instruction::BrTable(vec![0, 1], 1)))
+        );
+        assert!(control("br_table", &mut ctx).is_err());
+        assert_eq!(control("end $inner", &mut ctx), Ok(("", Instruction::End)));
+        assert_eq!(control("br $outer", &mut ctx), Ok(("", Instruction::Br(0))));
+        assert_eq!(control("end", &mut ctx), Ok(("", Instruction::End)));
+        assert!(ctx.borrow().labels.is_empty());
+    }
+
+    #[test]
+    fn if_else_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        assert_eq!(
+            instructions(
+                "if $l (param i32) (result i64)
+                  br $l
+                else $l
+                  unreachable
+                end)",
+                &mut ctx
+            ),
+            Ok((
+                ")",
+                vec![
+                    Instruction::If(BlockType::Type(0)),
+                    Instruction::Br(0),
+                    Instruction::Else,
+                    Instruction::Unreachable,
+                    Instruction::End,
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn try_table_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        ctx.borrow_mut()
+            .insert_tag(Some("$e".to_string()), &Tag { f_type: 0 });
+
+        assert_eq!(
+            instructions(
+                "block $h (result i32)
+                  try_table $t (catch $e $h) (catch_all_ref 1)
+                    local.get 0
+                    throw $e
+                  end
+                  throw_ref
+                end)",
+                &mut ctx
+            )
+            .map(|(_, instrs)| instrs[..2].to_vec()),
+            Ok(vec![
+                Instruction::Block(BlockType::Value(ValueType::I32)),
+                Instruction::TryTable(
+                    BlockType::Empty,
+                    vec![Catch::Catch(0, 0), Catch::CatchAllRef(1)]
+                ),
+            ])
+        );
     }
 
     #[test]
@@ -82,4 +945,63 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn bulk_instructions_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+
+        assert_eq!(
+            instructions(
+                "local.get 0
+                local.get 1
+                local.get 2
+                memory.copy
+                local.get 0
+                memory.init 1
+                data.drop 1)",
+                &mut ctx
+            ),
+            Ok((
+                ")",
+                vec![
+                    Instruction::LocalGet(0),
+                    Instruction::LocalGet(1),
+                    Instruction::LocalGet(2),
+                    Instruction::MemoryCopy(0, 0),
+                    Instruction::LocalGet(0),
+                    Instruction::MemoryInit(1, 0),
+                    Instruction::DataDrop(1),
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn simd_instructions_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+
+        assert_eq!(
+            instructions(
+                "v128.const i64x2 1 2
+                local.get 0
+                v128.load offset=4
+                i64x2.add
+                i64x2.extract_lane 1)",
+                &mut ctx
+            ),
+            Ok((
+                ")",
+                vec![
+                    Instruction::V128Const(2 << 64 | 1),
+                    Instruction::LocalGet(0),
+                    Instruction::V128Load(MemArg {
+                        offset: 4,
+                        align: 4
+                    }),
+                    Instruction::I64x2Add,
+                    Instruction::I64x2ExtractLane(1),
+                ]
+            ))
+        );
+    }
 }
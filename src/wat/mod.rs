@@ -1,6 +1,7 @@
 pub(crate) mod context;
 pub(crate) mod instruction;
 pub mod module;
+pub mod print;
 pub(crate) mod token;
 pub(crate) mod types;
 pub(crate) mod values;
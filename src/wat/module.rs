@@ -2,114 +2,692 @@ use std::{cell::RefCell, rc::Rc};
 
 use nom::{
     branch::alt,
-    combinator::map,
+    character::complete::char,
+    combinator::{fail, map, map_opt, opt, verify},
     multi::many0,
-    sequence::{preceded, tuple},
+    sequence::{pair, preceded, tuple},
     IResult,
 };
 
 use crate::{
-    ast::{Export, ExportDesc, Func, Module},
+    ast::{
+        Custom, CustomPlace, Data, DataMode, Elem, ElemMode, Export, ExportDesc, Expr, Func,
+        Global, Import, ImportDesc, Instruction, Limits, Mem, MemType, Module, Names, Section,
+        Table, TableType, Tag, ValueType,
+    },
+    features::Features,
     wat::{instruction, token, types, values},
 };
 
 use super::{
-    context::Context,
-    token::{bws, ws},
+    context::{self, Context},
+    token::{bws, keyword as kw, pt, ws},
 };
 
+// "(export "add")"のようなインラインのエクスポートをパースする
+// https://webassembly.github.io/spec/core/text/modules.html#text-func-abbrev-export
+fn inline_exports(input: &str) -> IResult<&str, Vec<String>> {
+    many0(preceded(ws, pt(preceded(token::export, values::literal))))(input)
+}
+
+// インラインのエクスポートをContextに登録する
+fn insert_exports(ctx: &Rc<RefCell<Context>>, names: Vec<String>, desc: ExportDesc) {
+    for name in names {
+        let export = Export {
+            name: name.clone(),
+            desc: desc.clone(),
+        };
+        ctx.borrow_mut().insert_export(&Some(name), &export);
+    }
+}
+
+// "(local $x i32)"や"(local i32 i64)"のようなローカル変数の宣言をパースする
+// https://webassembly.github.io/spec/core/text/modules.html#text-local
+fn locals<'a>(input: &'a str, ctx: &Rc<RefCell<Context>>) -> IResult<&'a str, Vec<ValueType>> {
+    let named = map(pair(values::id, types::value_type), |(id, t)| {
+        vec![(Some(id), t)]
+    });
+    let anonymous = map(many0(types::value_type), |ts| {
+        ts.into_iter().map(|t| (None, t)).collect()
+    });
+    let local = preceded(ws, pt(preceded(kw("local"), alt((named, anonymous)))));
+    let (input, locals) = many0(local)(input)?;
+
+    let locals = locals.concat();
+    for (id, _) in &locals {
+        if !ctx
+            .borrow_mut()
+            .insert_local_id(&id.map(|id| id.to_string()))
+        {
+            return fail(input);
+        }
+    }
+    Ok((input, locals.into_iter().map(|(_, t)| t).collect()))
+}
+
 // 関数をパースする
 fn func<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Func> {
-    fn inner<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Func> {
+    fn inner<'a>(
+        input: &'a str,
+        ctx: &mut Rc<RefCell<Context>>,
+    ) -> IResult<&'a str, (Vec<String>, Func)> {
         // 関数の識別子をパースする
-        let (input, id) = preceded(token::func, values::id)(input)?;
+        let (input, id) = preceded(token::func, opt(values::id))(input)?;
 
         // Context型に関数の識別子を登録する
-        ctx.borrow_mut().insert_func_id(Some(id.to_string()));
+        // ローカル変数は関数ごとに異なるため、リセットする
+        ctx.borrow_mut()
+            .declare_func_id(id.map(|id| id.to_string()));
+        ctx.borrow_mut().begin_func();
+
+        let (input, exports) = inline_exports(input)?;
 
         // 引数と戻り値の型をパースする
         let (input, func_type) = types::type_use(input, ctx)?;
 
+        // ローカル変数の宣言をパースする
+        let (input, locals) = locals(input, ctx)?;
+
         // 関数内に命令をパースする
         let (input, body) = instruction::instructions(input, ctx)?;
 
         // 関数の構造体を作成する
         let f = Func {
             f_type: func_type as u32,
-            locals: vec![],
+            locals,
             body,
         };
 
-        Ok((input, f))
+        Ok((input, (exports, f)))
     }
 
     // Contextを適応する
     let in_pt = |i| inner(i, ctx);
 
     // 括弧をパースする
-    let (input, func) = token::pt(in_pt)(input)?;
+    let (input, (exports, func)) = token::pt(in_pt)(input)?;
+    let idx = ctx.borrow().next_func_idx() as u32;
+    ctx.borrow_mut().end_func(idx);
     ctx.borrow_mut().insert_func(&func);
+    insert_exports(ctx, exports, ExportDesc::Func(idx));
 
     Ok((input, func))
 }
 
+// "$f 0"のような関数のインデックスのリストを、ref.funcの式のリストに変換する
+fn func_indices<'a>(input: &'a str, ctx: &Rc<RefCell<Context>>) -> IResult<&'a str, Vec<Expr>> {
+    let (input, idxs) = many0(preceded(ws, types::index))(input)?;
+    let exprs = idxs
+        .iter()
+        .map(|i| {
+            Some(vec![Instruction::RefFunc(
+                ctx.borrow().get_func_idx(i)? as u32
+            )])
+        })
+        .collect();
+    match exprs {
+        Some(exprs) => Ok((input, exprs)),
+        None => fail(input),
+    }
+}
+
+// "(item ref.func $f)"や"(ref.null func)"のような要素の式のリストをパースする
+fn elem_exprs<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Vec<Expr>> {
+    let item = |i| {
+        let body = |i| instruction::instructions(i, &mut ctx.clone());
+        preceded(ws, pt(preceded(kw("item"), body)))(i)
+    };
+    let folded = |i| instruction::folded(i, &mut ctx.clone());
+    many0(alt((item, folded)))(input)
+}
+
+// "func $f $g"や"funcref (ref.func $f)"のような要素のリストをパースする
+// https://webassembly.github.io/spec/core/text/modules.html#element-segments
+fn elem_list<'a>(
+    input: &'a str,
+    ctx: &mut Rc<RefCell<Context>>,
+) -> IResult<&'a str, (ValueType, Vec<Expr>)> {
+    let funcs = map(preceded(kw("func"), |i| func_indices(i, ctx)), |init| {
+        (ValueType::FuncRef, init)
+    });
+    let exprs = pair(preceded(ws, types::ref_type), |i| {
+        elem_exprs(i, &mut ctx.clone())
+    });
+    alt((funcs, exprs))(input)
+}
+
+// "(offset i32.const 0)"や"(i32.const 0)"のようなオフセットの式をパースする
+fn offset<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Expr> {
+    let explicit = |i| {
+        let body = |i| instruction::instructions(i, &mut ctx.clone());
+        preceded(ws, pt(preceded(kw("offset"), body)))(i)
+    };
+    let folded = |i| instruction::folded(i, &mut ctx.clone());
+    alt((explicit, folded))(input)
+}
+
+// "(table $t 1 funcref)"のようなテーブルをパースする
+// "(table funcref (elem $f $g))"のように、エレメントセグメントを直接書くこともできる
+// https://webassembly.github.io/spec/core/text/modules.html#tables
+fn table<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Table> {
+    type Parsed<'a> = (Option<&'a str>, Vec<String>, Table, Option<Vec<Expr>>);
+
+    fn inner<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Parsed<'a>> {
+        let (input, id) = preceded(kw("table"), opt(values::id))(input)?;
+        let (input, exports) = inline_exports(input)?;
+        if let (input, Some(tt)) = opt(types::table_type)(input)? {
+            return Ok((input, (id, exports, tt, None)));
+        }
+
+        // 要素の数をテーブルの大きさにする
+        let (input, elem) = preceded(ws, types::ref_type)(input)?;
+        let exprs = verify(
+            |i| elem_exprs(i, &mut ctx.clone()),
+            |e: &Vec<Expr>| !e.is_empty(),
+        );
+        let items = alt((exprs, |i| func_indices(i, ctx)));
+        let (input, init) = preceded(ws, pt(preceded(kw("elem"), items)))(input)?;
+        let n = init.len() as u32;
+        let tt = TableType {
+            limits: Limits {
+                min: n,
+                max: Some(n),
+            },
+            elem,
+        };
+        Ok((input, (id, exports, tt, Some(init))))
+    }
+
+    let (input, (id, exports, table, init)) = token::pt(|i| inner(i, ctx))(input)?;
+    let idx = ctx
+        .borrow_mut()
+        .insert_table(id.map(|id| id.to_string()), &table) as u32;
+    insert_exports(ctx, exports, ExportDesc::Table(idx));
+    if let Some(init) = init {
+        let elem = Elem {
+            e_type: table.elem,
+            init,
+            mode: ElemMode::Active {
+                table: idx,
+                offset: vec![Instruction::I32Const(0)],
+            },
+        };
+        ctx.borrow_mut().insert_elem(None, &elem);
+    }
+
+    Ok((input, table))
+}
+
+// "(memory $mem 1 1 shared)"のようなメモリをパースする
+// "(memory (data "abc"))"のように、データセグメントを直接書くこともできる
+// https://webassembly.github.io/spec/core/text/modules.html#memories
+fn memory<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Mem> {
+    // データの大きさをページ数に切り上げて、メモリの大きさにする
+    let data = map(
+        preceded(ws, pt(preceded(kw("data"), many0(values::string)))),
+        |strings| {
+            let init = strings.concat();
+            let pages = init.len().div_ceil(65536) as u32;
+            let limits = Limits {
+                min: pages,
+                max: Some(pages),
+            };
+            let mem = MemType {
+                limits,
+                shared: false,
+            };
+            (mem, Some(init))
+        },
+    );
+    let mut mem = token::pt(preceded(
+        token::memory,
+        tuple((
+            opt(values::id),
+            inline_exports,
+            alt((map(types::mem_type, |m| (m, None)), data)),
+        )),
+    ));
+
+    let (input, (id, exports, (mem, init))) = mem(input)?;
+    let idx = ctx
+        .borrow_mut()
+        .insert_mem(id.map(|id| id.to_string()), &mem) as u32;
+    insert_exports(ctx, exports, ExportDesc::Memory(idx));
+    if let Some(init) = init {
+        let data = Data {
+            init,
+            mode: DataMode::Active {
+                memory: idx,
+                offset: vec![Instruction::I32Const(0)],
+            },
+        };
+        ctx.borrow_mut().insert_data(None, &data);
+    }
+
+    Ok((input, mem))
+}
+
+// "(global $g (mut i32) (i32.const 0))"のようなグローバル変数をパースする
+// https://webassembly.github.io/spec/core/text/modules.html#globals
+fn global<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Global> {
+    type Parsed<'a> = (Option<&'a str>, Vec<String>, Global);
+
+    fn inner<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Parsed<'a>> {
+        let (input, id) = preceded(kw("global"), opt(values::id))(input)?;
+        let (input, exports) = inline_exports(input)?;
+        let (input, g_type) = types::global_type(input)?;
+        let (input, init) = instruction::instructions(input, ctx)?;
+        Ok((input, (id, exports, Global { g_type, init })))
+    }
+
+    let (input, (id, exports, global)) = token::pt(|i| inner(i, ctx))(input)?;
+    let idx = ctx
+        .borrow_mut()
+        .insert_global(id.map(|id| id.to_string()), &global) as u32;
+    insert_exports(ctx, exports, ExportDesc::Global(idx));
+
+    Ok((input, global))
+}
+
+// "(tag $e (param i32))"のような例外のタグをパースする
+// https://webassembly.github.io/exception-handling/core/text/modules.html#tags
+fn tag<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Tag> {
+    type Parsed<'a> = (Option<&'a str>, Vec<String>, Tag);
+
+    fn inner<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Parsed<'a>> {
+        let (input, id) = preceded(kw("tag"), opt(values::id))(input)?;
+        let (input, exports) = inline_exports(input)?;
+        let (input, f_type) = types::anonymous_type_use(input, ctx)?;
+        let tag = Tag {
+            f_type: f_type as u32,
+        };
+        Ok((input, (id, exports, tag)))
+    }
+
+    let (input, (id, exports, tag)) = token::pt(|i| inner(i, ctx))(input)?;
+    let idx = ctx
+        .borrow_mut()
+        .insert_tag(id.map(|id| id.to_string()), &tag) as u32;
+    insert_exports(ctx, exports, ExportDesc::Tag(idx));
+
+    Ok((input, tag))
+}
+
+// "(import "env" "log" (func $log (param i32)))"のようなインポートをパースする
+// https://webassembly.github.io/spec/core/text/modules.html#imports
+fn import<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Import> {
+    fn desc<'a>(
+        input: &'a str,
+        ctx: &mut Rc<RefCell<Context>>,
+    ) -> IResult<&'a str, (Option<&'a str>, ImportDesc)> {
+        let (input, (kind, id)) = pair(bws(values::keyword), opt(values::id))(input)?;
+        let (input, desc) = match kind {
+            "func" => map(
+                |i| types::anonymous_type_use(i, ctx),
+                |t| ImportDesc::Func(t as u32),
+            )(input)?,
+            "table" => map(types::table_type, ImportDesc::Table)(input)?,
+            "memory" => map(types::mem_type, ImportDesc::Memory)(input)?,
+            "global" => map(types::global_type, ImportDesc::Global)(input)?,
+            "tag" => map(
+                |i| types::anonymous_type_use(i, ctx),
+                |t| ImportDesc::Tag(Tag { f_type: t as u32 }),
+            )(input)?,
+            _ => fail(input)?,
+        };
+        Ok((input, (id, desc)))
+    }
+
+    let (input, (module, name, (id, desc))) = token::pt(preceded(
+        kw("import"),
+        tuple((
+            values::literal,
+            values::literal,
+            pt(bws(|i| desc(i, &mut ctx.clone()))),
+        )),
+    ))(input)?;
+    let import = Import { module, name, desc };
+    ctx.borrow_mut()
+        .insert_import(id.map(|id| id.to_string()), &import);
+
+    Ok((input, import))
+}
+
+// "(start $main)"のような開始関数をパースする
+// https://webassembly.github.io/spec/core/text/modules.html#start-function
+fn start<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, u32> {
+    let (input, idx) = token::pt(preceded(kw("start"), types::index))(input)?;
+    let Some(idx) = ctx.borrow().get_func_idx(&idx).map(|i| i as u32) else {
+        return fail(input);
+    };
+    ctx.borrow_mut().start = Some(idx);
+
+    Ok((input, idx))
+}
+
+// エレメントセグメントをパースする
+// https://webassembly.github.io/spec/core/text/modules.html#element-segments
+fn elem<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Elem> {
+    fn inner<'a>(
+        input: &'a str,
+        ctx: &mut Rc<RefCell<Context>>,
+    ) -> IResult<&'a str, (Option<&'a str>, Elem)> {
+        let (input, id) = preceded(kw("elem"), opt(values::id))(input)?;
+
+        // "(elem declare func $f)"のような宣言的なセグメント
+        if let (input, Some(_)) = opt(kw("declare"))(input)? {
+            let (input, (e_type, init)) = elem_list(input, ctx)?;
+            let mode = ElemMode::Declarative;
+            return Ok((input, (id, Elem { e_type, init, mode })));
+        }
+
+        let (input, table) = opt(preceded(ws, pt(preceded(kw("table"), types::index))))(input)?;
+        let (input, offset) = opt(|i| offset(i, ctx))(input)?;
+        let Some(offset) = offset else {
+            // オフセットがない場合は受動的なセグメント
+            let (input, (e_type, init)) = elem_list(input, ctx)?;
+            let mode = ElemMode::Passive;
+            return Ok((input, (id, Elem { e_type, init, mode })));
+        };
+
+        // 能動的なセグメントでは、"func"を省略して関数のインデックスだけを並べることができる
+        let Some(table) = table.map_or(Some(0), |t| ctx.borrow().get_table_idx(&t)) else {
+            return fail(input);
+        };
+        let table = table as u32;
+        let funcs = map(|i| func_indices(i, ctx), |init| (ValueType::FuncRef, init));
+        let (input, (e_type, init)) = alt((|i| elem_list(i, &mut ctx.clone()), funcs))(input)?;
+        let mode = ElemMode::Active { table, offset };
+        Ok((input, (id, Elem { e_type, init, mode })))
+    }
+
+    let (input, (id, elem)) = token::pt(|i| inner(i, ctx))(input)?;
+    ctx.borrow_mut()
+        .insert_elem(id.map(|id| id.to_string()), &elem);
+
+    Ok((input, elem))
+}
+
+// "(data (i32.const 0) "abc")"のようなデータセグメントをパースする
+// https://webassembly.github.io/spec/core/text/modules.html#data-segments
+fn data<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Data> {
+    fn inner<'a>(
+        input: &'a str,
+        ctx: &mut Rc<RefCell<Context>>,
+    ) -> IResult<&'a str, (Option<&'a str>, Data)> {
+        let (input, id) = preceded(kw("data"), opt(values::id))(input)?;
+        let (input, memory) = opt(preceded(ws, pt(preceded(token::memory, types::index))))(input)?;
+        let (input, offset) = opt(|i| offset(i, ctx))(input)?;
+        let (input, strings) = many0(values::string)(input)?;
+
+        let mode = match offset {
+            Some(offset) => {
+                let Some(memory) = memory.map_or(Some(0), |m| ctx.borrow().get_mem_idx(&m)) else {
+                    return fail(input);
+                };
+                DataMode::Active {
+                    memory: memory as u32,
+                    offset,
+                }
+            }
+            None => DataMode::Passive,
+        };
+        let data = Data {
+            init: strings.concat(),
+            mode,
+        };
+        Ok((input, (id, data)))
+    }
+
+    let (input, (id, data)) = token::pt(|i| inner(i, ctx))(input)?;
+    ctx.borrow_mut()
+        .insert_data(id.map(|id| id.to_string()), &data);
+
+    Ok((input, data))
+}
+
 // エクスポートをパースする
 fn export<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Export> {
-    // 関数の識別子をパースする
-    let index = token::pt(preceded(token::func, types::index));
+    // エクスポートするものの種類と識別子をパースする
+    let index = token::pt(pair(bws(values::keyword), types::index));
 
     // エクスポートをパースする
     let mut exp = token::pt(preceded(token::export, tuple((values::literal, index))));
 
-    let (input, (name, idx)) = exp(input)?;
+    let (input, (name, (kind, idx))) = exp(input)?;
+
+    let c = ctx.borrow();
+    let desc = match kind {
+        "func" => c.get_func_idx(&idx).map(|i| ExportDesc::Func(i as u32)),
+        "table" => c.get_table_idx(&idx).map(|i| ExportDesc::Table(i as u32)),
+        "memory" => c.get_mem_idx(&idx).map(|i| ExportDesc::Memory(i as u32)),
+        "global" => c.get_global_idx(&idx).map(|i| ExportDesc::Global(i as u32)),
+        "tag" => c.get_tag_idx(&idx).map(|i| ExportDesc::Tag(i as u32)),
+        _ => None,
+    };
+    drop(c);
+    let Some(desc) = desc else {
+        return fail(input);
+    };
 
     let export = Export {
         name: name.clone(),
-        desc: ExportDesc::Func(ctx.borrow().get_func_idx(&idx) as u32),
+        desc,
     };
 
-    //
     ctx.borrow_mut().insert_export(&Some(name), &export);
 
     Ok((input, export))
 }
 
+// "(@custom "name" (after data) "bytes")"のようなカスタムセクションの注釈をパースする
+// 位置を省略した場合は"(after last)"になる
+// https://github.com/WebAssembly/annotations/blob/main/proposals/annotations/Overview.md
+fn custom<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, Custom> {
+    fn section(input: &str) -> IResult<&str, Section> {
+        map_opt(bws(values::keyword), |k| {
+            Section::ALL.into_iter().find(|s| s.name() == k)
+        })(input)
+    }
+    let place = alt((
+        map(pair(kw("before"), kw("first")), |_| {
+            CustomPlace::BeforeFirst
+        }),
+        map(pair(kw("after"), kw("last")), |_| CustomPlace::AfterLast),
+        map(preceded(kw("before"), section), CustomPlace::Before),
+        map(preceded(kw("after"), section), CustomPlace::After),
+    ));
+    let (input, (name, place, strings)) = pt(preceded(
+        nom::bytes::complete::tag("@custom"),
+        tuple((
+            values::literal,
+            opt(preceded(ws, pt(place))),
+            many0(values::string),
+        )),
+    ))(input)?;
+
+    let custom = Custom {
+        name,
+        place: place.unwrap_or_default(),
+        data: strings.concat(),
+    };
+    ctx.borrow_mut().customs.push(custom.clone());
+
+    Ok((input, custom))
+}
+
+// 型の定義を読み飛ばす
+// 型はモジュールをパースする前に登録済みのため、別のContextでパースする
+fn type_field(input: &str) -> IResult<&str, ()> {
+    let ctx = Rc::new(RefCell::new(Context::new()));
+    let (input, _) = token::pt(|i| types::type_def(i, &mut ctx.clone()))(input)?;
+    Ok((input, ()))
+}
+
+// モジュールフィールドの識別子を、パースする前に登録する
+// 型の定義は先にパースし、関数本体やエクスポートから後で定義されるフィールドを参照できるようにする
+fn declare_ids(input: &str, ctx: &mut Rc<RefCell<Context>>) {
+    let mut depth = 0;
+    let mut field = "";
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            // 文字列の中の括弧は数えない
+            '"' => {
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => (),
+                    }
+                }
+            }
+            '(' => {
+                depth += 1;
+                let rest = &input[i + 1..];
+                let mut head = preceded(ws, tuple((values::keyword, opt(values::id))));
+                match (depth, head(rest)) {
+                    (2, Ok((_, ("type", _)))) => {
+                        if let Ok((_, (id, ft))) = types::type_def(rest, ctx) {
+                            ctx.borrow_mut()
+                                .insert_id_func_type(id.map(|id| id.to_string()), &ft);
+                        }
+                        field = "type";
+                    }
+                    (2, Ok((_, ("import", _)))) => {
+                        let mut desc = tuple((
+                            kw("import"),
+                            values::literal,
+                            values::literal,
+                            char('('),
+                            bws(values::keyword),
+                            opt(values::id),
+                        ));
+                        if let Ok((_, (.., kind, id))) = desc(rest) {
+                            ctx.borrow_mut()
+                                .predeclare(kind, id.map(|id| id.to_string()));
+                        }
+                        field = "import";
+                    }
+                    (2, Ok((_, (kind, id)))) => {
+                        ctx.borrow_mut()
+                            .predeclare(kind, id.map(|id| id.to_string()));
+                        field = kind;
+                    }
+                    // "(table funcref (elem ..))"と"(memory (data ..))"の省略形は、名前のないセグメントを定義する
+                    (3, Ok((_, ("elem", _)))) if field == "table" => {
+                        ctx.borrow_mut().predeclare("elem", None)
+                    }
+                    (3, Ok((_, ("data", _)))) if field == "memory" => {
+                        ctx.borrow_mut().predeclare("data", None)
+                    }
+                    _ => (),
+                }
+            }
+            ')' => depth -= 1,
+            _ => (),
+        }
+    }
+}
+
 // モジュールをパースする
 pub fn module(input: &str) -> IResult<&str, Module> {
     // Contextを作成する
-    let ctx = Rc::new(RefCell::new(Context::new()));
+    let mut ctx = Rc::new(RefCell::new(Context::new()));
 
-    // 関数にContextの適応とパースの作成
-    let func_ctx = |i| func(i, &mut ctx.clone());
+    // 型とフィールドの識別子を先に登録する
+    declare_ids(input, &mut ctx);
 
-    // エクスポートにContextの適応とパースの作成
+    // 各フィールドにContextの適応とパースの作成
+    let import_ctx = |i| import(i, &mut ctx.clone());
+    let func_ctx = |i| func(i, &mut ctx.clone());
+    let table_ctx = |i| table(i, &mut ctx.clone());
+    let memory_ctx = |i| memory(i, &mut ctx.clone());
+    let global_ctx = |i| global(i, &mut ctx.clone());
+    let tag_ctx = |i| tag(i, &mut ctx.clone());
     let export_ctx = |i| export(i, &mut ctx.clone());
+    let start_ctx = |i| start(i, &mut ctx.clone());
+    let elem_ctx = |i| elem(i, &mut ctx.clone());
+    let data_ctx = |i| data(i, &mut ctx.clone());
+    let custom_ctx = |i| custom(i, &mut ctx.clone());
 
-    // 関数とエクスポートをパースする
+    // モジュールの各フィールドをパースする
     let module_field = bws(many0(bws(alt((
+        type_field,
+        map(import_ctx, |_| ()),
         map(func_ctx, |_| ()),
+        map(table_ctx, |_| ()),
+        map(memory_ctx, |_| ()),
+        map(global_ctx, |_| ()),
+        map(tag_ctx, |_| ()),
         map(export_ctx, |_| ()),
+        map(start_ctx, |_| ()),
+        map(elem_ctx, |_| ()),
+        map(data_ctx, |_| ()),
+        map(custom_ctx, |_| ()),
     )))));
 
     // モジュールをパースする
-    preceded(ws, token::pt(preceded(token::module, module_field)))(input)?;
+    let (_, id) = preceded(
+        ws,
+        token::pt(preceded(
+            token::module,
+            map(pair(opt(values::id), module_field), |(id, _)| id),
+        )),
+    )(input)?;
 
     // パースした結果をContextから取り出し、Module構造体を作成する
+    let c = ctx.borrow();
     let module = Module {
-        types: ctx.borrow().types.list.clone(),
-        funcs: ctx.borrow().funcs.list.clone(),
-        exports: ctx.borrow().exports.list.clone(),
+        types: c.types.list.clone(),
+        funcs: c.funcs.list.clone(),
+        tables: c.tables.list.clone(),
+        mems: c.mems.list.clone(),
+        globals: c.globals.list.clone(),
+        tags: c.tags.list.clone(),
+        elems: c.elems.list.clone(),
+        datas: c.datas.list.clone(),
+        start: c.start,
+        imports: c.imports.clone(),
+        exports: c.exports.list.clone(),
+        names: Names {
+            module: id.map(context::name),
+            funcs: context::name_map(&c.funcs.ids),
+            types: context::name_map(&c.types.ids),
+            globals: context::name_map(&c.globals.ids),
+            ..c.names.clone()
+        },
+        customs: c.customs.clone(),
     };
 
     Ok(("", module))
 }
 
+// 有効にする提案を指定してモジュールをパースする
+// 構文の誤りはその位置のバイトオフセットを、無効な提案の機能はその理由をメッセージにする
+pub fn module_with_features(input: &str, features: &Features) -> Result<Module, String> {
+    let (_, m) = module(input).map_err(|e| {
+        let offset = match e {
+            nom::Err::Error(e) | nom::Err::Failure(e) => input.len() - e.input.len(),
+            nom::Err::Incomplete(_) => input.len(),
+        };
+        format!("syntax error at offset {offset}")
+    })?;
+    features.module(&m)?;
+    Ok(m)
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
     use crate::{
-        ast::{Instruction, ValueType::I32},
+        ast::{
+            BlockType, Catch, GlobalType, Instruction, Limits, MemArg,
+            ValueType::{ExnRef, FuncRef, F64, I32, I64},
+        },
         wat::context::Field,
     };
 
@@ -129,11 +707,12 @@ mod tests {
                 Instruction::I32Add,
             ],
         };
-        assert_eq!(func(&wat, &mut ctx), Ok(("", expected.clone())));
+        assert_eq!(func(wat, &mut ctx), Ok(("", expected.clone())));
         assert_eq!(
             ctx,
             Rc::new(RefCell::new(Context {
                 locals: vec![Some("$lhs".to_string()), Some("$rhs".to_string())],
+                labels: vec![],
                 types: Field {
                     ids: vec![None],
                     list: vec![(vec![I32, I32], vec![I32])],
@@ -142,7 +721,11 @@ mod tests {
                     ids: vec![Some("$add".to_string())],
                     list: vec![expected]
                 },
-                exports: Field::new()
+                names: Names {
+                    locals: vec![(0, vec![(0, "lhs".to_string()), (1, "rhs".to_string())])],
+                    ..Default::default()
+                },
+                ..Context::new()
             }))
         )
     }
@@ -168,6 +751,7 @@ mod tests {
             ctx,
             Rc::new(RefCell::new(Context {
                 locals: vec![],
+                labels: vec![],
                 types: Field::new(),
                 funcs: Field {
                     ids: vec![Some("$add".to_string())],
@@ -179,13 +763,163 @@ mod tests {
                         name: "add".to_string(),
                         desc: ExportDesc::Func(0)
                     }]
-                }
+                },
+                ..Context::new()
             }))
         )
     }
     #[test]
-    fn module_parse() {
+    fn memory_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        let expected = Mem {
+            limits: Limits {
+                min: 1,
+                max: Some(2),
+            },
+            shared: true,
+        };
+        assert_eq!(
+            memory("(memory $mem 1 2 shared)", &mut ctx),
+            Ok(("", expected))
+        );
+        assert_eq!(ctx.borrow().mems.ids, vec![Some("$mem".to_string())]);
+        assert_eq!(ctx.borrow().mems.list, vec![expected]);
+    }
+
+    #[test]
+    fn module_shared_memory_parse() {
         let wat = "(module
+                (memory 1 1 shared)
+                (func $add (param $addr i32) (param $n i32) (result i32)
+                  local.get $addr
+                  local.get $n
+                  i32.atomic.rmw.add offset=4)
+                (func $fence
+                  atomic.fence)
+            )";
+        let (_, m) = module(wat).unwrap();
+        assert_eq!(
+            m.mems,
+            vec![Mem {
+                limits: Limits {
+                    min: 1,
+                    max: Some(1)
+                },
+                shared: true
+            }]
+        );
+        assert_eq!(
+            m.funcs[0].body[2],
+            Instruction::I32AtomicRmwAdd(MemArg {
+                offset: 4,
+                align: 2
+            })
+        );
+        assert_eq!(m.funcs[1].body, vec![Instruction::AtomicFence]);
+    }
+
+    #[test]
+    fn tag_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        assert_eq!(
+            tag("(tag $e (param $x i32) (param f64))", &mut ctx),
+            Ok(("", Tag { f_type: 0 }))
+        );
+        assert_eq!(ctx.borrow().tags.ids, vec![Some("$e".to_string())]);
+        assert_eq!(ctx.borrow().types.list, vec![(vec![I32, F64], vec![])]);
+        assert!(ctx.borrow().locals.is_empty());
+    }
+
+    #[test]
+    fn module_exception_parse() {
+        let wat = "(module
+                (tag $e (param i32))
+                (func $catch (param $x i32) (result i32)
+                  block $h (result i32)
+                    try_table (catch $e $h)
+                      local.get $x
+                      throw $e
+                    end
+                    unreachable
+                  end)
+                (func $rethrow (param exnref)
+                  local.get 0
+                  throw_ref)
+            )";
+        let (_, m) = module(wat).unwrap();
+        assert_eq!(m.tags, vec![Tag { f_type: 0 }]);
+        assert_eq!(
+            m.types,
+            vec![
+                (vec![I32], vec![]),
+                (vec![I32], vec![I32]),
+                (vec![ExnRef], vec![])
+            ]
+        );
+        assert_eq!(
+            m.funcs[0].body,
+            vec![
+                Instruction::Block(BlockType::Value(I32)),
+                Instruction::TryTable(BlockType::Empty, vec![Catch::Catch(0, 0)]),
+                Instruction::LocalGet(0),
+                Instruction::Throw(0),
+                Instruction::End,
+                Instruction::Unreachable,
+                Instruction::End,
+            ]
+        );
+        assert_eq!(
+            m.funcs[1].body,
+            vec![Instruction::LocalGet(0), Instruction::ThrowRef]
+        );
+    }
+
+    #[test]
+    fn module_forward_call_parse() {
+        let wat = "(module
+                (func $even (param $n i32) (result i32)
+                  local.get $n
+                  return_call $odd)
+                (func $odd (param $n i32) (result i32)
+                  local.get $n
+                  return_call $even)
+                (export \"even\" (func $even))
+            )";
+        let body = |f| vec![Instruction::LocalGet(0), Instruction::ReturnCall(f)];
+        let expected = Module {
+            types: vec![(vec![I32], vec![I32])],
+            funcs: vec![
+                Func {
+                    f_type: 0,
+                    locals: vec![],
+                    body: body(1),
+                },
+                Func {
+                    f_type: 0,
+                    locals: vec![],
+                    body: body(0),
+                },
+            ],
+            exports: vec![Export {
+                name: "even".to_string(),
+                desc: ExportDesc::Func(0),
+            }],
+            names: Names {
+                funcs: vec![(0, "even".to_string()), (1, "odd".to_string())],
+                locals: vec![
+                    (0, vec![(0, "n".to_string())]),
+                    (1, vec![(0, "n".to_string())]),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(module(wat), Ok(("", expected)));
+    }
+
+    #[test]
+    fn module_parse() {
+        let wat = "(module $m
                 (func $add (param $lhs i32) (param $rhs i32) (result i32)
                   local.get $lhs
                   local.get $rhs
@@ -207,7 +941,302 @@ mod tests {
                 name: "add".to_string(),
                 desc: ExportDesc::Func(0),
             }],
+            names: Names {
+                module: Some("m".to_string()),
+                funcs: vec![(0, "add".to_string())],
+                locals: vec![(0, vec![(0, "lhs".to_string()), (1, "rhs".to_string())])],
+                ..Default::default()
+            },
+            ..Default::default()
         };
-        assert_eq!(module(&wat), Ok(("", expected)));
+        assert_eq!(module(wat), Ok(("", expected)));
+    }
+
+    #[test]
+    fn import_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        assert_eq!(
+            import("(import \"env\" \"log\" (func $log (param i32)))", &mut ctx),
+            Ok((
+                "",
+                Import {
+                    module: "env".to_string(),
+                    name: "log".to_string(),
+                    desc: ImportDesc::Func(0)
+                }
+            ))
+        );
+        assert_eq!(
+            import("(import \"env\" \"g\" (global $g (mut i64)))", &mut ctx).map(|(_, i)| i.desc),
+            Ok(ImportDesc::Global(GlobalType {
+                value_type: I64,
+                mutable: true
+            }))
+        );
+        assert_eq!(ctx.borrow().funcs.ids, vec![Some("$log".to_string())]);
+        assert_eq!(ctx.borrow().globals.ids, vec![Some("$g".to_string())]);
+        assert_eq!(ctx.borrow().next_func_idx(), 1);
+    }
+
+    #[test]
+    fn elem_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        ctx.borrow_mut().predeclare("func", Some("$f".to_string()));
+        ctx.borrow_mut().predeclare("table", Some("$t".to_string()));
+        let refs = vec![vec![Instruction::RefFunc(0)], vec![Instruction::RefFunc(1)]];
+        assert_eq!(
+            elem("(elem (i32.const 1) $f 1)", &mut ctx),
+            Ok((
+                "",
+                Elem {
+                    e_type: FuncRef,
+                    init: refs.clone(),
+                    mode: ElemMode::Active {
+                        table: 0,
+                        offset: vec![Instruction::I32Const(1)]
+                    }
+                }
+            ))
+        );
+        assert_eq!(
+            elem(
+                "(elem (table $t) (offset i32.const 0) funcref (item ref.func $f) (ref.func 1))",
+                &mut ctx
+            )
+            .map(|(_, e)| e.init),
+            Ok(refs.clone())
+        );
+        assert_eq!(
+            elem("(elem $p func $f 1)", &mut ctx).map(|(_, e)| e.mode),
+            Ok(ElemMode::Passive)
+        );
+        assert_eq!(
+            elem("(elem declare func $f)", &mut ctx).map(|(_, e)| e.mode),
+            Ok(ElemMode::Declarative)
+        );
+        assert_eq!(ctx.borrow().elems.ids[2], Some("$p".to_string()));
+    }
+
+    #[test]
+    fn data_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        assert_eq!(
+            data("(data (i32.const 8) \"ab\" \"\\00\")", &mut ctx),
+            Ok((
+                "",
+                Data {
+                    init: vec![b'a', b'b', 0],
+                    mode: DataMode::Active {
+                        memory: 0,
+                        offset: vec![Instruction::I32Const(8)]
+                    }
+                }
+            ))
+        );
+        assert_eq!(
+            data("(data $d \"x\")", &mut ctx),
+            Ok((
+                "",
+                Data {
+                    init: vec![b'x'],
+                    mode: DataMode::Passive
+                }
+            ))
+        );
+        assert_eq!(ctx.borrow().datas.ids, vec![None, Some("$d".to_string())]);
+    }
+
+    #[test]
+    fn custom_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        assert_eq!(
+            custom("(@custom \"producers\" \"a\" \"\\01\")", &mut ctx),
+            Ok((
+                "",
+                Custom {
+                    name: "producers".to_string(),
+                    place: CustomPlace::AfterLast,
+                    data: vec![b'a', 1],
+                }
+            ))
+        );
+        assert_eq!(
+            custom("(@custom \"x\" (before first))", &mut ctx).map(|(_, c)| c.place),
+            Ok(CustomPlace::BeforeFirst)
+        );
+        assert_eq!(
+            custom("(@custom \"x\" ( after datacount ) \"\")", &mut ctx).map(|(_, c)| c.place),
+            Ok(CustomPlace::After(Section::DataCount))
+        );
+        assert_eq!(
+            custom("(@custom \"x\" (before func))", &mut ctx).map(|(_, c)| c.place),
+            Ok(CustomPlace::Before(Section::Func))
+        );
+        assert!(custom("(@custom \"x\" (after custom))", &mut ctx).is_err());
+        assert!(custom("( @custom \"x\")", &mut ctx).is_err());
+        assert_eq!(ctx.borrow().customs.len(), 4);
+
+        let (_, m) = module(
+            "(module
+                (@custom \"a\" (after func) \"(\")
+                (func)
+                (@custom \"b\" \"\"))",
+        )
+        .unwrap();
+        assert_eq!(m.funcs.len(), 1);
+        assert_eq!(
+            m.customs
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn module_fields_parse() {
+        let wat = "(module $m
+                (type $binop (func (param i32 i32) (result i32)))
+                (import \"env\" \"log\" (func $log (param i32)))
+                (table $t (export \"table\") funcref (elem $add))
+                (memory (export \"mem\") (data \"hi\"))
+                (global $count (mut i32) (i32.const 0))
+                (func $add (type $binop)
+                  (i32.add (local.get 0) (local.get 1)))
+                (func $main (export \"main\") (local $tmp i32) (local i64)
+                  (local.set $tmp (call $add (i32.const 1) (i32.const 2)))
+                  (call $log (local.get $tmp))
+                  (global.set $count (global.get $count)))
+                (start $main)
+                (export \"count\" (global $count))
+            )";
+        let (_, m) = module(wat).unwrap();
+        assert_eq!(
+            m.types,
+            vec![
+                (vec![I32, I32], vec![I32]),
+                (vec![I32], vec![]),
+                (vec![], vec![])
+            ]
+        );
+        assert_eq!(m.imports.len(), 1);
+        assert_eq!(m.funcs[0].f_type, 0);
+        assert_eq!(m.funcs[1].locals, vec![I32, I64]);
+        assert_eq!(
+            m.funcs[1].body,
+            vec![
+                Instruction::I32Const(1),
+                Instruction::I32Const(2),
+                Instruction::Call(1),
+                Instruction::LocalSet(0),
+                Instruction::LocalGet(0),
+                Instruction::Call(0),
+                Instruction::GlobalGet(0),
+                Instruction::GlobalSet(0),
+            ]
+        );
+        assert_eq!(
+            m.elems,
+            vec![Elem {
+                e_type: FuncRef,
+                init: vec![vec![Instruction::RefFunc(1)]],
+                mode: ElemMode::Active {
+                    table: 0,
+                    offset: vec![Instruction::I32Const(0)]
+                }
+            }]
+        );
+        assert_eq!(
+            m.tables[0].limits,
+            Limits {
+                min: 1,
+                max: Some(1)
+            }
+        );
+        assert_eq!(
+            m.mems[0].limits,
+            Limits {
+                min: 1,
+                max: Some(1)
+            }
+        );
+        assert_eq!(m.datas[0].init, b"hi".to_vec());
+        assert_eq!(m.start, Some(2));
+        assert_eq!(
+            m.exports.iter().map(|e| e.desc.clone()).collect::<Vec<_>>(),
+            vec![
+                ExportDesc::Table(0),
+                ExportDesc::Memory(0),
+                ExportDesc::Func(2),
+                ExportDesc::Global(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_id_parse() {
+        // 存在しない識別子はpanicせずにパースのエラーになる
+        let wats = [
+            "(module (func (type $t)))",
+            "(module (func i32.const 0 table.get $t drop))",
+            "(module (memory 1) (func memory.size $m drop))",
+            "(module (func global.get $g drop))",
+            "(module (func throw $e))",
+            "(module (memory 1) (func data.drop $d))",
+            "(module (table 1 funcref) (func elem.drop $e))",
+            "(module (export \"g\" (global $g)))",
+            "(module (data (memory $m) (i32.const 0) \"\"))",
+            "(module (start $main))",
+            "(module (func (type 0)))",
+            "(module (type (func)) (func (type 6)))",
+            "(module (type $t (func)) (func (type $t) (param i32)))",
+            "(module (type $t (func (param i32))) (func (type $t) (param i64) (result i32) i32.const 0))",
+        ];
+        for wat in wats {
+            assert!(module(wat).is_err(), "{wat}");
+        }
+        assert!(module("(module (global $g i32 (i32.const 0)) (func global.get $g drop))").is_ok());
+    }
+
+    #[test]
+    fn parse_with_features() {
+        let wat = "(module (func $f return_call $f))";
+        let features = Features {
+            tail_call: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            module_with_features(wat, &features),
+            Err("tail-call instruction used but tail_call feature disabled".to_string())
+        );
+        assert!(module_with_features(wat, &Features::default()).is_ok());
+        assert_eq!(
+            module_with_features("(module (func", &Features::default()),
+            Err("syntax error at offset 13".to_string())
+        );
+
+        // 存在しない識別子や重複したローカル変数は、panicせずにエラーになる
+        let wats = [
+            "(module (func call $missing))",
+            "(module (func br $nope))",
+            "(module (func local.get $x drop))",
+            "(module (func (param $x i32) local.set $y))",
+            "(module (func (param $x i32) (local $x i32)))",
+            "(module (func (param $x i32) (param $x i32)))",
+        ];
+        for wat in wats {
+            assert!(
+                module_with_features(wat, &Features::default()).is_err(),
+                "{wat}"
+            );
+        }
+        let wat = "(module
+            (type (func (param $x i32)))
+            (func (param $x i32)
+              block $b
+                local.get $x
+                br_if $b
+              end))";
+        assert!(module_with_features(wat, &Features::default()).is_ok());
     }
 }
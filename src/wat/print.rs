@@ -0,0 +1,984 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use crate::ast::{
+    instr::{self, Shape},
+    BlockType, Catch, Custom, CustomPlace, Data, DataMode, Elem, ElemMode, Export, ExportDesc,
+    Expr, Func, FuncType, Global, GlobalType, Import, ImportDesc, Instruction, Limits, MemArg,
+    MemType, Module, NameMap, Names, TableType, ValueType,
+};
+
+use super::values::is_idchar;
+
+// モジュールをテキスト形式に変換する
+// 命令は折り畳まずに平らな形で出力し、ブロックの中は字下げする
+// 名前セクションの名前は識別子として出力し、インデックスの代わりに参照する
+// カスタムセクションは、位置を指定した注釈としてモジュールの最後に出力する
+// 出力はwat::module::moduleでパースすると同じモジュールになる
+// https://webassembly.github.io/spec/core/text/modules.html
+pub fn print(module: &Module) -> String {
+    let mut ids = Ids::new(&module.names);
+    let mut buf = String::from("(module");
+    if let Some(id) = module.names.module.as_deref().and_then(id) {
+        let _ = write!(buf, " {id}");
+    }
+    for (i, t) in module.types.iter().enumerate() {
+        buf.push_str("\n  (type");
+        declare(&mut buf, &ids.types, i as u32);
+        buf.push_str(" (func");
+        func_type(&mut buf, t);
+        buf.push_str("))");
+    }
+    let mut funcs = 0;
+    let mut globals = 0;
+    for i in &module.imports {
+        buf.push_str("\n  ");
+        import(&mut buf, &ids, i, &mut funcs, &mut globals);
+    }
+    for f in &module.funcs {
+        buf.push_str("\n  ");
+        ids.enter_func(&module.names, funcs);
+        func(&mut buf, module, &mut ids, f, funcs);
+        funcs += 1;
+    }
+    for t in &module.tables {
+        buf.push_str("\n  (table ");
+        table_type(&mut buf, t);
+        buf.push(')');
+    }
+    for m in &module.mems {
+        buf.push_str("\n  (memory ");
+        mem_type(&mut buf, m);
+        buf.push(')');
+    }
+    for t in &module.tags {
+        buf.push_str("\n  (tag (type");
+        index(&mut buf, &ids.types, t.f_type);
+        buf.push_str("))");
+    }
+    for g in &module.globals {
+        buf.push_str("\n  ");
+        global(&mut buf, &ids, g, globals);
+        globals += 1;
+    }
+    for e in &module.exports {
+        buf.push_str("\n  ");
+        export(&mut buf, &ids, e);
+    }
+    if let Some(start) = module.start {
+        buf.push_str("\n  (start");
+        index(&mut buf, &ids.funcs, start);
+        buf.push(')');
+    }
+    for e in &module.elems {
+        buf.push_str("\n  ");
+        elem(&mut buf, &ids, e);
+    }
+    for d in &module.datas {
+        buf.push_str("\n  ");
+        data(&mut buf, &ids, d);
+    }
+    for c in &module.customs {
+        buf.push_str("\n  ");
+        custom(&mut buf, c);
+    }
+    buf.push_str("\n)\n");
+    buf
+}
+
+// 名前から作った識別子
+// 関数の中の識別子は、関数を出力するたびに切り替える
+#[derive(Default)]
+struct Ids {
+    types: HashMap<u32, String>,
+    funcs: HashMap<u32, String>,
+    globals: HashMap<u32, String>,
+    locals: HashMap<u32, String>,
+    // 関数の中のブロックの番号と、そのラベルの識別子
+    labels: HashMap<u32, String>,
+    // 出力中の命令を囲むブロックのラベル
+    stack: Vec<Option<String>>,
+}
+
+impl Ids {
+    fn new(names: &Names) -> Self {
+        Self {
+            types: ids(&names.types),
+            funcs: ids(&names.funcs),
+            globals: ids(&names.globals),
+            ..Default::default()
+        }
+    }
+
+    // ラベルは内側のブロックで同じ識別子を使えるため、重複する名前も使う
+    fn enter_func(&mut self, names: &Names, idx: u32) {
+        fn find(map: &[(u32, NameMap)], idx: u32) -> &[(u32, String)] {
+            map.iter()
+                .find(|(i, _)| *i == idx)
+                .map_or(&[], |(_, names)| names)
+        }
+        self.locals = ids(find(&names.locals, idx));
+        self.labels = find(&names.labels, idx)
+            .iter()
+            .filter_map(|(i, name)| Some((*i, id(name)?)))
+            .collect();
+        self.stack.clear();
+    }
+
+    // ラベルのインデックスを、識別子で参照できる場合は識別子にする
+    // 内側のブロックに同じ識別子がある場合は、インデックスのまま出力する
+    fn label(&self, buf: &mut String, l: u32) {
+        let target = self.stack.len().checked_sub(l as usize + 1);
+        match target.and_then(|t| self.stack[t].as_ref()) {
+            Some(id)
+                if self.stack.iter().rev().position(|x| x.as_ref() == Some(id))
+                    == Some(l as usize) =>
+            {
+                let _ = write!(buf, " {id}");
+            }
+            _ => {
+                let _ = write!(buf, " {l}");
+            }
+        }
+    }
+}
+
+// 名前をテキスト形式の識別子にする
+// 識別子に使えない文字を含む名前は使わない
+// https://webassembly.github.io/spec/core/text/values.html#text-id
+fn id(name: &str) -> Option<String> {
+    (!name.is_empty() && name.chars().all(is_idchar)).then(|| format!("${name}"))
+}
+
+// NameMapから識別子の表を作る
+// 同じインデックス空間で重複する名前は、最初のものだけを使う
+fn ids(names: &[(u32, String)]) -> HashMap<u32, String> {
+    let mut used = HashSet::new();
+    let mut map = HashMap::new();
+    for (idx, name) in names {
+        if let Some(id) = id(name) {
+            if !map.contains_key(idx) && used.insert(id.clone()) {
+                map.insert(*idx, id);
+            }
+        }
+    }
+    map
+}
+
+// 定義する要素に識別子があれば、先頭に空白を置いて出力する
+fn declare(buf: &mut String, ids: &HashMap<u32, String>, idx: u32) {
+    if let Some(id) = ids.get(&idx) {
+        let _ = write!(buf, " {id}");
+    }
+}
+
+// 識別子があれば識別子を、なければインデックスを、先頭に空白を置いて出力する
+fn index(buf: &mut String, ids: &HashMap<u32, String>, idx: u32) {
+    match ids.get(&idx) {
+        Some(id) => {
+            let _ = write!(buf, " {id}");
+        }
+        None => {
+            let _ = write!(buf, " {idx}");
+        }
+    }
+}
+
+fn value_type(buf: &mut String, t: &ValueType) {
+    buf.push_str(match t {
+        ValueType::I32 => "i32",
+        ValueType::I64 => "i64",
+        ValueType::F32 => "f32",
+        ValueType::F64 => "f64",
+        ValueType::V128 => "v128",
+        ValueType::FuncRef => "funcref",
+        ValueType::ExternRef => "externref",
+        ValueType::ExnRef => "exnref",
+    });
+}
+
+// ref.nullの型は参照型ではなくヒープ型で書く
+fn heap_type(buf: &mut String, t: &ValueType) {
+    buf.push_str(match t {
+        ValueType::FuncRef => "func",
+        ValueType::ExternRef => "extern",
+        _ => "exn",
+    });
+}
+
+// " (param i32 i64) (result i32)"のように、先頭に空白を置いて出力する
+fn func_type(buf: &mut String, (params, results): &FuncType) {
+    for (keyword, types) in [("param", params), ("result", results)] {
+        if types.is_empty() {
+            continue;
+        }
+        let _ = write!(buf, " ({keyword}");
+        for t in types {
+            buf.push(' ');
+            value_type(buf, t);
+        }
+        buf.push(')');
+    }
+}
+
+fn limits(buf: &mut String, limits: &Limits) {
+    let _ = write!(buf, "{}", limits.min);
+    if let Some(max) = limits.max {
+        let _ = write!(buf, " {max}");
+    }
+}
+
+fn mem_type(buf: &mut String, m: &MemType) {
+    limits(buf, &m.limits);
+    if m.shared {
+        buf.push_str(" shared");
+    }
+}
+
+fn table_type(buf: &mut String, t: &TableType) {
+    limits(buf, &t.limits);
+    buf.push(' ');
+    value_type(buf, &t.elem);
+}
+
+fn global_type(buf: &mut String, g: &GlobalType) {
+    if g.mutable {
+        buf.push_str("(mut ");
+        value_type(buf, &g.value_type);
+        buf.push(')');
+    } else {
+        value_type(buf, &g.value_type);
+    }
+}
+
+// 文字列を引用符で囲んで出力する
+// 表示可能なASCII文字以外は"\hh"の形でエスケープする
+// https://webassembly.github.io/spec/core/text/values.html#strings
+fn string(buf: &mut String, bytes: &[u8]) {
+    buf.push('"');
+    for b in bytes {
+        match b {
+            b'"' => buf.push_str("\\\""),
+            b'\\' => buf.push_str("\\\\"),
+            0x20..=0x7e => buf.push(*b as char),
+            _ => {
+                let _ = write!(buf, "\\{b:02x}");
+            }
+        }
+    }
+    buf.push('"');
+}
+
+// インポートした関数とグローバル変数の識別子を出力するため、それぞれの数を数える
+fn import(buf: &mut String, ids: &Ids, import: &Import, funcs: &mut u32, globals: &mut u32) {
+    buf.push_str("(import ");
+    string(buf, import.module.as_bytes());
+    buf.push(' ');
+    string(buf, import.name.as_bytes());
+    match &import.desc {
+        ImportDesc::Func(t) => {
+            buf.push_str(" (func");
+            declare(buf, &ids.funcs, *funcs);
+            buf.push_str(" (type");
+            index(buf, &ids.types, *t);
+            buf.push_str("))");
+            *funcs += 1;
+        }
+        ImportDesc::Table(t) => {
+            buf.push_str(" (table ");
+            table_type(buf, t);
+            buf.push(')');
+        }
+        ImportDesc::Memory(m) => {
+            buf.push_str(" (memory ");
+            mem_type(buf, m);
+            buf.push(')');
+        }
+        ImportDesc::Global(g) => {
+            buf.push_str(" (global");
+            declare(buf, &ids.globals, *globals);
+            buf.push(' ');
+            global_type(buf, g);
+            buf.push(')');
+            *globals += 1;
+        }
+        ImportDesc::Tag(t) => {
+            buf.push_str(" (tag (type");
+            index(buf, &ids.types, t.f_type);
+            buf.push_str("))");
+        }
+    }
+    buf.push(')');
+}
+
+// 関数の型は"(type x)"で参照し、読みやすさのためにパラメータと戻り値も書く
+// 識別子のないパラメータとローカル変数はまとめて書く
+fn func(buf: &mut String, module: &Module, ids: &mut Ids, f: &Func, idx: u32) {
+    buf.push_str("(func");
+    declare(buf, &ids.funcs, idx);
+    buf.push_str(" (type");
+    index(buf, &ids.types, f.f_type);
+    buf.push(')');
+    let (params, results) = module
+        .types
+        .get(f.f_type as usize)
+        .cloned()
+        .unwrap_or_default();
+    locals(buf, &ids.locals, "param", &params, 0);
+    func_type(buf, &(vec![], results));
+    locals(buf, &ids.locals, "local", &f.locals, params.len() as u32);
+    if f.body.is_empty() {
+        buf.push(')');
+        return;
+    }
+    instructions(buf, ids, &f.body, 2);
+    buf.push_str("\n  )");
+}
+
+// "(param $x i32) (param i32 i64)"のようにパラメータやローカル変数を出力する
+// startは最初の変数のローカル変数のインデックス
+fn locals(
+    buf: &mut String,
+    ids: &HashMap<u32, String>,
+    keyword: &str,
+    types: &[ValueType],
+    start: u32,
+) {
+    let mut open = false;
+    for (i, t) in types.iter().enumerate() {
+        let id = ids.get(&(start + i as u32));
+        if open && id.is_some() {
+            buf.push(')');
+            open = false;
+        }
+        if !open {
+            let _ = write!(buf, " ({keyword}");
+            if let Some(id) = id {
+                let _ = write!(buf, " {id}");
+            }
+            open = id.is_none();
+        }
+        buf.push(' ');
+        value_type(buf, t);
+        if !open {
+            buf.push(')');
+        }
+    }
+    if open {
+        buf.push(')');
+    }
+}
+
+fn global(buf: &mut String, ids: &Ids, g: &Global, idx: u32) {
+    buf.push_str("(global");
+    declare(buf, &ids.globals, idx);
+    buf.push(' ');
+    global_type(buf, &g.g_type);
+    expr(buf, ids, &g.init);
+    buf.push(')');
+}
+
+fn export(buf: &mut String, ids: &Ids, export: &Export) {
+    buf.push_str("(export ");
+    string(buf, export.name.as_bytes());
+    match export.desc {
+        ExportDesc::Func(i) => {
+            buf.push_str(" (func");
+            index(buf, &ids.funcs, i);
+        }
+        ExportDesc::Global(i) => {
+            buf.push_str(" (global");
+            index(buf, &ids.globals, i);
+        }
+        ExportDesc::Table(i) => {
+            let _ = write!(buf, " (table {i}");
+        }
+        ExportDesc::Memory(i) => {
+            let _ = write!(buf, " (memory {i}");
+        }
+        ExportDesc::Tag(i) => {
+            let _ = write!(buf, " (tag {i}");
+        }
+    }
+    buf.push_str("))");
+}
+
+// 要素が全てref.funcの関数参照は"func 0 1"の形で、それ以外は"(item ..)"の形で書く
+// https://webassembly.github.io/spec/core/text/modules.html#element-segments
+fn elem(buf: &mut String, ids: &Ids, elem: &Elem) {
+    buf.push_str("(elem");
+    match &elem.mode {
+        ElemMode::Passive => (),
+        ElemMode::Active { table, offset } => {
+            let _ = write!(buf, " (table {table}) (offset");
+            expr(buf, ids, offset);
+            buf.push(')');
+        }
+        ElemMode::Declarative => buf.push_str(" declare"),
+    }
+    let funcs: Option<Vec<u32>> = elem
+        .init
+        .iter()
+        .map(|e| match e.as_slice() {
+            [Instruction::RefFunc(f)] => Some(*f),
+            _ => None,
+        })
+        .collect();
+    match funcs {
+        Some(funcs) if elem.e_type == ValueType::FuncRef => {
+            buf.push_str(" func");
+            for f in funcs {
+                index(buf, &ids.funcs, f);
+            }
+        }
+        _ => {
+            buf.push(' ');
+            value_type(buf, &elem.e_type);
+            for e in &elem.init {
+                buf.push_str(" (item");
+                expr(buf, ids, e);
+                buf.push(')');
+            }
+        }
+    }
+    buf.push(')');
+}
+
+fn data(buf: &mut String, ids: &Ids, data: &Data) {
+    buf.push_str("(data");
+    if let DataMode::Active { memory, offset } = &data.mode {
+        let _ = write!(buf, " (memory {memory}) (offset");
+        expr(buf, ids, offset);
+        buf.push(')');
+    }
+    buf.push(' ');
+    string(buf, &data.init);
+    buf.push(')');
+}
+
+// カスタムセクションを"(@custom "name" (after data) "bytes")"の形で出力する
+// 位置が"(after last)"の場合は省略する
+fn custom(buf: &mut String, custom: &Custom) {
+    buf.push_str("(@custom ");
+    string(buf, custom.name.as_bytes());
+    match custom.place {
+        CustomPlace::BeforeFirst => buf.push_str(" (before first)"),
+        CustomPlace::Before(s) => {
+            let _ = write!(buf, " (before {})", s.name());
+        }
+        CustomPlace::After(s) => {
+            let _ = write!(buf, " (after {})", s.name());
+        }
+        CustomPlace::AfterLast => (),
+    }
+    buf.push(' ');
+    string(buf, &custom.data);
+    buf.push(')');
+}
+
+// 定数式のような短い命令列を、空白で区切って1行に出力する
+fn expr(buf: &mut String, ids: &Ids, expr: &Expr) {
+    for i in expr {
+        buf.push(' ');
+        instruction(buf, ids, i, None);
+    }
+}
+
+// 命令列を1行に1つずつ出力する
+// depthは字下げの深さで、ブロックの中では1つ深くする
+fn instructions(buf: &mut String, ids: &mut Ids, instrs: &[Instruction], mut depth: usize) {
+    let mut blocks = 0;
+    for i in instrs {
+        if matches!(i, Instruction::Else | Instruction::End) {
+            depth = depth.saturating_sub(1);
+        }
+        buf.push('\n');
+        buf.push_str(&"  ".repeat(depth));
+        match i {
+            Instruction::Block(_)
+            | Instruction::Loop(_)
+            | Instruction::If(_)
+            | Instruction::TryTable(..) => {
+                let label = ids.labels.get(&blocks).cloned();
+                instruction(buf, ids, i, label.as_ref());
+                ids.stack.push(label);
+                blocks += 1;
+                depth += 1;
+            }
+            Instruction::Else => {
+                instruction(buf, ids, i, None);
+                depth += 1;
+            }
+            Instruction::End => {
+                instruction(buf, ids, i, None);
+                ids.stack.pop();
+            }
+            _ => instruction(buf, ids, i, None),
+        }
+    }
+}
+
+fn block_type(buf: &mut String, ids: &Ids, bt: &BlockType) {
+    match bt {
+        BlockType::Empty => (),
+        BlockType::Value(t) => {
+            buf.push_str(" (result ");
+            value_type(buf, t);
+            buf.push(')');
+        }
+        BlockType::Type(x) => {
+            buf.push_str(" (type");
+            index(buf, &ids.types, *x);
+            buf.push(')');
+        }
+    }
+}
+
+// 省略されたときの値と同じ場合は、offsetとalignを書かない
+fn memarg(buf: &mut String, m: &MemArg, natural: u32) {
+    if m.offset != 0 {
+        let _ = write!(buf, " offset={}", m.offset);
+    }
+    if m.align != natural {
+        let _ = write!(buf, " align={}", 1u64 << m.align);
+    }
+}
+
+// 浮動小数点数をビット列から出力する
+// 有限の値は最短で元に戻る10進数で書き、NaNはペイロードを保持する
+// https://webassembly.github.io/spec/core/text/values.html#floating-point
+fn float(buf: &mut String, bits: u64, mant_bits: u32, exp_bits: u32, finite: String) {
+    let exp_mask = (1u64 << exp_bits) - 1;
+    let mant = bits & ((1 << mant_bits) - 1);
+    if (bits >> mant_bits) & exp_mask != exp_mask {
+        buf.push_str(&finite);
+        return;
+    }
+    if bits >> (mant_bits + exp_bits) & 1 == 1 {
+        buf.push('-');
+    }
+    if mant == 0 {
+        buf.push_str("inf");
+    } else if mant == 1 << (mant_bits - 1) {
+        buf.push_str("nan");
+    } else {
+        let _ = write!(buf, "nan:{mant:#x}");
+    }
+}
+
+// labelはブロックを始める命令のラベルの識別子
+fn instruction(buf: &mut String, ids: &Ids, i: &Instruction, label: Option<&String>) {
+    use Instruction as I;
+    match i {
+        I::Block(bt) | I::Loop(bt) | I::If(bt) | I::TryTable(bt, _) => {
+            buf.push_str(match i {
+                I::Block(_) => "block",
+                I::Loop(_) => "loop",
+                I::If(_) => "if",
+                _ => "try_table",
+            });
+            if let Some(label) = label {
+                let _ = write!(buf, " {label}");
+            }
+            block_type(buf, ids, bt);
+            // catch節のラベルは、try_tableの外側から数える
+            if let I::TryTable(_, catches) = i {
+                for c in catches {
+                    let (keyword, tag, l) = match c {
+                        Catch::Catch(x, l) => ("catch", Some(x), l),
+                        Catch::CatchRef(x, l) => ("catch_ref", Some(x), l),
+                        Catch::CatchAll(l) => ("catch_all", None, l),
+                        Catch::CatchAllRef(l) => ("catch_all_ref", None, l),
+                    };
+                    let _ = write!(buf, " ({keyword}");
+                    if let Some(x) = tag {
+                        let _ = write!(buf, " {x}");
+                    }
+                    ids.label(buf, *l);
+                    buf.push(')');
+                }
+            }
+        }
+        I::Br(l) => {
+            buf.push_str("br");
+            ids.label(buf, *l);
+        }
+        I::BrIf(l) => {
+            buf.push_str("br_if");
+            ids.label(buf, *l);
+        }
+        I::BrTable(labels, default) => {
+            buf.push_str("br_table");
+            for l in labels.iter().chain([default]) {
+                ids.label(buf, *l);
+            }
+        }
+        I::Call(f) => {
+            buf.push_str("call");
+            index(buf, &ids.funcs, *f);
+        }
+        I::ReturnCall(f) => {
+            buf.push_str("return_call");
+            index(buf, &ids.funcs, *f);
+        }
+        I::CallIndirect(t, table) | I::ReturnCallIndirect(t, table) => {
+            buf.push_str(match i {
+                I::CallIndirect(..) => "call_indirect",
+                _ => "return_call_indirect",
+            });
+            let _ = write!(buf, " {table} (type");
+            index(buf, &ids.types, *t);
+            buf.push(')');
+        }
+        I::Throw(x) => {
+            let _ = write!(buf, "throw {x}");
+        }
+        I::RefNull(t) => {
+            buf.push_str("ref.null ");
+            heap_type(buf, t);
+        }
+        I::RefFunc(f) => {
+            buf.push_str("ref.func");
+            index(buf, &ids.funcs, *f);
+        }
+        I::Select(t) => {
+            buf.push_str("select");
+            if let Some(t) = t {
+                buf.push_str(" (result ");
+                value_type(buf, t);
+                buf.push(')');
+            }
+        }
+        I::LocalGet(x) | I::LocalSet(x) | I::LocalTee(x) => {
+            buf.push_str(match i {
+                I::LocalGet(_) => "local.get",
+                I::LocalSet(_) => "local.set",
+                _ => "local.tee",
+            });
+            index(buf, &ids.locals, *x as u32);
+        }
+        I::GlobalGet(x) | I::GlobalSet(x) => {
+            buf.push_str(match i {
+                I::GlobalGet(_) => "global.get",
+                _ => "global.set",
+            });
+            index(buf, &ids.globals, *x);
+        }
+        I::TableGet(x) => {
+            let _ = write!(buf, "table.get {x}");
+        }
+        I::TableSet(x) => {
+            let _ = write!(buf, "table.set {x}");
+        }
+        I::TableSize(x) => {
+            let _ = write!(buf, "table.size {x}");
+        }
+        I::TableGrow(x) => {
+            let _ = write!(buf, "table.grow {x}");
+        }
+        I::MemorySize(x) => {
+            let _ = write!(buf, "memory.size {x}");
+        }
+        I::MemoryGrow(x) => {
+            let _ = write!(buf, "memory.grow {x}");
+        }
+        // テキスト形式ではdataidxとelemidxを最後に書く
+        I::MemoryInit(d, m) => {
+            let _ = write!(buf, "memory.init {m} {d}");
+        }
+        I::DataDrop(d) => {
+            let _ = write!(buf, "data.drop {d}");
+        }
+        I::MemoryCopy(dst, src) => {
+            let _ = write!(buf, "memory.copy {dst} {src}");
+        }
+        I::MemoryFill(m) => {
+            let _ = write!(buf, "memory.fill {m}");
+        }
+        I::TableInit(e, t) => {
+            let _ = write!(buf, "table.init {t} {e}");
+        }
+        I::ElemDrop(e) => {
+            let _ = write!(buf, "elem.drop {e}");
+        }
+        I::TableCopy(dst, src) => {
+            let _ = write!(buf, "table.copy {dst} {src}");
+        }
+        I::TableFill(t) => {
+            let _ = write!(buf, "table.fill {t}");
+        }
+        I::I32Const(n) => {
+            let _ = write!(buf, "i32.const {n}");
+        }
+        I::I64Const(n) => {
+            let _ = write!(buf, "i64.const {n}");
+        }
+        I::F32Const(bits) => {
+            buf.push_str("f32.const ");
+            let finite = format!("{:?}", f32::from_bits(*bits));
+            float(buf, *bits as u64, 23, 8, finite);
+        }
+        I::F64Const(bits) => {
+            buf.push_str("f64.const ");
+            let finite = format!("{:?}", f64::from_bits(*bits));
+            float(buf, *bits, 52, 11, finite);
+        }
+        // レーンの値は16進数で書く
+        I::V128Const(v) => {
+            buf.push_str("v128.const i32x4");
+            for lane in 0..4 {
+                let _ = write!(buf, " {:#010x}", (v >> (lane * 32)) as u32);
+            }
+        }
+        I::I8x16Shuffle(lanes) => {
+            buf.push_str("i8x16.shuffle");
+            for l in lanes {
+                let _ = write!(buf, " {l}");
+            }
+        }
+        _ => {
+            let entry = instr::find(i).expect("instruction is in the table");
+            buf.push_str(entry.name);
+            match entry.shape {
+                Shape::Plain(_) => (),
+                Shape::Memory(_, natural) => {
+                    memarg(buf, &i.memarg().expect("memory instruction"), natural)
+                }
+                Shape::Lane(..) => {
+                    let _ = write!(buf, " {}", i.lane().expect("lane instruction"));
+                }
+                Shape::MemoryLane(_, natural, _) => {
+                    memarg(buf, &i.memarg().expect("memory instruction"), natural);
+                    let _ = write!(buf, " {}", i.lane().expect("lane instruction"));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::Section, binary::decode, wat::module::module};
+
+    fn round_trip(wat: &str) {
+        let (_, m) = module(wat).unwrap();
+        let printed = print(&m);
+        assert_eq!(module(&printed).map(|(_, p)| p), Ok(m), "{printed}");
+    }
+
+    fn print_instr(i: Instruction) -> String {
+        let mut buf = String::new();
+        instruction(&mut buf, &Ids::default(), &i, None);
+        buf
+    }
+
+    #[test]
+    fn test_wat_print() {
+        let (_, m) = module(include_str!("../../test.wat")).unwrap();
+        assert_eq!(
+            print(&m),
+            "(module
+  (type (func (param i32 i32) (result i32)))
+  (func $add (type 0) (param $lhs i32) (param $rhs i32) (result i32)
+    local.get $lhs
+    local.get $rhs
+    i32.add
+  )
+  (export \"add\" (func $add))
+)
+"
+        );
+    }
+
+    // 内側のブロックに同じ識別子がある場合は、ラベルをインデックスで参照する
+    #[test]
+    fn names_print() {
+        let wat = "(module $m
+                (global $g (mut i32) (i32.const 0))
+                (func $f (param $x i32) (param i32) (local $y i64) (local f32 f32)
+                  block $outer
+                    loop $l
+                      block $l
+                        br 1
+                        br $outer
+                        global.get $g
+                        br_if $l
+                      end
+                    end
+                  end))";
+        let (_, m) = module(wat).unwrap();
+        assert_eq!(
+            print(&m),
+            "(module $m
+  (type (func (param i32 i32)))
+  (func $f (type 0) (param $x i32) (param i32) (local $y i64) (local f32 f32)
+    block $outer
+      loop $l
+        block $l
+          br 1
+          br $outer
+          global.get $g
+          br_if $l
+        end
+      end
+    end
+  )
+  (global $g (mut i32) i32.const 0)
+)
+"
+        );
+        assert_eq!(module(&print(&m)).map(|(_, p)| p), Ok(m));
+    }
+
+    // 識別子に使えない名前や重複する名前は、インデックスで参照する
+    #[test]
+    fn invalid_names_print() {
+        let (_, mut m) = module("(module (func call 1) (func call 0))").unwrap();
+        m.names.funcs = vec![(0, "a b".to_string()), (1, "".to_string())];
+        assert!(print(&m).contains("(func (type 0)\n    call 1"));
+        m.names.funcs = vec![(0, "a".to_string()), (1, "a".to_string())];
+        assert!(print(&m).contains("(func (type 0)\n    call $a"));
+        assert_eq!(module(&print(&m)).map(|(_, p)| p.funcs), Ok(m.funcs));
+    }
+
+    #[test]
+    fn instruction_print() {
+        assert_eq!(
+            print_instr(Instruction::I64Load(MemArg {
+                offset: 8,
+                align: 3
+            })),
+            "i64.load offset=8"
+        );
+        assert_eq!(
+            print_instr(Instruction::I32Load(MemArg {
+                offset: 0,
+                align: 0
+            })),
+            "i32.load align=1"
+        );
+        assert_eq!(
+            print_instr(Instruction::V128Load8Lane(MemArg::default(), 3)),
+            "v128.load8_lane 3"
+        );
+        assert_eq!(
+            print_instr(Instruction::I32x4ExtractLane(2)),
+            "i32x4.extract_lane 2"
+        );
+        assert_eq!(
+            print_instr(Instruction::MemoryInit(1, 0)),
+            "memory.init 0 1"
+        );
+        assert_eq!(
+            print_instr(Instruction::CallIndirect(2, 1)),
+            "call_indirect 1 (type 2)"
+        );
+        assert_eq!(
+            print_instr(Instruction::V128Const(1 | 2 << 32)),
+            "v128.const i32x4 0x00000001 0x00000002 0x00000000 0x00000000"
+        );
+    }
+
+    #[test]
+    fn float_print() {
+        let f32 = |f: u32| print_instr(Instruction::F32Const(f));
+        let f64 = |f: u64| print_instr(Instruction::F64Const(f));
+        assert_eq!(f32(1.5f32.to_bits()), "f32.const 1.5");
+        assert_eq!(f32((-0.0f32).to_bits()), "f32.const -0.0");
+        assert_eq!(f32(1), "f32.const 1e-45");
+        assert_eq!(f32(f32::NEG_INFINITY.to_bits()), "f32.const -inf");
+        assert_eq!(f32(0x7fc0_0000), "f32.const nan");
+        assert_eq!(f32(0xff80_0001), "f32.const -nan:0x1");
+        assert_eq!(f64(f64::MAX.to_bits()), "f64.const 1.7976931348623157e308");
+        assert_eq!(f64(0x7ff0_0000_0000_0001), "f64.const nan:0x1");
+    }
+
+    #[test]
+    fn string_print() {
+        let mut buf = String::new();
+        string(&mut buf, b"a\"\\\n\xff");
+        assert_eq!(buf, "\"a\\\"\\\\\\0a\\ff\"");
+    }
+
+    #[test]
+    fn custom_print() {
+        let mut buf = String::new();
+        custom(
+            &mut buf,
+            &Custom {
+                name: "producers".to_string(),
+                place: CustomPlace::After(Section::Data),
+                data: vec![b'a', 0],
+            },
+        );
+        assert_eq!(buf, "(@custom \"producers\" (after data) \"a\\00\")");
+
+        let mut buf = String::new();
+        custom(&mut buf, &Custom::default());
+        assert_eq!(buf, "(@custom \"\" \"\")");
+    }
+
+    #[test]
+    fn module_round_trip() {
+        round_trip(
+            "(module
+                (type $t (func (param i32) (result i32)))
+                (type (func))
+                (import \"env\" \"log\" (func $log (param i32)))
+                (import \"env\" \"mem\" (memory 1 2 shared))
+                (import \"env\" \"g\" (global $ig (mut f32)))
+                (import \"env\" \"t\" (table 1 funcref))
+                (import \"env\" \"e\" (tag (param i32)))
+                (table $tab 2 externref)
+                (memory 1)
+                (tag $e (param i32))
+                (global $g (mut i64) (i64.const -1))
+                (global f64 (f64.const -0x1p-1074))
+                (func $f (type $t) (local f32 f32 v128)
+                  (block $b (result i32)
+                    (try_table (catch $e $b) (catch_all_ref 1)
+                      (call $log (local.get 0))
+                      (throw $e (i32.const 7))))
+                  (loop $l (br_table 0 1 $l))
+                  (if (local.get 0) (then (nop)) (else (unreachable)))
+                  (drop (select (result f64) (f64.const 1.5) (f64.const nan:0x1) (i32.const 0)))
+                  (memory.init 1 (i32.const 0) (i32.const 0) (i32.const 1))
+                  (data.drop 0)
+                  (memory.copy 1 0 (i32.const 0) (i32.const 0) (i32.const 0))
+                  (table.init 1 0 (i32.const 0) (i32.const 0) (i32.const 0))
+                  (drop (i32x4.extract_lane 3 (v128.const f32x4 1 -2.5 inf nan)))
+                  (drop (i8x16.shuffle 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 31 (v128.const i64x2 -1 2) (v128.const i8x16 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0)))
+                  (drop (i32.atomic.rmw.add offset=8 align=2 (i32.const 0) (i32.const 1)))
+                  (call_indirect 1 (type $t) (i32.const 0) (i32.const 0))
+                  (ref.is_null (ref.null exn))
+                  atomic.fence
+                  (return_call $f (i32.const 0)))
+                (func)
+                (start 1)
+                (elem (table $tab) (i32.const 0) externref (ref.null extern))
+                (elem declare func $f)
+                (elem func $f $log)
+                (data \"\\00\\01\\\"\")
+                (data (i32.const 16) \"abc\")
+                (export \"f\" (func $f))
+                (export \"mem\" (memory 0))
+                (export \"t\" (table $tab))
+                (export \"g\" (global $g))
+                (export \"e\" (tag $e))
+                (@custom \"a\" (before first) \"\\00\")
+                (@custom \"b\" (before func) \"x\")
+                (@custom \"c\" (after datacount) \"\")
+                (@custom \"d\" \"end\"))",
+        );
+    }
+
+    // バイナリ形式から読み込んだモジュールも、テキスト形式を経由して元に戻る
+    #[test]
+    fn test_wasm_print() {
+        let m = decode(include_bytes!("../../test.wasm")).unwrap();
+        assert_eq!(module(&print(&m)).map(|(_, p)| p), Ok(m));
+    }
+}
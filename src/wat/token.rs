@@ -1,13 +1,16 @@
 use std::ops::RangeFrom;
 
 use nom::{
-    bytes::streaming::tag,
+    bytes::{complete, streaming::tag},
     character::complete::{char, multispace0},
+    combinator::verify,
     error::ParseError,
     sequence::delimited,
     AsChar, IResult, InputIter, InputTakeAtPosition, Parser, Slice,
 };
 
+use super::values;
+
 // https://webassembly.github.io/spec/core/text/lexical.html#tokens
 
 // 前後の"("と")"を削除し、innerで指定したパーサーの結果を返す
@@ -35,6 +38,12 @@ pub fn ws(input: &str) -> IResult<&str, &str> {
     multispace0(input)
 }
 
+// 指定したキーワードをパースする
+// "local"が"local.get"の先頭に一致しないように、キーワード全体を比較する
+pub fn keyword<'a>(name: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    bws(verify(values::keyword, move |k: &str| k == name))
+}
+
 // "func"をパースする
 // "func"の前後の0個以上の空白文字列を削除し、以降の文字列を返す
 pub fn func(input: &str) -> IResult<&str, &str> {
@@ -59,6 +68,19 @@ pub fn export(input: &str) -> IResult<&str, &str> {
     bws(tag("export"))(input)
 }
 
+// "memory"をパースする
+// "memory"の前後の0個以上の空白文字列を削除し、以降の文字列を返す
+pub fn memory(input: &str) -> IResult<&str, &str> {
+    bws(tag("memory"))(input)
+}
+
+// "shared"をパースする
+// "shared"の前後の0個以上の空白文字列を削除し、以降の文字列を返す
+// 省略可能で入力の末尾に来ることがあるため、completeのtagでパースする
+pub fn shared(input: &str) -> IResult<&str, &str> {
+    bws(complete::tag("shared"))(input)
+}
+
 // "module"をパースする
 // "module"の前後の0個以上の空白文字列を削除し、以降の文字列を返す
 pub fn module(input: &str) -> IResult<&str, &str> {
@@ -119,6 +141,21 @@ mod tests {
         assert!(export("noexport").is_err());
     }
     #[test]
+    fn memory_parse() {
+        assert_eq!(memory(" memory 1"), Ok(("1", "memory")));
+        assert!(memory("nomemory").is_err());
+    }
+    #[test]
+    fn shared_parse() {
+        assert_eq!(shared(" shared"), Ok(("", "shared")));
+        assert!(shared("unshared").is_err());
+    }
+    #[test]
+    fn keyword_parse() {
+        assert_eq!(keyword("local")(" local i32"), Ok(("i32", "local")));
+        assert!(keyword("local")("local.get 0").is_err());
+    }
+    #[test]
     fn module_parse() {
         assert_eq!(module(" module "), Ok(("", "module")));
         assert!(module("nomodule").is_err());
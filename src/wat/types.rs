@@ -3,13 +3,13 @@ use std::{cell::RefCell, rc::Rc};
 use nom::{
     branch::alt,
     bytes::streaming::tag,
-    combinator::{map, opt, value},
+    combinator::{fail, map, opt, value, verify},
     multi::many0,
-    sequence::{preceded, tuple},
+    sequence::{pair, preceded, tuple},
     IResult,
 };
 
-use crate::ast::{FuncType, ValueType};
+use crate::ast::{BlockType, FuncType, GlobalType, Limits, MemType, TableType, ValueType};
 use crate::wat::{token, types};
 
 use super::{context::Context, token::bws, values};
@@ -35,10 +35,75 @@ pub fn value_type(input: &str) -> IResult<&str, ValueType> {
         value(ValueType::I64, tag("i64")),
         value(ValueType::F32, tag("f32")),
         value(ValueType::F64, tag("f64")),
+        value(ValueType::V128, tag("v128")),
+        value(ValueType::FuncRef, tag("funcref")),
+        value(ValueType::ExternRef, tag("externref")),
+        value(ValueType::ExnRef, tag("exnref")),
     ));
     bws(types)(input)
 }
 
+// "funcref"のような参照型をパースする
+// https://webassembly.github.io/spec/core/text/types.html#reference-types
+pub fn ref_type(input: &str) -> IResult<&str, ValueType> {
+    verify(value_type, ValueType::is_ref)(input)
+}
+
+// "ref.null func"のようなヒープ型をパースし、対応する参照型を返す
+pub fn heap_type(input: &str) -> IResult<&str, ValueType> {
+    let types = alt((
+        value(ValueType::FuncRef, token::keyword("func")),
+        value(ValueType::ExternRef, token::keyword("extern")),
+        value(ValueType::ExnRef, token::keyword("exn")),
+    ));
+    bws(types)(input)
+}
+
+// "(mut i32)"や"i64"のようなグローバル変数の型をパースする
+// https://webassembly.github.io/spec/core/text/types.html#global-types
+pub fn global_type(input: &str) -> IResult<&str, GlobalType> {
+    let mutable = map(
+        token::pt(preceded(token::keyword("mut"), value_type)),
+        |value_type| GlobalType {
+            value_type,
+            mutable: true,
+        },
+    );
+    let immutable = map(value_type, |value_type| GlobalType {
+        value_type,
+        mutable: false,
+    });
+    preceded(token::ws, alt((mutable, immutable)))(input)
+}
+
+// "1 10 funcref"のようなテーブルの型をパースする
+// https://webassembly.github.io/spec/core/text/types.html#table-types
+pub fn table_type(input: &str) -> IResult<&str, TableType> {
+    let (input, (limits, elem)) = tuple((limits, preceded(token::ws, ref_type)))(input)?;
+    Ok((input, TableType { limits, elem }))
+}
+
+// "1 2"のような最小値と省略可能な最大値をパースする
+// https://webassembly.github.io/spec/core/text/types.html#limits
+pub fn limits(input: &str) -> IResult<&str, Limits> {
+    let (input, (min, max)) = tuple((
+        preceded(token::ws, values::u32),
+        opt(preceded(token::ws, values::u32)),
+    ))(input)?;
+    Ok((input, Limits { min, max }))
+}
+
+// "1 1 shared"のようなメモリの型をパースする
+// https://webassembly.github.io/spec/core/text/types.html#memory-types
+pub fn mem_type(input: &str) -> IResult<&str, MemType> {
+    let (input, (limits, shared)) = tuple((limits, opt(token::shared)))(input)?;
+    let mt = MemType {
+        limits,
+        shared: shared.is_some(),
+    };
+    Ok((input, mt))
+}
+
 pub fn func_type<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, FuncType> {
     // 戻り値とパラメータの型を表す
     // パラメータには"$lhs"のような識別子を持っている場合がある
@@ -48,32 +113,37 @@ pub fn func_type<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<
         P(ValueType, Option<String>), // パラメータ
     }
 
-    // オプションの識別子をパースする
-    let param = map(
-        preceded(
-            // 空白を削除する
-            token::ws,
-            // "(param $lhs i32)"のようなパラメータをパースする
-            token::pt(tuple((token::param, opt(values::id), types::value_type))),
-        ),
-        |p| PR::P(p.2, p.1.map(|id| id.to_string())),
+    // "(param $lhs i32)"のような識別子を持つパラメータをパースする
+    let named = map(pair(values::id, types::value_type), |(id, t)| {
+        vec![PR::P(t, Some(id.to_string()))]
+    });
+
+    // "(param i32 i64)"のような複数のパラメータをパースする
+    let anonymous = map(many0(types::value_type), |ts| {
+        ts.into_iter().map(|t| PR::P(t, None)).collect()
+    });
+
+    let param = preceded(
+        // 空白を削除する
+        token::ws,
+        token::pt(preceded(token::param, alt((named, anonymous)))),
     );
 
-    // 戻り値をパースする
+    // "(result i32 i64)"のような戻り値をパースする
     let result = map(
         preceded(
             // 空白を削除する
             token::ws,
-            // "(result i32)"のような戻り値をパースする
-            token::pt(preceded(token::result, types::value_type)),
+            token::pt(preceded(token::result, many0(types::value_type))),
         ),
-        PR::R,
+        |ts| ts.into_iter().map(PR::R).collect(),
     );
 
     // パラメータか戻り値のいずれかの値を取得する
     let t = alt((param, result));
     // パラメータと戻り値をパースする
     let (input, many_t) = many0(t)(input)?;
+    let many_t: Vec<PR> = many_t.concat();
 
     // リストから戻り値を取得し、Vec<ValueType>に変換する
     let results = many_t
@@ -85,35 +155,133 @@ pub fn func_type<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<
         .collect::<Vec<ValueType>>();
 
     // リストからパラメータを取得し、Vec<ValueType>に変換する
-    let params = many_t
-        .iter()
-        .filter_map(|t| match t {
-            PR::P(p, id) => {
-                ctx.borrow_mut().locals.push(id.clone()); // idをContextに追加する
-                Some(*p)
+    // idはContextに追加し、重複する場合はエラーにする
+    let mut params = Vec::new();
+    for t in &many_t {
+        if let PR::P(p, id) = t {
+            if !ctx.borrow_mut().insert_local_id(id) {
+                return fail(input);
             }
-            _ => None,
-        })
-        .collect::<Vec<ValueType>>();
+            params.push(*p);
+        }
+    }
 
     // 戻り値とパラメータをFuncType型に変換する
     let ft = (params, results);
     Ok((input, ft))
 }
 
+// "(type $t)"のような型の参照をパースする
+fn type_ref(input: &str) -> IResult<&str, Index> {
+    preceded(
+        token::ws,
+        token::pt(preceded(token::keyword("type"), index)),
+    )(input)
+}
+
+// "(type $t (func (param i32)))"のような型の定義をパースする
+// https://webassembly.github.io/spec/core/text/modules.html#types
+pub fn type_def<'a>(
+    input: &'a str,
+    ctx: &mut Rc<RefCell<Context>>,
+) -> IResult<&'a str, (Option<&'a str>, FuncType)> {
+    let (input, id) = preceded(token::keyword("type"), opt(values::id))(input)?;
+    let func = preceded(token::func, |i| anonymous_func_type(i, ctx));
+    let (input, ft) = preceded(token::ws, token::pt(func))(input)?;
+    Ok((input, (id, ft)))
+}
+
 pub fn type_use<'a>(input: &'a str, ctx: &mut Rc<RefCell<Context>>) -> IResult<&'a str, usize> {
+    // "(type $t)"で型を参照する場合は、パラメータと戻り値を省略できる
+    let (input, idx) = opt(type_ref)(input)?;
+
     let mut ft = |i| func_type(i, ctx);
 
     // 文字列をFuncType型にパースする
     let (input, ft) = ft(input)?;
 
-    // FuncType型をContextに追加する
-    // すでに存在する場合は、そのインデックスを返す
-    let index = ctx.borrow_mut().upsert_func_type(&ft);
+    let index = match idx {
+        Some(idx) => {
+            let Some(index) = ctx.borrow().get_type_idx(&idx) else {
+                return fail(input);
+            };
+            // 定義されていない型のインデックスはエラーにする
+            let Some(t) = ctx.borrow().types.list.get(index).cloned() else {
+                return fail(input);
+            };
+            // パラメータか戻り値が書かれた場合は、参照する型と一致しなければならない
+            // https://webassembly.github.io/spec/core/text/modules.html#type-uses
+            if !(ft.0.is_empty() && ft.1.is_empty()) && ft != t {
+                return fail(input);
+            }
+            let params = t.0.len();
+            // パラメータが省略された場合は、識別子のないローカル変数として登録する
+            if ft.0.is_empty() {
+                for _ in 0..params {
+                    ctx.borrow_mut().insert_local_id(&None);
+                }
+            }
+            index
+        }
+        // FuncType型をContextに追加する
+        // すでに存在する場合は、そのインデックスを返す
+        None => ctx.borrow_mut().upsert_func_type(&ft),
+    };
 
     Ok((input, index))
 }
 
+// ローカル変数を登録せずに関数型をパースする
+// call_indirectやブロック、タグの型のパラメータはローカル変数ではない
+fn anonymous_func_type<'a>(
+    input: &'a str,
+    ctx: &mut Rc<RefCell<Context>>,
+) -> IResult<&'a str, FuncType> {
+    let locals = ctx.borrow().locals.len();
+    let (input, ft) = func_type(input, ctx)?;
+    ctx.borrow_mut().locals.truncate(locals);
+    Ok((input, ft))
+}
+
+// ローカル変数を登録せずに型をパースし、型のインデックスを返す
+pub fn anonymous_type_use<'a>(
+    input: &'a str,
+    ctx: &mut Rc<RefCell<Context>>,
+) -> IResult<&'a str, usize> {
+    let locals = ctx.borrow().locals.len();
+    let (input, index) = type_use(input, ctx)?;
+    ctx.borrow_mut().locals.truncate(locals);
+    Ok((input, index))
+}
+
+// ブロックの型をパースする
+// パラメータがなく戻り値が1つ以下の場合は、型のリストに追加しない
+// https://webassembly.github.io/spec/core/text/instructions.html#text-blocktype
+pub fn block_type<'a>(
+    input: &'a str,
+    ctx: &mut Rc<RefCell<Context>>,
+) -> IResult<&'a str, BlockType> {
+    let (input, idx) = opt(type_ref)(input)?;
+    let (input, ft) = anonymous_func_type(input, ctx)?;
+    if let Some(idx) = idx {
+        let Some(index) = ctx.borrow().get_type_idx(&idx) else {
+            return fail(input);
+        };
+        let c = ctx.borrow();
+        let inline = !(ft.0.is_empty() && ft.1.is_empty());
+        if inline && c.types.list.get(index) != Some(&ft) {
+            return fail(input);
+        }
+        return Ok((input, BlockType::Type(index as u32)));
+    }
+    let bt = match (ft.0.as_slice(), ft.1.as_slice()) {
+        ([], []) => BlockType::Empty,
+        ([], [t]) => BlockType::Value(*t),
+        _ => BlockType::Type(ctx.borrow_mut().upsert_func_type(&ft) as u32),
+    };
+    Ok((input, bt))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -210,12 +378,177 @@ mod tests {
         );
     }
 
+    #[test]
+    fn func_type_parse_9() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        assert_eq!(
+            func_type("(param i32 i64) (param) (result f32 f64)", &mut ctx),
+            Ok((
+                "",
+                (
+                    vec![ValueType::I32, ValueType::I64],
+                    vec![ValueType::F32, ValueType::F64]
+                )
+            ))
+        );
+        assert_eq!(ctx.borrow().locals, vec![None, None]);
+    }
+
+    #[test]
+    fn limits_parse() {
+        assert_eq!(limits("1"), Ok(("", Limits { min: 1, max: None })));
+        assert_eq!(
+            limits(" 1 0x10)"),
+            Ok((
+                ")",
+                Limits {
+                    min: 1,
+                    max: Some(16)
+                }
+            ))
+        );
+        assert!(limits("$mem").is_err());
+    }
+
+    #[test]
+    fn mem_type_parse() {
+        let limits = Limits {
+            min: 1,
+            max: Some(1),
+        };
+        assert_eq!(
+            mem_type("1 1 shared"),
+            Ok((
+                "",
+                MemType {
+                    limits,
+                    shared: true
+                }
+            ))
+        );
+        assert_eq!(
+            mem_type("1 1"),
+            Ok((
+                "",
+                MemType {
+                    limits,
+                    shared: false
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn block_type_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        assert_eq!(block_type(")", &mut ctx), Ok((")", BlockType::Empty)));
+        assert_eq!(
+            block_type(" (result i64)", &mut ctx),
+            Ok(("", BlockType::Value(ValueType::I64)))
+        );
+        assert_eq!(
+            block_type(" (param i32) (result i32)", &mut ctx),
+            Ok(("", BlockType::Type(0)))
+        );
+        assert_eq!(
+            ctx.borrow().types.list,
+            vec![(vec![ValueType::I32], vec![ValueType::I32])]
+        );
+        assert!(ctx.borrow().locals.is_empty());
+    }
+
     #[test]
     fn value_type_parse() {
         assert_eq!(value_type("i32"), Ok(("", ValueType::I32)));
         assert_eq!(value_type("i64"), Ok(("", ValueType::I64)));
         assert_eq!(value_type("f32"), Ok(("", ValueType::F32)));
         assert_eq!(value_type("f64"), Ok(("", ValueType::F64)));
+        assert_eq!(value_type("v128"), Ok(("", ValueType::V128)));
+        assert_eq!(value_type("exnref"), Ok(("", ValueType::ExnRef)));
+        assert_eq!(value_type("funcref"), Ok(("", ValueType::FuncRef)));
+        assert_eq!(value_type("externref"), Ok(("", ValueType::ExternRef)));
         assert!(value_type("x32").is_err());
     }
+
+    #[test]
+    fn global_type_parse() {
+        assert_eq!(
+            global_type("(mut i32)"),
+            Ok((
+                "",
+                GlobalType {
+                    value_type: ValueType::I32,
+                    mutable: true
+                }
+            ))
+        );
+        assert_eq!(
+            global_type(" f64 "),
+            Ok((
+                "",
+                GlobalType {
+                    value_type: ValueType::F64,
+                    mutable: false
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn table_type_parse() {
+        assert_eq!(
+            table_type("1 2 funcref"),
+            Ok((
+                "",
+                TableType {
+                    limits: Limits {
+                        min: 1,
+                        max: Some(2)
+                    },
+                    elem: ValueType::FuncRef
+                }
+            ))
+        );
+        assert!(table_type("1 i32").is_err());
+    }
+
+    #[test]
+    fn type_use_parse() {
+        let mut ctx = Rc::new(RefCell::new(Context::new()));
+        let (_, (id, ft)) = type_def(
+            "type $t (func (param $x i32) (param i64) (result i32))",
+            &mut ctx,
+        )
+        .unwrap();
+        ctx.borrow_mut()
+            .insert_id_func_type(id.map(|id| id.to_string()), &ft);
+        assert!(ctx.borrow().locals.is_empty());
+
+        assert_eq!(type_use(" (type $t)", &mut ctx), Ok(("", 0)));
+        assert_eq!(ctx.borrow().locals, vec![None, None]);
+        assert_eq!(
+            block_type(" (type 0)", &mut ctx),
+            Ok(("", BlockType::Type(0)))
+        );
+
+        // 存在しない型のインデックスは、panicせずにエラーになる
+        assert!(type_use(" (type 1)", &mut ctx).is_err());
+
+        // 型と一緒に書いたパラメータと戻り値は、参照する型と一致しなければならない
+        ctx.borrow_mut().locals.clear();
+        assert_eq!(
+            type_use(
+                " (type $t) (param $y i32) (param i64) (result i32)",
+                &mut ctx
+            ),
+            Ok(("", 0))
+        );
+        assert_eq!(ctx.borrow().locals, vec![Some("$y".to_string()), None]);
+        ctx.borrow_mut().locals.clear();
+        assert!(type_use(" (type $t) (param i32)", &mut ctx).is_err());
+        assert!(type_use(" (type $t) (param i64 i32) (result i32)", &mut ctx).is_err());
+        assert!(block_type(" (type 0) (result i32)", &mut ctx).is_err());
+        let mut empty = Rc::new(RefCell::new(Context::new()));
+        assert!(type_use(" (type 0)", &mut empty).is_err());
+    }
 }
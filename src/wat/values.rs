@@ -1,15 +1,11 @@
 use crate::wat::token::bws;
 use nom::{
-    branch::alt,
-    bytes::{
-        complete::{is_a, is_not},
-        streaming::tag,
-    },
-    character::complete::{alphanumeric1, char, digit1},
-    combinator::{map, recognize},
-    multi::many1,
+    bytes::complete::{tag, take_while, take_while1},
+    character::complete::{char, satisfy},
+    combinator::{map_opt, recognize},
+    error::{Error, ErrorKind},
     sequence::{delimited, pair},
-    IResult,
+    Err, IResult,
 };
 
 // 仕様
@@ -19,27 +15,300 @@ use nom::{
 // パースされた識別子に対して、前後の空白文字を除去する
 // https://webassembly.github.io/spec/core/text/values.html#text-id
 pub fn id(input: &str) -> IResult<&str, &str> {
-    // WATの仕様では、識別子で以下の特殊文字を含むことができる
-    let additional_chars = "!#$%&′∗+−./:<=>?@∖^_`|~";
-    let id_char = alt((alphanumeric1, is_a(additional_chars)));
-    let id = recognize(pair(tag("$"), many1(id_char)));
+    let id = recognize(pair(tag("$"), take_while1(is_idchar)));
     bws(id)(input)
 }
 
+// 識別子やキーワード、数値に使われる文字かどうか
+// https://webassembly.github.io/spec/core/text/values.html#text-idchar
+pub fn is_idchar(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-./:<=>?@\\^_`|~".contains(c)
+}
+
+// "i32.add"や"offset=4"のようなキーワードをパースする
+// https://webassembly.github.io/spec/core/text/lexical.html#text-keyword
+pub fn keyword(input: &str) -> IResult<&str, &str> {
+    recognize(pair(
+        satisfy(|c| c.is_ascii_lowercase()),
+        take_while(is_idchar),
+    ))(input)
+}
+
+// 数値として解釈する前のトークンをパースする
+fn num_token(input: &str) -> IResult<&str, &str> {
+    take_while1(is_idchar)(input)
+}
+
 // 文字列の数値をu32をパースする
+// 10進数と"0x"で始まる16進数に対応する
 pub fn u32(input: &str) -> IResult<&str, u32> {
-    map(digit1, |d: &str| {
-        d.parse().expect("Integer format not supported")
-    })(input)
+    map_opt(num_token, |s| parse_nat(s)?.try_into().ok())(input)
+}
+
+// 符号付き・符号無しの整数値をパースし、2の補数表現の値を返す
+// https://webassembly.github.io/spec/core/text/values.html#integers
+pub fn i8(input: &str) -> IResult<&str, i8> {
+    map_opt(num_token, |s| parse_int(s, 8).map(|n| n as i8))(input)
+}
+
+pub fn i16(input: &str) -> IResult<&str, i16> {
+    map_opt(num_token, |s| parse_int(s, 16).map(|n| n as i16))(input)
+}
+
+pub fn i32(input: &str) -> IResult<&str, i32> {
+    map_opt(num_token, |s| parse_int(s, 32).map(|n| n as i32))(input)
+}
+
+pub fn i64(input: &str) -> IResult<&str, i64> {
+    map_opt(num_token, |s| parse_int(s, 64).map(|n| n as i64))(input)
+}
+
+// 浮動小数点数をパースする
+// NaNのペイロードを保持するため、値ではなくビット列を返す
+// https://webassembly.github.io/spec/core/text/values.html#floating-point
+pub fn f32(input: &str) -> IResult<&str, u32> {
+    map_opt(num_token, |s| parse_float(s, 23, 8).map(|n| n as u32))(input)
+}
+
+pub fn f64(input: &str) -> IResult<&str, u64> {
+    map_opt(num_token, |s| parse_float(s, 52, 11))(input)
+}
+
+// 先頭の符号を取り除き、負数かどうかと残りの文字列を返す
+fn split_sign(s: &str) -> (bool, &str) {
+    match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    }
+}
+
+// "_"で区切られた数字列を整数に変換する
+// "_"は数字の間にのみ置くことができる
+fn parse_digits(s: &str, radix: u32) -> Option<u128> {
+    if s.is_empty() || s.starts_with('_') || s.ends_with('_') || s.contains("__") {
+        return None;
+    }
+    s.chars().filter(|c| *c != '_').try_fold(0u128, |acc, c| {
+        acc.checked_mul(radix as u128)?
+            .checked_add(c.to_digit(radix)? as u128)
+    })
+}
+
+// 符号無しの10進数または16進数を整数に変換する
+fn parse_nat(s: &str) -> Option<u128> {
+    match s.strip_prefix("0x") {
+        Some(hex) => parse_digits(hex, 16),
+        None => parse_digits(s, 10),
+    }
+}
+
+// 整数をbitsビットの2の補数表現に変換する
+// 符号付きと符号無しの両方の範囲を受け付ける
+fn parse_int(s: &str, bits: u32) -> Option<u64> {
+    let (neg, rest) = split_sign(s);
+    let n = parse_nat(rest)?;
+    let mask = u64::MAX >> (64 - bits);
+    if neg {
+        (n <= 1 << (bits - 1)).then(|| (n as u64).wrapping_neg() & mask)
+    } else {
+        (n <= mask as u128).then_some(n as u64)
+    }
+}
+
+// 浮動小数点数を仮数部mant_bitsビット、指数部exp_bitsビットのビット列に変換する
+fn parse_float(s: &str, mant_bits: u32, exp_bits: u32) -> Option<u64> {
+    let (neg, rest) = split_sign(s);
+    let sign = (neg as u64) << (mant_bits + exp_bits);
+    let exp_mask = ((1u64 << exp_bits) - 1) << mant_bits;
+    let bits = if rest == "inf" {
+        exp_mask
+    } else if rest == "nan" {
+        // 正規化されたNaN
+        exp_mask | 1 << (mant_bits - 1)
+    } else if let Some(payload) = rest.strip_prefix("nan:0x") {
+        let payload = parse_digits(payload, 16)?;
+        if payload == 0 || payload >> mant_bits != 0 {
+            return None;
+        }
+        exp_mask | payload as u64
+    } else if let Some(hex) = rest.strip_prefix("0x") {
+        parse_hex_float(hex, mant_bits, exp_bits)?
+    } else {
+        parse_dec_float(rest, exp_bits)?
+    };
+    Some(sign | bits)
+}
+
+// 10進数の浮動小数点数を変換する
+// 丸めはRustの標準ライブラリに任せ、表現できない大きさの値はエラーにする
+fn parse_dec_float(s: &str, exp_bits: u32) -> Option<u64> {
+    let valid = s.starts_with(|c: char| c.is_ascii_digit())
+        && s.chars()
+            .all(|c| c.is_ascii_digit() || "._eE+-".contains(c));
+    if !valid || s.contains("__") || s.contains("_.") || s.contains("._") {
+        return None;
+    }
+    let s = s.replace('_', "");
+    if exp_bits == 8 {
+        let f = s.parse::<f32>().ok().filter(|f| f.is_finite())?;
+        Some(f.to_bits() as u64)
+    } else {
+        let f = s.parse::<f64>().ok().filter(|f| f.is_finite())?;
+        Some(f.to_bits())
+    }
+}
+
+// 16進数の浮動小数点数を変換する
+// 仮数部を整数として読み取り、最近接偶数丸めで指定された精度に丸める
+fn parse_hex_float(s: &str, mant_bits: u32, exp_bits: u32) -> Option<u64> {
+    let (mantissa, exp) = match s.find(['p', 'P']) {
+        Some(i) => {
+            let (neg, e) = split_sign(&s[i + 1..]);
+            let e = parse_digits(e, 10)?.min(1 << 20) as i64;
+            (&s[..i], if neg { -e } else { e })
+        }
+        None => (s, 0),
+    };
+    let (int, frac) = match mantissa.split_once('.') {
+        Some((int, frac)) => (int, frac),
+        None => (mantissa, ""),
+    };
+    if int.is_empty() || (!frac.is_empty() && parse_digits(frac, 16).is_none()) {
+        return None;
+    }
+    parse_digits(int, 16)?;
+
+    // 仮数部を整数として読み取る
+    // 溢れる桁は指数に繰り入れ、0でない桁があればstickyとして丸めに使う
+    let mut m: u128 = 0;
+    let mut e2 = exp - 4 * frac.chars().filter(|c| *c != '_').count() as i64;
+    let mut sticky = false;
+    for c in int.chars().chain(frac.chars()).filter(|c| *c != '_') {
+        let d = c.to_digit(16)? as u128;
+        if m >> 120 == 0 {
+            m = m << 4 | d;
+        } else {
+            sticky |= d != 0;
+            e2 += 4;
+        }
+    }
+    if m == 0 {
+        return Some(0);
+    }
+
+    let precision = mant_bits as i64 + 1;
+    let bias = (1i64 << (exp_bits - 1)) - 1;
+    let top = 127 - m.leading_zeros() as i64;
+    let e = top + e2;
+    if e > bias {
+        return None;
+    }
+
+    // 落とすビット数を求める
+    // 非正規化数になる場合は、その分だけ多く落とす
+    let mut shift = top - (precision - 1);
+    if e < 1 - bias {
+        shift += 1 - bias - e;
+    }
+    let mut mant = if shift > top + 1 {
+        0
+    } else if shift > 0 {
+        let kept = m.checked_shr(shift as u32).unwrap_or(0);
+        let dropped = if shift >= 128 {
+            m
+        } else {
+            m & ((1 << shift) - 1)
+        };
+        let half = 1u128 << (shift - 1);
+        if dropped > half || (dropped == half && (sticky || kept & 1 == 1)) {
+            kept + 1
+        } else {
+            kept
+        }
+    } else {
+        m << -shift
+    };
+
+    let mut biased = if e < 1 - bias { 0 } else { e + bias };
+    if mant >> precision != 0 {
+        // 丸めによって桁が繰り上がった
+        mant >>= 1;
+        biased += 1;
+    } else if biased == 0 && mant >> (precision - 1) != 0 {
+        // 非正規化数が丸めによって正規化数になった
+        biased = 1;
+    }
+    if biased >= (1 << exp_bits) - 1 {
+        return None;
+    }
+    let mant = mant as u64 & ((1 << mant_bits) - 1);
+    Some((biased as u64) << mant_bits | mant)
 }
 
-// 引用符で囲まれた文字列をパースする
+// 引用符で囲まれた文字列をバイト列としてパースする
+// "\n"や"\ff"、"\u{1F600}"のようなエスケープに対応する
+// 前後の空白文字を除去する
+// https://webassembly.github.io/spec/core/text/values.html#strings
+pub fn string(input: &str) -> IResult<&str, Vec<u8>> {
+    bws(delimited(char('"'), string_body, char('"')))(input)
+}
+
+// 終わりの引用符の手前までの文字列をバイト列に変換する
+fn string_body(input: &str) -> IResult<&str, Vec<u8>> {
+    let error = |i| Err(Err::Error(Error::new(i, ErrorKind::Escaped)));
+    let mut bytes = Vec::new();
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((&input[i..], bytes)),
+            '\\' => {
+                let rest = &input[i + 1..];
+                let (len, escaped) = match rest.chars().next() {
+                    Some('t') => (1, vec![b'\t']),
+                    Some('n') => (1, vec![b'\n']),
+                    Some('r') => (1, vec![b'\r']),
+                    Some('"') => (1, vec![b'"']),
+                    Some('\'') => (1, vec![b'\'']),
+                    Some('\\') => (1, vec![b'\\']),
+                    Some('u') => {
+                        let Some(end) = rest.find('}') else {
+                            return error(rest);
+                        };
+                        let c = rest[1..end]
+                            .strip_prefix('{')
+                            .and_then(|hex| parse_digits(hex, 16))
+                            .and_then(|n| char::from_u32(n.try_into().ok()?));
+                        let Some(c) = c else {
+                            return error(rest);
+                        };
+                        (end + 1, c.to_string().into_bytes())
+                    }
+                    _ => match rest.get(..2).and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                        Some(b) => (2, vec![b]),
+                        None => return error(rest),
+                    },
+                };
+                bytes.extend(escaped);
+                // エスケープの残りの文字を読み飛ばす
+                for _ in 0..rest[..len].chars().count() {
+                    chars.next();
+                }
+            }
+            c if (c as u32) < 0x20 || c == '\u{7f}' => return error(&input[i..]),
+            c => {
+                let mut buf = [0; 4];
+                bytes.extend(c.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+    error(input)
+}
+
+// 引用符で囲まれたUTF-8の文字列をパースする
 // 前後の空白文字を除去する
 pub fn literal(input: &str) -> IResult<&str, String> {
-    map(
-        bws(delimited(char('"'), is_not("\""), char('"'))),
-        |s: &str| s.to_string(),
-    )(input)
+    map_opt(string, |b| String::from_utf8(b).ok())(input)
 }
 
 #[cfg(test)]
@@ -50,12 +319,72 @@ mod tests {
         assert_eq!(id("$valid_id%#! foo "), Ok(("foo ", "$valid_id%#!")));
         assert_eq!(id("  $valid_id%#! foo "), Ok(("foo ", "$valid_id%#!")));
         assert!(id("valid_id%#! foo ").is_err());
+        assert_eq!(id("$a-b'c*d\\e)"), Ok((")", "$a-b'c*d\\e")));
+        assert!(id("$ foo").is_err());
+    }
+
+    #[test]
+    fn keyword_parse() {
+        assert_eq!(keyword("i32.add)"), Ok((")", "i32.add")));
+        assert_eq!(keyword("offset=4 "), Ok((" ", "offset=4")));
+        assert!(keyword("$add").is_err());
+        assert!(keyword("(func").is_err());
     }
 
     #[test]
     fn u32_parse() {
         assert_eq!(u32("12"), Ok(("", 12)));
+        assert_eq!(u32("0x1_0)"), Ok((")", 16)));
         assert!(u32("hello").is_err());
+        assert!(u32("4294967296").is_err());
+        assert!(u32("-1").is_err());
+    }
+
+    #[test]
+    fn int_parse() {
+        assert_eq!(i8("255"), Ok(("", -1)));
+        assert_eq!(i8("-128"), Ok(("", -128)));
+        assert!(i8("256").is_err());
+        assert!(i8("-129").is_err());
+        assert_eq!(i16("0xffff"), Ok(("", -1)));
+        assert_eq!(i32("-0x8000_0000"), Ok(("", i32::MIN)));
+        assert_eq!(i32("+42"), Ok(("", 42)));
+        assert_eq!(i64("18446744073709551615"), Ok(("", -1)));
+        assert!(i32("1__0").is_err());
+        assert!(i32("_1").is_err());
+    }
+
+    #[test]
+    fn float_parse() {
+        assert_eq!(f32("1.5"), Ok(("", 1.5f32.to_bits())));
+        assert_eq!(f32("-0"), Ok(("", (-0.0f32).to_bits())));
+        assert_eq!(f32("1e3"), Ok(("", 1000f32.to_bits())));
+        assert_eq!(f32("inf"), Ok(("", f32::INFINITY.to_bits())));
+        assert_eq!(f32("-nan"), Ok(("", 0xffc0_0000)));
+        assert_eq!(f32("nan:0x1"), Ok(("", 0x7f80_0001)));
+        assert!(f32("nan:0x0").is_err());
+        assert!(f32("nan:0x800000").is_err());
+        assert!(f32("1e39").is_err());
+        assert!(f32(".5").is_err());
+        assert_eq!(f64("1_000.000_1"), Ok(("", 1000.0001f64.to_bits())));
+    }
+
+    #[test]
+    fn hex_float_parse() {
+        assert_eq!(f32("0x1p-1"), Ok(("", 0.5f32.to_bits())));
+        assert_eq!(f32("0x1.8p1"), Ok(("", 3.0f32.to_bits())));
+        assert!(f32("-0x.8").is_err());
+        assert_eq!(f32("-0x0.8"), Ok(("", (-0.5f32).to_bits())));
+        assert_eq!(f32("0x1p-149"), Ok(("", 1)));
+        assert_eq!(f32("0x1p-150"), Ok(("", 0)));
+        assert_eq!(f32("0x1.8p-149"), Ok(("", 2)));
+        assert!(f32("0x1.fffffffp127").is_err());
+        assert_eq!(f32("0x1.fffffep127"), Ok(("", f32::MAX.to_bits())));
+        // 最近接偶数丸め
+        assert_eq!(f32("0x1.000001p0"), Ok(("", 1.0f32.to_bits())));
+        assert_eq!(f32("0x1.000003p0"), Ok(("", 1.0f32.to_bits() + 2)));
+        assert_eq!(f64("0x1.fffffffffffffp1023"), Ok(("", f64::MAX.to_bits())));
+        assert_eq!(f64("0x0.0000000000001p-1022"), Ok(("", 1)));
     }
 
     #[test]
@@ -65,5 +394,23 @@ mod tests {
             Ok(("", "valid#+123".to_string()))
         );
         assert!(literal("invalid").is_err());
+        assert!(literal("\"\\ff\"").is_err());
+    }
+
+    #[test]
+    fn string_parse() {
+        assert_eq!(string("\"\""), Ok(("", vec![])));
+        assert_eq!(
+            string("\"a\\00\\ff\\n\\\"\" "),
+            Ok(("", vec![b'a', 0, 0xff, b'\n', b'"']))
+        );
+        assert_eq!(
+            string("\"\\u{1F600}\\u{41}\""),
+            Ok(("", "\u{1F600}A".as_bytes().to_vec()))
+        );
+        assert_eq!(string("\"日本\""), Ok(("", "日本".as_bytes().to_vec())));
+        assert!(string("\"\\u{d800}\"").is_err());
+        assert!(string("\"\\x\"").is_err());
+        assert!(string("\"unterminated").is_err());
     }
 }